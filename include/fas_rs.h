@@ -0,0 +1,67 @@
+/*
+ * Copyright 2025-2025, shadow3aaa
+ *
+ * This file is part of fas-rs.
+ *
+ * fas-rs is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free
+ * Software Foundation, either version 3 of the License, or (at your option)
+ * any later version.
+ *
+ * fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+ * FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+ * details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#ifndef FAS_RS_H
+#define FAS_RS_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct FasEngine FasEngine;
+
+typedef struct {
+    uint32_t target_fps;
+    /* Frequency change in kHz, added to the current frequency of the game's cores */
+    int64_t control_khz;
+    bool janked;
+} FasDecision;
+
+/*
+ * Creates an engine for pkg with the targets, margins and gains config_path (a fas-rs
+ * games.toml) has for it. mode is "powersave", "balance", "performance" or "fast", NULL uses
+ * the game's configured mode or "balance". Returns NULL when the config can not be read or has
+ * no target fps for pkg.
+ */
+FasEngine *fas_engine_new(const char *config_path, const char *pkg, const char *mode);
+
+/*
+ * Feeds one presented frame. timestamp_ms is any monotonic clock, frametime_ns the time since
+ * the previous frame, util_max the busiest game thread's share of a cpu (0.0 - 1.0) and
+ * temperature the hottest cpu core in 0.001 degrees. Returns true and fills decision when the
+ * engine has a decision, false while it still learns the target fps or when it failed. Not thread
+ * safe per engine.
+ */
+bool fas_engine_frame(FasEngine *engine, uint64_t timestamp_ms, uint64_t frametime_ns,
+                      double util_max, uint64_t temperature, FasDecision *decision);
+
+/* The target fps the engine locked onto, 0 while unknown */
+uint32_t fas_engine_target_fps(const FasEngine *engine);
+
+/* Frees an engine, NULL is ignored */
+void fas_engine_free(FasEngine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FAS_RS_H */
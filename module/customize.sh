@@ -52,7 +52,11 @@ if [ -f $CONF ]; then
 	touch $MERGE_FLAG
 else
 	mkdir -p $DIR
-	cp $MODPATH/games.toml $CONF
+	# The standard config with the overlays matching this device
+	chmod 0755 $MODPATH/fas-rs
+	if ! $MODPATH/fas-rs std-config $MODPATH/games.toml >$CONF 2>/dev/null; then
+		cp $MODPATH/games.toml $CONF
+	fi
 fi
 
 cp -f $MODPATH/README_CN.md $DIR/doc_cn.md
@@ -30,10 +30,20 @@ until [ -d $DIR ]; do
 done
 
 if [ -f $MERGE_FLAG ]; then
-	$MODDIR/fas-rs merge $MODDIR/games.toml >$DIR/.update_games.toml
+	if $MODDIR/fas-rs merge $MODDIR/games.toml >$DIR/.update_games.toml 2>$DIR/merge_error.txt; then
+		mv $DIR/.update_games.toml $DIR/games.toml
+		rm -f $DIR/merge_report.txt
+		if [ -s $DIR/merge_error.txt ]; then
+			mv $DIR/merge_error.txt $DIR/merge_report.txt
+		else
+			rm -f $DIR/merge_error.txt
+		fi
+	else
+		rm -f $DIR/.update_games.toml
+	fi
 	rm $MERGE_FLAG
-	mv $DIR/.update_games.toml $DIR/games.toml
 fi
 
-killall fas-rs
-RUST_BACKTRACE=1 nohup $MODDIR/fas-rs run $MODDIR/games.toml >$LOG 2>&1 &
+# Versions without the instance lock can only be killed
+$MODDIR/fas-rs stop >/dev/null 2>&1 || killall fas-rs
+RUST_BACKTRACE=1 $MODDIR/fas-rs run --daemon $MODDIR/games.toml >$LOG 2>&1
@@ -0,0 +1,326 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use log::info;
+
+use crate::paths::{self, Root};
+
+const MOUNTS: &str = "/proc/mounts";
+
+// Files that move tasks rather than hold a setting, writing the same tid twice is not redundant
+const TASK_LISTS: [&str; 3] = ["tasks", "cgroup.procs", "cgroup.threads"];
+
+static CPUSET: OnceLock<Option<Mount>> = OnceLock::new();
+static CPU: OnceLock<Option<Mount>> = OnceLock::new();
+static MEMORY: OnceLock<Option<Mount>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    Cpuset,
+    Cpu,
+    Memory,
+}
+
+impl Subsystem {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Cpuset => "cpuset",
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+        }
+    }
+
+    // Where Android mounts the v1 hierarchy when /proc/mounts cannot tell us
+    const fn fallback(self) -> &'static str {
+        match self {
+            Self::Cpuset => "/dev/cpuset",
+            Self::Cpu => "/dev/cpuctl",
+            Self::Memory => "/dev/memcg",
+        }
+    }
+
+    fn mount(self) -> Option<&'static Mount> {
+        let mount = match self {
+            Self::Cpuset => &CPUSET,
+            Self::Cpu => &CPU,
+            Self::Memory => &MEMORY,
+        };
+
+        mount.get_or_init(|| Mount::detect(self)).as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    // `noprefix` drops the `cpuset.` prefix of the v1 control files, which Android does for cpuset
+    V1 { noprefix: bool },
+    V2,
+}
+
+#[derive(Debug)]
+struct Mount {
+    root: PathBuf,
+    version: Version,
+}
+
+impl Mount {
+    fn detect(subsystem: Subsystem) -> Option<Self> {
+        // An override from `[paths]` is taken as is, its files tell the version
+        if subsystem == Subsystem::Cpuset {
+            if let Some(root) = paths::get(Root::Cpuset) {
+                let version = if root.join("cgroup.controllers").exists() {
+                    Version::V2
+                } else {
+                    Version::V1 {
+                        noprefix: !root.join("cpuset.cpus").exists(),
+                    }
+                };
+                return Some(Self::found(subsystem, &root.to_string_lossy(), version));
+            }
+        }
+
+        let mounts = fs::read_to_string(MOUNTS).unwrap_or_default();
+        let mut unified = None;
+
+        for line in mounts.lines() {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let [_, root, fstype, options, ..] = fields[..] else {
+                continue;
+            };
+
+            match fstype {
+                "cgroup" => {
+                    let options: Vec<_> = options.split(',').collect();
+                    if options.contains(&subsystem.name()) {
+                        return Some(Self::found(
+                            subsystem,
+                            root,
+                            Version::V1 {
+                                noprefix: options.contains(&"noprefix"),
+                            },
+                        ));
+                    }
+                }
+                "cgroup2" if unified.is_none() => unified = Some(PathBuf::from(root)),
+                _ => (),
+            }
+        }
+
+        // v1 mounts take precedence, the unified tree only counts if the controller is enabled in it
+        if let Some(root) = unified {
+            let controllers =
+                fs::read_to_string(root.join("cgroup.controllers")).unwrap_or_default();
+            if controllers
+                .split_whitespace()
+                .any(|c| c == subsystem.name())
+            {
+                return Some(Self::found(subsystem, &root.to_string_lossy(), Version::V2));
+            }
+        }
+
+        let root = Path::new(subsystem.fallback());
+        root.exists().then(|| {
+            Self::found(
+                subsystem,
+                subsystem.fallback(),
+                Version::V1 {
+                    noprefix: subsystem == Subsystem::Cpuset,
+                },
+            )
+        })
+    }
+
+    fn found(subsystem: Subsystem, root: &str, version: Version) -> Self {
+        info!("cgroup {}: {root} ({version:?})", subsystem.name());
+
+        Self {
+            root: PathBuf::from(root),
+            version,
+        }
+    }
+}
+
+pub fn is_task_list(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| TASK_LISTS.contains(&name))
+}
+
+// A group of one controller, e.g. cpuset `top-app` or cpu `top-app`, with file names resolved
+// for the hierarchy it lives in
+#[derive(Debug, Clone)]
+pub struct Cgroup {
+    path: PathBuf,
+    subsystem: Subsystem,
+    version: Version,
+}
+
+impl Cgroup {
+    // `name` is relative to the controller root, empty for the root group itself
+    pub fn open<S: AsRef<str>>(subsystem: Subsystem, name: S) -> Option<Self> {
+        let mount = subsystem.mount()?;
+        let path = mount.root.join(name.as_ref());
+
+        path.exists().then_some(Self {
+            path,
+            subsystem,
+            version: mount.version,
+        })
+    }
+
+    pub fn create<S: AsRef<str>>(subsystem: Subsystem, name: S) -> io::Result<Self> {
+        let mount = subsystem
+            .mount()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cgroup not mounted"))?;
+        let path = mount.root.join(name.as_ref());
+
+        if !path.exists() {
+            if mount.version == Version::V2 {
+                // Children only get the controller's files once the parent delegates it
+                if let Some(parent) = path.parent() {
+                    let _ = fs::write(
+                        parent.join("cgroup.subtree_control"),
+                        format!("+{}", subsystem.name()),
+                    );
+                }
+            }
+
+            fs::create_dir(&path)?;
+        }
+
+        Ok(Self {
+            path,
+            subsystem,
+            version: mount.version,
+        })
+    }
+
+    // The group `pid` lives in, from the line of /proc/<pid>/cgroup for this hierarchy
+    pub fn of_process(subsystem: Subsystem, pid: i32) -> Option<Self> {
+        let mount = subsystem.mount()?;
+        let groups = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+
+        let name = groups.lines().find_map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let (_, controllers, name) = (fields.next()?, fields.next()?, fields.next()?);
+            let matches = match mount.version {
+                Version::V1 { .. } => controllers.split(',').any(|c| c == subsystem.name()),
+                Version::V2 => controllers.is_empty(),
+            };
+            matches.then(|| name.trim_start_matches('/').to_string())
+        })?;
+
+        Self::open(subsystem, name)
+    }
+
+    pub const fn is_unified(&self) -> bool {
+        matches!(self.version, Version::V2)
+    }
+
+    // Relative to the controller root, what `open` takes
+    pub fn name(&self) -> String {
+        self.subsystem
+            .mount()
+            .and_then(|mount| self.path.strip_prefix(&mount.root).ok())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        match self.version {
+            Version::V1 { noprefix: true } => self.path.join(name),
+            _ => self.path.join(format!("{}.{name}", self.subsystem.name())),
+        }
+    }
+
+    pub fn cpus(&self) -> PathBuf {
+        self.file("cpus")
+    }
+
+    pub fn mems(&self) -> PathBuf {
+        self.file("mems")
+    }
+
+    // Memory the group keeps under any reclaim, the unified hierarchy only
+    pub fn memory_min(&self) -> PathBuf {
+        self.file("min")
+    }
+
+    pub fn uclamp_min(&self) -> PathBuf {
+        self.path.join("cpu.uclamp.min")
+    }
+
+    // Per-group psi, only the unified hierarchy has it
+    pub fn pressure(&self) -> PathBuf {
+        self.path
+            .join(format!("{}.pressure", self.subsystem.name()))
+    }
+
+    pub fn procs(&self) -> PathBuf {
+        self.path.join("cgroup.procs")
+    }
+
+    // v2 only moves single threads inside threaded subtrees, writes to a domain group fail
+    pub fn threads(&self) -> PathBuf {
+        match self.version {
+            Version::V1 { .. } => self.path.join("tasks"),
+            Version::V2 => self.path.join("cgroup.threads"),
+        }
+    }
+
+    // v2 leaves cpuset.cpus empty until set, the effective list is what the group really runs on
+    pub fn read_cpus(&self) -> io::Result<String> {
+        let cpus = fs::read_to_string(self.cpus())?;
+        if cpus.trim().is_empty() && self.version == Version::V2 {
+            return fs::read_to_string(self.file("cpus.effective")).map(|s| s.trim().to_string());
+        }
+
+        Ok(cpus.trim().to_string())
+    }
+
+    pub fn read_mems(&self) -> io::Result<String> {
+        let mems = fs::read_to_string(self.mems())?;
+        if mems.trim().is_empty() && self.version == Version::V2 {
+            return fs::read_to_string(self.file("mems.effective")).map(|s| s.trim().to_string());
+        }
+
+        Ok(mems.trim().to_string())
+    }
+
+    pub fn add_process(&self, pid: u32) -> io::Result<()> {
+        fs::write(self.procs(), pid.to_string())
+    }
+
+    pub fn processes(&self) -> Vec<u32> {
+        fs::read_to_string(self.procs())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|pid| pid.trim().parse().ok())
+            .collect()
+    }
+
+    // Only empty groups can go
+    pub fn remove(self) -> io::Result<()> {
+        fs::remove_dir(&self.path)
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use hashbrown::{HashMap, HashSet};
+use log::{info, warn};
+
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    file_handler::FileHandler,
+};
+
+const GAME_CPUSET: &str = "fas-rs";
+const BACKGROUND_CPUSETS: [&str; 3] = ["background", "system-background", "restricted"];
+
+fn cpu_list(cores: &[usize]) -> String {
+    cores
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[derive(Debug, Default)]
+pub struct Affinity {
+    enabled: bool,
+    isolated: bool,
+    little_cores: String,
+    big_cores: String,
+    game_cpuset: Option<Cgroup>,
+    pinned_tids: HashSet<i32>,
+    saved_cpus: HashMap<PathBuf, String>,
+}
+
+impl Affinity {
+    pub fn new(little_cores: &[usize], big_cores: &[usize]) -> Self {
+        Self {
+            little_cores: cpu_list(little_cores),
+            big_cores: cpu_list(big_cores),
+            ..Self::default()
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, file_handler: &mut FileHandler) {
+        if self.enabled && !enabled {
+            self.reset(file_handler);
+        }
+
+        self.enabled = enabled;
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn apply(
+        &mut self,
+        game_threads: impl Iterator<Item = i32>,
+        file_handler: &mut FileHandler,
+    ) {
+        if !self.enabled || self.little_cores.is_empty() || self.big_cores.is_empty() {
+            return;
+        }
+
+        if !self.isolated {
+            if let Err(e) = self.isolate(file_handler) {
+                warn!("Failed to isolate big cores, disable core pinning: {e:?}");
+                self.set_enabled(false, file_handler);
+                return;
+            }
+        }
+
+        let Some(tasks) = self.game_cpuset.as_ref().map(Cgroup::threads) else {
+            return;
+        };
+        for tid in game_threads {
+            if !self.pinned_tids.contains(&tid)
+                && file_handler
+                    .write_with_workround(&tasks, tid.to_string())
+                    .is_ok()
+            {
+                self.pinned_tids.insert(tid);
+            }
+        }
+    }
+
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        if let Some(top_app) = Cgroup::open(Subsystem::Cpuset, "top-app") {
+            for tid in self.pinned_tids.drain() {
+                let _ = file_handler.write_with_workround(top_app.threads(), tid.to_string());
+            }
+        }
+        self.pinned_tids.clear();
+
+        for (path, cpus) in self.saved_cpus.drain() {
+            let _ = file_handler.write_with_workround(path, cpus);
+        }
+
+        self.isolated = false;
+    }
+
+    // Give the game its own cpuset on the big cores and squeeze background groups onto the little ones
+    fn isolate(&mut self, file_handler: &mut FileHandler) -> Result<()> {
+        self.isolated = true;
+
+        let mems = Cgroup::open(Subsystem::Cpuset, "")
+            .context("cpuset is not mounted")?
+            .read_mems()?;
+        let game_cpuset = Cgroup::create(Subsystem::Cpuset, GAME_CPUSET)?;
+        file_handler.write_with_workround(game_cpuset.mems(), mems)?;
+        file_handler.write_with_workround(game_cpuset.cpus(), &self.big_cores)?;
+        self.game_cpuset = Some(game_cpuset);
+
+        for name in BACKGROUND_CPUSETS {
+            let Some(cpuset) = Cgroup::open(Subsystem::Cpuset, name) else {
+                continue;
+            };
+            let Ok(cpus) = cpuset.read_cpus() else {
+                continue;
+            };
+
+            let path = cpuset.cpus();
+            self.saved_cpus.insert(path.clone(), cpus);
+            file_handler.write_with_workround(path, &self.little_cores)?;
+        }
+
+        info!("Pinned game threads to cpus {}", self.big_cores);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+
+use crate::{file_handler::FileHandler, sysfs};
+
+const DEVFREQ_PATH: &str = "/sys/class/devfreq";
+// DDR, LLCC and cpu bandwidth voters on Qualcomm, the memory controller elsewhere
+const BUS_NODES: [&str; 4] = ["ddr", "llcc", "cpubw", "dmc"];
+// Share of the highest cpu frequency the decision has to reach before the bus follows it
+const HEAVY_RATIO: f64 = 0.75;
+
+#[derive(Debug)]
+struct BusNode {
+    path: PathBuf,
+    freqs: Vec<isize>,
+    stock_min: Option<String>,
+    boosted: Option<isize>,
+}
+
+impl BusNode {
+    fn new(path: PathBuf) -> Result<Self> {
+        let mut freqs: Vec<isize> = sysfs::read_to_string(path.join("available_frequencies"))?
+            .split_whitespace()
+            .map(|freq| freq.parse().context("Failed to parse bus frequency"))
+            .collect::<Result<_>>()?;
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        if freqs.len() < 2 {
+            bail!("Nothing to scale");
+        }
+
+        Ok(Self {
+            path,
+            freqs,
+            stock_min: None,
+            boosted: None,
+        })
+    }
+
+    fn boost(&mut self, ratio: f64, file_handler: &mut FileHandler) -> Result<()> {
+        let max = self.freqs.last().copied().unwrap_or_default();
+        let target = (max as f64 * ratio) as isize;
+        let freq = self
+            .freqs
+            .iter()
+            .find(|freq| **freq >= target)
+            .copied()
+            .unwrap_or(max);
+
+        if self.boosted == Some(freq) {
+            return Ok(());
+        }
+
+        let min_freq = self.path.join("min_freq");
+        if self.stock_min.is_none() {
+            self.stock_min = Some(sysfs::read_to_string(&min_freq)?.trim().to_string());
+        }
+
+        file_handler.write_with_workround(min_freq, freq.to_string())?;
+        self.boosted = Some(freq);
+        Ok(())
+    }
+
+    fn reset(&mut self, file_handler: &mut FileHandler) {
+        self.boosted = None;
+        if let Some(stock) = self.stock_min.take() {
+            let _ = file_handler.write_with_workround(self.path.join("min_freq"), stock);
+        }
+    }
+}
+
+// Raises the minimum frequency of the memory bus devfreq nodes in heavy scenes, where
+// bandwidth rather than the cpu clock is often what holds frames back
+#[derive(Debug, Default)]
+pub struct BusBoost {
+    enabled: bool,
+    nodes: Vec<BusNode>,
+}
+
+impl BusBoost {
+    pub fn new() -> Self {
+        let nodes = sysfs::read_dir(DEVFREQ_PATH)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| BUS_NODES.iter().any(|node| name.contains(node)))
+            })
+            .filter_map(|path| BusNode::new(path).ok())
+            .collect();
+
+        Self {
+            enabled: false,
+            nodes,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, file_handler: &mut FileHandler) {
+        if self.enabled && !enabled {
+            self.reset(file_handler);
+        }
+
+        if !self.enabled && enabled {
+            if self.nodes.is_empty() {
+                warn!("bus_boost: no bus devfreq nodes found");
+            } else {
+                info!(
+                    "bus_boost: {}",
+                    self.nodes
+                        .iter()
+                        .filter_map(|node| node.path.file_name()?.to_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        self.enabled = enabled;
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // `ratio` is the decided frequency against the highest cpu frequency
+    pub fn apply(
+        &mut self,
+        ratio: f64,
+        is_janked: bool,
+        throttled: bool,
+        file_handler: &mut FileHandler,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        // The bus heats the same package, thermal limits win
+        if throttled || (ratio < HEAVY_RATIO && !is_janked) {
+            self.reset(file_handler);
+            return;
+        }
+
+        let ratio = ratio.clamp(0.0, 1.0);
+        // A node the kernel refuses once would only fail again every frame
+        self.nodes.retain_mut(|node| {
+            node.boost(ratio, file_handler)
+                .map_err(|e| {
+                    node.reset(file_handler);
+                    warn!(
+                        "Failed to raise bus frequency of {}, leaving it alone: {e:?}",
+                        node.path.display()
+                    );
+                })
+                .is_ok()
+        });
+    }
+
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        for node in &mut self.nodes {
+            node.reset(file_handler);
+        }
+    }
+}
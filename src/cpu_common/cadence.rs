@@ -0,0 +1,75 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+// Steady samples in a row before the interval is stretched once
+const CALM_SAMPLES: u32 = 3;
+const STRETCH: f64 = 1.5;
+
+// A sampling interval that stretches from `base` up to `max` while what it samples holds still,
+// and snaps back to `base` the moment it moves. A `max` not above `base` keeps it fixed
+#[derive(Debug, Clone, Copy)]
+pub struct Cadence {
+    base: Duration,
+    max: Duration,
+    current: Duration,
+    calm: u32,
+}
+
+impl Cadence {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            current: base,
+            calm: 0,
+        }
+    }
+
+    pub fn set_bounds(&mut self, base: Duration, max: Duration) {
+        self.base = base;
+        self.max = max.max(base);
+        self.current = self.current.clamp(self.base, self.max);
+        self.calm = 0;
+    }
+
+    pub const fn interval(&self) -> Duration {
+        self.current
+    }
+
+    pub const fn stretched(&self) -> bool {
+        self.current.as_nanos() > self.base.as_nanos()
+    }
+
+    // Returns true when the interval got shorter, a deadline set with the old one is too far
+    pub fn settle(&mut self, steady: bool) -> bool {
+        if !steady {
+            self.calm = 0;
+            let tightened = self.stretched();
+            self.current = self.base;
+            return tightened;
+        }
+
+        self.calm += 1;
+        if self.calm >= CALM_SAMPLES {
+            self.calm = 0;
+            self.current = self.current.mul_f64(STRETCH).min(self.max);
+        }
+        false
+    }
+}
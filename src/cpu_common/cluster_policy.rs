@@ -0,0 +1,60 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusterPolicy {
+    pub margin: isize,
+    pub ceiling: f64,
+    pub min_freq: Option<isize>,
+    pub max_freq: Option<isize>,
+}
+
+impl ClusterPolicy {
+    pub fn apply(self, freq: isize, max_freq: isize) -> isize {
+        let freq = freq
+            .saturating_add(self.margin)
+            .min((max_freq as f64 * self.ceiling.clamp(0.0, 1.0)) as isize);
+        self.bound(freq)
+    }
+
+    // The absolute floor wins over the ceiling if they are misconfigured to cross
+    pub fn bound(self, freq: isize) -> isize {
+        freq.min(self.max_freq.unwrap_or(isize::MAX))
+            .max(self.min_freq.unwrap_or(isize::MIN))
+    }
+}
+
+impl Default for ClusterPolicy {
+    fn default() -> Self {
+        Self {
+            margin: 0,
+            ceiling: 1.0,
+            min_freq: None,
+            max_freq: None,
+        }
+    }
+}
+
+pub const fn cluster_name(index: usize, count: usize) -> &'static str {
+    if index == 0 {
+        "little"
+    } else if count >= 3 && index == count - 1 {
+        "prime"
+    } else {
+        "big"
+    }
+}
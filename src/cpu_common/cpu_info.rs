@@ -16,18 +16,21 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
-    fs,
     path::{Path, PathBuf},
     sync::atomic::Ordering,
-    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
-use log::warn;
+use hashbrown::HashMap;
 use nix::sched::CpuSet;
 
-use super::IGNORE_MAP;
-use crate::file_handler::FileHandler;
+use super::{
+    IGNORE_MAP,
+    cluster_policy::ClusterPolicy,
+    freq_backend::{FreqBackend, PolicyLimits},
+    topology::CAPACITY_SCALE,
+};
+use crate::{file_handler::FileHandler, sysfs};
 
 #[derive(Debug)]
 pub struct Info {
@@ -36,8 +39,11 @@ pub struct Info {
     affected_cpus: Vec<usize>,
     pub cur_fas_freq: isize,
     pub freqs: Vec<isize>,
-    verify_freq: Option<isize>,
-    verify_timer: Instant,
+    // Set from the topology, see `Topology::detect`
+    pub capacity: u32,
+    // Last limits handed to the backend, what `LimitGuard` holds the kernel to
+    written_limits: Option<(isize, isize)>,
+    saved_governor: Option<String>,
 }
 
 impl Info {
@@ -52,7 +58,7 @@ impl Info {
             .parse::<i32>()
             .context("Failed to parse policy")?;
 
-        let freqs_content = fs::read_to_string(path.join("scaling_available_frequencies"))
+        let freqs_content = sysfs::read_to_string(path.join("scaling_available_frequencies"))
             .context("Failed to read frequencies")?;
         let mut freqs: Vec<isize> = freqs_content
             .split_whitespace()
@@ -60,7 +66,7 @@ impl Info {
             .collect::<Result<_>>()?;
         freqs.sort_unstable();
 
-        let affected_cpus = fs::read_to_string(path.join("affected_cpus"))
+        let affected_cpus = sysfs::read_to_string(path.join("affected_cpus"))
             .context("Failed to read affected_cpus")?
             .split_whitespace()
             .map(|core| {
@@ -76,40 +82,35 @@ impl Info {
             affected_cpus,
             cur_fas_freq: *freqs.last().context("No frequencies available")?,
             freqs,
-            verify_freq: None,
-            verify_timer: Instant::now(),
+            capacity: CAPACITY_SCALE,
+            written_limits: None,
+            saved_governor: None,
         })
     }
 
-    fn verify_freq(&mut self, write_freq: isize) {
-        if self.verify_timer.elapsed() >= Duration::from_secs(3) {
-            self.verify_timer = Instant::now();
-
-            if let Some(verify_freq) = self.verify_freq {
-                let current_freq = self.read_freq();
-                let min_acceptable_freq = self
-                    .freqs
-                    .iter()
-                    .take_while(|freq| **freq <= verify_freq)
-                    .last()
-                    .copied()
-                    .unwrap_or(verify_freq);
-                let max_acceptable_freq = self
-                    .freqs
-                    .iter()
-                    .find(|freq| **freq >= verify_freq)
-                    .copied()
-                    .unwrap_or(verify_freq);
-                if !(min_acceptable_freq..=max_acceptable_freq).contains(&current_freq) {
-                    warn!(
-                        "CPU Policy{}: Frequency control does not meet expectations! Expected: {}-{}, Actual: {}",
-                        self.policy, min_acceptable_freq, max_acceptable_freq, current_freq
-                    );
-                }
-            }
-        }
+    // Highest table entry not above `freq`, the kernel would round anything in between anyway
+    pub fn snap(&self, freq: isize) -> isize {
+        self.freqs
+            .iter()
+            .rev()
+            .find(|opp| **opp <= freq)
+            .or_else(|| self.freqs.first())
+            .copied()
+            .unwrap_or(freq)
+    }
+
+    // Moves `steps` table entries away from the entry `freq` snaps to
+    pub fn step(&self, freq: isize, steps: isize) -> isize {
+        let index = self
+            .freqs
+            .iter()
+            .rposition(|opp| *opp <= freq)
+            .unwrap_or_default();
+        let index = index
+            .saturating_add_signed(steps)
+            .min(self.freqs.len().saturating_sub(1));
 
-        self.verify_freq = Some(write_freq);
+        self.freqs.get(index).copied().unwrap_or(freq)
     }
 
     fn ignore_write(&self) -> Result<bool> {
@@ -131,55 +132,123 @@ impl Info {
         &mut self,
         top_used_cores: CpuSet,
         freq: isize,
+        cluster_policy: ClusterPolicy,
+        backend: &mut dyn FreqBackend,
         file_handler: &mut FileHandler,
     ) -> Result<()> {
         let min_freq = *self.freqs.first().context("No frequencies available")?;
         let max_freq = *self.freqs.last().context("No frequencies available")?;
 
-        let adjusted_freq = freq.clamp(min_freq, max_freq);
-        self.cur_fas_freq = adjusted_freq;
+        self.cur_fas_freq = self.snap(freq.clamp(min_freq, max_freq));
+        let adjusted_freq = self.snap(
+            cluster_policy
+                .apply(freq, max_freq)
+                .clamp(min_freq, max_freq),
+        );
 
         if !self.ignore_write()? {
-            if self.critical_policy(top_used_cores) {
-                self.verify_freq(adjusted_freq);
-                let adjusted_freq = adjusted_freq.to_string();
-                file_handler.write_with_workround(self.max_freq_path(), &adjusted_freq)?;
-                file_handler.write_with_workround(self.min_freq_path(), &adjusted_freq)?;
+            let limit_min = if self.critical_policy(top_used_cores) {
+                adjusted_freq
             } else {
-                let adjusted_freq = adjusted_freq.to_string();
-                let min_freq = self
-                    .freqs
-                    .first()
-                    .context("No frequencies available")?
-                    .to_string();
-                file_handler.write_with_workround(self.min_freq_path(), &min_freq)?;
-                file_handler.write_with_workround(self.max_freq_path(), &adjusted_freq)?;
-            }
+                self.snap(cluster_policy.bound(min_freq))
+                    .clamp(min_freq, adjusted_freq)
+            };
+
+            backend.write_limits(
+                PolicyLimits {
+                    path: &self.path,
+                    cpus: &self.affected_cpus,
+                    min: limit_min,
+                    max: adjusted_freq,
+                    hw_max: max_freq,
+                },
+                file_handler,
+            );
+            self.written_limits = Some((limit_min, adjusted_freq));
+        } else {
+            // Someone else owns the policy meanwhile, there is nothing of ours to hold it to
+            self.written_limits = None;
         }
 
         Ok(())
     }
 
-    pub fn reset(&mut self, file_handler: &mut FileHandler) -> Result<()> {
-        let min_freq = self
-            .freqs
-            .first()
-            .context("No frequencies available")?
-            .to_string();
-        let max_freq = self
-            .freqs
-            .last()
-            .context("No frequencies available")?
-            .to_string();
-        self.verify_freq = None;
+    pub const fn written_limits(&self) -> Option<(isize, isize)> {
+        self.written_limits
+    }
+
+    // Writes the last limits again, after something else overwrote them
+    pub fn reassert(&self, backend: &mut dyn FreqBackend, file_handler: &mut FileHandler) {
+        let (Some((min, max)), Some(hw_max)) = (self.written_limits, self.freqs.last().copied())
+        else {
+            return;
+        };
+
+        backend.write_limits(
+            PolicyLimits {
+                path: &self.path,
+                cpus: &self.affected_cpus,
+                min,
+                max,
+                hw_max,
+            },
+            file_handler,
+        );
+    }
+
+    pub fn reset(
+        &mut self,
+        backend: &mut dyn FreqBackend,
+        file_handler: &mut FileHandler,
+    ) -> Result<()> {
+        let min_freq = *self.freqs.first().context("No frequencies available")?;
+        let max_freq = *self.freqs.last().context("No frequencies available")?;
+        self.written_limits = None;
+
+        backend.write_limits(
+            PolicyLimits {
+                path: &self.path,
+                cpus: &self.affected_cpus,
+                min: min_freq,
+                max: max_freq,
+                hw_max: max_freq,
+            },
+            file_handler,
+        );
 
-        file_handler.write_with_workround(self.max_freq_path(), &max_freq)?;
-        file_handler.write_with_workround(self.min_freq_path(), &min_freq)?;
         Ok(())
     }
 
+    pub fn affected_cpus(&self) -> &[usize] {
+        &self.affected_cpus
+    }
+
+    // Every core of the policy, offline ones included unlike `affected_cpus`
+    pub fn related_cpus(&self) -> Vec<usize> {
+        sysfs::read_to_string(self.path.join("related_cpus"))
+            .ok()
+            .map(|cpus| {
+                cpus.split_whitespace()
+                    .filter_map(|core| core.parse().ok())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|cpus| !cpus.is_empty())
+            .unwrap_or_else(|| self.affected_cpus.clone())
+    }
+
+    pub fn read_limits(&self) -> (isize, isize) {
+        let read = |path: PathBuf| {
+            sysfs::read_to_string(path)
+                .ok()
+                .and_then(|freq| freq.trim().parse::<isize>().ok())
+                .unwrap_or_default()
+        };
+
+        (read(self.min_freq_path()), read(self.max_freq_path()))
+    }
+
     pub fn read_freq(&self) -> isize {
-        fs::read_to_string(self.path.join("scaling_cur_freq"))
+        sysfs::read_to_string(self.path.join("scaling_cur_freq"))
             .context("Failed to read scaling_cur_freq")
             .unwrap()
             .trim()
@@ -188,7 +257,56 @@ impl Info {
             .unwrap()
     }
 
-    fn max_freq_path(&self) -> PathBuf {
+    // Residency per frequency since boot, in the kernel's 10ms units. Not every kernel has
+    // cpufreq stats
+    pub fn read_time_in_state(&self) -> Option<HashMap<isize, u64>> {
+        let content = sysfs::read_to_string(self.path.join("stats/time_in_state")).ok()?;
+
+        Some(
+            content
+                .lines()
+                .filter_map(|line| {
+                    let (freq, time) = line.split_once(char::is_whitespace)?;
+                    Some((freq.trim().parse().ok()?, time.trim().parse().ok()?))
+                })
+                .collect(),
+        )
+    }
+
+    pub fn set_governor(&mut self, governor: &str, file_handler: &mut FileHandler) -> Result<()> {
+        if self.saved_governor.is_none() {
+            let governor = sysfs::read_to_string(self.governor_path())
+                .context("Failed to read scaling_governor")?;
+            self.saved_governor = Some(governor.trim().to_string());
+        }
+
+        file_handler.write_with_workround(self.governor_path(), governor)?;
+        Ok(())
+    }
+
+    pub fn restore_governor(&mut self, file_handler: &mut FileHandler) -> Result<()> {
+        if let Some(governor) = self.saved_governor.take() {
+            file_handler.write_with_workround(self.governor_path(), governor)?;
+        }
+
+        Ok(())
+    }
+
+    // Tunables of the current governor, per policy or shared by all of them
+    pub fn governor_dir(&self) -> Option<PathBuf> {
+        let governor = sysfs::read_to_string(self.governor_path()).ok()?;
+        let governor = governor.trim();
+
+        [self.path.join(governor), self.path.parent()?.join(governor)]
+            .into_iter()
+            .find(|dir| sysfs::resolve(dir).is_dir())
+    }
+
+    fn governor_path(&self) -> PathBuf {
+        self.path.join("scaling_governor")
+    }
+
+    pub fn max_freq_path(&self) -> PathBuf {
         self.path.join("scaling_max_freq")
     }
 
@@ -0,0 +1,71 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use hashbrown::HashMap;
+
+use crate::sysfs;
+
+#[derive(Debug, Default)]
+pub struct CpuUsage {
+    last_times: HashMap<usize, (u64, u64)>,
+}
+
+impl CpuUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self) -> HashMap<usize, f64> {
+        let Ok(stat) = sysfs::read_to_string("/proc/stat") else {
+            return HashMap::new();
+        };
+
+        let mut usages = HashMap::new();
+
+        for line in stat.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(core) = fields
+                .next()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .and_then(|core| core.parse::<usize>().ok())
+            else {
+                continue;
+            };
+
+            let times: Vec<u64> = fields.filter_map(|t| t.parse().ok()).collect();
+            let total: u64 = times.iter().sum();
+            let idle = times.get(3).copied().unwrap_or_default()
+                + times.get(4).copied().unwrap_or_default();
+            let busy = total.saturating_sub(idle);
+
+            let (last_busy, last_total) = self
+                .last_times
+                .insert(core, (busy, total))
+                .unwrap_or_default();
+            let total_delta = total.saturating_sub(last_total);
+
+            if total_delta > 0 {
+                usages.insert(
+                    core,
+                    busy.saturating_sub(last_busy) as f64 / total_delta as f64,
+                );
+            }
+        }
+
+        usages
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+
+use super::topology::Topology;
+use crate::{file_handler::FileHandler, sysfs};
+
+const DMA_LATENCY: &str = "/dev/cpu_dma_latency";
+// The render thread rarely changes cluster, no need to look it up every frame
+const REFRESH_TIME: Duration = Duration::from_secs(1);
+
+// The cpu a thread last ran on
+fn last_cpu(tid: i32) -> Result<usize> {
+    let stat = sysfs::read_to_string(format!("/proc/{tid}/stat"))?;
+    // Fields after the `comm`, which may contain spaces, start from `state`
+    let (_, fields) = stat.rsplit_once(')').context("Malformed stat")?;
+    Ok(fields
+        .split_whitespace()
+        .nth(36)
+        .context("Missing processor in stat")?
+        .parse()?)
+}
+
+// Every state but the shallowest one (wfi), which can never be disabled
+fn deep_states(cpu: usize) -> Vec<PathBuf> {
+    let mut states: Vec<_> = sysfs::read_dir(format!("/sys/devices/system/cpu/cpu{cpu}/cpuidle"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("state"))
+                .and_then(|index| index.parse::<usize>().ok())
+                .is_some_and(|index| index > 0)
+        })
+        .collect();
+    states.sort();
+    states
+}
+
+// Keeps the cores of the clusters the render threads run on out of deep idle states
+#[derive(Debug, Default)]
+pub struct CpuIdle {
+    enabled: bool,
+    // Cores whose deep states are held back now
+    restricted: Vec<usize>,
+    // `disable` nodes written with their stock values
+    saved: Vec<(PathBuf, String)>,
+    // PM QoS request, the fallback where the per-state nodes cannot be written, lasts as long
+    // as the file is open
+    qos: Option<File>,
+    last_refresh: Option<Instant>,
+}
+
+impl CpuIdle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, file_handler: &mut FileHandler) {
+        if self.enabled && !enabled {
+            self.reset(file_handler);
+        }
+
+        self.enabled = enabled;
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn apply(
+        &mut self,
+        render_threads: impl Iterator<Item = i32>,
+        topology: &Topology,
+        throttled: bool,
+        file_handler: &mut FileHandler,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        // Idle states are what cools the cores down, thermal limits win
+        if throttled {
+            if !self.restricted.is_empty() {
+                info!("Thermal throttling, allowing deep idle states again");
+                self.reset(file_handler);
+            }
+            return;
+        }
+
+        if self
+            .last_refresh
+            .is_some_and(|last| last.elapsed() < REFRESH_TIME)
+        {
+            return;
+        }
+        self.last_refresh = Some(Instant::now());
+
+        let render_cpus: Vec<_> = render_threads
+            .filter_map(|tid| last_cpu(tid).ok())
+            .collect();
+        let mut cores: Vec<_> = topology
+            .clusters()
+            .iter()
+            .filter(|cluster| cluster.cpus.iter().any(|cpu| render_cpus.contains(cpu)))
+            .flat_map(|cluster| cluster.cpus.iter().copied())
+            .collect();
+        cores.sort_unstable();
+        cores.dedup();
+
+        if cores.is_empty() || cores == self.restricted {
+            return;
+        }
+
+        self.reset(file_handler);
+        self.restrict(&cores, file_handler);
+        self.restricted = cores;
+    }
+
+    fn restrict(&mut self, cores: &[usize], file_handler: &mut FileHandler) {
+        let mut shallowest_latency = None;
+
+        for state in cores.iter().flat_map(|cpu| deep_states(*cpu)) {
+            if let Some(latency) = sysfs::read_to_string(state.join("latency"))
+                .ok()
+                .and_then(|latency| latency.trim().parse::<i32>().ok())
+            {
+                shallowest_latency =
+                    Some(shallowest_latency.map_or(latency, |min: i32| min.min(latency)));
+            }
+
+            let disable = state.join("disable");
+            let Ok(stock) = sysfs::read_to_string(&disable) else {
+                continue;
+            };
+
+            if file_handler.write_with_workround(&disable, "1").is_ok() {
+                self.saved.push((disable, stock.trim().to_string()));
+            }
+        }
+
+        if !self.saved.is_empty() {
+            return;
+        }
+
+        // PM QoS holds every core, not only the cluster, so it is the last resort
+        let Some(latency) = shallowest_latency else {
+            return;
+        };
+        match Self::request_latency(latency.saturating_sub(1).max(0)) {
+            Ok(qos) => self.qos = Some(qos),
+            Err(e) => warn!("Failed to restrict cpu idle states: {e:?}"),
+        }
+    }
+
+    fn request_latency(latency: i32) -> Result<File> {
+        let mut qos = OpenOptions::new()
+            .write(true)
+            .open(sysfs::resolve(DMA_LATENCY))?;
+        qos.write_all(&latency.to_ne_bytes())?;
+        Ok(qos)
+    }
+
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        for (disable, stock) in self.saved.drain(..).rev() {
+            let _ = file_handler.write_with_workround(disable, stock);
+        }
+
+        self.qos = None;
+        self.restricted.clear();
+        self.last_refresh = None;
+    }
+}
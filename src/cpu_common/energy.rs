@@ -0,0 +1,263 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use hashbrown::HashMap;
+use log::info;
+
+use super::{cluster_policy::cluster_name, cpu_usage::CpuUsage};
+use crate::{paths, sysfs};
+
+const ENERGY_MODEL_PATH: &str = "/sys/kernel/debug/energy_model";
+// time_in_state counts in USER_HZ ticks
+const TICKS_PER_SEC: f64 = 100.0;
+// Newer kernels report the energy model in uW, no phone core draws this many mW
+const MICROWATT_THRESHOLD: f64 = 100_000.0;
+
+#[derive(Debug)]
+struct PolicyEnergy {
+    path: PathBuf,
+    cpus: Vec<usize>,
+    // mW of one busy core per frequency in kHz, sorted by frequency
+    power: Vec<(isize, f64)>,
+    time_in_state: Option<HashMap<isize, u64>>,
+}
+
+impl PolicyEnergy {
+    // Power of the nearest table entry at or above `freq`
+    fn power_at(&self, freq: isize) -> f64 {
+        self.power
+            .iter()
+            .find(|(opp, _)| *opp >= freq)
+            .or_else(|| self.power.last())
+            .map(|(_, power)| *power)
+            .unwrap_or_default()
+    }
+
+    // Seconds spent per frequency since the last call, None without time_in_state
+    fn residency(&mut self) -> Option<Vec<(isize, f64)>> {
+        let current = read_time_in_state(&self.path)?;
+        let residency = self.time_in_state.as_ref().map_or_else(Vec::new, |last| {
+            current
+                .iter()
+                .map(|(freq, ticks)| {
+                    let delta = ticks.saturating_sub(last.get(freq).copied().unwrap_or(*ticks));
+                    (*freq, delta as f64 / TICKS_PER_SEC)
+                })
+                .collect()
+        });
+
+        self.time_in_state = Some(current);
+        Some(residency)
+    }
+}
+
+// Estimates cpu energy as frequency residency x per-core power x busy cores, per cluster.
+// Power comes from the kernel energy model when debugfs exposes it, else from a rough table
+#[derive(Debug)]
+pub struct EnergyModel {
+    policies: Vec<PolicyEnergy>,
+    cpu_usage: CpuUsage,
+    sample_timer: Instant,
+}
+
+impl EnergyModel {
+    pub fn new() -> Self {
+        let mut policies: Vec<_> = sysfs::read_dir(paths::cpufreq())
+            .into_iter()
+            .flatten()
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("policy"))
+            })
+            .filter_map(|path| {
+                let cpus = read_cpus(&path.join("affected_cpus"))?;
+                let freqs: Vec<isize> =
+                    sysfs::read_to_string(path.join("scaling_available_frequencies"))
+                        .ok()?
+                        .split_whitespace()
+                        .filter_map(|freq| freq.parse().ok())
+                        .collect();
+                Some((path, cpus, freqs))
+            })
+            .map(|(path, cpus, mut freqs)| {
+                freqs.sort_unstable();
+                PolicyEnergy {
+                    path,
+                    cpus,
+                    power: freqs.into_iter().map(|freq| (freq, 0.0)).collect(),
+                    time_in_state: None,
+                }
+            })
+            .collect();
+        policies.sort_by_key(|policy| policy.cpus.first().copied());
+
+        let count = policies.len();
+        let mut from_kernel = true;
+        for (index, policy) in policies.iter_mut().enumerate() {
+            if let Some(power) = policy.cpus.first().and_then(|cpu| read_energy_model(*cpu)) {
+                policy.power = power;
+            } else {
+                from_kernel = false;
+                policy.power = fallback_power(&policy.power, cluster_name(index, count));
+            }
+        }
+
+        info!(
+            "Energy model: {}",
+            if from_kernel {
+                "kernel energy model"
+            } else {
+                "built-in estimate"
+            }
+        );
+
+        Self {
+            policies,
+            cpu_usage: CpuUsage::new(),
+            sample_timer: Instant::now(),
+        }
+    }
+
+    // Estimated mWh since the last call, the first call only sets the baseline
+    pub fn sample(&mut self) -> f64 {
+        let elapsed = self.sample_timer.elapsed().as_secs_f64();
+        self.sample_timer = Instant::now();
+        let usages = self.cpu_usage.update();
+
+        let mut energy_mj = 0.0;
+        for policy in &mut self.policies {
+            let busy_cores: f64 = policy.cpus.iter().filter_map(|cpu| usages.get(cpu)).sum();
+
+            // Without time_in_state the whole interval is charged to the current frequency
+            let residency = policy.residency().unwrap_or_else(|| {
+                read_freq(&policy.path)
+                    .map(|freq| vec![(freq, elapsed)])
+                    .unwrap_or_default()
+            });
+
+            energy_mj += residency
+                .iter()
+                .map(|(freq, secs)| policy.power_at(*freq) * secs)
+                .sum::<f64>()
+                * busy_cores;
+        }
+
+        energy_mj / 3600.0
+    }
+}
+
+// Dynamic power goes roughly with f * V^2 and V with f, so the table is cubic in frequency
+fn fallback_power(freqs: &[(isize, f64)], cluster: &str) -> Vec<(isize, f64)> {
+    let max_power = match cluster {
+        "little" => 250.0,
+        "prime" => 2000.0,
+        _ => 1000.0,
+    };
+    let max_freq = freqs.last().map_or(1, |(freq, _)| (*freq).max(1)) as f64;
+
+    freqs
+        .iter()
+        .map(|(freq, _)| (*freq, max_power * (*freq as f64 / max_freq).powi(3)))
+        .collect()
+}
+
+// Perf domains are named after their first cpu on recent kernels and `pdN` on older ones,
+// the `cpus` file inside tells which one covers the policy
+fn read_energy_model(first_cpu: usize) -> Option<Vec<(isize, f64)>> {
+    let domain = sysfs::read_dir(ENERGY_MODEL_PATH)
+        .ok()?
+        .into_iter()
+        .find(|path| {
+            sysfs::read_to_string(path.join("cpus")).is_ok_and(|cpus| {
+                cpus.split([',', '-'])
+                    .next()
+                    .and_then(|cpu| cpu.trim().parse::<usize>().ok())
+                    == Some(first_cpu)
+            })
+        })?;
+
+    let mut power: Vec<(isize, f64)> = sysfs::read_dir(domain)
+        .ok()?
+        .into_iter()
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("ps:"))
+        })
+        .filter_map(|state| {
+            let read = |node: &str| sysfs::read_to_string(state.join(node)).ok();
+            Some((
+                read("frequency")?.trim().parse().ok()?,
+                read("power")?.trim().parse().ok()?,
+            ))
+        })
+        .collect();
+
+    if power.is_empty() {
+        return None;
+    }
+
+    power.sort_unstable_by_key(|(freq, _)| *freq);
+    if power
+        .last()
+        .is_some_and(|(_, max_power)| *max_power > MICROWATT_THRESHOLD)
+    {
+        for (_, power) in &mut power {
+            *power /= 1000.0;
+        }
+    }
+
+    Some(power)
+}
+
+fn read_time_in_state(policy: &Path) -> Option<HashMap<isize, u64>> {
+    let content = sysfs::read_to_string(policy.join("stats/time_in_state")).ok()?;
+
+    let time_in_state: HashMap<_, _> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+        })
+        .collect();
+
+    (!time_in_state.is_empty()).then_some(time_in_state)
+}
+
+fn read_freq(policy: &Path) -> Option<isize> {
+    sysfs::read_to_string(policy.join("scaling_cur_freq"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn read_cpus(path: &Path) -> Option<Vec<usize>> {
+    let cpus: Vec<_> = sysfs::read_to_string(path)
+        .ok()?
+        .split_whitespace()
+        .filter_map(|cpu| cpu.parse().ok())
+        .collect();
+
+    (!cpus.is_empty()).then_some(cpus)
+}
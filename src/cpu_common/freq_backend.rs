@@ -0,0 +1,249 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt::Debug,
+    fs::OpenOptions,
+    path::Path,
+    process::{Command, Stdio},
+    sync::OnceLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{file_handler::FileHandler, sysfs};
+
+const MSM_PERFORMANCE_MAX: &str = "/sys/module/msm_performance/parameters/cpu_max_freq";
+const MSM_PERFORMANCE_MIN: &str = "/sys/module/msm_performance/parameters/cpu_min_freq";
+// Fraction of the hardware max above which PowerHAL is held in fixed performance mode, and
+// below which it is released again
+const POWER_HAL_HIGH: f64 = 0.85;
+const POWER_HAL_LOW: f64 = 0.5;
+const POWER_HAL_HOLD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreqBackendKind {
+    Auto,
+    Sysfs,
+    MsmPerformance,
+    PowerHal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PolicyLimits<'a> {
+    pub path: &'a Path,
+    pub cpus: &'a [usize],
+    pub min: isize,
+    pub max: isize,
+    pub hw_max: isize,
+}
+
+pub trait FreqBackend: Debug + Send {
+    fn name(&self) -> &'static str;
+    fn write_limits(&mut self, limits: PolicyLimits, file_handler: &mut FileHandler);
+    // Called once all policies of a decision have been written
+    fn flush(&mut self) {}
+    fn reset(&mut self) {}
+    // Whether the kernel should end up with the written limits, which `LimitGuard` checks
+    fn enforces_limits(&self) -> bool {
+        true
+    }
+}
+
+// Pick automatically prefers msm_performance, it arbitrates with the vendor perf stack
+// instead of racing it on scaling_max_freq. PowerHAL hints are coarse and never auto picked.
+// Some kernels keep the parameters of a stubbed out module, so both nodes have to open for
+// writing and the max has to read back as `cpu:freq` pairs
+pub fn msm_performance_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+
+    *AVAILABLE.get_or_init(|| {
+        let writable = [MSM_PERFORMANCE_MAX, MSM_PERFORMANCE_MIN]
+            .iter()
+            .all(|node| {
+                OpenOptions::new()
+                    .write(true)
+                    .open(sysfs::resolve(node))
+                    .is_ok()
+            });
+        let functional = sysfs::read_to_string(MSM_PERFORMANCE_MAX).is_ok_and(|content| {
+            content.split_whitespace().any(|pair| {
+                pair.split_once(':').is_some_and(|(cpu, freq)| {
+                    cpu.parse::<usize>().is_ok() && freq.parse::<u64>().is_ok()
+                })
+            })
+        });
+
+        writable && functional
+    })
+}
+
+pub fn new_backend(kind: FreqBackendKind) -> Box<dyn FreqBackend> {
+    let backend: Box<dyn FreqBackend> = match kind {
+        FreqBackendKind::Auto if msm_performance_available() => Box::new(MsmPerformance),
+        FreqBackendKind::Auto | FreqBackendKind::Sysfs => Box::new(Sysfs),
+        FreqBackendKind::MsmPerformance => Box::new(MsmPerformance),
+        FreqBackendKind::PowerHal => Box::new(PowerHal::default()),
+    };
+
+    info!("Frequency limit backend: {}", backend.name());
+    backend
+}
+
+#[derive(Debug)]
+pub struct Sysfs;
+
+impl FreqBackend for Sysfs {
+    fn name(&self) -> &'static str {
+        "sysfs"
+    }
+
+    // Raising a pinned policy has to move max first, lowering a floor has to move min first
+    fn write_limits(&mut self, limits: PolicyLimits, file_handler: &mut FileHandler) {
+        let max_path = limits.path.join("scaling_max_freq");
+        let min_path = limits.path.join("scaling_min_freq");
+
+        if limits.min == limits.max {
+            file_handler.queue(max_path, limits.max.to_string());
+            file_handler.queue(min_path, limits.min.to_string());
+        } else {
+            file_handler.queue(min_path, limits.min.to_string());
+            file_handler.queue(max_path, limits.max.to_string());
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MsmPerformance;
+
+impl MsmPerformance {
+    fn format(cpus: &[usize], freq: isize) -> String {
+        cpus.iter()
+            .map(|cpu| format!("{cpu}:{freq}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl FreqBackend for MsmPerformance {
+    fn name(&self) -> &'static str {
+        "msm_performance"
+    }
+
+    fn write_limits(&mut self, limits: PolicyLimits, file_handler: &mut FileHandler) {
+        let max = Self::format(limits.cpus, limits.max);
+        let min = Self::format(limits.cpus, limits.min);
+
+        if limits.min == limits.max {
+            file_handler.queue(MSM_PERFORMANCE_MAX, max);
+            file_handler.queue(MSM_PERFORMANCE_MIN, min);
+        } else {
+            file_handler.queue(MSM_PERFORMANCE_MIN, min);
+            file_handler.queue(MSM_PERFORMANCE_MAX, max);
+        }
+    }
+}
+
+// Leaves cpufreq alone and only tells PowerHAL which way the decisions lean, through its
+// FIXED_PERFORMANCE mode. `cmd power set-mode` is Battery Saver, not a PowerHAL hint, and there
+// is no shell path to the HAL's LOW_POWER mode, so low demand only releases the hint
+#[derive(Debug, Default)]
+pub struct PowerHal {
+    demand: f64,
+    fixed_performance: bool,
+    last_switch: Option<Instant>,
+}
+
+impl PowerHal {
+    // `cmd` takes a binder round trip to start, it is waited for off the looper thread
+    fn send(fixed_performance: bool) {
+        let spawned = thread::Builder::new()
+            .name("PowerHalHint".into())
+            .spawn(move || {
+                let status = Command::new("cmd")
+                    .args([
+                        "power",
+                        "set-fixed-performance-mode-enabled",
+                        if fixed_performance { "true" } else { "false" },
+                    ])
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status();
+
+                match status {
+                    Ok(status) if status.success() => (),
+                    Ok(status) => warn!("cmd power set-fixed-performance-mode-enabled: {status}"),
+                    Err(e) => warn!("Failed to run cmd power: {e}"),
+                }
+            });
+
+        if let Err(e) = spawned {
+            warn!("Failed to send PowerHAL hint: {e}");
+        }
+    }
+}
+
+impl FreqBackend for PowerHal {
+    fn name(&self) -> &'static str {
+        "power_hal"
+    }
+
+    fn enforces_limits(&self) -> bool {
+        false
+    }
+
+    fn write_limits(&mut self, limits: PolicyLimits, _: &mut FileHandler) {
+        let demand = limits.max as f64 / limits.hw_max.max(1) as f64;
+        self.demand = self.demand.max(demand);
+    }
+
+    fn flush(&mut self) {
+        // In between the two the current hint is kept
+        let fixed_performance = if self.demand >= POWER_HAL_HIGH {
+            true
+        } else if self.demand <= POWER_HAL_LOW {
+            false
+        } else {
+            self.fixed_performance
+        };
+        self.demand = 0.0;
+
+        // Decisions change every frame, hold a hint for a while so `cmd` is not run per frame
+        if fixed_performance != self.fixed_performance
+            && self
+                .last_switch
+                .is_none_or(|last_switch| last_switch.elapsed() >= POWER_HAL_HOLD)
+        {
+            self.fixed_performance = fixed_performance;
+            self.last_switch = Some(Instant::now());
+            Self::send(fixed_performance);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.demand = 0.0;
+        self.last_switch = None;
+        if self.fixed_performance {
+            self.fixed_performance = false;
+            Self::send(false);
+        }
+    }
+}
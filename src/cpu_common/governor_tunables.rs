@@ -0,0 +1,90 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use hashbrown::HashMap;
+use log::{info, warn};
+
+use super::{cpu_info::Info, topology::Topology};
+use crate::{file_handler::FileHandler, sysfs};
+
+// Writes governor tunables (schedutil's rate limits, walt's hispeed_freq, ...) of each cluster
+// while a game runs and puts the stock values back afterwards. Tunables are looked up in the
+// directory of whatever governor the policy runs at that moment, so they follow `governor`
+#[derive(Debug, Default)]
+pub struct GovernorTunables {
+    // Cluster name to tunable name and value
+    rules: HashMap<String, Vec<(String, String)>>,
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl GovernorTunables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_rules(&mut self, rules: HashMap<String, Vec<(String, String)>>) {
+        self.rules = rules;
+    }
+
+    pub fn apply(&mut self, cpus: &[Info], topology: &Topology, file_handler: &mut FileHandler) {
+        self.restore(file_handler);
+
+        for cpu in cpus {
+            let Some(rules) = self.rules.get(topology.name(cpu.policy)) else {
+                continue;
+            };
+            let Some(dir) = cpu.governor_dir() else {
+                warn!("No governor tunables directory for policy{}", cpu.policy);
+                continue;
+            };
+
+            for (name, value) in rules {
+                let path = dir.join(name);
+                // Global governor tunables are shared, a second cluster must not save ours
+                if self.saved.iter().any(|(saved, _)| *saved == path) {
+                    continue;
+                }
+
+                let Ok(stock) = sysfs::read_to_string(&path) else {
+                    warn!("Governor tunable {} does not exist", path.display());
+                    continue;
+                };
+
+                match file_handler.write_with_workround(&path, value) {
+                    Ok(()) => {
+                        info!(
+                            "Governor tunable {}: {} -> {value}",
+                            path.display(),
+                            stock.trim()
+                        );
+                        self.saved.push((path, stock.trim().to_string()));
+                    }
+                    Err(e) => warn!("Failed to write governor tunable {}: {e:?}", path.display()),
+                }
+            }
+        }
+    }
+
+    // Restored in reverse, a tunable validated against another one gets its order back
+    pub fn restore(&mut self, file_handler: &mut FileHandler) {
+        while let Some((path, stock)) = self.saved.pop() {
+            let _ = file_handler.write_with_workround(path, stock);
+        }
+    }
+}
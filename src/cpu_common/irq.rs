@@ -0,0 +1,110 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::{file_handler::FileHandler, misc::glob_match, sysfs};
+
+const INTERRUPTS_PATH: &str = "/proc/interrupts";
+const IRQ_PATH: &str = "/proc/irq";
+
+// Irq numbers and names in /proc/interrupts, the name is the last column. Ipis and other
+// architecture rows have no number and cannot be moved anyway
+fn interrupts() -> Vec<(u32, String)> {
+    sysfs::read_to_string(INTERRUPTS_PATH)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (irq, rest) = line.split_once(':')?;
+            let irq = irq.trim().parse().ok()?;
+            let name = rest.split_whitespace().last()?;
+            Some((irq, name.to_string()))
+        })
+        .collect()
+}
+
+// Moves the touchscreen, gpu and display interrupts to `cpus` while a game runs, so that
+// interrupt storms stop preempting the render thread on the cores it runs on. The stock
+// affinities come back when the game exits
+#[derive(Debug, Default)]
+pub struct IrqSteering {
+    patterns: Vec<String>,
+    cpus: Vec<u32>,
+    saved: Vec<(PathBuf, String)>,
+}
+
+impl IrqSteering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Nothing is moved while `cpus` is empty
+    pub fn set_rules(&mut self, patterns: Vec<String>, cpus: Vec<u32>) {
+        self.patterns = patterns;
+        self.cpus = cpus;
+    }
+
+    pub fn apply(&mut self, file_handler: &mut FileHandler) {
+        self.restore(file_handler);
+
+        if self.cpus.is_empty() {
+            return;
+        }
+
+        let affinity = self
+            .cpus
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        for (irq, name) in interrupts() {
+            let lower = name.to_lowercase();
+            if !self
+                .patterns
+                .iter()
+                .any(|pattern| glob_match(pattern.to_lowercase().as_bytes(), lower.as_bytes()))
+            {
+                continue;
+            }
+
+            let path = PathBuf::from(IRQ_PATH)
+                .join(irq.to_string())
+                .join("smp_affinity_list");
+            let Ok(stock) = sysfs::read_to_string(&path) else {
+                continue;
+            };
+
+            // Per-cpu and kernel managed interrupts refuse any affinity
+            match file_handler.write_with_workround(&path, &affinity) {
+                Ok(()) => {
+                    info!("irq {irq} ({name}): cpu {} -> {affinity}", stock.trim());
+                    self.saved.push((path, stock.trim().to_string()));
+                }
+                Err(e) => warn!("Failed to move irq {irq} ({name}): {e}"),
+            }
+        }
+    }
+
+    pub fn restore(&mut self, file_handler: &mut FileHandler) {
+        while let Some((path, stock)) = self.saved.pop() {
+            let _ = file_handler.write_with_workround(path, stock);
+        }
+    }
+}
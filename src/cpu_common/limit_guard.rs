@@ -0,0 +1,225 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::cpu_info::Info;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+// Share of a window a policy may spend above its cap, our own raises reach the kernel a little
+// late and boost frameworks hold a policy up for a moment
+const MAX_OVER_CAP_SHARE: f64 = 0.2;
+// Failed checks in a row before the guard asks for another backend
+const ESCALATE_AFTER: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitGuardMode {
+    Off,
+    // Only warn
+    Log,
+    // Also write the limits again right away
+    #[default]
+    Reassert,
+    // Also move off sysfs to msm_performance when the limits keep getting overwritten
+    Escalate,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Violation {
+    // scaling_max_freq reads above anything we wrote, another writer replaced it
+    Overwritten {
+        policy: i32,
+        cap: isize,
+        read: isize,
+    },
+    // The limit reads right but the policy still ran above it
+    NotHonored {
+        policy: i32,
+        cap: isize,
+        share: f64,
+    },
+}
+
+impl Violation {
+    pub const fn policy(&self) -> i32 {
+        match self {
+            Self::Overwritten { policy, .. } | Self::NotHonored { policy, .. } => *policy,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overwritten { policy, cap, read } => write!(
+                f,
+                "policy{policy}: max limit {cap} was overwritten with {read}"
+            ),
+            Self::NotHonored { policy, cap, share } => write!(
+                f,
+                "policy{policy}: ran above its {cap} cap {:.0}% of the time",
+                share * 100.0
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PolicyState {
+    // Highest max limit written this window, the writer thread may lag one decision behind
+    cap: Option<isize>,
+    time_in_state: Option<HashMap<isize, u64>>,
+    failures: u32,
+}
+
+// Checks every few seconds that the kernel keeps the limits we wrote, vendor daemons like perfd
+// or mi_thermald tend to write scaling_max_freq right after us
+#[derive(Debug)]
+pub struct LimitGuard {
+    policies: HashMap<i32, PolicyState>,
+    last_check: Instant,
+}
+
+impl LimitGuard {
+    pub fn new() -> Self {
+        Self {
+            policies: HashMap::new(),
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.policies.clear();
+        self.last_check = Instant::now();
+    }
+
+    // Called after every decision, returns what went wrong once a window is over
+    pub fn check(&mut self, cpus: &[Info]) -> Vec<Violation> {
+        for cpu in cpus {
+            if let Some((_, max)) = cpu.written_limits() {
+                let state = self.policies.entry(cpu.policy).or_default();
+                state.cap = Some(state.cap.map_or(max, |cap| cap.max(max)));
+            }
+        }
+
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+
+        let mut violations = Vec::new();
+        for cpu in cpus {
+            let Some(state) = self.policies.get_mut(&cpu.policy) else {
+                continue;
+            };
+            let Some(cap) = state.cap.take() else {
+                continue;
+            };
+
+            let violation = Self::check_policy(cpu, cap, state);
+            if let Some(violation) = violation {
+                state.failures += 1;
+                violations.push(violation);
+            } else {
+                state.failures = 0;
+            }
+
+            // The next window starts from what is in force now
+            state.cap = cpu.written_limits().map(|(_, max)| max);
+        }
+
+        violations
+    }
+
+    // Policies whose last window failed
+    pub fn failing(&self) -> impl Iterator<Item = i32> + '_ {
+        self.policies
+            .iter()
+            .filter(|(_, state)| state.failures > 0)
+            .map(|(policy, _)| *policy)
+    }
+
+    pub fn persistent(&self) -> bool {
+        self.policies
+            .values()
+            .any(|state| state.failures >= ESCALATE_AFTER)
+    }
+
+    fn check_policy(cpu: &Info, cap: isize, state: &mut PolicyState) -> Option<Violation> {
+        let (_, read) = cpu.read_limits();
+        if read > cap {
+            state.time_in_state = cpu.read_time_in_state();
+            return Some(Violation::Overwritten {
+                policy: cpu.policy,
+                cap,
+                read,
+            });
+        }
+
+        let share = match (cpu.read_time_in_state(), state.time_in_state.take()) {
+            (Some(current), previous) => {
+                let share = previous.map(|previous| over_cap_share(&current, &previous, cap));
+                state.time_in_state = Some(current);
+                share?
+            }
+            // Without cpufreq stats a single sample is all there is
+            (None, _) => {
+                if cpu.read_freq() > cap {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+
+        (share > MAX_OVER_CAP_SHARE).then_some(Violation::NotHonored {
+            policy: cpu.policy,
+            cap,
+            share,
+        })
+    }
+}
+
+fn over_cap_share(
+    current: &HashMap<isize, u64>,
+    previous: &HashMap<isize, u64>,
+    cap: isize,
+) -> f64 {
+    let mut total = 0;
+    let mut over = 0;
+
+    for (freq, time) in current {
+        let delta = time.saturating_sub(previous.get(freq).copied().unwrap_or_default());
+        total += delta;
+        if *freq > cap {
+            over += delta;
+        }
+    }
+
+    if total == 0 {
+        0.0
+    } else {
+        over as f64 / total as f64
+    }
+}
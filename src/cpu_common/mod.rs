@@ -15,11 +15,32 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod affinity;
+mod bus_boost;
+mod cadence;
+mod cluster_policy;
 mod cpu_info;
+mod cpu_usage;
+mod cpuidle;
+mod energy;
 pub mod extra_policy;
+mod freq_backend;
+mod governor_tunables;
+mod irq;
+mod limit_guard;
 mod process_monitor;
+mod sched_priority;
+pub mod snapshot;
+mod strategy;
+mod task_dir;
+#[cfg(test)]
+mod tests;
+mod thread_affinity;
+mod topology;
+mod uclamp;
+mod util_history;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use hashbrown::HashMap;
 #[cfg(debug_assertions)]
 use log::debug;
@@ -30,37 +51,110 @@ use nix::{
 };
 use parking_lot::Mutex;
 use process_monitor::ProcessMonitor;
+use serde::{Deserialize, Serialize};
 use std::{
-    fs,
     path::Path,
     sync::{OnceLock, atomic::AtomicBool},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
     Extension,
     api::{trigger_init_cpu_freq, trigger_reset_cpu_freq},
+    error::{Degradation, ErrorClass, NodeError},
     file_handler::FileHandler,
+    framework::{Heartbeat, ModePreset},
+    paths, sysfs,
 };
+use affinity::Affinity;
+use bus_boost::BusBoost;
+pub use cadence::Cadence;
+pub use cluster_policy::ClusterPolicy;
 use cpu_info::Info;
+use cpu_usage::CpuUsage;
+use cpuidle::CpuIdle;
+pub use energy::EnergyModel;
 use extra_policy::ExtraPolicy;
+pub use freq_backend::FreqBackendKind;
+use freq_backend::{FreqBackend, msm_performance_available, new_backend};
+use governor_tunables::GovernorTunables;
+use irq::IrqSteering;
+use limit_guard::LimitGuard;
+pub use limit_guard::LimitGuardMode;
+pub use sched_priority::RenderPriority;
+use sched_priority::SchedPriority;
+pub use strategy::{LadderConfig, StrategyKind};
+use strategy::{Strategy, StrategyInput, new_strategy};
+pub use thread_affinity::AffinityRule;
+use thread_affinity::ThreadAffinity;
+pub use topology::ClusterTopology;
+use topology::Topology;
+use uclamp::Uclamp;
+pub use uclamp::UclampScope;
+pub use util_history::UtilSmoothing;
 
 pub static EXTRA_POLICY_MAP: OnceLock<HashMap<i32, Mutex<ExtraPolicy>>> = OnceLock::new();
 pub static IGNORE_MAP: OnceLock<HashMap<i32, AtomicBool>> = OnceLock::new();
 
+// Seconds a policy gets to become readable at startup
+const LOAD_RETRIES: u32 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterStat {
+    #[serde(flatten)]
+    pub topology: ClusterTopology,
+    pub cur_freq: isize,
+    pub min_limit: isize,
+    pub max_limit: isize,
+    pub util: f64,
+}
+
 #[derive(Debug)]
 pub struct Controller {
     max_freq: isize,
     cpu_infos: Vec<Info>,
+    topology: Topology,
     file_handler: FileHandler,
+    freq_backend: Box<dyn FreqBackend>,
+    freq_backend_kind: FreqBackendKind,
+    limit_guard: LimitGuard,
+    limit_guard_mode: LimitGuardMode,
     process_monitor: ProcessMonitor,
+    cpu_usage: CpuUsage,
+    uclamp: Uclamp,
+    bus_boost: BusBoost,
+    affinity: Affinity,
+    cpuidle: CpuIdle,
+    sched_priority: SchedPriority,
+    thread_affinity: ThreadAffinity,
+    governor_tunables: GovernorTunables,
+    irq_steering: IrqSteering,
     util_max: Option<f64>,
+    util_history: UtilHistory,
+    strategy: Box<dyn Strategy>,
+    strategy_kind: StrategyKind,
+    ladder: LadderConfig,
+    battery_powersave: bool,
+    freq_cap: f64,
+    // A policy got the most it may in the last update, more control could not raise it
+    at_ceiling: bool,
+    mode_preset: ModePreset,
+    touch_boost: Option<(Instant, f64)>,
+    // Policies of the render thread's cluster with when the burst started and how long it holds
+    jank_burst: Option<(Instant, Duration, Vec<i32>)>,
+    launch_boost: Option<Instant>,
+    cluster_policies: HashMap<i32, ClusterPolicy>,
+    // Policies left out at startup, the rest are controlled as usual
+    unavailable: Vec<Degradation>,
 }
 
 impl Controller {
     pub fn new() -> Result<Self> {
-        let mut cpu_infos = Self::load_cpu_infos()?;
+        let (mut cpu_infos, unavailable) = Self::load_cpu_infos()?;
+        if cpu_infos.is_empty() {
+            bail!("No usable cpufreq policy");
+        }
         cpu_infos.sort_by_key(|cpu| cpu.policy);
 
         EXTRA_POLICY_MAP.get_or_init(|| {
@@ -86,28 +180,52 @@ impl Controller {
             .copied()
             .unwrap_or(0);
 
+        let topology = Topology::detect(&mut cpu_infos);
+        let (little_cores, big_cores) = topology.split_little();
+        let affinity = Affinity::new(&little_cores, &big_cores);
+
         Ok(Self {
             max_freq,
             cpu_infos,
+            topology,
             file_handler: FileHandler::new(),
+            freq_backend: new_backend(FreqBackendKind::Auto),
+            freq_backend_kind: FreqBackendKind::Auto,
+            limit_guard: LimitGuard::new(),
+            limit_guard_mode: LimitGuardMode::default(),
             process_monitor: ProcessMonitor::new(),
+            cpu_usage: CpuUsage::new(),
+            uclamp: Uclamp::new(),
+            bus_boost: BusBoost::new(),
+            affinity,
+            cpuidle: CpuIdle::new(),
+            sched_priority: SchedPriority::new(),
+            thread_affinity: ThreadAffinity::new(),
+            governor_tunables: GovernorTunables::new(),
+            irq_steering: IrqSteering::new(),
             util_max: None,
+            util_history: UtilHistory::new(),
+            strategy: new_strategy(StrategyKind::Heuristic, LadderConfig::default()),
+            strategy_kind: StrategyKind::Heuristic,
+            ladder: LadderConfig::default(),
+            battery_powersave: false,
+            freq_cap: 1.0,
+            at_ceiling: false,
+            mode_preset: ModePreset::default(),
+            touch_boost: None,
+            jank_burst: None,
+            launch_boost: None,
+            cluster_policies: HashMap::new(),
+            unavailable,
         })
     }
 
-    fn load_cpu_infos() -> Result<Vec<Info>> {
+    fn load_cpu_infos() -> Result<(Vec<Info>, Vec<Degradation>)> {
         let mut cpu_infos = Vec::new();
+        let mut unavailable = Vec::new();
 
-        for entry in fs::read_dir("/sys/devices/system/cpu/cpufreq")? {
-            let path = match entry {
-                Ok(entry) => entry.path(),
-                Err(e) => {
-                    warn!("Failed to read entry: {e:?}");
-                    continue;
-                }
-            };
-
-            if !path.is_dir() {
+        for path in sysfs::read_dir(paths::cpufreq())? {
+            if !sysfs::resolve(&path).is_dir() {
                 continue;
             }
 
@@ -119,50 +237,124 @@ impl Controller {
                 continue;
             }
 
-            cpu_infos.push(Self::retry_load_info(&path));
+            match Self::retry_load_info(&path) {
+                Ok(info) => cpu_infos.push(info),
+                Err(e) => {
+                    let error = NodeError::classify(&path, &e);
+                    warn!("Leaving {filename} alone, {error}");
+                    unavailable.push(Degradation {
+                        class: error.class(),
+                        node: error.path().to_path_buf(),
+                        feature: format!("{filename} frequency control"),
+                    });
+                }
+            }
         }
 
-        Ok(cpu_infos)
+        Ok((cpu_infos, unavailable))
     }
 
-    fn retry_load_info(path: &Path) -> Info {
+    // Nodes may still be coming up early in boot, a policy that never reads is left out
+    fn retry_load_info(path: &Path) -> Result<Info> {
+        let mut retries = 0;
         loop {
             match Info::new(path) {
-                Ok(info) => return info,
-                Err(e) => {
+                Ok(info) => return Ok(info),
+                Err(e) if retries < LOAD_RETRIES => {
                     warn!(
                         "Failed to read cpu info from: {}, reason: {e:?}",
                         path.display()
                     );
                     warn!("Retrying...");
+                    retries += 1;
                     thread::sleep(Duration::from_secs(1));
                 }
+                Err(e) => return Err(e),
             }
         }
     }
 
-    pub fn init_game(&mut self, pid: i32, extension: &Extension) {
+    pub fn init_game(&mut self, pid: i32, governor: Option<&str>, extension: &Extension) {
         trigger_init_cpu_freq(extension);
+        self.apply_governor(governor);
+        self.governor_tunables
+            .apply(&self.cpu_infos, &self.topology, &mut self.file_handler);
+        self.irq_steering.apply(&mut self.file_handler);
         self.reset_all_cpu_freq();
+        if self.launch_boost.is_some() {
+            self.write_max_freq();
+        }
+        self.sched_priority.reset();
+        self.thread_affinity.set_pid(Some(pid));
         self.process_monitor.set_pid(Some(pid));
         self.util_max = None;
+        self.util_history.clear();
+        self.strategy.reset();
+        self.jank_burst = None;
+        self.at_ceiling = false;
+    }
+
+    // The game restarted its process, only the per-process state follows it
+    pub fn rebind_game(&mut self, pid: i32) {
+        self.sched_priority.reset();
+        self.thread_affinity.set_pid(Some(pid));
+        self.process_monitor.set_pid(Some(pid));
     }
 
     pub fn init_default(&mut self, extension: &Extension) {
         trigger_reset_cpu_freq(extension);
+        self.launch_boost = None;
+        // Before the governor goes back, its tunables may live in the directory of the game's one
+        self.governor_tunables.restore(&mut self.file_handler);
+        self.irq_steering.restore(&mut self.file_handler);
+        self.apply_governor(None);
         self.reset_all_cpu_freq();
+        self.uclamp.reset(&mut self.file_handler);
+        self.bus_boost.reset(&mut self.file_handler);
+        self.affinity.reset(&mut self.file_handler);
+        self.cpuidle.reset(&mut self.file_handler);
+        self.sched_priority.reset();
+        self.thread_affinity.set_pid(None);
         self.process_monitor.set_pid(None);
         self.util_max = None;
+        self.util_history.clear();
+        self.jank_burst = None;
     }
 
     pub fn fas_update_freq(&mut self, control: isize, is_janked: bool) {
         #[cfg(debug_assertions)]
         debug!("change freq: {control}");
 
+        if let Some(until) = self.launch_boost {
+            if Instant::now() < until {
+                self.write_max_freq();
+                return;
+            }
+
+            self.launch_boost = None;
+        }
+
+        let control = if control > 0 {
+            (control as f64 * self.mode_preset.boost) as isize
+        } else {
+            control
+        };
         let fas_freqs = self.compute_target_frequencies(control, is_janked);
         let sorted_policies = self.sort_policies_topologically();
         let fas_freqs = Self::apply_absolute_constraints(fas_freqs, &sorted_policies);
         let fas_freqs = Self::apply_relative_constraints(fas_freqs, &sorted_policies);
+        let fas_freqs = self.apply_touch_boost(fas_freqs);
+        let fas_freqs = self.apply_freq_cap(fas_freqs);
+        // Taken before the burst, which must not drag the other policies along
+        let fas_freq_max = fas_freqs.values().max().copied().unwrap_or_default();
+        let (fas_freqs, bursting) = self.apply_jank_burst(fas_freqs);
+        self.at_ceiling = self.reached_ceiling(&fas_freqs, &sorted_policies);
+        self.update_uclamp(&fas_freqs);
+        self.update_bus_boost(&fas_freqs, is_janked);
+        self.update_affinity();
+        self.update_cpuidle();
+        self.update_sched_priority();
+        self.thread_affinity.apply();
         let top_used_cores = self.top_used_cores().unwrap_or_else(|| {
             let mut all_cores = CpuSet::new();
             for core in 0..num_cpus::get() {
@@ -172,28 +364,108 @@ impl Controller {
         });
 
         if no_extra_policy() {
-            let fas_freq_max = fas_freqs.values().max().copied().unwrap();
             for cpu in &mut self.cpu_infos {
                 if let Some(freq) = fas_freqs.get(&cpu.policy).copied() {
-                    let freq = freq.clamp(
-                        fas_freq_max.saturating_sub(100_000),
-                        fas_freq_max.saturating_add(100_000),
+                    let freq = if bursting.contains(&cpu.policy) {
+                        freq
+                    } else {
+                        freq.clamp(
+                            fas_freq_max.saturating_sub(100_000),
+                            fas_freq_max.saturating_add(100_000),
+                        )
+                    };
+                    let cluster_policy = self
+                        .cluster_policies
+                        .get(&cpu.policy)
+                        .copied()
+                        .unwrap_or_default();
+                    let _ = cpu.write_freq(
+                        top_used_cores,
+                        freq,
+                        cluster_policy,
+                        self.freq_backend.as_mut(),
+                        &mut self.file_handler,
                     );
-                    let _ = cpu.write_freq(top_used_cores, freq, &mut self.file_handler);
                 }
             }
         } else {
             for cpu in &mut self.cpu_infos {
                 if let Some(freq) = fas_freqs.get(&cpu.policy).copied() {
-                    let _ = cpu.write_freq(top_used_cores, freq, &mut self.file_handler);
+                    let cluster_policy = self
+                        .cluster_policies
+                        .get(&cpu.policy)
+                        .copied()
+                        .unwrap_or_default();
+                    let _ = cpu.write_freq(
+                        top_used_cores,
+                        freq,
+                        cluster_policy,
+                        self.freq_backend.as_mut(),
+                        &mut self.file_handler,
+                    );
                 }
             }
         }
+
+        self.file_handler.flush();
+        self.freq_backend.flush();
+        self.verify_limits();
+    }
+
+    fn verify_limits(&mut self) {
+        if self.limit_guard_mode == LimitGuardMode::Off || !self.freq_backend.enforces_limits() {
+            return;
+        }
+
+        let violations = self.limit_guard.check(&self.cpu_infos);
+        if violations.is_empty() {
+            return;
+        }
+
+        for violation in &violations {
+            warn!(
+                "Frequency limit not in force through {}: {violation}",
+                self.freq_backend.name()
+            );
+        }
+
+        if self.limit_guard_mode == LimitGuardMode::Log {
+            return;
+        }
+
+        if self.limit_guard_mode == LimitGuardMode::Escalate
+            && self.limit_guard.persistent()
+            && self.freq_backend.name() == "sysfs"
+            && msm_performance_available()
+        {
+            // Stays until the configured backend changes, the configured kind is kept so the
+            // looper does not switch straight back
+            warn!("Limits keep getting overwritten, switching to msm_performance");
+            self.reset_all_cpu_freq();
+            self.freq_backend = new_backend(FreqBackendKind::MsmPerformance);
+            return;
+        }
+
+        for cpu in &self.cpu_infos {
+            if violations
+                .iter()
+                .any(|violation| violation.policy() == cpu.policy)
+            {
+                cpu.reassert(self.freq_backend.as_mut(), &mut self.file_handler);
+            }
+        }
+
+        self.file_handler.flush_forced();
+        self.freq_backend.flush();
+    }
+
+    pub const fn set_limit_guard(&mut self, mode: LimitGuardMode) {
+        self.limit_guard_mode = mode;
     }
 
     fn update_util_max(&mut self) {
         if let Some(util_max) = self.process_monitor.update() {
-            self.util_max = Some(util_max);
+            self.util_max = Some(self.util_history.push(util_max));
         }
     }
 
@@ -202,52 +474,34 @@ impl Controller {
         control: isize,
         is_janked: bool,
     ) -> HashMap<i32, isize> {
-        let cur_fas_freq_max = self
+        // What the fastest policy delivers right now, in capacity units of the biggest core
+        let cur_capacity_max = self
             .cpu_infos
             .iter()
-            .map(|cpu| cpu.cur_fas_freq)
-            .max()
-            .unwrap_or_default();
-        let cur_freq_max = self
-            .cpu_infos
-            .iter()
-            .map(cpu_info::Info::read_freq)
-            .max()
-            .unwrap_or_default();
+            .map(|cpu| {
+                let max_freq = cpu.freqs.last().copied().unwrap_or(1).max(1);
+                f64::from(cpu.capacity) * cpu.read_freq() as f64 / max_freq as f64
+            })
+            .fold(0.0, f64::max);
 
         if is_janked {
             self.util_max = None;
+            self.process_monitor.tighten();
         } else {
             self.update_util_max();
         }
 
-        self.cpu_infos
-            .iter()
-            .map(|cpu| {
-                (
-                    cpu.policy,
-                    if is_janked || self.util_max.is_none() {
-                        cur_fas_freq_max
-                            .saturating_add(control)
-                            .clamp(0, self.max_freq)
-                    } else {
-                        let util_tracking_sugg_freq =
-                            (cur_freq_max as f64 * self.util_max.unwrap() / 0.5) as isize; // min_util: 50%
-                        #[cfg(debug_assertions)]
-                        debug!(
-                            "util: {}, cur_freq_max: {}, util_tracking_sugg_freq: {}",
-                            self.util_max.unwrap(),
-                            cur_freq_max,
-                            util_tracking_sugg_freq
-                        );
-                        cur_fas_freq_max
-                            .saturating_add(control)
-                            .min(util_tracking_sugg_freq)
-                            .clamp(0, self.max_freq)
-                    },
-                )
-            })
-            .collect()
+        let input = StrategyInput {
+            control,
+            is_janked,
+            util: self.util_max,
+            cur_capacity_max,
+            headroom: self.mode_preset.headroom,
+            freq_cap: self.freq_cap.min(self.mode_preset.freq_ceiling),
+            battery_powersave: self.battery_powersave,
+        };
+
+        self.strategy.decide(&input, &self.cpu_infos)
     }
 
     fn sort_policies_topologically(&self) -> Vec<i32> {
@@ -410,10 +664,490 @@ impl Controller {
         fas_freqs
     }
 
+    fn apply_governor(&mut self, governor: Option<&str>) {
+        for cpu in &mut self.cpu_infos {
+            let result = match governor {
+                Some(governor) => cpu.set_governor(governor, &mut self.file_handler),
+                None => cpu.restore_governor(&mut self.file_handler),
+            };
+
+            if let Err(e) = result {
+                warn!("Failed to apply governor on policy{}: {e:?}", cpu.policy);
+            }
+        }
+    }
+
+    pub fn set_governor_tunables(&mut self, tunables: HashMap<String, Vec<(String, String)>>) {
+        self.governor_tunables.set_rules(tunables);
+    }
+
+    pub fn set_irq_steering(&mut self, patterns: Vec<String>, cpus: Vec<u32>) {
+        self.irq_steering.set_rules(patterns, cpus);
+    }
+
+    pub fn set_cluster_policies(&mut self, policies: &HashMap<String, ClusterPolicy>) {
+        self.cluster_policies = self
+            .cpu_infos
+            .iter()
+            .filter_map(|cpu| {
+                policies
+                    .get(self.topology.name(cpu.policy))
+                    .map(|policy| (cpu.policy, *policy))
+            })
+            .collect();
+
+        #[cfg(debug_assertions)]
+        debug!("cluster policies: {:?}", self.cluster_policies);
+    }
+
+    // Pins every policy at its (thermally capped) maximum until `duration` passed, meanwhile
+    // the regular loop is skipped
+    pub fn launch_boost(&mut self, duration: Duration) {
+        self.launch_boost = Some(Instant::now() + duration);
+        self.write_max_freq();
+    }
+
+    // Whether a launch boost ran out, the caller hands the limits back when fas is not running
+    pub fn launch_boost_expired(&mut self) -> bool {
+        if self
+            .launch_boost
+            .is_some_and(|until| Instant::now() >= until)
+        {
+            self.launch_boost = None;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn cancel_launch_boost(&mut self) {
+        if self.launch_boost.take().is_some() {
+            self.reset_all_cpu_freq();
+        }
+    }
+
+    fn write_max_freq(&mut self) {
+        let max_freqs = self
+            .cpu_infos
+            .iter()
+            .filter_map(|cpu| Some((cpu.policy, cpu.freqs.last().copied()?)))
+            .collect();
+        let max_freqs = self.apply_freq_cap(max_freqs);
+
+        let mut all_cores = CpuSet::new();
+        for core in 0..num_cpus::get() {
+            let _ = all_cores.set(core);
+        }
+
+        for cpu in &mut self.cpu_infos {
+            if let Some(freq) = max_freqs.get(&cpu.policy).copied() {
+                let _ = cpu.write_freq(
+                    all_cores,
+                    freq,
+                    ClusterPolicy::default(),
+                    self.freq_backend.as_mut(),
+                    &mut self.file_handler,
+                );
+            }
+        }
+
+        self.file_handler.flush();
+        self.freq_backend.flush();
+    }
+
+    // Touch and psi share the boost, a weaker request never cuts a stronger one short
+    pub fn touch_boost(&mut self, duration: Duration, ratio: f64) {
+        let mut until = Instant::now() + duration;
+        let mut ratio = ratio.clamp(0.0, 1.0);
+
+        if let Some((active_until, active_ratio)) = self.touch_boost {
+            if Instant::now() < active_until && active_ratio >= ratio {
+                until = until.max(active_until);
+                ratio = active_ratio;
+            }
+        }
+
+        self.touch_boost = Some((until, ratio));
+    }
+
+    fn apply_touch_boost(&mut self, mut fas_freqs: HashMap<i32, isize>) -> HashMap<i32, isize> {
+        let Some((until, ratio)) = self.touch_boost else {
+            return fas_freqs;
+        };
+
+        if Instant::now() >= until {
+            self.touch_boost = None;
+            return fas_freqs;
+        }
+
+        for cpu in &self.cpu_infos {
+            if let (Some(freq), Some(max_freq)) =
+                (fas_freqs.get_mut(&cpu.policy), cpu.freqs.last().copied())
+            {
+                *freq = (*freq).max((max_freq as f64 * ratio) as isize);
+            }
+        }
+
+        fas_freqs
+    }
+
+    // Pins the clusters the frame threads last ran on at their maximum for `duration`, the floor
+    // then falls back linearly over the same time. A running burst is restarted, not stacked
+    pub fn jank_burst(&mut self, duration: Duration) {
+        let cpus = self.process_monitor.frame_thread_cpus();
+        let policies: Vec<_> = self
+            .cpu_infos
+            .iter()
+            .filter(|cpu| cpu.affected_cpus().iter().any(|core| cpus.contains(core)))
+            .map(|cpu| cpu.policy)
+            .collect();
+        if policies.is_empty() || duration.is_zero() {
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        debug!("jank burst on policies: {policies:?}");
+
+        self.jank_burst = Some((Instant::now(), duration, policies));
+    }
+
+    // Also hands back the bursting policies, the floor is capped like any other frequency
+    fn apply_jank_burst(
+        &mut self,
+        mut fas_freqs: HashMap<i32, isize>,
+    ) -> (HashMap<i32, isize>, Vec<i32>) {
+        let Some((start, duration, policies)) = &self.jank_burst else {
+            return (fas_freqs, Vec::new());
+        };
+
+        let Some(ratio) = jank_burst_ratio(start.elapsed(), *duration) else {
+            self.jank_burst = None;
+            return (fas_freqs, Vec::new());
+        };
+        let ratio = ratio.min(self.freq_cap.min(self.mode_preset.freq_ceiling));
+
+        let policies = policies.clone();
+        for cpu in &self.cpu_infos {
+            if !policies.contains(&cpu.policy) {
+                continue;
+            }
+
+            if let (Some(freq), Some(max_freq)) =
+                (fas_freqs.get_mut(&cpu.policy), cpu.freqs.last().copied())
+            {
+                *freq = (*freq).max((max_freq as f64 * ratio) as isize);
+            }
+        }
+
+        (fas_freqs, policies)
+    }
+
+    // The same limits applied to the hardware maximum give each policy's ceiling, cluster
+    // ceilings included
+    fn reached_ceiling(&self, fas_freqs: &HashMap<i32, isize>, sorted_policies: &[i32]) -> bool {
+        let ceilings = self
+            .cpu_infos
+            .iter()
+            .filter_map(|cpu| Some((cpu.policy, cpu.freqs.last().copied()?)))
+            .collect();
+        let ceilings = Self::apply_absolute_constraints(ceilings, sorted_policies);
+        let ceilings = self.apply_freq_cap(ceilings);
+
+        self.cpu_infos.iter().any(|cpu| {
+            let (Some(freq), Some(ceiling), Some(max_freq)) = (
+                fas_freqs.get(&cpu.policy).copied(),
+                ceilings.get(&cpu.policy).copied(),
+                cpu.freqs.last().copied(),
+            ) else {
+                return false;
+            };
+            let cluster_policy = self
+                .cluster_policies
+                .get(&cpu.policy)
+                .copied()
+                .unwrap_or_default();
+
+            cluster_policy.apply(freq, max_freq) >= cluster_policy.apply(ceiling, max_freq)
+        })
+    }
+
+    fn apply_freq_cap(&self, mut fas_freqs: HashMap<i32, isize>) -> HashMap<i32, isize> {
+        let freq_cap = self.freq_cap.min(self.mode_preset.freq_ceiling);
+
+        for cpu in &self.cpu_infos {
+            if let (Some(freq), Some(max_freq)) =
+                (fas_freqs.get_mut(&cpu.policy), cpu.freqs.last().copied())
+            {
+                *freq = (*freq).min((max_freq as f64 * freq_cap) as isize);
+            }
+        }
+
+        fas_freqs
+    }
+
+    pub const fn set_mode_preset(&mut self, mode_preset: ModePreset) {
+        self.mode_preset = mode_preset;
+    }
+
+    pub fn set_freq_cap(&mut self, freq_cap: f64) {
+        self.freq_cap = freq_cap.clamp(0.0, 1.0);
+    }
+
     fn reset_all_cpu_freq(&mut self) {
         for cpu in &mut self.cpu_infos {
-            let _ = cpu.reset(&mut self.file_handler);
+            let _ = cpu.reset(self.freq_backend.as_mut(), &mut self.file_handler);
         }
+
+        self.file_handler.flush();
+        self.freq_backend.reset();
+        self.limit_guard.reset();
+    }
+
+    // Limits written through the old backend are handed back before switching
+    pub fn set_freq_backend(&mut self, kind: FreqBackendKind) {
+        if self.freq_backend_kind != kind {
+            self.reset_all_cpu_freq();
+            self.freq_backend = new_backend(kind);
+            self.freq_backend_kind = kind;
+        }
+    }
+
+    pub fn set_strategy(&mut self, kind: StrategyKind, ladder: LadderConfig) {
+        if self.strategy_kind != kind || self.ladder != ladder {
+            self.strategy = new_strategy(kind, ladder);
+            self.strategy_kind = kind;
+            self.ladder = ladder;
+        }
+    }
+
+    pub const fn set_battery_powersave(&mut self, battery_powersave: bool) {
+        self.battery_powersave = battery_powersave;
+    }
+
+    pub fn set_uclamp_boost(&mut self, enabled: bool, scope: UclampScope) {
+        self.uclamp.set_enabled(enabled, &mut self.file_handler);
+        self.uclamp.set_scope(scope, &mut self.file_handler);
+    }
+
+    fn update_uclamp(&mut self, fas_freqs: &HashMap<i32, isize>) {
+        if !self.uclamp.enabled() {
+            return;
+        }
+
+        let fas_freq_max = fas_freqs.values().max().copied().unwrap_or_default();
+        // schedutil requests 1.25x of the utilization as frequency
+        let util_min = fas_freq_max as f64 / self.max_freq.max(1) as f64 / 1.25;
+        self.uclamp.apply(
+            util_min,
+            self.process_monitor.frame_threads(),
+            &mut self.file_handler,
+        );
+    }
+
+    pub fn set_bus_boost(&mut self, enabled: bool) {
+        self.bus_boost.set_enabled(enabled, &mut self.file_handler);
+    }
+
+    fn update_bus_boost(&mut self, fas_freqs: &HashMap<i32, isize>, is_janked: bool) {
+        if !self.bus_boost.enabled() {
+            return;
+        }
+
+        let fas_freq_max = fas_freqs.values().max().copied().unwrap_or_default();
+        self.bus_boost.apply(
+            fas_freq_max as f64 / self.max_freq.max(1) as f64,
+            is_janked,
+            self.freq_cap < 1.0,
+            &mut self.file_handler,
+        );
+    }
+
+    pub fn set_monitor_params(
+        &mut self,
+        sample_time: Duration,
+        max_sample_time: Duration,
+        refresh_time: Duration,
+        top_threads: usize,
+        runqueue_weight: f64,
+    ) {
+        self.process_monitor.set_params(
+            sample_time,
+            max_sample_time,
+            refresh_time,
+            top_threads,
+            runqueue_weight,
+        );
+    }
+
+    pub fn set_util_smoothing(&mut self, smoothing: UtilSmoothing, alpha: f64, window: usize) {
+        self.util_history.set_smoothing(smoothing, alpha, window);
+    }
+
+    // Raw samples behind util_max, for spike filtering and trend detection
+    pub const fn util_history(&self) -> &UtilHistory {
+        &self.util_history
+    }
+
+    // Other visible games, their threads count towards util_max like the current game's
+    pub fn set_extra_pids(&mut self, pids: Vec<i32>) {
+        self.process_monitor.set_extra_pids(pids);
+    }
+
+    pub fn set_known_threads(&mut self, names: Vec<String>) {
+        self.process_monitor.set_known_threads(names);
+    }
+
+    pub fn set_stream_threads(&mut self, globs: Vec<String>) {
+        self.process_monitor.set_stream_threads(globs);
+    }
+
+    pub fn render_thread_names(&self) -> Vec<String> {
+        self.process_monitor.render_thread_names()
+    }
+
+    // Utilization learned in earlier sessions, used until the first sample of this one
+    pub const fn seed_util_max(&mut self, util: Option<f64>) {
+        self.util_max = util;
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.process_monitor.set_heartbeat(heartbeat);
+    }
+
+    pub fn set_pin_big_cores(&mut self, enabled: bool) {
+        self.affinity.set_enabled(enabled, &mut self.file_handler);
+    }
+
+    fn update_affinity(&mut self) {
+        if !self.affinity.enabled() {
+            return;
+        }
+
+        self.affinity.apply(
+            self.process_monitor
+                .render_threads()
+                .chain(self.process_monitor.top_threads()),
+            &mut self.file_handler,
+        );
+    }
+
+    pub fn set_shallow_idle(&mut self, enabled: bool) {
+        self.cpuidle.set_enabled(enabled, &mut self.file_handler);
+    }
+
+    fn update_cpuidle(&mut self) {
+        if !self.cpuidle.enabled() {
+            return;
+        }
+
+        self.cpuidle.apply(
+            self.process_monitor.render_threads(),
+            &self.topology,
+            self.freq_cap < 1.0,
+            &mut self.file_handler,
+        );
+    }
+
+    pub fn set_thread_affinity(&mut self, rules: Vec<AffinityRule>) {
+        self.thread_affinity.set_rules(rules);
+    }
+
+    pub fn set_render_priority(&mut self, priority: Option<RenderPriority>) {
+        self.sched_priority.set_priority(priority);
+    }
+
+    fn update_sched_priority(&mut self) {
+        if !self.sched_priority.enabled() {
+            return;
+        }
+
+        self.sched_priority
+            .apply(self.process_monitor.render_threads());
+    }
+
+    // What does not work on this device right now, and why
+    pub fn degraded(&self) -> Vec<Degradation> {
+        let failures = self
+            .file_handler
+            .failures()
+            .into_iter()
+            .map(|error| Degradation {
+                class: error.class(),
+                feature: feature_of(error.path()).to_string(),
+                node: error.path().to_path_buf(),
+            });
+        let overridden = self
+            .cpu_infos
+            .iter()
+            .filter(|cpu| {
+                self.limit_guard
+                    .failing()
+                    .any(|policy| policy == cpu.policy)
+            })
+            .map(|cpu| Degradation {
+                class: ErrorClass::VendorOverride,
+                node: cpu.max_freq_path(),
+                feature: format!("policy{} frequency limits", cpu.policy),
+            });
+
+        self.unavailable
+            .iter()
+            .cloned()
+            .chain(failures)
+            .chain(overridden)
+            .collect()
+    }
+
+    pub fn cluster_stats(&mut self) -> Vec<ClusterStat> {
+        let usages = self.cpu_usage.update();
+
+        self.cpu_infos
+            .iter()
+            .map(|cpu| {
+                let (min_limit, max_limit) = cpu.read_limits();
+                let cores = cpu.affected_cpus();
+                let util = cores
+                    .iter()
+                    .filter_map(|core| usages.get(core))
+                    .sum::<f64>()
+                    / cores.len().max(1) as f64;
+
+                ClusterStat {
+                    topology: self
+                        .topology
+                        .cluster(cpu.policy)
+                        .cloned()
+                        .unwrap_or_else(|| ClusterTopology {
+                            policy: cpu.policy,
+                            name: self.topology.name(cpu.policy).to_string(),
+                            cpus: cores.to_vec(),
+                            capacity: cpu.capacity,
+                            cluster_id: None,
+                            package_id: None,
+                        }),
+                    cur_freq: cpu.read_freq(),
+                    min_limit,
+                    max_limit,
+                    util,
+                }
+            })
+            .collect()
+    }
+
+    pub fn cur_fas_freq_max(&self) -> isize {
+        self.cpu_infos
+            .iter()
+            .map(|cpu| cpu.cur_fas_freq)
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub const fn max_freq(&self) -> isize {
+        self.max_freq
+    }
+
+    pub const fn at_ceiling(&self) -> bool {
+        self.at_ceiling
     }
 
     pub fn util_max(&self) -> f64 {
@@ -421,6 +1155,17 @@ impl Controller {
     }
 }
 
+// Share of the max frequency a jank burst holds `elapsed` into it: all of it for `duration`,
+// then falling linearly to nothing over the same time. None once it is over
+fn jank_burst_ratio(elapsed: Duration, duration: Duration) -> Option<f64> {
+    let decay = elapsed.saturating_sub(duration);
+    if duration.is_zero() || decay >= duration {
+        return None;
+    }
+
+    Some(1.0 - decay.as_secs_f64() / duration.as_secs_f64())
+}
+
 fn no_extra_policy() -> bool {
     EXTRA_POLICY_MAP
         .get()
@@ -429,3 +1174,23 @@ fn no_extra_policy() -> bool {
         .values()
         .all(|policy| *policy.lock() == ExtraPolicy::None)
 }
+
+// The feature a failing node belongs to, for the degraded capabilities report
+fn feature_of(path: &Path) -> &'static str {
+    let path = path.to_string_lossy();
+    if path.contains("/cpufreq/") {
+        "frequency control"
+    } else if path.contains("/cpuidle/") {
+        "shallow_idle"
+    } else if path.contains("/devfreq/") {
+        "bus_boost"
+    } else if path.contains("uclamp") {
+        "uclamp_boost"
+    } else if path.contains("/cpuset/") {
+        "pin_big_cores"
+    } else if path.starts_with("/proc/irq/") {
+        "irq"
+    } else {
+        "cpu control"
+    }
+}
@@ -0,0 +1,422 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    ffi::CString,
+    io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    ptr,
+};
+
+use hashbrown::HashMap;
+
+const BPF_MAP_CREATE: i32 = 0;
+const BPF_MAP_LOOKUP_ELEM: i32 = 1;
+const BPF_MAP_UPDATE_ELEM: i32 = 2;
+const BPF_MAP_DELETE_ELEM: i32 = 3;
+const BPF_MAP_GET_NEXT_KEY: i32 = 4;
+const BPF_PROG_LOAD: i32 = 5;
+const BPF_RAW_TRACEPOINT_OPEN: i32 = 17;
+const BPF_MAP_LOOKUP_BATCH: i32 = 24;
+
+const BPF_MAP_TYPE_HASH: u32 = 1;
+const BPF_MAP_TYPE_ARRAY: u32 = 2;
+const BPF_MAP_TYPE_PERCPU_ARRAY: u32 = 6;
+const BPF_PROG_TYPE_RAW_TRACEPOINT: u32 = 17;
+
+const BPF_FUNC_MAP_LOOKUP_ELEM: i32 = 1;
+const BPF_FUNC_MAP_UPDATE_ELEM: i32 = 2;
+const BPF_FUNC_KTIME_GET_NS: i32 = 5;
+const BPF_FUNC_GET_CURRENT_PID_TGID: i32 = 14;
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+const MAX_THREADS: u32 = 4096;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct Insn {
+    code: u8,
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+const fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Insn {
+    Insn {
+        code,
+        regs: (src << 4) | dst,
+        off,
+        imm,
+    }
+}
+
+const fn mov_imm(dst: u8, imm: i32) -> Insn {
+    insn(0xb7, dst, 0, 0, imm)
+}
+
+const fn mov_reg(dst: u8, src: u8) -> Insn {
+    insn(0xbf, dst, src, 0, 0)
+}
+
+const fn add_imm(dst: u8, imm: i32) -> Insn {
+    insn(0x07, dst, 0, 0, imm)
+}
+
+const fn sub_reg(dst: u8, src: u8) -> Insn {
+    insn(0x1f, dst, src, 0, 0)
+}
+
+const fn rsh_imm(dst: u8, imm: i32) -> Insn {
+    insn(0x77, dst, 0, 0, imm)
+}
+
+const fn call(func: i32) -> Insn {
+    insn(0x85, 0, 0, 0, func)
+}
+
+const fn jeq_imm(dst: u8, imm: i32, off: i16) -> Insn {
+    insn(0x15, dst, 0, off, imm)
+}
+
+const fn jne_reg(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0x5d, dst, src, off, 0)
+}
+
+const fn ja(off: i16) -> Insn {
+    insn(0x05, 0, 0, off, 0)
+}
+
+const fn ldx_dw(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0x79, dst, src, off, 0)
+}
+
+const fn ldx_w(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0x61, dst, src, off, 0)
+}
+
+const fn stx_dw(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0x7b, dst, src, off, 0)
+}
+
+const fn stx_w(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0x63, dst, src, off, 0)
+}
+
+const fn st_w(dst: u8, off: i16, imm: i32) -> Insn {
+    insn(0x62, dst, 0, off, imm)
+}
+
+const fn xadd_dw(dst: u8, src: u8, off: i16) -> Insn {
+    insn(0xdb, dst, src, off, 0)
+}
+
+const fn exit() -> Insn {
+    insn(0x95, 0, 0, 0, 0)
+}
+
+fn ld_map_fd(dst: u8, fd: RawFd) -> [Insn; 2] {
+    [insn(0x18, dst, BPF_PSEUDO_MAP_FD, 0, fd), Insn::default()]
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapElemAttr {
+    map_fd: u32,
+    pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapBatchAttr {
+    in_batch: u64,
+    out_batch: u64,
+    keys: u64,
+    values: u64,
+    count: u32,
+    map_fd: u32,
+    elem_flags: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct RawTracepointAttr {
+    name: u64,
+    prog_fd: u32,
+}
+
+fn bpf<T>(cmd: i32, attr: &mut T) -> io::Result<i32> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            cmd,
+            ptr::from_mut(attr),
+            mem::size_of::<T>() as u32,
+        )
+    };
+
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(ret as i32)
+}
+
+fn bpf_fd<T>(cmd: i32, attr: &mut T) -> io::Result<OwnedFd> {
+    let fd = bpf(cmd, attr)?;
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+fn create_map(
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+) -> io::Result<OwnedFd> {
+    let mut attr = MapCreateAttr {
+        map_type,
+        key_size,
+        value_size,
+        max_entries,
+        ..MapCreateAttr::default()
+    };
+    bpf_fd(BPF_MAP_CREATE, &mut attr)
+}
+
+// sched_switch runs in the context of the task being switched out, so the current pid/tgid
+// is the previous task and the per-cpu start stamp tells how long it has just been running
+fn program(start: RawFd, target: RawFd, runtime: RawFd) -> Vec<Insn> {
+    let mut insns = vec![call(BPF_FUNC_KTIME_GET_NS), mov_reg(6, 0), st_w(10, -4, 0)];
+    insns.extend(ld_map_fd(1, start));
+    insns.extend([
+        mov_reg(2, 10),
+        add_imm(2, -4),
+        call(BPF_FUNC_MAP_LOOKUP_ELEM),
+        jeq_imm(0, 0, 34),
+        ldx_dw(7, 0, 0),
+        stx_dw(0, 6, 0),
+        jeq_imm(7, 0, 31),
+        sub_reg(6, 7),
+        call(BPF_FUNC_GET_CURRENT_PID_TGID),
+        mov_reg(8, 0),
+        rsh_imm(0, 32),
+        mov_reg(9, 0),
+    ]);
+    insns.extend(ld_map_fd(1, target));
+    insns.extend([
+        mov_reg(2, 10),
+        add_imm(2, -4),
+        call(BPF_FUNC_MAP_LOOKUP_ELEM),
+        jeq_imm(0, 0, 20),
+        ldx_w(1, 0, 0),
+        jne_reg(1, 9, 18),
+        stx_w(10, 8, -8),
+    ]);
+    insns.extend(ld_map_fd(1, runtime));
+    insns.extend([
+        mov_reg(2, 10),
+        add_imm(2, -8),
+        call(BPF_FUNC_MAP_LOOKUP_ELEM),
+        jeq_imm(0, 0, 2),
+        xadd_dw(0, 6, 0),
+        ja(9),
+        stx_dw(10, 6, -16),
+    ]);
+    insns.extend(ld_map_fd(1, runtime));
+    insns.extend([
+        mov_reg(2, 10),
+        add_imm(2, -8),
+        mov_reg(3, 10),
+        add_imm(3, -16),
+        mov_imm(4, 0),
+        call(BPF_FUNC_MAP_UPDATE_ELEM),
+        mov_imm(0, 0),
+        exit(),
+    ]);
+    insns
+}
+
+#[derive(Debug)]
+pub struct RuntimeMap {
+    target: OwnedFd,
+    runtime: OwnedFd,
+    batch: bool,
+    keys: Vec<u32>,
+    values: Vec<u64>,
+    _start: OwnedFd,
+    _prog: OwnedFd,
+    _link: OwnedFd,
+}
+
+impl RuntimeMap {
+    pub fn load() -> io::Result<Self> {
+        let start = create_map(BPF_MAP_TYPE_PERCPU_ARRAY, 4, 8, 1)?;
+        let target = create_map(BPF_MAP_TYPE_ARRAY, 4, 4, 1)?;
+        let runtime = create_map(BPF_MAP_TYPE_HASH, 4, 8, MAX_THREADS)?;
+
+        let insns = program(start.as_raw_fd(), target.as_raw_fd(), runtime.as_raw_fd());
+        let license = CString::new("GPL")?;
+        let mut attr = ProgLoadAttr {
+            prog_type: BPF_PROG_TYPE_RAW_TRACEPOINT,
+            insn_cnt: insns.len() as u32,
+            insns: insns.as_ptr() as u64,
+            license: license.as_ptr() as u64,
+            ..ProgLoadAttr::default()
+        };
+        let prog = bpf_fd(BPF_PROG_LOAD, &mut attr)?;
+
+        let name = CString::new("sched_switch")?;
+        let mut attr = RawTracepointAttr {
+            name: name.as_ptr() as u64,
+            prog_fd: prog.as_raw_fd() as u32,
+        };
+        let link = bpf_fd(BPF_RAW_TRACEPOINT_OPEN, &mut attr)?;
+
+        Ok(Self {
+            target,
+            runtime,
+            batch: true,
+            keys: vec![0; MAX_THREADS as usize],
+            values: vec![0; MAX_THREADS as usize],
+            _start: start,
+            _prog: prog,
+            _link: link,
+        })
+    }
+
+    pub fn set_tgid(&mut self, tgid: i32) {
+        let key = 0u32;
+        let mut attr = MapElemAttr {
+            map_fd: self.target.as_raw_fd() as u32,
+            key: ptr::from_ref(&key) as u64,
+            value: ptr::from_ref(&tgid) as u64,
+            ..MapElemAttr::default()
+        };
+        let _ = bpf(BPF_MAP_UPDATE_ELEM, &mut attr);
+
+        for tid in self.tids() {
+            let mut attr = MapElemAttr {
+                map_fd: self.runtime.as_raw_fd() as u32,
+                key: ptr::from_ref(&tid) as u64,
+                ..MapElemAttr::default()
+            };
+            let _ = bpf(BPF_MAP_DELETE_ELEM, &mut attr);
+        }
+    }
+
+    // Nanoseconds each thread of the target spent on cpu since it was selected
+    pub fn runtimes(&mut self) -> HashMap<i32, u64> {
+        if self.batch {
+            match self.lookup_batch() {
+                Ok(runtimes) => return runtimes,
+                Err(e) if e.raw_os_error() == Some(libc::EINVAL) => self.batch = false,
+                Err(_) => return HashMap::new(),
+            }
+        }
+
+        self.tids()
+            .into_iter()
+            .filter_map(|tid| {
+                let mut runtime = 0u64;
+                let mut attr = MapElemAttr {
+                    map_fd: self.runtime.as_raw_fd() as u32,
+                    key: ptr::from_ref(&tid) as u64,
+                    value: ptr::from_mut(&mut runtime) as u64,
+                    ..MapElemAttr::default()
+                };
+                bpf(BPF_MAP_LOOKUP_ELEM, &mut attr).ok()?;
+                Some((tid as i32, runtime))
+            })
+            .collect()
+    }
+
+    // Batched lookups need 5.6+, older kernels report EINVAL and are walked key by key
+    fn lookup_batch(&mut self) -> io::Result<HashMap<i32, u64>> {
+        let mut out_batch = 0u32;
+        let mut attr = MapBatchAttr {
+            out_batch: ptr::from_mut(&mut out_batch) as u64,
+            keys: self.keys.as_mut_ptr() as u64,
+            values: self.values.as_mut_ptr() as u64,
+            count: MAX_THREADS,
+            map_fd: self.runtime.as_raw_fd() as u32,
+            ..MapBatchAttr::default()
+        };
+
+        match bpf(BPF_MAP_LOOKUP_BATCH, &mut attr) {
+            Ok(_) => (),
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => (),
+            Err(e) => return Err(e),
+        }
+
+        let count = attr.count as usize;
+        Ok(self.keys[..count]
+            .iter()
+            .zip(&self.values[..count])
+            .map(|(tid, runtime)| (*tid as i32, *runtime))
+            .collect())
+    }
+
+    fn tids(&self) -> Vec<u32> {
+        let mut tids = Vec::new();
+        let mut key = None;
+
+        loop {
+            let mut next = 0u32;
+            let mut attr = MapElemAttr {
+                map_fd: self.runtime.as_raw_fd() as u32,
+                key: key.as_ref().map_or(0, |key| ptr::from_ref(key) as u64),
+                value: ptr::from_mut(&mut next) as u64,
+                ..MapElemAttr::default()
+            };
+
+            if bpf(BPF_MAP_GET_NEXT_KEY, &mut attr).is_err() {
+                break;
+            }
+
+            tids.push(next);
+            key = Some(next);
+        }
+
+        tids
+    }
+}
@@ -0,0 +1,603 @@
+// Copyright 2025-2025, shadow3, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+#[cfg(feature = "bpf")]
+mod bpf;
+mod proc_files;
+
+use anyhow::Result;
+use hashbrown::{HashMap, hash_map::Entry};
+use std::{
+    cmp,
+    time::{Duration, Instant},
+};
+
+use proc_files::ProcFiles;
+
+use super::Cadence;
+use crate::{framework::Heartbeat, misc::glob_match, sysfs};
+
+#[cfg(feature = "bpf")]
+use log::{info, warn};
+
+const RENDER_THREAD_PREFIXES: [&str; 8] = [
+    "RenderThread",
+    "GLThread",
+    "UnityMain",
+    "UnityGfx",
+    "GameThread",
+    "RHIThread",
+    "MainThread-UE4",
+    "VkThread",
+];
+const NON_RENDER_WEIGHT: f64 = 0.8;
+// The busiest thread counts as carrying the frame only above this usage
+const HOT_THREAD_USAGE: f64 = 0.5;
+// Shortest sample interval, below this the schedstat deltas are mostly rounding
+const MIN_SAMPLE_TIME: Duration = Duration::from_millis(50);
+// A thread born since the last sample that already ran this share of a sample interval is
+// tracked right away instead of from the next thread list refresh
+const NEWCOMER_USAGE: f64 = 0.3;
+// util_max moving less than this between two samples counts as steady
+const STEADY_UTIL_DELTA: f64 = 0.05;
+
+// What marks a thread as carrying the frame
+#[derive(Debug, Default)]
+struct RenderNames {
+    // Render threads learned in earlier sessions, on top of the well-known prefixes
+    known: Vec<String>,
+    // Decode and network thread globs of a streaming client, they replace the render thread
+    // heuristics, which pick the wrong threads in such apps
+    stream: Vec<String>,
+}
+
+impl RenderNames {
+    fn matches(&self, comm: &str) -> bool {
+        if !self.stream.is_empty() {
+            return self
+                .stream
+                .iter()
+                .any(|glob| glob_match(glob.as_bytes(), comm.as_bytes()));
+        }
+
+        RENDER_THREAD_PREFIXES
+            .iter()
+            .any(|prefix| comm.starts_with(prefix))
+            || self.known.iter().any(|known| known == comm)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ThreadTime {
+    cputime: u64,
+    // Time spent runnable but waiting on a runqueue, the second field of schedstat. None when
+    // bpf supplied the runtime and nobody asked for it
+    wait_time: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UsageTracker {
+    pid: i32,
+    tid: i32,
+    render: bool,
+    last_time: ThreadTime,
+    // When the thread times were last read, every tracker of a sample shares the same instant
+    last_read: Instant,
+    current_usage: f64,
+}
+
+impl UsageTracker {
+    fn new(
+        pid: i32,
+        tid: i32,
+        now: Instant,
+        files: &ProcFiles,
+        runtimes: Option<&HashMap<i32, u64>>,
+        runqueue_weight: f64,
+        render_names: &RenderNames,
+    ) -> Result<Self> {
+        Ok(Self {
+            pid,
+            tid,
+            render: read_comm(pid, tid).is_some_and(|comm| render_names.matches(&comm)),
+            last_time: get_thread_time(tid, files, runtimes, runqueue_weight > 0.0)?,
+            last_read: now,
+            current_usage: 0.0,
+        })
+    }
+
+    // Share of the elapsed time the thread ran plus `runqueue_weight` times the share it waited.
+    // Fails once the thread exited, the tracker is a tombstone then and has to be dropped. None
+    // when there is no usable delta, in the sample the tracker was created or after its counters
+    // went backwards (the tid was reused or the bpf entry recreated)
+    fn try_calculate(
+        &mut self,
+        now: Instant,
+        files: &ProcFiles,
+        runtimes: Option<&HashMap<i32, u64>>,
+        runqueue_weight: f64,
+    ) -> Result<Option<f64>> {
+        if now <= self.last_read {
+            return Ok(None);
+        }
+
+        let tick_per_sec = 1_000_000_000.0;
+        let new_time = get_thread_time(self.tid, files, runtimes, runqueue_weight > 0.0)?;
+        let elapsed_ticks = now.duration_since(self.last_read).as_secs_f64() * tick_per_sec;
+        let last_time = self.last_time;
+        self.last_read = now;
+        self.last_time = new_time;
+
+        let Some(cputime_slice) = new_time.cputime.checked_sub(last_time.cputime) else {
+            return Ok(None);
+        };
+        let wait_slice = match (new_time.wait_time, last_time.wait_time) {
+            (Some(new), Some(last)) => match new.checked_sub(last) {
+                Some(slice) => slice,
+                None => return Ok(None),
+            },
+            _ => 0,
+        };
+
+        Ok(Some(
+            (wait_slice as f64).mul_add(runqueue_weight, cputime_slice as f64) / elapsed_ticks,
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct ProcessMonitor {
+    current_pid: Option<i32>,
+    // Other games visible next to the current one, split screen or floating windows
+    extra_pids: Vec<i32>,
+    // Stretched from the sample interval while util_max holds still
+    cadence: Cadence,
+    last_util: Option<f64>,
+    refresh_time: Duration,
+    top_threads: usize,
+    runqueue_weight: f64,
+    render_names: RenderNames,
+    files: ProcFiles,
+    all_trackers: HashMap<i32, UsageTracker>,
+    top_trackers: HashMap<i32, UsageTracker>,
+    last_full_update: Instant,
+    // Absolute deadline of the next sample, advanced by whole intervals so sampling does not
+    // drift with the cadence update is called at
+    next_sample: Instant,
+    heartbeat: Option<Heartbeat>,
+    #[cfg(feature = "bpf")]
+    bpf: Option<bpf::RuntimeMap>,
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        Self {
+            current_pid: None,
+            extra_pids: Vec::new(),
+            cadence: Cadence::new(Duration::from_millis(300), Duration::from_secs(1)),
+            last_util: None,
+            refresh_time: Duration::from_secs(1),
+            top_threads: 8,
+            runqueue_weight: 0.0,
+            render_names: RenderNames::default(),
+            files: ProcFiles::new(),
+            all_trackers: HashMap::new(),
+            top_trackers: HashMap::new(),
+            last_full_update: Instant::now(),
+            next_sample: Instant::now(),
+            heartbeat: None,
+            #[cfg(feature = "bpf")]
+            bpf: bpf::RuntimeMap::load()
+                .inspect(|_| info!("Thread runtime accounting backed by bpf"))
+                .map_err(|e| warn!("Bpf runtime accounting unavailable, using procfs: {e}"))
+                .ok(),
+        }
+    }
+
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.heartbeat = Some(heartbeat);
+    }
+
+    pub fn set_pid(&mut self, pid: Option<i32>) {
+        if self.current_pid != pid {
+            self.current_pid = pid;
+            self.extra_pids.clear();
+            self.files.clear();
+            self.all_trackers.clear();
+            self.top_trackers.clear();
+            self.last_full_update = Instant::now();
+            self.next_sample = Instant::now();
+            self.last_util = None;
+            self.cadence.settle(false);
+            #[cfg(feature = "bpf")]
+            if let Some(bpf) = self.bpf.as_mut() {
+                bpf.set_tgid(pid.unwrap_or(0));
+            }
+        }
+    }
+
+    // Threads of the extra games join the current game's on the next thread list refresh
+    pub fn set_extra_pids(&mut self, pids: Vec<i32>) {
+        self.extra_pids = pids;
+    }
+
+    fn pids(&self) -> Vec<i32> {
+        self.current_pid
+            .into_iter()
+            .chain(self.extra_pids.iter().copied())
+            .collect()
+    }
+
+    pub fn set_known_threads(&mut self, names: Vec<String>) {
+        if self.render_names.known != names {
+            self.render_names.known = names;
+            self.redetect_render_threads();
+        }
+    }
+
+    pub fn set_stream_threads(&mut self, globs: Vec<String>) {
+        if self.render_names.stream != globs {
+            self.render_names.stream = globs;
+            self.redetect_render_threads();
+        }
+    }
+
+    fn redetect_render_threads(&mut self) {
+        self.all_trackers.clear();
+        self.top_trackers.clear();
+        self.last_full_update = Instant::now();
+    }
+
+    pub fn set_params(
+        &mut self,
+        sample_time: Duration,
+        max_sample_time: Duration,
+        refresh_time: Duration,
+        top_threads: usize,
+        runqueue_weight: f64,
+    ) {
+        let sample_time = sample_time.max(MIN_SAMPLE_TIME);
+        self.cadence.set_bounds(sample_time, max_sample_time);
+        self.refresh_time = refresh_time;
+        self.top_threads = top_threads.max(1);
+        self.runqueue_weight = runqueue_weight.max(0.0);
+    }
+
+    // Back to the configured sample interval, the next sample is pulled in if it was further
+    // away than that
+    pub fn tighten(&mut self) {
+        let stretched = self.cadence.interval();
+        if self.cadence.settle(false) {
+            if let Some(last_sample) = self.next_sample.checked_sub(stretched) {
+                self.next_sample = self.next_sample.min(last_sample + self.cadence.interval());
+            }
+        }
+    }
+
+    pub fn update(&mut self) -> Option<f64> {
+        let now = Instant::now();
+        if now < self.next_sample {
+            return None;
+        }
+
+        // Missed deadlines are skipped rather than caught up with a burst of short samples
+        let sample_time = self.cadence.interval();
+        let missed = (now.duration_since(self.next_sample).as_nanos() / sample_time.as_nanos())
+            .try_into()
+            .unwrap_or(u32::MAX);
+        self.next_sample += sample_time.saturating_mul(missed.saturating_add(1));
+        if self.current_pid.is_none() {
+            return None;
+        }
+
+        let _busy = self.heartbeat.as_ref().map(Heartbeat::busy);
+        let runtimes = self.runtimes();
+
+        if self.last_full_update.elapsed() >= self.refresh_time {
+            self.update_thread_list(now, runtimes.as_ref());
+            self.last_full_update = Instant::now();
+        } else {
+            self.track_newcomers(now, runtimes.as_ref());
+        }
+
+        let has_render_thread = self.top_trackers.values().any(|tracker| tracker.render);
+        let mut util_max: f64 = 0.0;
+        let mut exited = Vec::new();
+        for (tid, tracker) in &mut self.top_trackers {
+            match tracker.try_calculate(now, &self.files, runtimes.as_ref(), self.runqueue_weight) {
+                Ok(Some(usage)) => {
+                    tracker.current_usage = usage;
+                    let usage = if has_render_thread && !tracker.render {
+                        usage * NON_RENDER_WEIGHT
+                    } else {
+                        usage
+                    };
+                    util_max = util_max.max(usage);
+                }
+                Ok(None) => (),
+                Err(_) => exited.push(*tid),
+            }
+        }
+
+        // Dropped right away instead of lingering with a stale usage until the next refresh
+        for tid in exited {
+            self.top_trackers.remove(&tid);
+            self.all_trackers.remove(&tid);
+            self.files.forget(tid);
+        }
+
+        let steady = self
+            .last_util
+            .is_some_and(|last| (util_max - last).abs() <= STEADY_UTIL_DELTA);
+        self.last_util = Some(util_max);
+        if steady {
+            self.cadence.settle(true);
+        } else {
+            self.tighten();
+        }
+
+        Some(util_max)
+    }
+
+    // One map read per sample with bpf, None makes every tracker read its own schedstat. Bpf
+    // accounts only the current game, extra games fall back to schedstat
+    #[cfg_attr(
+        not(feature = "bpf"),
+        allow(
+            clippy::missing_const_for_fn,
+            clippy::unused_self,
+            clippy::needless_pass_by_ref_mut
+        )
+    )]
+    fn runtimes(&mut self) -> Option<HashMap<i32, u64>> {
+        #[cfg(feature = "bpf")]
+        if let Some(bpf) = self.bpf.as_mut().filter(|_| self.extra_pids.is_empty()) {
+            return Some(bpf.runtimes());
+        }
+
+        None
+    }
+
+    fn update_thread_list(&mut self, now: Instant, runtimes: Option<&HashMap<i32, u64>>) {
+        let pids = self.pids();
+        if self.files.sync(&pids).is_ok() {
+            self.all_trackers = self
+                .files
+                .tids()
+                .iter()
+                .copied()
+                .filter_map(|(tid, pid)| {
+                    Some((
+                        tid,
+                        match self.all_trackers.entry(tid) {
+                            Entry::Occupied(o) => o.remove(),
+                            Entry::Vacant(_) => UsageTracker::new(
+                                pid,
+                                tid,
+                                now,
+                                &self.files,
+                                runtimes,
+                                self.runqueue_weight,
+                                &self.render_names,
+                            )
+                            .ok()?,
+                        },
+                    ))
+                })
+                .collect();
+
+            let mut top_threads: Vec<_> = self
+                .all_trackers
+                .iter()
+                .filter_map(|(tid, tracker)| {
+                    Some((
+                        *tid,
+                        tracker
+                            .clone()
+                            .try_calculate(now, &self.files, runtimes, self.runqueue_weight)
+                            .ok()??,
+                    ))
+                })
+                .collect();
+
+            top_threads.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(cmp::Ordering::Equal));
+            top_threads.truncate(self.top_threads);
+            let render_threads: Vec<_> = self
+                .all_trackers
+                .iter()
+                .filter(|(tid, tracker)| {
+                    tracker.render && !top_threads.iter().any(|(top, _)| top == *tid)
+                })
+                .map(|(tid, _)| (*tid, 0.0))
+                .collect();
+            top_threads.extend(render_threads);
+
+            self.top_trackers = top_threads
+                .into_iter()
+                .filter_map(|(tid, _)| match self.top_trackers.entry(tid) {
+                    Entry::Occupied(o) => Some((tid, o.remove())),
+                    Entry::Vacant(_) => Some((
+                        tid,
+                        UsageTracker::new(
+                            self.all_trackers.get(&tid)?.pid,
+                            tid,
+                            now,
+                            &self.files,
+                            runtimes,
+                            self.runqueue_weight,
+                            &self.render_names,
+                        )
+                        .ok()?,
+                    )),
+                })
+                .collect();
+        }
+    }
+
+    // Between refreshes only the thread list is diffed, a shader compile or decompression thread
+    // must not stay invisible for up to a refresh interval. A newcomer lived one sample interval
+    // at most, so its whole run time tells how busy it is. Busy ones and render threads join
+    // the top threads until the next refresh ranks them, the busiest `top_threads` at most
+    fn track_newcomers(&mut self, now: Instant, runtimes: Option<&HashMap<i32, u64>>) {
+        // Before the first refresh every thread would look new
+        if self.all_trackers.is_empty() {
+            return;
+        }
+
+        let pids = self.pids();
+        if self.files.sync(&pids).is_err() {
+            return;
+        }
+
+        let mut newcomers: Vec<_> = self
+            .files
+            .tids()
+            .iter()
+            .filter(|(tid, _)| !self.all_trackers.contains_key(tid))
+            .filter_map(|(tid, pid)| {
+                UsageTracker::new(
+                    *pid,
+                    *tid,
+                    now,
+                    &self.files,
+                    runtimes,
+                    self.runqueue_weight,
+                    &self.render_names,
+                )
+                .ok()
+            })
+            .collect();
+        if newcomers.is_empty() {
+            return;
+        }
+
+        newcomers.sort_unstable_by_key(|tracker| cmp::Reverse(tracker.last_time.cputime));
+        let busy_time = self.cadence.interval().as_nanos() as f64 * NEWCOMER_USAGE;
+        for (index, tracker) in newcomers.into_iter().enumerate() {
+            let busy = index < self.top_threads && tracker.last_time.cputime as f64 >= busy_time;
+            if busy || tracker.render {
+                self.top_trackers.insert(tracker.tid, tracker);
+            }
+            self.all_trackers.insert(tracker.tid, tracker);
+        }
+    }
+
+    pub fn render_threads(&self) -> impl Iterator<Item = i32> {
+        self.all_trackers
+            .iter()
+            .filter(|(_, tracker)| tracker.render)
+            .map(|(tid, _)| *tid)
+    }
+
+    pub fn top_threads(&self) -> impl Iterator<Item = i32> {
+        self.top_trackers.keys().copied()
+    }
+
+    // The busiest thread, which carries the frame in engines whose render thread has no
+    // well-known name
+    fn hottest(&self) -> Option<&UsageTracker> {
+        self.top_trackers
+            .values()
+            .filter(|tracker| tracker.current_usage >= HOT_THREAD_USAGE)
+            .max_by(|a, b| {
+                a.current_usage
+                    .partial_cmp(&b.current_usage)
+                    .unwrap_or(cmp::Ordering::Equal)
+            })
+    }
+
+    // The render threads plus the busiest thread, once each
+    pub fn frame_threads(&self) -> impl Iterator<Item = i32> {
+        let hottest = self.hottest().map(|tracker| tracker.tid).filter(|tid| {
+            !self
+                .all_trackers
+                .get(tid)
+                .is_some_and(|tracker| tracker.render)
+        });
+
+        self.render_threads().chain(hottest)
+    }
+
+    // Names of the render threads plus the busiest thread
+    pub fn render_thread_names(&self) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .top_trackers
+            .values()
+            .filter(|tracker| tracker.render)
+            .chain(self.hottest())
+            .filter_map(|tracker| read_comm(tracker.pid, tracker.tid))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    // Cpus the render threads plus the busiest thread last ran on
+    pub fn frame_thread_cpus(&self) -> Vec<usize> {
+        let mut cpus: Vec<_> = self
+            .top_trackers
+            .values()
+            .filter(|tracker| tracker.render)
+            .chain(self.hottest())
+            .filter_map(|tracker| read_last_cpu(tracker.pid, tracker.tid))
+            .collect();
+        cpus.sort_unstable();
+        cpus.dedup();
+        cpus
+    }
+}
+
+fn read_comm(pid: i32, tid: i32) -> Option<String> {
+    sysfs::read_to_string(format!("/proc/{pid}/task/{tid}/comm"))
+        .ok()
+        .map(|comm| comm.trim().to_string())
+}
+
+// The `processor` field of stat, counted after the comm as that may contain spaces
+fn read_last_cpu(pid: i32, tid: i32) -> Option<usize> {
+    let stat = sysfs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(36)?.parse().ok()
+}
+
+// bpf only accounts runtime, schedstat is still read when the wait time is wanted
+fn get_thread_time(
+    tid: i32,
+    files: &ProcFiles,
+    runtimes: Option<&HashMap<i32, u64>>,
+    want_wait: bool,
+) -> Result<ThreadTime> {
+    let runtime = runtimes.map(|runtimes| runtimes.get(&tid).copied());
+    // A thread that has not run since bpf attached has no entry, neither has one that exited.
+    // Its schedstat tells them apart
+    if runtime == Some(None) {
+        files.schedstat(tid)?;
+    }
+    let runtime = runtime.map(|runtime| runtime.unwrap_or(0));
+    if let Some(cputime) = runtime.filter(|_| !want_wait) {
+        return Ok(ThreadTime {
+            cputime,
+            wait_time: None,
+        });
+    }
+
+    let (cputime, wait_time) = files.schedstat(tid)?;
+    Ok(ThreadTime {
+        cputime: runtime.unwrap_or(cputime),
+        wait_time,
+    })
+}
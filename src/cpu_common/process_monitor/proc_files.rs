@@ -0,0 +1,133 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs::File, os::unix::fs::FileExt};
+
+use anyhow::{Context, Result, bail};
+use atoi::atoi;
+use hashbrown::HashMap;
+
+use crate::{
+    cpu_common::task_dir::{DIRENT_BUFFER_SIZE, TaskDir},
+    sysfs,
+};
+
+// Keeps the task directories and every schedstat of the watched processes open, so a sample is
+// a single pread per thread into a stack buffer instead of open, read and close on a fresh path.
+// Files are only opened and closed when processes or threads come and go
+#[derive(Debug)]
+pub struct ProcFiles {
+    task_dirs: HashMap<i32, TaskDir>,
+    schedstats: HashMap<i32, File>,
+    // (tid, pid) of every thread, sorted by tid
+    tids: Vec<(i32, i32)>,
+    dirents: Box<[u8]>,
+}
+
+impl ProcFiles {
+    pub fn new() -> Self {
+        Self {
+            task_dirs: HashMap::new(),
+            schedstats: HashMap::new(),
+            tids: Vec::new(),
+            dirents: vec![0; DIRENT_BUFFER_SIZE].into_boxed_slice(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.task_dirs.clear();
+        self.schedstats.clear();
+        self.tids.clear();
+    }
+
+    // Rereads the thread lists of `pids` and opens the schedstat of new threads. Processes
+    // that exited are skipped, it only fails when none of them could be read
+    pub fn sync(&mut self, pids: &[i32]) -> Result<()> {
+        self.task_dirs.retain(|pid, _| pids.contains(pid));
+        for pid in pids {
+            if !self.task_dirs.contains_key(pid) {
+                if let Ok(dir) = TaskDir::open(*pid) {
+                    self.task_dirs.insert(*pid, dir);
+                }
+            }
+        }
+
+        self.tids.clear();
+        let mut read = false;
+        for dir in self.task_dirs.values() {
+            let pid = dir.pid();
+            read |= dir
+                .read_tids(&mut self.dirents, |tid| self.tids.push((tid, pid)))
+                .is_ok();
+        }
+        if !read {
+            bail!("No task directory could be read");
+        }
+
+        self.tids.sort_unstable();
+        self.schedstats
+            .retain(|tid, _| self.tids.binary_search_by_key(tid, |(tid, _)| *tid).is_ok());
+        for (tid, pid) in &self.tids {
+            if !self.schedstats.contains_key(tid) {
+                if let Ok(file) =
+                    File::open(sysfs::resolve(format!("/proc/{pid}/task/{tid}/schedstat")))
+                {
+                    self.schedstats.insert(*tid, file);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn tids(&self) -> &[(i32, i32)] {
+        &self.tids
+    }
+
+    // Closes the schedstat of a thread that exited, before the next sync notices
+    pub fn forget(&mut self, tid: i32) {
+        self.schedstats.remove(&tid);
+    }
+
+    // Run time and runqueue wait time in ns, the first two fields of schedstat. Fails once the
+    // thread exited, a read of an exited thread is empty or ESRCH rather than zeros
+    pub fn schedstat(&self, tid: i32) -> Result<(u64, Option<u64>)> {
+        let file = self
+            .schedstats
+            .get(&tid)
+            .context("Thread schedstat is not open")?;
+
+        // Three u64 of up to 20 digits each, their separators and the newline
+        let mut buffer = [0u8; 96];
+        let len = file.read_at(&mut buffer, 0)?;
+        let buffer = &buffer[..len];
+        if buffer.is_empty() {
+            bail!("Thread exited");
+        }
+
+        let mut fields = buffer
+            .split(|byte| byte.is_ascii_whitespace())
+            .filter(|field| !field.is_empty());
+        let cputime = fields
+            .next()
+            .and_then(atoi::<u64>)
+            .context("Malformed schedstat")?;
+        let wait_time = fields.next().and_then(atoi::<u64>);
+
+        Ok((cputime, wait_time))
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::io;
+
+use anyhow::{Context, Result};
+use hashbrown::HashMap;
+use log::{info, warn};
+
+use crate::sysfs;
+
+const FIFO_PRIORITY: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPriority {
+    Fifo,
+    Nice(i32),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SavedSched {
+    policy: i32,
+    priority: i32,
+    nice: i32,
+}
+
+impl SavedSched {
+    fn read(tid: i32) -> Result<Self> {
+        let policy = unsafe { libc::sched_getscheduler(tid) };
+        if policy < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let mut param = libc::sched_param { sched_priority: 0 };
+        if unsafe { libc::sched_getparam(tid, &raw mut param) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        Ok(Self {
+            policy,
+            priority: param.sched_priority,
+            nice: read_nice(tid)?,
+        })
+    }
+
+    fn restore(self, tid: i32) -> io::Result<()> {
+        set_scheduler(tid, self.policy, self.priority)?;
+        set_nice(tid, self.nice)
+    }
+}
+
+fn read_nice(tid: i32) -> Result<i32> {
+    let stat = sysfs::read_to_string(format!("/proc/{tid}/stat"))?;
+    // Fields after the `comm`, which may contain spaces, start from `state`
+    let (_, fields) = stat.rsplit_once(')').context("Malformed stat")?;
+    Ok(fields
+        .split_whitespace()
+        .nth(16)
+        .context("Missing nice in stat")?
+        .parse()?)
+}
+
+fn set_scheduler(tid: i32, policy: i32, priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+
+    if unsafe { libc::sched_setscheduler(tid, policy, &raw const param) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn set_nice(tid: i32, nice: i32) -> io::Result<()> {
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct SchedPriority {
+    priority: Option<RenderPriority>,
+    promoted: HashMap<i32, SavedSched>,
+}
+
+impl SchedPriority {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_priority(&mut self, priority: Option<RenderPriority>) {
+        if self.priority != priority {
+            self.reset();
+        }
+
+        self.priority = priority;
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.priority.is_some()
+    }
+
+    pub fn apply(&mut self, render_threads: impl Iterator<Item = i32>) {
+        let Some(priority) = self.priority else {
+            return;
+        };
+
+        for tid in render_threads {
+            if self.promoted.contains_key(&tid) {
+                continue;
+            }
+
+            let Ok(saved) = SavedSched::read(tid) else {
+                continue;
+            };
+
+            let result = match priority {
+                RenderPriority::Fifo => set_scheduler(tid, libc::SCHED_FIFO, FIFO_PRIORITY),
+                RenderPriority::Nice(nice) => set_nice(tid, nice),
+            };
+
+            match result {
+                Ok(()) => info!("Promoted render thread {tid} to {priority:?}"),
+                Err(e) => warn!("Failed to promote render thread {tid}: {e}"),
+            }
+
+            // Threads the kernel refused are remembered too, so they are not retried every frame
+            self.promoted.insert(tid, saved);
+        }
+    }
+
+    // Demote everything we promoted, threads that already exited are skipped silently
+    pub fn reset(&mut self) {
+        for (tid, saved) in self.promoted.drain() {
+            let _ = saved.restore(tid);
+        }
+    }
+}
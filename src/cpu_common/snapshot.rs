@@ -0,0 +1,178 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::Result;
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cgroup,
+    paths::{self, Root},
+    sysfs,
+};
+
+const MANIFEST_PATH: &str = "/data/adb/fas-rs/restore.json";
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+// Nodes whose value can be written back as it was read. Task lists move threads rather than
+// hold a setting, and character devices like cpu_dma_latency only act while kept open
+const RESTORABLE_PREFIXES: [&str; 6] = [
+    "/sys/",
+    "/proc/irq/",
+    "/proc/sys/",
+    "/dev/cpuset/",
+    "/dev/stune/",
+    "/dev/cpuctl/",
+];
+
+static SNAPSHOT: OnceLock<Mutex<Manifest>> = OnceLock::new();
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    // Values from before a reboot were reset by it, a manifest of another boot is dropped
+    boot_id: String,
+    // Node to the value it had before fas-rs first wrote it
+    values: BTreeMap<PathBuf, String>,
+}
+
+impl Manifest {
+    // A manifest left by a run that crashed is kept, its values are still the original ones
+    fn load() -> Self {
+        let boot_id = boot_id();
+        fs::read_to_string(manifest_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<Self>(&content).ok())
+            .filter(|manifest| manifest.boot_id == boot_id)
+            .unwrap_or(Self {
+                boot_id,
+                values: BTreeMap::new(),
+            })
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = manifest_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Never half a manifest, a crash right now is exactly when it is needed
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+}
+
+// Below the fake root too, a test run must not touch the device's manifest
+fn manifest_path() -> PathBuf {
+    sysfs::resolve(MANIFEST_PATH)
+}
+
+fn boot_id() -> String {
+    sysfs::read_to_string(BOOT_ID_PATH)
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn restorable(path: &Path) -> bool {
+    let lossy = path.to_string_lossy();
+
+    // `[paths]` may point outside of the usual places
+    (RESTORABLE_PREFIXES
+        .iter()
+        .any(|prefix| lossy.starts_with(prefix))
+        || Root::ALL
+            .into_iter()
+            .filter_map(paths::get)
+            .any(|root| path.starts_with(root)))
+        && !cgroup::is_task_list(path)
+}
+
+// Records the value of `path` the first time fas-rs is about to write it, every later write
+// of the same node is a lookup
+pub fn capture(path: &Path) {
+    if !restorable(path) {
+        return;
+    }
+
+    let mut manifest = SNAPSHOT.get_or_init(|| Mutex::new(Manifest::load())).lock();
+    if manifest.values.contains_key(path) {
+        return;
+    }
+
+    let Ok(value) = sysfs::read_to_string(path) else {
+        return;
+    };
+    manifest
+        .values
+        .insert(path.to_path_buf(), value.trim().to_string());
+
+    if let Err(e) = manifest.save() {
+        warn!("Failed to save restore manifest {MANIFEST_PATH}: {e}");
+    }
+}
+
+// After a clean exit every value is back already
+pub fn discard() {
+    if let Some(manifest) = SNAPSHOT.get() {
+        manifest.lock().values.clear();
+    }
+    let _ = fs::remove_file(manifest_path());
+}
+
+// Writes every recorded value back, returns how many nodes were restored and the ones that
+// failed. Nodes that depend on each other (a min above the current max) are written twice
+pub fn restore() -> Result<(usize, Vec<PathBuf>)> {
+    let content = match fs::read_to_string(manifest_path()) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, Vec::new())),
+        Err(e) => return Err(e.into()),
+    };
+    let manifest: Manifest = serde_json::from_str(&content)?;
+    // The reboot already reset everything the manifest holds
+    if manifest.boot_id != boot_id() {
+        discard();
+        return Ok((0, Vec::new()));
+    }
+
+    let mut failed = Vec::new();
+    for _ in 0..2 {
+        failed = manifest
+            .values
+            .iter()
+            .filter(|(path, value)| sysfs::write(path, value).is_err())
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        if failed.is_empty() {
+            break;
+        }
+    }
+
+    if failed.is_empty() {
+        discard();
+    }
+
+    Ok((manifest.values.len() - failed.len(), failed))
+}
@@ -0,0 +1,341 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+#[cfg(debug_assertions)]
+use log::debug;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::cpu_info::Info;
+
+// Control output is stepped through the frequency tables, one entry per this much of it, so a
+// decision moves every device by the same number of OPPs whatever its table spacing
+const CONTROL_PER_STEP: f64 = 100_000.0;
+const LADDER_JANK_STEPS: isize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyKind {
+    #[default]
+    Heuristic,
+    Pid,
+    StepLadder,
+}
+
+// Utilization tiers of the step ladder and how long each direction has to be asked for before
+// it is taken, down is slower so a load hovering around a threshold does not bounce
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LadderConfig {
+    // Below: one step down
+    #[serde(default = "LadderConfig::default_value_down")]
+    pub down: f64,
+    // Above: one step up, in between the ladder holds
+    #[serde(default = "LadderConfig::default_value_up")]
+    pub up: f64,
+    // Above: two steps up
+    #[serde(default = "LadderConfig::default_value_jump")]
+    pub jump: f64,
+    #[serde(default = "LadderConfig::default_value_up_hold_ms")]
+    pub up_hold_ms: u64,
+    // Halved on battery saver
+    #[serde(default = "LadderConfig::default_value_down_hold_ms")]
+    pub down_hold_ms: u64,
+}
+
+impl LadderConfig {
+    pub const fn default_value_down() -> f64 {
+        0.4
+    }
+
+    pub const fn default_value_up() -> f64 {
+        0.7
+    }
+
+    pub const fn default_value_jump() -> f64 {
+        0.9
+    }
+
+    pub const fn default_value_up_hold_ms() -> u64 {
+        0
+    }
+
+    pub const fn default_value_down_hold_ms() -> u64 {
+        500
+    }
+}
+
+impl Default for LadderConfig {
+    fn default() -> Self {
+        Self {
+            down: Self::default_value_down(),
+            up: Self::default_value_up(),
+            jump: Self::default_value_jump(),
+            up_hold_ms: Self::default_value_up_hold_ms(),
+            down_hold_ms: Self::default_value_down_hold_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StrategyInput {
+    // Frame time error through the pid controller in kHz, positive asks for more
+    pub control: isize,
+    pub is_janked: bool,
+    // Busiest game thread's share of a core, None right after a jank or before the first sample
+    pub util: Option<f64>,
+    // Highest capacity any policy delivers at its current frequency, 1024 is the biggest core
+    // at its max
+    pub cur_capacity_max: f64,
+    pub headroom: isize,
+    // Thermal and mode ceiling as a fraction of each policy's max, applied again afterwards
+    pub freq_cap: f64,
+    pub battery_powersave: bool,
+}
+
+// Turns one decision's inputs into a target frequency per policy. The targets still go through
+// the cluster constraints, touch boost and the frequency cap before they are written
+pub trait Strategy: Debug + Send {
+    fn name(&self) -> &'static str;
+    // Called when a game starts, learned state of the last one is dropped
+    fn reset(&mut self) {}
+    fn decide(&mut self, input: &StrategyInput, cpus: &[Info]) -> HashMap<i32, isize>;
+}
+
+pub fn new_strategy(kind: StrategyKind, ladder: LadderConfig) -> Box<dyn Strategy> {
+    let strategy: Box<dyn Strategy> = match kind {
+        StrategyKind::Heuristic => Box::new(Heuristic::default()),
+        StrategyKind::Pid => Box::new(Pid::default()),
+        StrategyKind::StepLadder => Box::new(StepLadder::new(ladder)),
+    };
+
+    info!("Frequency strategy: {}", strategy.name());
+    strategy
+}
+
+fn cur_fas_freq_max(cpus: &[Info]) -> isize {
+    cpus.iter()
+        .map(|cpu| cpu.cur_fas_freq)
+        .max()
+        .unwrap_or_default()
+}
+
+// Steps through the frequency tables by the control, capped by what the utilization suggests
+#[derive(Debug, Default)]
+struct Heuristic {
+    // Control that did not add up to a whole table step yet
+    step_residue: f64,
+}
+
+impl Strategy for Heuristic {
+    fn name(&self) -> &'static str {
+        "heuristic"
+    }
+
+    fn reset(&mut self) {
+        self.step_residue = 0.0;
+    }
+
+    fn decide(&mut self, input: &StrategyInput, cpus: &[Info]) -> HashMap<i32, isize> {
+        let cur_fas_freq_max = cur_fas_freq_max(cpus);
+
+        // Whole table steps for this control, the remainder carries over to the next decision
+        self.step_residue += input.control as f64 / CONTROL_PER_STEP;
+        let steps = self.step_residue.trunc();
+        self.step_residue -= steps;
+
+        cpus.iter()
+            .map(|cpu| {
+                let stepped_freq = cpu.step(cur_fas_freq_max, steps as isize);
+
+                (
+                    cpu.policy,
+                    match input.util {
+                        Some(util) if !input.is_janked => {
+                            // The same work needs more frequency on a cluster of smaller cores
+                            let max_freq = cpu.freqs.last().copied().unwrap_or_default() as f64;
+                            let util_tracking_sugg_freq = (input.cur_capacity_max * util
+                                / f64::from(cpu.capacity)
+                                * max_freq
+                                / 0.5) as isize // min_util: 50%
+                                + input.headroom;
+                            #[cfg(debug_assertions)]
+                            debug!(
+                                "policy{}: util: {util}, cur_capacity_max: {:.0}, util_tracking_sugg_freq: {util_tracking_sugg_freq}",
+                                cpu.policy, input.cur_capacity_max
+                            );
+                            stepped_freq.min(cpu.snap(util_tracking_sugg_freq))
+                        }
+                        _ => stepped_freq,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+// The pid output taken as is as a frequency change, without utilization tracking. The target
+// is kept unsnapped so controls smaller than a table step still add up
+#[derive(Debug, Default)]
+struct Pid {
+    target: Option<f64>,
+}
+
+impl Strategy for Pid {
+    fn name(&self) -> &'static str {
+        "pid"
+    }
+
+    fn reset(&mut self) {
+        self.target = None;
+    }
+
+    fn decide(&mut self, input: &StrategyInput, cpus: &[Info]) -> HashMap<i32, isize> {
+        let min_freq = cpus
+            .iter()
+            .filter_map(|cpu| cpu.freqs.first())
+            .min()
+            .copied()
+            .unwrap_or_default();
+        let max_freq = cpus
+            .iter()
+            .filter_map(|cpu| cpu.freqs.last())
+            .max()
+            .copied()
+            .unwrap_or_default();
+
+        let target = (self.target.unwrap_or_else(|| cur_fas_freq_max(cpus) as f64)
+            + input.control as f64)
+            .clamp(min_freq as f64, max_freq as f64);
+        self.target = Some(target);
+
+        cpus.iter()
+            .map(|cpu| (cpu.policy, cpu.snap(target as isize)))
+            .collect()
+    }
+}
+
+// Steps through the frequency tables by utilization tiers: down below `down`, hold up to `up`,
+// one step up above it and two above `jump`. A direction is only taken once it has been asked
+// for during its hold time, janks climb a few steps at once right away. Without a utilization
+// sample the sign of the control decides
+#[derive(Debug)]
+struct StepLadder {
+    ladder: LadderConfig,
+    // Direction asked for and since when
+    pending: Option<(isize, Instant)>,
+}
+
+impl StepLadder {
+    const fn new(ladder: LadderConfig) -> Self {
+        Self {
+            ladder,
+            pending: None,
+        }
+    }
+
+    fn tier(&self, input: &StrategyInput) -> isize {
+        match input.util {
+            Some(util) if util >= self.ladder.jump => 2,
+            Some(util) if util >= self.ladder.up => 1,
+            // Never down while frames are late
+            Some(util) if util < self.ladder.down && input.control <= 0 => -1,
+            Some(_) => 0,
+            None => input.control.signum(),
+        }
+    }
+
+    fn hysteresis(&mut self, steps: isize, battery_powersave: bool) -> isize {
+        if steps == 0 {
+            self.pending = None;
+            return 0;
+        }
+
+        let direction = steps.signum();
+        let hold = if direction > 0 {
+            self.ladder.up_hold_ms
+        } else if battery_powersave {
+            self.ladder.down_hold_ms / 2
+        } else {
+            self.ladder.down_hold_ms
+        };
+
+        let since = match self.pending {
+            Some((pending, since)) if pending == direction => since,
+            _ => {
+                let now = Instant::now();
+                self.pending = Some((direction, now));
+                now
+            }
+        };
+
+        if since.elapsed() < Duration::from_millis(hold) {
+            return 0;
+        }
+
+        // The next step in the same direction waits a whole hold time again
+        self.pending = Some((direction, Instant::now()));
+        steps
+    }
+}
+
+impl Strategy for StepLadder {
+    fn name(&self) -> &'static str {
+        "step_ladder"
+    }
+
+    fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    fn decide(&mut self, input: &StrategyInput, cpus: &[Info]) -> HashMap<i32, isize> {
+        let cur_fas_freq_max = cur_fas_freq_max(cpus);
+
+        let steps = if input.is_janked {
+            self.pending = None;
+            LADDER_JANK_STEPS
+        } else {
+            let tier = self.tier(input);
+            self.hysteresis(tier, input.battery_powersave)
+        };
+
+        #[cfg(debug_assertions)]
+        debug!("step_ladder: util: {:?}, steps: {steps}", input.util);
+
+        cpus.iter()
+            .map(|cpu| {
+                // Climbing past the cap only winds the ladder up for nothing
+                let cap = cpu
+                    .freqs
+                    .last()
+                    .map_or(isize::MAX, |max| (*max as f64 * input.freq_cap) as isize);
+                let steps = if cur_fas_freq_max >= cap {
+                    steps.min(0)
+                } else {
+                    steps
+                };
+
+                (cpu.policy, cpu.step(cur_fas_freq_max, steps))
+            })
+            .collect()
+    }
+}
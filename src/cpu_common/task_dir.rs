@@ -0,0 +1,96 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+};
+
+use atoi::atoi;
+use stringzilla::sz;
+
+use crate::sysfs;
+
+// Large enough for a few hundred dirents per getdents64 call
+pub const DIRENT_BUFFER_SIZE: usize = 8192;
+// Offset of d_name in struct linux_dirent64
+const DIRENT_NAME_OFFSET: usize = 19;
+
+// `/proc/<pid>/task` kept open, listing the threads again is a rewind and getdents64 into a
+// reused buffer instead of opening the directory and allocating a path per thread
+#[derive(Debug)]
+pub struct TaskDir {
+    pid: i32,
+    dir: File,
+}
+
+impl TaskDir {
+    pub fn open(pid: i32) -> io::Result<Self> {
+        let dir = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY)
+            .open(sysfs::resolve(format!("/proc/{pid}/task")))?;
+
+        Ok(Self { pid, dir })
+    }
+
+    pub const fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    // Calls `f` with every thread id, fails once the process exited
+    pub fn read_tids<F: FnMut(i32)>(&self, dirents: &mut [u8], mut f: F) -> io::Result<()> {
+        let fd = self.dir.as_raw_fd();
+
+        if unsafe { libc::lseek(fd, 0, libc::SEEK_SET) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        loop {
+            let len = unsafe {
+                libc::syscall(
+                    libc::SYS_getdents64,
+                    fd,
+                    dirents.as_mut_ptr(),
+                    dirents.len(),
+                )
+            };
+            if len < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if len == 0 {
+                return Ok(());
+            }
+
+            let mut offset = 0;
+            while offset < len as usize {
+                let record = &dirents[offset..];
+                let reclen = u16::from_ne_bytes([record[16], record[17]]) as usize;
+                let name = &record[DIRENT_NAME_OFFSET..reclen];
+                let name = &name[..sz::find(name, b"\0").unwrap_or(name.len())];
+
+                // `.` and `..` are skipped since they do not parse
+                if let Some(tid) = atoi::<i32>(name) {
+                    f(tid);
+                }
+
+                offset += reclen;
+            }
+        }
+    }
+}
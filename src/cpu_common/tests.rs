@@ -0,0 +1,231 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+// Runs the cpu side against a fake device tree in a temp dir, see `sysfs::Rooted`
+
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use super::{
+    Controller, cpu_info::Info, jank_burst_ratio, process_monitor::ProcessMonitor, snapshot,
+    topology::Topology,
+};
+use crate::sysfs::{self, Rooted};
+
+const CPUFREQ: &str = "sys/devices/system/cpu/cpufreq";
+
+// Tests holding the tree, the last one to drop it deletes it
+static USERS: Mutex<usize> = Mutex::new(0);
+
+// `sysfs::install` holds for the whole process, so every test shares one tree. Built again if
+// a test starts after the others already deleted it
+struct Tree;
+
+impl Deref for Tree {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        path()
+    }
+}
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        let mut users = USERS.lock();
+        *users -= 1;
+        if *users == 0 {
+            let _ = fs::remove_dir_all(path());
+        }
+    }
+}
+
+fn path() -> &'static Path {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+    PATH.get_or_init(|| {
+        let root = env::temp_dir().join(format!("fas-rs-sysfs-{}", process::id()));
+        assert!(sysfs::install(Box::new(Rooted::new(&root))));
+        root
+    })
+}
+
+fn root() -> Tree {
+    let root = path();
+    let mut users = USERS.lock();
+    if *users == 0 {
+        let _ = fs::remove_dir_all(root);
+
+        // A 1+3+4 numbered like most 8 core socs, frequencies deliberately unsorted
+        policy(root, 0, &[0, 1, 2, 3], 325, "1800000 300000 1200000");
+        policy(root, 4, &[4, 5, 6], 870, "2400000 600000 1500000");
+        policy(root, 7, &[7], 1024, "3000000 800000 2000000");
+        put(root, "proc/sys/kernel/random/boot_id", "test-boot");
+    }
+    *users += 1;
+
+    Tree
+}
+
+fn put(root: &Path, node: &str, content: &str) {
+    let path = root.join(node);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+fn policy(root: &Path, policy: usize, cpus: &[usize], capacity: u32, freqs: &str) {
+    let dir = format!("{CPUFREQ}/policy{policy}");
+    let cpu_list = cpus
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    put(root, &format!("{dir}/scaling_available_frequencies"), freqs);
+    put(root, &format!("{dir}/affected_cpus"), &cpu_list);
+    put(root, &format!("{dir}/related_cpus"), &cpu_list);
+    put(root, &format!("{dir}/scaling_min_freq"), "0");
+    put(root, &format!("{dir}/scaling_max_freq"), "0");
+
+    for cpu in cpus {
+        put(
+            root,
+            &format!("sys/devices/system/cpu/cpu{cpu}/cpu_capacity"),
+            &capacity.to_string(),
+        );
+    }
+}
+
+fn info(policy: usize) -> Info {
+    Info::new(format!("/{CPUFREQ}/policy{policy}")).unwrap()
+}
+
+#[test]
+fn topology_names_clusters_by_capacity() {
+    let _root = root();
+    let (mut cpus, unavailable) = Controller::load_cpu_infos().unwrap();
+    assert_eq!(cpus.len(), 3);
+    assert!(unavailable.is_empty());
+
+    let topology = Topology::detect(&mut cpus);
+    assert_eq!(topology.name(0), "little");
+    assert_eq!(topology.name(4), "big");
+    assert_eq!(topology.name(7), "prime");
+    assert_eq!(topology.cluster(4).unwrap().cpus, [4, 5, 6]);
+    assert_eq!(
+        topology.split_little(),
+        (vec![0, 1, 2, 3], vec![4, 5, 6, 7])
+    );
+
+    let little = cpus.iter().find(|cpu| cpu.policy == 0).unwrap();
+    assert_eq!(little.capacity, 325);
+}
+
+#[test]
+fn frequencies_snap_to_the_table() {
+    let _root = root();
+    let cpu = info(4);
+    assert_eq!(cpu.freqs, [600_000, 1_500_000, 2_400_000]);
+
+    assert_eq!(cpu.snap(2_000_000), 1_500_000);
+    assert_eq!(cpu.snap(1_500_000), 1_500_000);
+    assert_eq!(cpu.snap(100_000), 600_000);
+    assert_eq!(cpu.snap(9_000_000), 2_400_000);
+
+    assert_eq!(cpu.step(2_000_000, 1), 2_400_000);
+    assert_eq!(cpu.step(2_000_000, -5), 600_000);
+    assert_eq!(cpu.step(2_000_000, 5), 2_400_000);
+}
+
+#[test]
+fn restore_writes_back_the_captured_values() {
+    let root = root();
+    let node = Path::new("/sys/devices/system/cpu/cpufreq/policy7/scaling_max_freq");
+    sysfs::write(node, "3000000").unwrap();
+
+    snapshot::capture(node);
+    sysfs::write(node, "800000").unwrap();
+    // Only the first value before fas-rs touched the node counts
+    snapshot::capture(node);
+
+    assert_eq!(snapshot::restore().unwrap(), (1, Vec::new()));
+    assert_eq!(sysfs::read_to_string(node).unwrap(), "3000000");
+    assert!(!root.join("data/adb/fas-rs/restore.json").exists());
+    assert_eq!(info(7).read_limits(), (0, 3_000_000));
+}
+
+#[test]
+fn jank_burst_holds_then_decays_linearly() {
+    let duration = Duration::from_millis(100);
+    let ratio_near = |ms, expected: f64| {
+        jank_burst_ratio(Duration::from_millis(ms), duration)
+            .is_some_and(|ratio| (ratio - expected).abs() < 1e-9)
+    };
+
+    assert!(ratio_near(0, 1.0));
+    assert!(ratio_near(100, 1.0));
+    assert!(ratio_near(150, 0.5));
+    assert!(ratio_near(190, 0.1));
+    assert!(jank_burst_ratio(Duration::from_millis(200), duration).is_none());
+    assert!(jank_burst_ratio(Duration::ZERO, Duration::ZERO).is_none());
+}
+
+#[test]
+fn process_monitor_reads_schedstat_utilization() {
+    let root = root();
+    let task = |tid: i32| format!("proc/42/task/{tid}");
+    // Run time in ns, the wait time is not weighted in by default
+    let run = |tid: i32, ms: u64| {
+        put(
+            &root,
+            &format!("{}/schedstat", task(tid)),
+            &format!("{} 0 0\n", ms * 1_000_000),
+        );
+    };
+    put(&root, &format!("{}/comm", task(100)), "Worker\n");
+    put(&root, &format!("{}/comm", task(101)), "Idle\n");
+    run(100, 0);
+    run(101, 0);
+
+    let mut monitor = ProcessMonitor::new();
+    let interval = Duration::from_millis(200);
+    monitor.set_params(interval, interval, Duration::ZERO, 8, 0.0);
+    monitor.set_pid(Some(42));
+
+    // The first sample opens the trackers, the second ranks the threads and the third is the
+    // first with a delta for the top threads
+    monitor.update().unwrap();
+    let mut util = 0.0;
+    for ms in [100, 200] {
+        thread::sleep(interval);
+        run(100, ms);
+        run(101, ms / 20);
+        util = monitor.update().unwrap();
+    }
+
+    // 100ms run of the busy thread over at least one interval, the idle one ran 5ms
+    assert!(util > 0.1 && util <= 0.5, "{util}");
+    assert!(monitor.top_threads().any(|tid| tid == 100));
+}
@@ -0,0 +1,204 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use hashbrown::HashMap;
+use log::{info, warn};
+use nix::{
+    sched::{CpuSet, sched_getaffinity, sched_setaffinity},
+    unistd::Pid,
+};
+
+use super::task_dir::{DIRENT_BUFFER_SIZE, TaskDir};
+use crate::{misc::glob_match, sysfs};
+
+// Threads are recreated by loading screens and scene switches, new ones are picked up this often
+const SCAN_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AffinityRule {
+    pub thread: String,
+    pub cpus: Vec<usize>,
+}
+
+impl AffinityRule {
+    // `cpus` is a cpu list like `7` or `0-3,6`
+    pub fn parse<S: AsRef<str>>(thread: S, cpus: &str) -> Option<Self> {
+        let mut list = Vec::new();
+
+        for range in cpus.split(',').map(str::trim) {
+            match range.split_once('-') {
+                Some((first, last)) => {
+                    let first: usize = first.trim().parse().ok()?;
+                    let last: usize = last.trim().parse().ok()?;
+                    if first > last {
+                        return None;
+                    }
+                    list.extend(first..=last);
+                }
+                None => list.push(range.parse().ok()?),
+            }
+        }
+
+        list.sort_unstable();
+        list.dedup();
+        (!list.is_empty() && list.iter().all(|cpu| *cpu < CpuSet::count())).then(|| Self {
+            thread: thread.as_ref().to_string(),
+            cpus: list,
+        })
+    }
+
+    fn cpu_set(&self) -> CpuSet {
+        let mut cpu_set = CpuSet::new();
+        for cpu in &self.cpus {
+            let _ = cpu_set.set(*cpu);
+        }
+        cpu_set
+    }
+
+    fn matches(&self, comm: &str) -> bool {
+        glob_match(self.thread.as_bytes(), comm.as_bytes())
+    }
+}
+
+#[derive(Debug)]
+pub struct ThreadAffinity {
+    pid: Option<i32>,
+    // Exact thread names first, then the longest pattern
+    rules: Vec<AffinityRule>,
+    // Affinity of every thread we moved, as it was before
+    saved: HashMap<i32, CpuSet>,
+    // Threads no rule matched when last looked at, renamed threads are rechecked
+    unmatched: HashMap<i32, String>,
+    scan_timer: Instant,
+    // Kept across scans, opened again when the game changes
+    task_dir: Option<TaskDir>,
+    dirents: Box<[u8]>,
+    // Sorted thread ids of the last scan
+    tids: Vec<i32>,
+}
+
+impl ThreadAffinity {
+    pub fn new() -> Self {
+        Self {
+            pid: None,
+            rules: Vec::new(),
+            saved: HashMap::new(),
+            unmatched: HashMap::new(),
+            scan_timer: Instant::now(),
+            task_dir: None,
+            dirents: vec![0; DIRENT_BUFFER_SIZE].into_boxed_slice(),
+            tids: Vec::new(),
+        }
+    }
+
+    pub fn set_rules(&mut self, mut rules: Vec<AffinityRule>) {
+        rules.sort_by(|a, b| {
+            let is_pattern = |rule: &AffinityRule| rule.thread.contains(['*', '?']);
+            is_pattern(a)
+                .cmp(&is_pattern(b))
+                .then_with(|| b.thread.len().cmp(&a.thread.len()))
+        });
+
+        if self.rules != rules {
+            self.reset();
+            self.rules = rules;
+        }
+    }
+
+    pub fn set_pid(&mut self, pid: Option<i32>) {
+        if self.pid != pid {
+            self.reset();
+            self.pid = pid;
+            self.task_dir = None;
+        }
+    }
+
+    pub fn apply(&mut self) {
+        if self.rules.is_empty() || self.scan_timer.elapsed() < SCAN_TIME {
+            return;
+        }
+        self.scan_timer = Instant::now();
+
+        let Some(pid) = self.pid else {
+            return;
+        };
+        if self.task_dir.is_none() {
+            self.task_dir = TaskDir::open(pid).ok();
+        }
+        let Some(task_dir) = &self.task_dir else {
+            return;
+        };
+
+        self.tids.clear();
+        let tids = &mut self.tids;
+        if task_dir
+            .read_tids(&mut self.dirents, |tid| tids.push(tid))
+            .is_err()
+        {
+            self.task_dir = None;
+            return;
+        }
+        self.tids.sort_unstable();
+
+        let tids = &self.tids;
+        self.saved.retain(|tid, _| tids.binary_search(tid).is_ok());
+        self.unmatched
+            .retain(|tid, _| tids.binary_search(tid).is_ok());
+
+        for &tid in &self.tids {
+            if self.saved.contains_key(&tid) {
+                continue;
+            }
+
+            let Ok(comm) = sysfs::read_to_string(format!("/proc/{pid}/task/{tid}/comm")) else {
+                continue;
+            };
+            let comm = comm.trim();
+            if self.unmatched.get(&tid).is_some_and(|last| last == comm) {
+                continue;
+            }
+
+            let Some(rule) = self.rules.iter().find(|rule| rule.matches(comm)) else {
+                self.unmatched.insert(tid, comm.to_string());
+                continue;
+            };
+            self.unmatched.remove(&tid);
+
+            let Ok(saved) = sched_getaffinity(Pid::from_raw(tid)) else {
+                continue;
+            };
+
+            match sched_setaffinity(Pid::from_raw(tid), &rule.cpu_set()) {
+                Ok(()) => info!("Bound thread {comm} ({tid}) to cpus {:?}", rule.cpus),
+                Err(e) => warn!("Failed to bind thread {comm} ({tid}): {e}"),
+            }
+
+            // Threads the kernel refused are remembered too, so they are not retried every scan
+            self.saved.insert(tid, saved);
+        }
+    }
+
+    // Threads that already exited are skipped silently
+    pub fn reset(&mut self) {
+        for (tid, saved) in self.saved.drain() {
+            let _ = sched_setaffinity(Pid::from_raw(tid), &saved);
+        }
+        self.unmatched.clear();
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{cluster_policy::cluster_name, cpu_info::Info};
+use crate::sysfs;
+
+const CPU_PATH: &str = "/sys/devices/system/cpu";
+// Capacity of the biggest core, cpu_capacity is normalized to it
+pub const CAPACITY_SCALE: u32 = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterTopology {
+    pub policy: i32,
+    // `little`, `big` or `prime`, as used by `clusters` and `[tunables]`
+    pub name: String,
+    pub cpus: Vec<usize>,
+    // Compute capacity of one core at the cluster's max frequency, 1024 for the biggest core
+    pub capacity: u32,
+    pub cluster_id: Option<i32>,
+    pub package_id: Option<i32>,
+}
+
+// Which policy is which cluster, by core capacity rather than by policy number, so layouts
+// like 1+3+4, 1+4+3 or 1+2+2+3 and policies numbered out of order come out right
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    clusters: Vec<ClusterTopology>,
+}
+
+impl Topology {
+    // Also stores each policy's capacity in its `Info`
+    pub fn detect(cpus: &mut [Info]) -> Self {
+        let max_freq = cpus
+            .iter()
+            .filter_map(|cpu| cpu.freqs.last())
+            .max()
+            .copied()
+            .unwrap_or(1)
+            .max(1);
+
+        let mut clusters: Vec<ClusterTopology> = cpus
+            .iter_mut()
+            .map(|cpu| {
+                let related = cpu.related_cpus();
+                let first = related.first().copied().unwrap_or_default();
+                // Kernels without cpu_capacity get an estimate from the max frequency
+                let capacity = related
+                    .iter()
+                    .filter_map(|core| read_value::<u32>(*core, "cpu_capacity"))
+                    .max()
+                    .unwrap_or_else(|| {
+                        (cpu.freqs.last().copied().unwrap_or_default() as f64 / max_freq as f64
+                            * f64::from(CAPACITY_SCALE)) as u32
+                    })
+                    .max(1);
+                cpu.capacity = capacity;
+
+                ClusterTopology {
+                    policy: cpu.policy,
+                    name: String::new(),
+                    cpus: related,
+                    capacity,
+                    cluster_id: read_value(first, "topology/cluster_id"),
+                    package_id: read_value(first, "topology/physical_package_id"),
+                }
+            })
+            .collect();
+
+        let count = clusters.len();
+        let min_capacity = clusters.iter().map(|cluster| cluster.capacity).min();
+        let max_capacity = clusters.iter().map(|cluster| cluster.capacity).max();
+        // A prime cluster needs a middle one below it and must be the only one on top
+        let has_prime = clusters.len() >= 3
+            && clusters
+                .iter()
+                .filter(|cluster| Some(cluster.capacity) == max_capacity)
+                .count()
+                == 1;
+
+        for (index, cluster) in clusters.iter_mut().enumerate() {
+            // Identical clusters (4+4 of the same core) can only be told apart by policy order
+            cluster.name = if count > 1 && min_capacity == max_capacity {
+                cluster_name(index, count)
+            } else if Some(cluster.capacity) == min_capacity {
+                "little"
+            } else if has_prime && Some(cluster.capacity) == max_capacity {
+                "prime"
+            } else {
+                "big"
+            }
+            .to_string();
+        }
+
+        let topology = Self { clusters };
+        info!("Cpu topology: {}", topology.summary());
+
+        topology
+    }
+
+    pub fn clusters(&self) -> &[ClusterTopology] {
+        &self.clusters
+    }
+
+    pub fn cluster(&self, policy: i32) -> Option<&ClusterTopology> {
+        self.clusters
+            .iter()
+            .find(|cluster| cluster.policy == policy)
+    }
+
+    pub fn name(&self, policy: i32) -> &str {
+        self.cluster(policy)
+            .map_or("big", |cluster| cluster.name.as_str())
+    }
+
+    // Cores of the little clusters and of all the others
+    pub fn split_little(&self) -> (Vec<usize>, Vec<usize>) {
+        let (little, big): (Vec<_>, Vec<_>) = self
+            .clusters
+            .iter()
+            .partition(|cluster| cluster.name == "little");
+        let cores = |clusters: Vec<&ClusterTopology>| {
+            let mut cores: Vec<usize> = clusters
+                .into_iter()
+                .flat_map(|cluster| cluster.cpus.iter().copied())
+                .collect();
+            cores.sort_unstable();
+            cores
+        };
+
+        (cores(little), cores(big))
+    }
+
+    // Like `1+3+4 (policy7 prime 1024, policy4 big 870, policy0 little 325)`, biggest first
+    fn summary(&self) -> String {
+        let mut clusters: Vec<_> = self.clusters.iter().collect();
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.capacity));
+
+        let layout = clusters
+            .iter()
+            .map(|cluster| cluster.cpus.len().to_string())
+            .collect::<Vec<_>>()
+            .join("+");
+        let detail = clusters
+            .iter()
+            .map(|cluster| {
+                format!(
+                    "policy{} {} {}",
+                    cluster.policy, cluster.name, cluster.capacity
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("{layout} ({detail})")
+    }
+}
+
+fn read_value<T: std::str::FromStr>(cpu: usize, node: &str) -> Option<T> {
+    sysfs::read_to_string(Path::new(CPU_PATH).join(format!("cpu{cpu}")).join(node))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
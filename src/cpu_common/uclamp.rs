@@ -0,0 +1,198 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io, mem,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    file_handler::FileHandler,
+    sysfs,
+};
+const SCHED_FLAG_KEEP_POLICY: u64 = 0x08;
+const SCHED_FLAG_KEEP_PARAMS: u64 = 0x10;
+const SCHED_FLAG_UTIL_CLAMP_MIN: u64 = 0x20;
+const SCHED_CAPACITY_SCALE: f64 = 1024.0;
+// The boost only moves the scheduler's util estimate, a few frames late is as good as per frame.
+// A change of the frame threads is applied right away
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+#[repr(C)]
+#[derive(Debug, Default)]
+struct SchedAttr {
+    size: u32,
+    sched_policy: u32,
+    sched_flags: u64,
+    sched_nice: i32,
+    sched_priority: u32,
+    sched_runtime: u64,
+    sched_deadline: u64,
+    sched_period: u64,
+    sched_util_min: u32,
+    sched_util_max: u32,
+}
+
+fn set_task_uclamp_min(tid: i32, util_min: u32) -> io::Result<()> {
+    let attr = SchedAttr {
+        size: mem::size_of::<SchedAttr>() as u32,
+        sched_flags: SCHED_FLAG_KEEP_POLICY | SCHED_FLAG_KEEP_PARAMS | SCHED_FLAG_UTIL_CLAMP_MIN,
+        sched_util_min: util_min,
+        ..SchedAttr::default()
+    };
+
+    if unsafe { libc::syscall(libc::SYS_sched_setattr, tid, &raw const attr, 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+// What `uclamp_boost` raises the uclamp.min of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UclampScope {
+    // The whole top-app cgroup and the frame threads
+    #[default]
+    TopApp,
+    // Only the frame threads, EAS moves them to the big cores while the cluster floors stay put
+    Threads,
+}
+
+#[derive(Debug, Default)]
+pub struct Uclamp {
+    enabled: bool,
+    scope: UclampScope,
+    boosted_tids: Vec<i32>,
+    saved_top_app: Option<String>,
+    top_app: Option<PathBuf>,
+    // Whole percents and the frame threads they were applied to, finer steps are noise
+    applied: Option<(u32, Vec<i32>)>,
+    update_timer: Option<Instant>,
+}
+
+impl Uclamp {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool, file_handler: &mut FileHandler) {
+        if self.enabled && !enabled {
+            self.reset(file_handler);
+        }
+
+        self.enabled = enabled;
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_scope(&mut self, scope: UclampScope, file_handler: &mut FileHandler) {
+        if self.scope != scope {
+            self.reset(file_handler);
+            self.scope = scope;
+        }
+    }
+
+    pub fn apply(
+        &mut self,
+        util_min: f64,
+        frame_threads: impl Iterator<Item = i32>,
+        file_handler: &mut FileHandler,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let percent = (util_min.clamp(0.0, 1.0) * 100.0).round() as u32;
+        let mut frame_threads: Vec<_> = frame_threads.collect();
+        frame_threads.sort_unstable();
+
+        // Same threads: only a new value, and that at most once per `UPDATE_INTERVAL`
+        let due = self
+            .update_timer
+            .is_none_or(|timer| timer.elapsed() >= UPDATE_INTERVAL);
+        if let Some((last, tids)) = &self.applied {
+            if *tids == frame_threads && (*last == percent || !due) {
+                return;
+            }
+        }
+        self.update_timer = Some(Instant::now());
+
+        if self.scope == UclampScope::TopApp {
+            if let Err(e) = self.write_top_app(&percent.to_string(), file_handler) {
+                warn!("Failed to write top-app uclamp.min: {e:?}");
+            }
+        }
+
+        // Threads that dropped out of the frame threads lose their boost
+        let task_util_min = (f64::from(percent) / 100.0 * SCHED_CAPACITY_SCALE) as u32;
+        let boosted_tids: Vec<_> = frame_threads
+            .iter()
+            .copied()
+            .filter(|tid| set_task_uclamp_min(*tid, task_util_min).is_ok())
+            .collect();
+        for tid in &self.boosted_tids {
+            if !boosted_tids.contains(tid) {
+                let _ = set_task_uclamp_min(*tid, 0);
+            }
+        }
+        self.boosted_tids = boosted_tids;
+        self.applied = Some((percent, frame_threads));
+    }
+
+    pub fn reset(&mut self, file_handler: &mut FileHandler) {
+        self.applied = None;
+        self.update_timer = None;
+        for tid in self.boosted_tids.drain(..) {
+            let _ = set_task_uclamp_min(tid, 0);
+        }
+
+        if let Some(value) = self.saved_top_app.take() {
+            if let Some(uclamp_min) = &self.top_app {
+                let _ = file_handler.write_with_workround(uclamp_min, value);
+            }
+        }
+    }
+
+    fn write_top_app(&mut self, value: &str, file_handler: &mut FileHandler) -> Result<()> {
+        let uclamp_min = match self.top_app.clone() {
+            Some(uclamp_min) => uclamp_min,
+            None => {
+                let uclamp_min = Cgroup::open(Subsystem::Cpu, "top-app")
+                    .context("No top-app cpu cgroup")?
+                    .uclamp_min();
+                self.top_app = Some(uclamp_min.clone());
+                uclamp_min
+            }
+        };
+
+        if self.saved_top_app.is_none() {
+            self.saved_top_app = Some(sysfs::read_to_string(&uclamp_min)?.trim().to_string());
+        }
+
+        file_handler.write_with_workround(uclamp_min, value)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, time::Instant};
+
+use serde::{Deserialize, Serialize};
+
+// About 20 seconds at the default 300ms sample interval
+const HISTORY_LEN: usize = 64;
+// Samples a trend needs before it means anything
+const MIN_TREND_SAMPLES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UtilSmoothing {
+    // The raw sample, reacts to every spike
+    #[default]
+    None,
+    // Exponentially weighted moving average, `util_ewma_alpha` is the weight of a new sample
+    Ewma,
+    // Median of the last `util_median_window` samples, drops single spikes but not steps
+    Median,
+}
+
+// Raw util_max samples of the game with the smoothed value the controller acts on
+#[derive(Debug)]
+pub struct UtilHistory {
+    // Newest first
+    samples: VecDeque<(Instant, f64)>,
+    smoothing: UtilSmoothing,
+    alpha: f64,
+    window: usize,
+    ewma: Option<f64>,
+}
+
+impl UtilHistory {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(HISTORY_LEN),
+            smoothing: UtilSmoothing::None,
+            alpha: 1.0,
+            window: 1,
+            ewma: None,
+        }
+    }
+
+    pub fn set_smoothing(&mut self, smoothing: UtilSmoothing, alpha: f64, window: usize) {
+        self.smoothing = smoothing;
+        self.alpha = alpha.clamp(0.01, 1.0);
+        self.window = window.clamp(1, HISTORY_LEN);
+        self.ewma = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.ewma = None;
+    }
+
+    // Records a raw sample and returns the smoothed value
+    pub fn push(&mut self, util: f64) -> f64 {
+        self.samples.truncate(HISTORY_LEN - 1);
+        self.samples.push_front((Instant::now(), util));
+
+        match self.smoothing {
+            UtilSmoothing::None => util,
+            UtilSmoothing::Ewma => {
+                let ewma = self
+                    .ewma
+                    .map_or(util, |ewma| self.alpha.mul_add(util - ewma, ewma));
+                self.ewma = Some(ewma);
+                ewma
+            }
+            UtilSmoothing::Median => {
+                let mut window: Vec<_> = self
+                    .samples
+                    .iter()
+                    .take(self.window)
+                    .map(|(_, util)| *util)
+                    .collect();
+                window.sort_unstable_by(f64::total_cmp);
+                let mid = window.len() / 2;
+
+                if window.len() % 2 == 0 {
+                    f64::midpoint(window[mid - 1], window[mid])
+                } else {
+                    window[mid]
+                }
+            }
+        }
+    }
+
+    // Raw samples, newest first
+    pub fn samples(&self) -> impl Iterator<Item = (Instant, f64)> + '_ {
+        self.samples.iter().copied()
+    }
+
+    // Least squares slope of the raw samples in util per second, positive while load builds up
+    pub fn trend(&self) -> Option<f64> {
+        if self.samples.len() < MIN_TREND_SAMPLES {
+            return None;
+        }
+
+        let oldest = self.samples.back()?.0;
+        let points: Vec<_> = self
+            .samples()
+            .map(|(at, util)| (at.duration_since(oldest).as_secs_f64(), util))
+            .collect();
+        let count = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / count;
+        let mean_u = points.iter().map(|(_, u)| u).sum::<f64>() / count;
+
+        let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (t, u)| {
+            let dt = t - mean_t;
+            (dt.mul_add(u - mean_u, cov), dt.mul_add(dt, var))
+        });
+
+        (variance > 0.0).then(|| covariance / variance)
+    }
+}
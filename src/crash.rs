@@ -0,0 +1,100 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use log::warn;
+
+const CRASH_FILE: &str = "/data/adb/fas-rs/crashes.json";
+pub const SAFE_MODE_PROP: &str = "fas-rs-safe-mode";
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+// Every `run` leaves its start time in the file and takes it out again when it exits cleanly,
+// so what is left over from earlier starts are runs that aborted, panicked, were killed or
+// were restarted in place by the watchdog
+pub struct CrashGuard {
+    start: u64,
+    safe_mode: bool,
+}
+
+impl CrashGuard {
+    // Safe mode once more than `limit` runs aborted within the last `window` seconds
+    pub fn start(limit: u32, window: u64) -> Result<Self> {
+        let start = now();
+        let mut starts = read();
+        starts.retain(|time| start.saturating_sub(*time) < window);
+
+        let crashes = starts.len();
+        let safe_mode = crashes > limit as usize;
+        if safe_mode {
+            warn!(
+                "fas-rs aborted {crashes} times in the last {} minutes",
+                window / 60
+            );
+        }
+
+        starts.push(start);
+        write(&starts)?;
+
+        Ok(Self { start, safe_mode })
+    }
+
+    pub const fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    // Stopping the daemon on purpose while in safe mode gives the next start a clean slate
+    pub fn clean_exit(self) {
+        let mut starts = read();
+        if self.safe_mode {
+            starts.clear();
+        } else if let Some(index) = starts.iter().position(|time| *time == self.start) {
+            starts.remove(index);
+        }
+
+        if let Err(e) = write(&starts) {
+            warn!("Failed to update {CRASH_FILE}: {e}");
+        }
+    }
+}
+
+fn read() -> Vec<u64> {
+    fs::read_to_string(CRASH_FILE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write(starts: &[u64]) -> Result<()> {
+    if let Some(dir) = Path::new(CRASH_FILE).parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(CRASH_FILE, serde_json::to_string(starts)?)?;
+    Ok(())
+}
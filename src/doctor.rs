@@ -0,0 +1,355 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    paths::{self, Root},
+};
+
+const MSM_PERFORMANCE_MAX: &str = "/sys/module/msm_performance/parameters/cpu_max_freq";
+const MSM_PERFORMANCE_MIN: &str = "/sys/module/msm_performance/parameters/cpu_min_freq";
+const CMD_PATH: &str = "/system/bin/cmd";
+const SELINUX_ENFORCE: &str = "/sys/fs/selinux/enforce";
+const SELINUX_CONTEXT: &str = "/proc/self/attr/current";
+const GPU_PATHS: [&str; 2] = ["/sys/class/kgsl/kgsl-3d0", "/sys/class/devfreq"];
+const BATTERY_PATH: &str = "/sys/class/power_supply/battery";
+const BACKLIGHT_PATH: &str = "/sys/class/backlight";
+const INPUT_PATH: &str = "/dev/input";
+const DATA_PATH: &str = "/sdcard/Android/fas-rs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Finding {
+    level: Level,
+    message: String,
+    hint: Option<&'static str>,
+}
+
+#[derive(Default)]
+struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn ok(&mut self, message: impl Into<String>) {
+        self.push(Level::Ok, message, None);
+    }
+
+    fn warn(&mut self, message: impl Into<String>, hint: &'static str) {
+        self.push(Level::Warn, message, Some(hint));
+    }
+
+    fn fail(&mut self, message: impl Into<String>, hint: &'static str) {
+        self.push(Level::Fail, message, Some(hint));
+    }
+
+    fn push(&mut self, level: Level, message: impl Into<String>, hint: Option<&'static str>) {
+        self.findings.push(Finding {
+            level,
+            message: message.into(),
+            hint,
+        });
+    }
+
+    // A node that opens for writing passes both the permission bits and the SELinux write check
+    fn writable(&mut self, path: &Path, what: &str, level: Level) -> bool {
+        match OpenOptions::new().write(true).open(path) {
+            Ok(_) => {
+                self.ok(format!("{what}: {} is writable", path.display()));
+                true
+            }
+            Err(e) => {
+                let (message, hint) = match e.kind() {
+                    ErrorKind::NotFound => (
+                        format!("{what}: {} does not exist", path.display()),
+                        "The kernel does not expose this node, the feature stays off on this device",
+                    ),
+                    ErrorKind::PermissionDenied => (
+                        format!("{what}: {} is not writable ({e})", path.display()),
+                        "Run fas-rs as root; if it already is, the SELinux policy denies the write, check `dmesg | grep avc` and add an allow rule for the denied context",
+                    ),
+                    _ => (
+                        format!("{what}: {} failed to open ({e})", path.display()),
+                        "Something else holds or blocks the node, check `ls -lZ` on it",
+                    ),
+                };
+                self.push(level, message, Some(hint));
+                false
+            }
+        }
+    }
+
+    fn print(&self) -> bool {
+        for finding in &self.findings {
+            let tag = match finding.level {
+                Level::Ok => "ok",
+                Level::Warn => "warn",
+                Level::Fail => "fail",
+            };
+            println!("[{tag}] {}", finding.message);
+            if let Some(hint) = finding.hint {
+                println!("       hint: {hint}");
+            }
+        }
+
+        let failed = self
+            .findings
+            .iter()
+            .filter(|finding| finding.level == Level::Fail)
+            .count();
+        let warned = self
+            .findings
+            .iter()
+            .filter(|finding| finding.level == Level::Warn)
+            .count();
+        println!("{failed} failed, {warned} warnings");
+
+        failed == 0
+    }
+}
+
+// Returns whether fas-rs can run here, warnings only switch features off
+pub fn diagnose() -> bool {
+    let mut report = Report::default();
+
+    check_identity(&mut report);
+    let policies = check_cpufreq(&mut report);
+    check_backends(&mut report, policies);
+    check_cgroups(&mut report);
+    check_misc(&mut report);
+
+    report.print()
+}
+
+fn check_identity(report: &mut Report) {
+    if unsafe { libc::geteuid() } == 0 {
+        report.ok("Running as root");
+    } else {
+        report.fail(
+            "Not running as root",
+            "fas-rs writes cpufreq and cgroup nodes, run it from the module's service.sh or `su`",
+        );
+    }
+
+    match fs::read_to_string(SELINUX_ENFORCE).map(|enforce| enforce.trim() == "1") {
+        Ok(enforcing) => {
+            let context = fs::read_to_string(SELINUX_CONTEXT)
+                .map(|context| context.trim_end_matches('\0').trim().to_string())
+                .unwrap_or_else(|_| "unknown".into());
+            let mode = if enforcing { "enforcing" } else { "permissive" };
+            report.ok(format!("SELinux is {mode}, running as {context}"));
+        }
+        Err(_) => report.ok("SELinux is not available"),
+    }
+}
+
+// Number of policies whose limits fas-rs can write
+fn check_cpufreq(report: &mut Report) -> usize {
+    let cpufreq = paths::cpufreq();
+    let Ok(entries) = fs::read_dir(cpufreq) else {
+        report.fail(
+            format!("{} is not readable", cpufreq.display()),
+            "fas-rs needs cpufreq, it cannot run on this kernel",
+        );
+        return 0;
+    };
+
+    let mut policies: Vec<PathBuf> = entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("policy"))
+        })
+        .collect();
+    policies.sort();
+
+    if policies.is_empty() {
+        report.fail(
+            format!("No cpufreq policies under {}", cpufreq.display()),
+            "fas-rs needs cpufreq, it cannot run on this kernel",
+        );
+        return 0;
+    }
+
+    let mut usable = 0;
+    for policy in &policies {
+        let name = policy
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if fs::read_to_string(policy.join("scaling_available_frequencies")).is_err() {
+            report.fail(
+                format!("{name}: scaling_available_frequencies is missing"),
+                "fas-rs needs the frequency table of every policy, the vendor kernel hides it",
+            );
+            continue;
+        }
+
+        let max = report.writable(&policy.join("scaling_max_freq"), &name, Level::Fail);
+        let min = report.writable(&policy.join("scaling_min_freq"), &name, Level::Fail);
+        report.writable(&policy.join("scaling_governor"), &name, Level::Warn);
+        if max && min {
+            usable += 1;
+        }
+    }
+
+    usable
+}
+
+fn check_backends(report: &mut Report, sysfs_policies: usize) {
+    if sysfs_policies > 0 {
+        report.ok(format!(
+            "Frequency backend sysfs is usable on {sysfs_policies} policies"
+        ));
+    } else {
+        report.fail(
+            "Frequency backend sysfs is unusable",
+            "See the cpufreq findings above",
+        );
+    }
+
+    let msm_performance = Path::new(MSM_PERFORMANCE_MAX);
+    if msm_performance.exists() {
+        // Both, `auto` only picks it when the two nodes open for writing
+        if report.writable(msm_performance, "msm_performance", Level::Warn)
+            && report.writable(
+                Path::new(MSM_PERFORMANCE_MIN),
+                "msm_performance",
+                Level::Warn,
+            )
+        {
+            report.ok(
+                "Frequency backend msm_performance is usable and picked by freq_backend = \"auto\"",
+            );
+        }
+    } else {
+        report.ok("Frequency backend msm_performance is not available, \"auto\" uses sysfs");
+    }
+
+    if Path::new(CMD_PATH).exists() {
+        report.ok("Frequency backend power_hal is usable");
+    } else {
+        report.warn(
+            format!("Frequency backend power_hal is unusable, {CMD_PATH} is missing"),
+            "Only set freq_backend = \"power_hal\" on Android builds that ship `cmd power`",
+        );
+    }
+}
+
+fn check_cgroups(report: &mut Report) {
+    match Cgroup::open(Subsystem::Cpuset, "top-app") {
+        Some(top_app) => {
+            if fs::read_to_string(top_app.procs()).is_ok() {
+                report.ok(format!(
+                    "{} is readable for focus events",
+                    top_app.procs().display()
+                ));
+            } else {
+                report.warn(
+                    format!("{} is not readable", top_app.procs().display()),
+                    "Focus changes fall back to polling dumpsys, which reacts slower",
+                );
+            }
+            report.writable(&top_app.threads(), "pin_big_cores", Level::Warn);
+        }
+        None => report.warn(
+            "No top-app cpuset",
+            "Focus events and pin_big_cores need the Android cpuset layout, both stay off",
+        ),
+    }
+
+    if let Some(background) = Cgroup::open(Subsystem::Cpuset, "background") {
+        report.writable(&background.procs(), "self demotion", Level::Warn);
+        report.writable(&background.cpus(), "pin_big_cores", Level::Warn);
+    }
+
+    match Cgroup::open(Subsystem::Cpu, "top-app") {
+        Some(top_app) => {
+            report.writable(&top_app.uclamp_min(), "uclamp_boost", Level::Warn);
+        }
+        None => report.warn(
+            "No top-app cpu cgroup",
+            "uclamp_boost only boosts the render threads on this device",
+        ),
+    }
+}
+
+fn check_misc(report: &mut Report) {
+    if paths::get(Root::Gpu).is_some() || GPU_PATHS.iter().any(|path| Path::new(path).exists()) {
+        report.ok("GPU frequency nodes found");
+    } else {
+        report.warn(
+            "No GPU frequency nodes",
+            "GPU frequency control stays off, the CPU side is unaffected",
+        );
+    }
+
+    for (path, what, hint) in [
+        (
+            paths::thermal(),
+            "thermal zones",
+            "Built-in thermal control cannot read temperatures and stays off",
+        ),
+        (
+            Path::new(BATTERY_PATH),
+            "battery",
+            "Battery powersave and energy estimates stay off",
+        ),
+        (
+            Path::new(BACKLIGHT_PATH),
+            "backlight",
+            "Screen off detection falls back to lcd-backlight or stays off",
+        ),
+        (
+            Path::new(INPUT_PATH),
+            "input devices",
+            "touch_boost needs readable input devices",
+        ),
+    ] {
+        if fs::read_dir(path).is_ok() {
+            report.ok(format!("{what}: {} is readable", path.display()));
+        } else {
+            report.warn(format!("{what}: {} is not readable", path.display()), hint);
+        }
+    }
+
+    let data = Path::new(DATA_PATH);
+    if fs::create_dir_all(data).is_ok()
+        && fs::metadata(data).is_ok_and(|meta| !meta.permissions().readonly())
+    {
+        report.ok(format!("{DATA_PATH} is writable"));
+    } else {
+        report.warn(
+            format!("{DATA_PATH} is not writable"),
+            "Records, session history and adaptive margins cannot be saved, check that storage is mounted",
+        );
+    }
+}
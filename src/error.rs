@@ -0,0 +1,124 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error as ThisError;
+
+// Why a kernel node could not be used. A missing node will not come back, a denied one may once
+// selinux or the mount changes, an overridden one works but something else keeps writing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Missing,
+    PermissionDenied,
+    VendorOverride,
+    Other,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Missing => "missing",
+            Self::PermissionDenied => "permission denied",
+            Self::VendorOverride => "vendor override",
+            Self::Other => "other",
+        })
+    }
+}
+
+#[derive(ThisError, Debug, Clone, PartialEq, Eq)]
+pub enum NodeError {
+    #[error("{} does not exist", .0.display())]
+    Missing(PathBuf),
+    #[error("No permission to access {}", .0.display())]
+    PermissionDenied(PathBuf),
+    #[error("{} is held by another writer", .0.display())]
+    VendorOverride(PathBuf),
+    #[error("Failed to access {}: {1}", .0.display())]
+    Other(PathBuf, io::ErrorKind),
+}
+
+impl NodeError {
+    pub fn from_io(path: &Path, e: &io::Error) -> Self {
+        let path = path.to_path_buf();
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::Missing(path),
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                Self::PermissionDenied(path)
+            }
+            // Vendor drivers answer EBUSY while their own daemon holds the node
+            io::ErrorKind::ResourceBusy => Self::VendorOverride(path),
+            kind => Self::Other(path, kind),
+        }
+    }
+
+    // Sorts an error from anywhere in a chain, for failures that only come as `anyhow::Error`
+    pub fn classify(path: &Path, e: &anyhow::Error) -> Self {
+        e.chain()
+            .find_map(|cause| {
+                cause
+                    .downcast_ref::<Self>()
+                    .cloned()
+                    .or_else(|| Some(Self::from_io(path, cause.downcast_ref::<io::Error>()?)))
+            })
+            .unwrap_or_else(|| Self::Other(path.to_path_buf(), io::ErrorKind::InvalidData))
+    }
+
+    pub const fn class(&self) -> ErrorClass {
+        match self {
+            Self::Missing(_) => ErrorClass::Missing,
+            Self::PermissionDenied(_) => ErrorClass::PermissionDenied,
+            Self::VendorOverride(_) => ErrorClass::VendorOverride,
+            Self::Other(..) => ErrorClass::Other,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Missing(path)
+            | Self::PermissionDenied(path)
+            | Self::VendorOverride(path)
+            | Self::Other(path, _) => path,
+        }
+    }
+}
+
+// One entry of the degraded capabilities report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Degradation {
+    pub class: ErrorClass,
+    pub node: PathBuf,
+    // What does not work because of it
+    pub feature: String,
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({})",
+            self.feature,
+            self.node.display(),
+            self.class
+        )
+    }
+}
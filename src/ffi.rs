@@ -0,0 +1,160 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+// C interface to the decision engine, for daemons that want fas-rs's frequency decisions on
+// their own frame and utilization signals without running fas-rs. See `include/fas_rs.h`
+
+use std::{
+    ffi::{CStr, c_char},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    time::Duration,
+};
+
+use crate::framework::{Config, Engine, Mode};
+
+// An engine for one game, opaque to C
+pub struct FasEngine(Engine);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FasDecision {
+    pub target_fps: u32,
+    // Frequency change in kHz, added by the caller to the current frequency of the game's cores
+    pub control_khz: i64,
+    pub janked: bool,
+}
+
+// Unwinding out of an `extern "C"` fn aborts the host, a panic becomes the failure value instead
+fn guard<T>(failure: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(failure)
+}
+
+unsafe fn str_arg<'a>(arg: *const c_char) -> Option<&'a str> {
+    if arg.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(arg) }.to_str().ok()
+}
+
+/// Creates an engine for `pkg` with the targets, margins and gains `config_path` (a fas-rs
+/// `games.toml`) has for it. `mode` is one of `powersave`, `balance`, `performance` or `fast`,
+/// NULL uses the game's configured mode or `balance`. Returns NULL when the config can not be
+/// read or has no target fps for `pkg`.
+///
+/// # Safety
+///
+/// `config_path` and `pkg` must be valid NUL terminated strings, `mode` one or NULL.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fas_engine_new(
+    config_path: *const c_char,
+    pkg: *const c_char,
+    mode: *const c_char,
+) -> *mut FasEngine {
+    guard(ptr::null_mut(), || {
+        let (Some(config_path), Some(pkg)) =
+            (unsafe { str_arg(config_path) }, unsafe { str_arg(pkg) })
+        else {
+            return ptr::null_mut();
+        };
+        let Ok(mut config) = Config::snapshot(config_path) else {
+            return ptr::null_mut();
+        };
+
+        let mode = match unsafe { str_arg(mode) } {
+            Some(mode) => match mode.parse::<Mode>() {
+                Ok(mode) => mode,
+                Err(_) => return ptr::null_mut(),
+            },
+            None => config.game_mode(pkg).unwrap_or(Mode::Balance),
+        };
+
+        Engine::new(&mut config, pkg, mode).map_or_else(ptr::null_mut, |engine| {
+            Box::into_raw(Box::new(FasEngine(engine)))
+        })
+    })
+}
+
+/// Feeds one presented frame. `timestamp_ms` is any monotonic clock, `frametime_ns` the time
+/// since the previous frame, `util_max` the busiest game thread's share of a cpu (0.0 - 1.0) and
+/// `temperature` the hottest cpu core in 0.001℃. Returns true and fills `decision` when the
+/// engine has a decision, false while it still learns the target fps or when it failed.
+///
+/// # Safety
+///
+/// `engine` must come from `fas_engine_new` and not be freed, `decision` must be valid for
+/// writes. An engine must not be used from two threads at once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fas_engine_frame(
+    engine: *mut FasEngine,
+    timestamp_ms: u64,
+    frametime_ns: u64,
+    util_max: f64,
+    temperature: u64,
+    decision: *mut FasDecision,
+) -> bool {
+    guard(false, || {
+        let Some(engine) = (unsafe { engine.as_mut() }) else {
+            return false;
+        };
+        let Some(result) = engine.0.frame(
+            timestamp_ms,
+            Duration::from_nanos(frametime_ns),
+            util_max,
+            temperature,
+        ) else {
+            return false;
+        };
+
+        if let Some(decision) = unsafe { decision.as_mut() } {
+            *decision = FasDecision {
+                target_fps: result.target_fps,
+                control_khz: result.control as i64,
+                janked: result.janked,
+            };
+        }
+
+        true
+    })
+}
+
+/// The target fps the engine locked onto, 0 while unknown.
+///
+/// # Safety
+///
+/// `engine` must come from `fas_engine_new` and not be freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fas_engine_target_fps(engine: *const FasEngine) -> u32 {
+    guard(0, || {
+        unsafe { engine.as_ref() }
+            .and_then(|engine| engine.0.target_fps())
+            .unwrap_or_default()
+    })
+}
+
+/// Frees an engine, NULL is ignored.
+///
+/// # Safety
+///
+/// `engine` must come from `fas_engine_new` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fas_engine_free(engine: *mut FasEngine) {
+    if !engine.is_null() {
+        guard((), || drop(unsafe { Box::from_raw(engine) }));
+    }
+}
@@ -17,36 +17,162 @@
 
 use anyhow::Result;
 use hashbrown::{HashMap, hash_map::Entry};
+use log::warn;
+use parking_lot::Mutex;
 use std::{
     fs::{File, set_permissions},
     io::{self, ErrorKind, prelude::*},
+    mem,
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 use sys_mount::{UnmountFlags, unmount};
 
+use crate::{cgroup, cpu_common::snapshot, error::NodeError, sysfs};
+
+// An unchanged value is still rewritten after this long, in case something else touched the
+// node. 0 writes every value
+static REASSERT_INTERVAL_MS: AtomicU64 = AtomicU64::new(1000);
+
+type Batch = Vec<(PathBuf, Vec<u8>)>;
+// A forced batch skips the unchanged value check, for values something else overwrote
+type Message = (Batch, bool);
+// Nodes whose last write failed, by device path, shared with the writer thread
+type Failures = Arc<Mutex<HashMap<PathBuf, NodeError>>>;
+// Shared with the writer thread too, so either side sees what the other wrote
+type Cache = Arc<Mutex<Written>>;
+
+pub fn set_reassert_interval(interval: Duration) {
+    REASSERT_INTERVAL_MS.store(interval.as_millis() as u64, Ordering::Relaxed);
+}
+
+// The last value successfully written to every node, a write of the same value within the
+// reassert interval is skipped. Task lists are never cached, every write moves a task
+#[derive(Debug, Default)]
+struct Written {
+    values: HashMap<PathBuf, (Vec<u8>, Instant)>,
+}
+
+impl Written {
+    fn unchanged(&self, path: &Path, content: &[u8]) -> bool {
+        let interval = Duration::from_millis(REASSERT_INTERVAL_MS.load(Ordering::Relaxed));
+
+        self.values
+            .get(path)
+            .is_some_and(|(last, at)| last == content && at.elapsed() < interval)
+    }
+
+    fn update(&mut self, path: &Path, content: &[u8], written: bool) {
+        if written && !cgroup::is_task_list(path) {
+            match self.values.get_mut(path) {
+                Some((last, at)) => {
+                    last.clear();
+                    last.extend_from_slice(content);
+                    *at = Instant::now();
+                }
+                None => {
+                    self.values
+                        .insert(path.to_path_buf(), (content.to_vec(), Instant::now()));
+                }
+            }
+        } else {
+            self.values.remove(path);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Files {
+    files: HashMap<PathBuf, File>,
+}
+
+impl Files {
+    // Ok(false) when the kernel rejected the value, which is not an error of the node
+    fn write_with_workround(&mut self, path: &Path, content: &[u8]) -> io::Result<bool> {
+        if let Err(e) = self.write(path, content) {
+            match e.kind() {
+                ErrorKind::PermissionDenied => {
+                    set_permissions(path, PermissionsExt::from_mode(0o644))?;
+                    self.write(path, content).map(|()| true)
+                }
+                ErrorKind::InvalidInput => Ok(false),
+                _ => Err(e),
+            }
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn write(&mut self, path: &Path, content: &[u8]) -> io::Result<()> {
+        match self.files.entry(path.to_path_buf()) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().write_all(content)?;
+            }
+            Entry::Vacant(entry) => {
+                let _ = unmount(path, UnmountFlags::DETACH);
+                set_permissions(path, PermissionsExt::from_mode(0o644))?;
+                let mut file = File::create(path)?;
+                file.write_all(content)?;
+                entry.insert(file);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Synchronous writes for nodes whose result matters, plus queued writes that a dedicated
+// thread applies off the control loop. Both skip values the node already got from us
 #[derive(Debug)]
 pub struct FileHandler {
-    files: HashMap<PathBuf, File>,
+    files: Files,
+    written: Cache,
+    batch: Batch,
+    failures: Failures,
+    sender: Option<Sender<Message>>,
+    writer: Option<JoinHandle<()>>,
 }
 
 impl FileHandler {
     pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let failures = Failures::default();
+        let written = Cache::default();
+        let (writer_failures, writer_written) = (failures.clone(), written.clone());
+        let writer = thread::Builder::new()
+            .name("FileWriter".into())
+            .spawn(move || write_loop(&receiver, &writer_failures, &writer_written))
+            .map_err(|e| warn!("Failed to spawn file writer, writing synchronously: {e}"))
+            .ok();
+
         Self {
-            files: HashMap::new(),
+            files: Files::default(),
+            written,
+            batch: Batch::new(),
+            failures,
+            sender: writer.is_some().then_some(sender),
+            writer,
         }
     }
 
     pub fn read_to_string(&mut self, path: impl AsRef<Path>) -> Result<String> {
+        let path = sysfs::resolve(path);
         let mut string = String::new();
-        match self.files.entry(path.as_ref().to_path_buf()) {
+        match self.files.files.entry(path.clone()) {
             Entry::Occupied(mut entry) => {
                 let mut string = String::new();
                 entry.get_mut().rewind()?;
                 entry.get().read_to_string(&mut string)?;
             }
             Entry::Vacant(entry) => {
-                let mut file = File::open(path.as_ref())?;
+                let mut file = File::open(&path)?;
                 file.read_to_string(&mut string)?;
                 entry.insert(file);
             }
@@ -59,36 +185,149 @@ impl FileHandler {
         &mut self,
         path: impl AsRef<Path>,
         content: impl AsRef<[u8]>,
-    ) -> Result<()> {
-        if let Err(e) = self.write(path.as_ref(), content.as_ref()) {
-            match e.kind() {
-                ErrorKind::PermissionDenied => {
-                    set_permissions(path.as_ref(), PermissionsExt::from_mode(0o644))?;
-                    self.write(path, content)?;
-                    Ok(())
-                }
-                ErrorKind::InvalidInput => Ok(()),
-                _ => Err(e.into()),
-            }
-        } else {
-            Ok(())
-        }
+    ) -> Result<(), NodeError> {
+        let (path, content) = (path.as_ref(), content.as_ref());
+        snapshot::capture(path);
+        apply(
+            &mut self.files,
+            &self.written,
+            &self.failures,
+            path,
+            content,
+            false,
+        )
     }
 
     pub fn write(&mut self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
-        match self.files.entry(path.as_ref().to_path_buf()) {
-            Entry::Occupied(mut entry) => {
-                entry.get_mut().write_all(content.as_ref())?;
+        let (path, content) = (path.as_ref(), content.as_ref());
+        if self.written.lock().unchanged(path, content) {
+            return Ok(());
+        }
+
+        snapshot::capture(path);
+        let result = self.files.write(&sysfs::resolve(path), content);
+        self.written.lock().update(path, content, result.is_ok());
+        result
+    }
+
+    // Writes keep their order within a batch, nothing reaches the node before `flush`
+    pub fn queue(&mut self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) {
+        snapshot::capture(path.as_ref());
+        self.batch
+            .push((path.as_ref().to_path_buf(), content.as_ref().to_vec()));
+    }
+
+    pub fn flush(&mut self) {
+        self.send(false);
+    }
+
+    // Like `flush`, but values equal to the last ones written are written again too
+    pub fn flush_forced(&mut self) {
+        self.send(true);
+    }
+
+    fn send(&mut self, forced: bool) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        let batch = mem::take(&mut self.batch);
+        match &self.sender {
+            Some(sender) => {
+                let _ = sender.send((batch, forced));
             }
-            Entry::Vacant(entry) => {
-                let _ = unmount(path.as_ref(), UnmountFlags::DETACH);
-                set_permissions(path.as_ref(), PermissionsExt::from_mode(0o644))?;
-                let mut file = File::create(path)?;
-                file.write_all(content.as_ref())?;
-                entry.insert(file);
+            None => {
+                for (path, content) in batch {
+                    let _ = apply(
+                        &mut self.files,
+                        &self.written,
+                        &self.failures,
+                        &path,
+                        &content,
+                        forced,
+                    );
+                }
             }
         }
+    }
 
-        Ok(())
+    // Nodes whose last write failed, by path
+    pub fn failures(&self) -> Vec<NodeError> {
+        let mut failures: Vec<_> = self.failures.lock().values().cloned().collect();
+        failures.sort_by(|a, b| a.path().cmp(b.path()));
+        failures
+    }
+}
+
+// Writes a value unless the node already got it from us, only values the node took are
+// remembered as written
+fn apply(
+    files: &mut Files,
+    written: &Mutex<Written>,
+    failures: &Failures,
+    path: &Path,
+    content: &[u8],
+    forced: bool,
+) -> Result<(), NodeError> {
+    if !forced && written.lock().unchanged(path, content) {
+        return Ok(());
+    }
+
+    let result = files.write_with_workround(&sysfs::resolve(path), content);
+    let recorded = record(failures, path, result.as_ref().err());
+    written
+        .lock()
+        .update(path, content, matches!(result, Ok(true)));
+    recorded
+}
+
+// Keeps the failure set current, a node is reported once when it starts failing
+fn record(failures: &Failures, path: &Path, error: Option<&io::Error>) -> Result<(), NodeError> {
+    let mut failures = failures.lock();
+    let Some(io_error) = error else {
+        failures.remove(path);
+        return Ok(());
+    };
+
+    let error = NodeError::from_io(path, io_error);
+    // A thread that exited before it was moved says nothing about the node
+    if io_error.raw_os_error() == Some(libc::ESRCH) {
+        return Err(error);
+    }
+
+    if failures.insert(path.to_path_buf(), error.clone()).is_none() {
+        warn!("{error}, class: {}", error.class());
+    }
+
+    Err(error)
+}
+
+// Let the writer drain before the handler goes away, the last batch is usually a reset
+impl Drop for FileHandler {
+    fn drop(&mut self) {
+        self.flush();
+        self.sender = None;
+
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_loop(receiver: &Receiver<Message>, failures: &Failures, written: &Cache) {
+    let mut files = Files::default();
+
+    while let Ok((mut pending, mut forced)) = receiver.recv() {
+        // Batches that piled up meanwhile are coalesced, a newer batch supersedes older writes
+        // of the paths it touches
+        for (newer, newer_forced) in receiver.try_iter() {
+            pending.retain(|(path, _)| !newer.iter().any(|(newer_path, _)| newer_path == path));
+            pending.extend(newer);
+            forced |= newer_forced;
+        }
+
+        for (path, content) in pending {
+            let _ = apply(&mut files, written, failures, &path, &content, forced);
+        }
     }
 }
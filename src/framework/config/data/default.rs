@@ -15,7 +15,14 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use super::Config;
+use std::collections::HashMap;
+
+use super::{
+    BatteryConfig, CaptureConfig, Config, DaemonConfig, FocusSource, IrqConfig, LogConfig,
+    LogFormat, LogLevel, MemoryConfig, MonitorConfig, PidGains, PsiConfig, TemperatureThreshold,
+    ThermalConfig,
+};
+use crate::cpu_common::{FreqBackendKind, LimitGuardMode, UclampScope, UtilSmoothing};
 
 impl Config {
     pub const fn default_value_keep_std() -> bool {
@@ -25,4 +32,367 @@ impl Config {
     pub const fn default_value_scene_game_list() -> bool {
         true
     }
+
+    pub const fn default_value_uclamp_boost() -> bool {
+        false
+    }
+
+    pub const fn default_value_uclamp_scope() -> UclampScope {
+        UclampScope::TopApp
+    }
+
+    pub const fn default_value_auto_target_fps() -> bool {
+        false
+    }
+
+    pub const fn default_value_adaptive_margin() -> bool {
+        false
+    }
+
+    pub const fn default_value_gpu_control() -> bool {
+        false
+    }
+
+    pub const fn default_value_bound_detection() -> bool {
+        true
+    }
+
+    pub const fn default_value_saturation_drop_fps() -> bool {
+        false
+    }
+
+    pub const fn default_value_touch_boost_duration() -> u64 {
+        200
+    }
+
+    pub const fn default_value_touch_boost_ratio() -> f64 {
+        0.6
+    }
+
+    pub const fn default_value_jank_burst_threshold() -> u32 {
+        0
+    }
+
+    pub const fn default_value_jank_burst_duration() -> u64 {
+        100
+    }
+
+    pub const fn default_value_freq_backend() -> FreqBackendKind {
+        FreqBackendKind::Auto
+    }
+
+    pub const fn default_value_limit_guard() -> LimitGuardMode {
+        LimitGuardMode::Reassert
+    }
+
+    pub const fn default_value_reassert_interval() -> u64 {
+        1000
+    }
+
+    pub const fn default_value_metrics_port() -> u16 {
+        0
+    }
+
+    pub const fn default_value_tuner_port() -> u16 {
+        0
+    }
+
+    pub const fn default_value_broadcast() -> bool {
+        false
+    }
+
+    pub const fn default_value_overlay_feed() -> bool {
+        false
+    }
+
+    pub const fn default_value_vrr_tracking() -> bool {
+        false
+    }
+
+    pub const fn default_value_bus_boost() -> bool {
+        false
+    }
+
+    pub const fn default_value_focus_source() -> FocusSource {
+        FocusSource::Cpuset
+    }
+}
+
+impl ThermalConfig {
+    pub const fn default_value_throttle_temp() -> TemperatureThreshold {
+        TemperatureThreshold::Disabled
+    }
+
+    pub const fn default_value_critical_temp() -> TemperatureThreshold {
+        TemperatureThreshold::Temp(100_000)
+    }
+
+    pub const fn default_value_critical_cooldown() -> u64 {
+        60
+    }
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            zones: Vec::new(),
+            throttle_temp: Self::default_value_throttle_temp(),
+            critical_temp: Self::default_value_critical_temp(),
+            critical_cooldown: Self::default_value_critical_cooldown(),
+        }
+    }
+}
+
+impl BatteryConfig {
+    pub const fn default_value_powersave_capacity() -> u8 {
+        0
+    }
+
+    pub const fn default_value_powersave_on_hot_charging() -> bool {
+        false
+    }
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            powersave_capacity: Self::default_value_powersave_capacity(),
+            powersave_on_hot_charging: Self::default_value_powersave_on_hot_charging(),
+        }
+    }
+}
+
+impl PsiConfig {
+    pub const fn default_value_enabled() -> bool {
+        false
+    }
+
+    pub const fn default_value_cpu_threshold() -> f64 {
+        20.0
+    }
+
+    pub const fn default_value_memory_threshold() -> f64 {
+        10.0
+    }
+
+    pub const fn default_value_poll_trigger() -> bool {
+        false
+    }
+
+    pub const fn default_value_boost_duration() -> u64 {
+        300
+    }
+
+    pub const fn default_value_boost_ratio() -> f64 {
+        0.6
+    }
+}
+
+impl Default for PsiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_value_enabled(),
+            cpu_threshold: Self::default_value_cpu_threshold(),
+            memory_threshold: Self::default_value_memory_threshold(),
+            poll_trigger: Self::default_value_poll_trigger(),
+            boost_duration: Self::default_value_boost_duration(),
+            boost_ratio: Self::default_value_boost_ratio(),
+        }
+    }
+}
+
+impl CaptureConfig {
+    pub const fn default_value_margin_fps() -> f64 {
+        0.0
+    }
+
+    // adb's screenrecord and the OEM recorders
+    pub fn default_value_processes() -> Vec<String> {
+        [
+            "screenrecord",
+            "com.miui.screenrecorder",
+            "com.coloros.screenrecorder",
+            "com.oplus.screenrecorder",
+            "com.samsung.android.app.screenrecorder",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            margin_fps: Self::default_value_margin_fps(),
+            profile: String::new(),
+            processes: Self::default_value_processes(),
+            props: Vec::new(),
+        }
+    }
+}
+
+impl IrqConfig {
+    // Touchscreen controllers, the gpu and the display pipeline
+    pub fn default_value_patterns() -> Vec<String> {
+        [
+            "*touch*",
+            "*fts*",
+            "*goodix*",
+            "*synaptics*",
+            "*nvt*",
+            "kgsl*",
+            "*mali*",
+            "*gpu*",
+            "*mdss*",
+            "*sde*",
+            "*dsi*",
+            "*disp*",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
+
+impl Default for IrqConfig {
+    fn default() -> Self {
+        Self {
+            cpus: Vec::new(),
+            patterns: Self::default_value_patterns(),
+        }
+    }
+}
+
+impl MemoryConfig {
+    // Where ActivityManager keeps persistent processes, below every app it may kill
+    pub const fn default_value_oom_score_adj() -> i32 {
+        -800
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            oom_score_adj: Self::default_value_oom_score_adj(),
+            watermark_scale_factor: 0,
+            memcg_min_mb: 0,
+        }
+    }
+}
+
+impl LogConfig {
+    pub const fn default_value_ring_size() -> usize {
+        512
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            games: HashMap::new(),
+            ring_size: Self::default_value_ring_size(),
+        }
+    }
+}
+
+impl MonitorConfig {
+    pub const fn default_value_sample_interval() -> u64 {
+        300
+    }
+
+    pub const fn default_value_max_sample_interval() -> u64 {
+        1000
+    }
+
+    pub const fn default_value_refresh_interval() -> u64 {
+        1000
+    }
+
+    pub const fn default_value_top_threads() -> usize {
+        8
+    }
+
+    pub const fn default_value_runqueue_weight() -> f64 {
+        0.0
+    }
+
+    pub const fn default_value_util_smoothing() -> UtilSmoothing {
+        UtilSmoothing::None
+    }
+
+    pub const fn default_value_util_ewma_alpha() -> f64 {
+        0.5
+    }
+
+    pub const fn default_value_util_median_window() -> usize {
+        5
+    }
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            sample_interval: Self::default_value_sample_interval(),
+            max_sample_interval: Self::default_value_max_sample_interval(),
+            refresh_interval: Self::default_value_refresh_interval(),
+            top_threads: Self::default_value_top_threads(),
+            runqueue_weight: Self::default_value_runqueue_weight(),
+            util_smoothing: Self::default_value_util_smoothing(),
+            util_ewma_alpha: Self::default_value_util_ewma_alpha(),
+            util_median_window: Self::default_value_util_median_window(),
+        }
+    }
+}
+
+impl DaemonConfig {
+    pub const fn default_value_log_format() -> LogFormat {
+        LogFormat::Text
+    }
+
+    pub const fn default_value_log_level() -> LogLevel {
+        LogLevel::Info
+    }
+
+    pub const fn default_value_crash_limit() -> u32 {
+        3
+    }
+
+    pub const fn default_value_crash_window() -> u64 {
+        10
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            log_format: Self::default_value_log_format(),
+            log_level: Self::default_value_log_level(),
+            crash_limit: Self::default_value_crash_limit(),
+            crash_window: Self::default_value_crash_window(),
+        }
+    }
+}
+
+// Without `ki` and `kd` this is the plain proportional loop fas-rs always used
+impl PidGains {
+    pub const fn default_value_kp() -> f64 {
+        0.000_3
+    }
+
+    pub const fn default_value_ki() -> f64 {
+        0.0
+    }
+
+    pub const fn default_value_kd() -> f64 {
+        0.0
+    }
+}
+
+impl Default for PidGains {
+    fn default() -> Self {
+        Self {
+            kp: Self::default_value_kp(),
+            ki: Self::default_value_ki(),
+            kd: Self::default_value_kd(),
+        }
+    }
 }
@@ -22,16 +22,50 @@ use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use toml::Table;
 
+use crate::cpu_common::{FreqBackendKind, LimitGuardMode, UclampScope, UtilSmoothing};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ConfigData {
     pub config: Config,
     pub game_list: Table,
+    // Base entries games inherit from with `extends = "<name>"`
+    #[serde(default)]
+    pub profiles: Table,
     #[serde(skip)]
     pub scene_game_list: HashSet<String>,
     pub powersave: ModeConfig,
     pub balance: ModeConfig,
     pub performance: ModeConfig,
     pub fast: ModeConfig,
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+    #[serde(default)]
+    pub battery: BatteryConfig,
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    #[serde(default)]
+    pub packages: PackagesConfig,
+    #[serde(default)]
+    pub psi: PsiConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub irq: IrqConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    // Governor tunables per cluster, device specific so they are kept as written
+    #[serde(default)]
+    pub tunables: Table,
+    #[serde(default)]
+    pub mitigation: MitigationConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub paths: PathsConfig,
+    #[serde(default)]
+    pub oem: OemConfig,
 }
 
 #[allow(clippy::struct_excessive_bools)]
@@ -41,6 +75,48 @@ pub struct Config {
     pub keep_std: bool,
     #[serde(default = "Config::default_value_scene_game_list")]
     pub scene_game_list: bool,
+    #[serde(default = "Config::default_value_uclamp_boost")]
+    pub uclamp_boost: bool,
+    #[serde(default = "Config::default_value_uclamp_scope")]
+    pub uclamp_scope: UclampScope,
+    #[serde(default = "Config::default_value_auto_target_fps")]
+    pub auto_target_fps: bool,
+    #[serde(default = "Config::default_value_adaptive_margin")]
+    pub adaptive_margin: bool,
+    #[serde(default = "Config::default_value_gpu_control")]
+    pub gpu_control: bool,
+    #[serde(default = "Config::default_value_bound_detection")]
+    pub bound_detection: bool,
+    #[serde(default = "Config::default_value_saturation_drop_fps")]
+    pub saturation_drop_fps: bool,
+    #[serde(default = "Config::default_value_touch_boost_duration")]
+    pub touch_boost_duration: u64,
+    #[serde(default = "Config::default_value_touch_boost_ratio")]
+    pub touch_boost_ratio: f64,
+    #[serde(default = "Config::default_value_jank_burst_threshold")]
+    pub jank_burst_threshold: u32,
+    #[serde(default = "Config::default_value_jank_burst_duration")]
+    pub jank_burst_duration: u64,
+    #[serde(default = "Config::default_value_freq_backend")]
+    pub freq_backend: FreqBackendKind,
+    #[serde(default = "Config::default_value_limit_guard")]
+    pub limit_guard: LimitGuardMode,
+    #[serde(default = "Config::default_value_reassert_interval")]
+    pub reassert_interval: u64,
+    #[serde(default = "Config::default_value_metrics_port")]
+    pub metrics_port: u16,
+    #[serde(default = "Config::default_value_tuner_port")]
+    pub tuner_port: u16,
+    #[serde(default = "Config::default_value_broadcast")]
+    pub broadcast: bool,
+    #[serde(default = "Config::default_value_overlay_feed")]
+    pub overlay_feed: bool,
+    #[serde(default = "Config::default_value_vrr_tracking")]
+    pub vrr_tracking: bool,
+    #[serde(default = "Config::default_value_bus_boost")]
+    pub bus_boost: bool,
+    #[serde(default = "Config::default_value_focus_source")]
+    pub focus_source: FocusSource,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -49,6 +125,203 @@ pub struct ModeConfig {
     pub core_temp_thresh: TemperatureThreshold,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThermalConfig {
+    #[serde(default)]
+    pub zones: Vec<String>,
+    #[serde(default = "ThermalConfig::default_value_throttle_temp")]
+    pub throttle_temp: TemperatureThreshold,
+    #[serde(default = "ThermalConfig::default_value_critical_temp")]
+    pub critical_temp: TemperatureThreshold,
+    #[serde(default = "ThermalConfig::default_value_critical_cooldown")]
+    pub critical_cooldown: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct BatteryConfig {
+    #[serde(default = "BatteryConfig::default_value_powersave_capacity")]
+    pub powersave_capacity: u8,
+    #[serde(default = "BatteryConfig::default_value_powersave_on_hot_charging")]
+    pub powersave_on_hot_charging: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct MonitorConfig {
+    #[serde(default = "MonitorConfig::default_value_sample_interval")]
+    pub sample_interval: u64,
+    #[serde(default = "MonitorConfig::default_value_max_sample_interval")]
+    pub max_sample_interval: u64,
+    #[serde(default = "MonitorConfig::default_value_refresh_interval")]
+    pub refresh_interval: u64,
+    #[serde(default = "MonitorConfig::default_value_top_threads")]
+    pub top_threads: usize,
+    #[serde(default = "MonitorConfig::default_value_runqueue_weight")]
+    pub runqueue_weight: f64,
+    #[serde(default = "MonitorConfig::default_value_util_smoothing")]
+    pub util_smoothing: UtilSmoothing,
+    #[serde(default = "MonitorConfig::default_value_util_ewma_alpha")]
+    pub util_ewma_alpha: f64,
+    #[serde(default = "MonitorConfig::default_value_util_median_window")]
+    pub util_median_window: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct PsiConfig {
+    #[serde(default = "PsiConfig::default_value_enabled")]
+    pub enabled: bool,
+    #[serde(default = "PsiConfig::default_value_cpu_threshold")]
+    pub cpu_threshold: f64,
+    #[serde(default = "PsiConfig::default_value_memory_threshold")]
+    pub memory_threshold: f64,
+    #[serde(default = "PsiConfig::default_value_poll_trigger")]
+    pub poll_trigger: bool,
+    #[serde(default = "PsiConfig::default_value_boost_duration")]
+    pub boost_duration: u64,
+    #[serde(default = "PsiConfig::default_value_boost_ratio")]
+    pub boost_ratio: f64,
+}
+
+// What changes while the screen is being recorded, nothing with the defaults
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureConfig {
+    #[serde(default = "CaptureConfig::default_value_margin_fps")]
+    pub margin_fps: f64,
+    #[serde(default)]
+    pub profile: String,
+    #[serde(default = "CaptureConfig::default_value_processes")]
+    pub processes: Vec<String>,
+    #[serde(default)]
+    pub props: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct DaemonConfig {
+    #[serde(default = "DaemonConfig::default_value_log_format")]
+    pub log_format: LogFormat,
+    #[serde(default = "DaemonConfig::default_value_log_level")]
+    pub log_level: LogLevel,
+    #[serde(default = "DaemonConfig::default_value_crash_limit")]
+    pub crash_limit: u32,
+    // Minutes
+    #[serde(default = "DaemonConfig::default_value_crash_window")]
+    pub crash_window: u64,
+}
+
+// Debug output for single games and the decision events kept in memory for `dump-log`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogConfig {
+    // Package to the log level while it is the scheduled game
+    #[serde(default)]
+    pub games: HashMap<String, LogLevel>,
+    // 0 keeps none
+    #[serde(default = "LogConfig::default_value_ring_size")]
+    pub ring_size: usize,
+}
+
+// Decides which apps fas-rs may engage on at all, on top of game_list and auto_target_fps
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PackagesConfig {
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+}
+
+// Interrupts moved off the game's cores while it runs, nothing is moved without `cpus`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IrqConfig {
+    #[serde(default)]
+    pub cpus: Vec<u32>,
+    #[serde(default = "IrqConfig::default_value_patterns")]
+    pub patterns: Vec<String>,
+}
+
+// Vendor services, props and OEM game boosters neutralized while a game is controlled, only
+// names on the allow-list of `Mitigation` are ever touched
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MitigationConfig {
+    #[serde(default)]
+    pub services: Vec<String>,
+    #[serde(default)]
+    pub props: HashMap<String, String>,
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+// Props and Android settings written by OEM game spaces, each value mapped to the fas-rs mode
+// it stands for. Settings are keyed `<namespace>/<key>`, namespace system, secure or global
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OemConfig {
+    #[serde(default)]
+    pub props: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    pub settings: HashMap<String, HashMap<String, String>>,
+}
+
+// Memory protection of games with `protect_memory = true`, 0 leaves that part alone
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct MemoryConfig {
+    #[serde(default = "MemoryConfig::default_value_oom_score_adj")]
+    pub oom_score_adj: i32,
+    #[serde(default)]
+    pub watermark_scale_factor: u32,
+    #[serde(default)]
+    pub memcg_min_mb: u64,
+}
+
+// Where a kernel with a non-standard layout keeps these directories, empty detects them
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PathsConfig {
+    // The directory of the `policy*` directories
+    #[serde(default)]
+    pub cpufreq: String,
+    // The directory of the `thermal_zone*` directories
+    #[serde(default)]
+    pub thermal: String,
+    // The devfreq directory of the gpu
+    #[serde(default)]
+    pub gpu: String,
+    // The root of the cpuset hierarchy
+    #[serde(default)]
+    pub cpuset: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusSource {
+    // inotify on the top-app cpuset and the oom_score_adj of the top apps
+    Cpuset,
+    // ActivityManager / WindowManager events of `logcat -b events` on top of the cpuset watch
+    Logcat,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct PidGains {
+    #[serde(default = "PidGains::default_value_kp")]
+    pub kp: f64,
+    #[serde(default = "PidGains::default_value_ki")]
+    pub ki: f64,
+    #[serde(default = "PidGains::default_value_kd")]
+    pub kd: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub enum TemperatureThreshold {
     #[serde(rename = "disabled")]
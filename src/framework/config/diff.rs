@@ -0,0 +1,125 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+const CONTEXT: usize = 3;
+
+enum Op<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+// Line based unified diff with three lines of context, empty when both sides are equal
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let ops = diff_ops(&old, &new);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {old_name}\n+++ {new_name}\n");
+    let mut group_start = 0;
+    for (index, change) in changes.iter().enumerate() {
+        // Changes whose contexts touch share a hunk
+        if changes
+            .get(index + 1)
+            .is_some_and(|next| next - change <= 2 * CONTEXT + 1)
+        {
+            continue;
+        }
+
+        let start = changes[group_start].saturating_sub(CONTEXT);
+        let end = (change + CONTEXT + 1).min(ops.len());
+        write_hunk(&mut out, &ops, start, end);
+        group_start = index + 1;
+    }
+
+    out
+}
+
+// Longest common subsequence, configs are a few hundred lines at most
+fn diff_ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push(Op::Equal(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| Op::Delete(line)));
+    ops.extend(new[j..].iter().map(|line| Op::Insert(line)));
+
+    ops
+}
+
+fn write_hunk(out: &mut String, ops: &[Op], start: usize, end: usize) {
+    let old_lines = |ops: &[Op]| ops.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+    let new_lines = |ops: &[Op]| ops.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+
+    let _ = writeln!(
+        out,
+        "@@ -{} +{} @@",
+        hunk_range(old_lines(&ops[..start]), old_lines(&ops[start..end])),
+        hunk_range(new_lines(&ops[..start]), new_lines(&ops[start..end]))
+    );
+
+    for op in &ops[start..end] {
+        let _ = match op {
+            Op::Equal(line) => writeln!(out, " {line}"),
+            Op::Delete(line) => writeln!(out, "-{line}"),
+            Op::Insert(line) => writeln!(out, "+{line}"),
+        };
+    }
+}
+
+// An empty range names the line before it, as diff(1) does
+fn hunk_range(before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{before},0"),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{len}", before + 1),
+    }
+}
@@ -23,18 +23,34 @@ use super::data::ConfigData;
 pub struct Inner {
     rx: Receiver<ConfigData>,
     config: ConfigData,
+    // Bumped whenever the config is replaced, lets callers invalidate what they derived from it
+    generation: u64,
 }
 
 impl Inner {
     pub const fn new(config: ConfigData, rx: Receiver<ConfigData>) -> Self {
-        Self { rx, config }
+        Self {
+            rx,
+            config,
+            generation: 0,
+        }
     }
 
     pub fn config(&mut self) -> &mut ConfigData {
         if let Some(config) = self.rx.try_iter().last() {
             self.config = config;
+            self.generation += 1;
         }
 
         &mut self.config
     }
+
+    pub fn set(&mut self, config: ConfigData) {
+        self.config = config;
+        self.generation += 1;
+    }
+
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
 }
@@ -0,0 +1,120 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use hashbrown::HashMap;
+use log::warn;
+use regex::Regex;
+use toml::Table;
+
+use crate::misc::glob_match;
+
+const REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug)]
+enum Pattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    // None for plain package names, those are looked up directly
+    fn parse(key: &str) -> Option<Result<Self, regex::Error>> {
+        if let Some(regex) = key.strip_prefix(REGEX_PREFIX) {
+            return Some(Regex::new(regex).map(Self::Regex));
+        }
+
+        is_glob(key).then(|| Ok(Self::Glob(key.to_string())))
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Glob(glob) => glob_match(glob.as_bytes(), name.as_bytes()),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+// Resolves process names to game_list keys. Compiled patterns and resolved names are kept
+// until the config changes
+#[derive(Debug, Default)]
+pub struct GameMatcher {
+    generation: Option<u64>,
+    patterns: Vec<(String, Pattern)>,
+    resolved: HashMap<String, Option<String>>,
+}
+
+impl GameMatcher {
+    // Exact process names win over the package without its `:sub` suffix, then the longest
+    // matching pattern wins
+    pub fn resolve(&mut self, game_list: &Table, generation: u64, name: &str) -> Option<String> {
+        if self.generation != Some(generation) {
+            self.rebuild(game_list, generation);
+        }
+
+        if let Some(key) = self.resolved.get(name) {
+            return key.clone();
+        }
+
+        let pkg = name.split(':').next().unwrap_or(name);
+        let key = [name, pkg]
+            .into_iter()
+            .find(|key| game_list.contains_key(*key))
+            .map(String::from)
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(_, pattern)| pattern.matches(name))
+                    .map(|(key, _)| key.clone())
+            });
+
+        self.resolved.insert(name.to_string(), key.clone());
+        key
+    }
+
+    fn rebuild(&mut self, game_list: &Table, generation: u64) {
+        self.generation = Some(generation);
+        self.resolved.clear();
+        self.patterns = game_list
+            .keys()
+            .filter_map(|key| match Pattern::parse(key)? {
+                Ok(pattern) => Some((key.clone(), pattern)),
+                Err(e) => {
+                    warn!("Ignoring game_list key '{key}': {e}");
+                    None
+                }
+            })
+            .collect();
+        self.patterns
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    }
+}
+
+pub fn is_pattern(key: &str) -> bool {
+    key.starts_with(REGEX_PREFIX) || is_glob(key)
+}
+
+// Err with the reason for keys that are patterns but do not compile
+pub fn check_pattern(key: &str) -> Result<(), String> {
+    match Pattern::parse(key) {
+        Some(Err(e)) => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn is_glob(key: &str) -> bool {
+    key.contains(['*', '?'])
+}
@@ -15,30 +15,234 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+use std::fmt;
+
 use likely_stable::LikelyOption;
 use serde::{Deserialize, Serialize};
 use toml::{Table, Value};
 
-use super::Config;
+use super::{Config, diff::unified_diff, migrate::CONFIG_VERSION};
 use crate::framework::error::{Error, Result};
 
 #[derive(Deserialize, Serialize)]
 struct ConfigData {
+    #[serde(default)]
+    pub version: i64,
     pub config: Table,
     pub game_list: Table,
+    #[serde(default)]
+    pub profiles: Table,
     pub powersave: Table,
     pub balance: Table,
     pub performance: Table,
     pub fast: Table,
+    #[serde(default)]
+    pub thermal: Table,
+    #[serde(default)]
+    pub battery: Table,
+    #[serde(default)]
+    pub monitor: Table,
+    #[serde(default)]
+    pub daemon: Table,
+    #[serde(default)]
+    pub packages: Table,
+    #[serde(default)]
+    pub psi: Table,
+    #[serde(default)]
+    pub capture: Table,
+    #[serde(default)]
+    pub irq: Table,
+    #[serde(default)]
+    pub log: Table,
+    #[serde(default)]
+    pub tunables: Table,
+    #[serde(default)]
+    pub mitigation: Table,
+    #[serde(default)]
+    pub memory: Table,
+    #[serde(default)]
+    pub paths: Table,
+    #[serde(default)]
+    pub oem: Table,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Kept,
+    Added,
+    // The user key is gone, with the reason
+    Dropped(String),
+    // The user value was replaced by the standard one, with the reason
+    Overridden(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeEntry {
+    pub key: String,
+    pub change: Change,
+}
+
+impl MergeEntry {
+    // Whether the user loses something, those are worth telling about
+    pub const fn is_loss(&self) -> bool {
+        matches!(self.change, Change::Dropped(_) | Change::Overridden(_))
+    }
+}
+
+impl fmt::Display for MergeEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.change {
+            Change::Kept => write!(f, "{}: kept", self.key),
+            Change::Added => write!(f, "{}: added from the standard config", self.key),
+            Change::Dropped(reason) => write!(f, "{}: dropped, {reason}", self.key),
+            Change::Overridden(reason) => write!(f, "{}: overridden, {reason}", self.key),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Merged {
+    pub config: String,
+    pub report: Vec<MergeEntry>,
+    // The local config as the merge serializes it, so a diff only shows real changes
+    local: String,
+}
+
+impl Merged {
+    // Games of the standard list the user has no entry for, the user's own entries always win
+    #[cfg(feature = "update")]
+    pub fn add_std_games(&mut self, std: &str) -> Result<()> {
+        let std_conf: ConfigData = toml::from_str(std)?;
+        let mut conf: ConfigData = toml::from_str(&self.config)?;
+
+        for (pkg, value) in std_conf.game_list {
+            if !conf.game_list.contains_key(&pkg) {
+                self.report.push(MergeEntry {
+                    key: format!("game_list.{pkg}"),
+                    change: Change::Added,
+                });
+                conf.game_list.insert(pkg, value);
+            }
+        }
+
+        for (name, value) in std_conf.profiles {
+            if !conf.profiles.contains_key(&name) {
+                self.report.push(MergeEntry {
+                    key: format!("profiles.{name}"),
+                    change: Change::Added,
+                });
+                conf.profiles.insert(name, value);
+            }
+        }
+
+        self.config = toml::to_string(&conf)?;
+
+        Ok(())
+    }
+
+    pub fn diff(&self, local_name: &str, merged_name: &str) -> String {
+        unified_diff(&self.local, &self.config, local_name, merged_name)
+    }
+}
+
+#[derive(Default)]
+struct Merger {
+    report: Vec<MergeEntry>,
+}
+
+impl Merger {
+    fn record(&mut self, section: &str, key: &str, change: Change) {
+        self.report.push(MergeEntry {
+            key: format!("{section}.{key}"),
+            change,
+        });
+    }
+
+    // Standard keys with the user's values where the user has a usable one
+    fn table(&mut self, section: &str, mut s: Table, l: Table) -> Table {
+        for key in s.keys().filter(|key| !l.contains_key(*key)) {
+            self.record(section, key, Change::Added);
+        }
+
+        for (key, value) in l {
+            let change = match s.get(&key) {
+                None => Change::Dropped("not a key of the standard config".into()),
+                Some(std) if !same_type(std, &value) => Change::Dropped(format!(
+                    "expected {}, found {}",
+                    std.type_str(),
+                    value.type_str()
+                )),
+                Some(_) => Change::Kept,
+            };
+
+            if change == Change::Kept {
+                s.insert(key.clone(), value);
+            }
+            self.record(section, &key, change);
+        }
+
+        s
+    }
+
+    // The standard table as is, every differing user value is reported as overridden
+    fn keep_std(&mut self, section: &str, s: Table, l: &Table) -> Table {
+        for key in s.keys().filter(|key| !l.contains_key(*key)) {
+            self.record(section, key, Change::Added);
+        }
+
+        for (key, value) in l {
+            let change = match s.get(key) {
+                Some(std) if std == value => Change::Kept,
+                Some(_) => Change::Overridden("keep_std = true keeps the standard value".into()),
+                None => Change::Dropped("keep_std = true keeps the standard config".into()),
+            };
+            self.record(section, key, change);
+        }
+
+        s
+    }
+
+    // The user's profiles as written, standard ones the user lacks are added so standard games
+    // extending them keep working
+    fn profiles(&mut self, s: Table, mut l: Table) -> Table {
+        for (name, value) in s {
+            if !l.contains_key(&name) {
+                self.record("profiles", &name, Change::Added);
+                l.insert(name, value);
+            }
+        }
+
+        l
+    }
+
+    // Device specific, so kept as written. Only a user without any gets the ones a device
+    // overlay of the standard config brings
+    fn tunables(&mut self, s: Table, l: Table) -> Table {
+        if !l.is_empty() {
+            return l;
+        }
+
+        for cluster in s.keys() {
+            self.record("tunables", cluster, Change::Added);
+        }
+
+        s
+    }
 }
 
 impl Config {
-    pub fn merge<S: AsRef<str>>(l: S, s: S) -> Result<String> {
+    pub fn merge<S: AsRef<str>>(l: S, s: S) -> Result<Merged> {
         let local_conf = l.as_ref();
         let std_conf = s.as_ref();
 
         let std_conf: ConfigData = toml::from_str(std_conf)?;
-        let local_conf: ConfigData = toml::from_str(local_conf)?;
+
+        let mut local_conf: Table = toml::from_str(local_conf)?;
+        Self::migrate(&mut local_conf);
+        let local_conf: ConfigData = Value::Table(local_conf).try_into()?;
+        let local = toml::to_string(&local_conf)?;
+
+        let mut merger = Merger::default();
 
         if local_conf
             .config
@@ -47,37 +251,78 @@ impl Config {
             .ok_or(Error::ParseConfig)?
         {
             let new_conf = ConfigData {
-                config: std_conf.config,
+                version: CONFIG_VERSION,
+                config: merger.keep_std("config", std_conf.config, &local_conf.config),
                 game_list: local_conf.game_list,
-                powersave: std_conf.powersave,
-                balance: std_conf.balance,
-                performance: std_conf.performance,
-                fast: std_conf.fast,
+                profiles: merger.profiles(std_conf.profiles, local_conf.profiles),
+                powersave: merger.keep_std("powersave", std_conf.powersave, &local_conf.powersave),
+                balance: merger.keep_std("balance", std_conf.balance, &local_conf.balance),
+                performance: merger.keep_std(
+                    "performance",
+                    std_conf.performance,
+                    &local_conf.performance,
+                ),
+                fast: merger.keep_std("fast", std_conf.fast, &local_conf.fast),
+                thermal: merger.keep_std("thermal", std_conf.thermal, &local_conf.thermal),
+                battery: merger.keep_std("battery", std_conf.battery, &local_conf.battery),
+                monitor: merger.keep_std("monitor", std_conf.monitor, &local_conf.monitor),
+                daemon: merger.keep_std("daemon", std_conf.daemon, &local_conf.daemon),
+                packages: merger.table("packages", std_conf.packages, local_conf.packages),
+                psi: merger.keep_std("psi", std_conf.psi, &local_conf.psi),
+                capture: merger.keep_std("capture", std_conf.capture, &local_conf.capture),
+                irq: merger.keep_std("irq", std_conf.irq, &local_conf.irq),
+                log: merger.keep_std("log", std_conf.log, &local_conf.log),
+                tunables: merger.tunables(std_conf.tunables, local_conf.tunables),
+                mitigation: local_conf.mitigation,
+                memory: merger.keep_std("memory", std_conf.memory, &local_conf.memory),
+                paths: merger.table("paths", std_conf.paths, local_conf.paths),
+                oem: local_conf.oem,
             };
-            return Ok(toml::to_string(&new_conf)?);
-        }
 
-        let config = Self::table_merge(std_conf.config, local_conf.config);
-        let powersave = Self::table_merge(std_conf.powersave, local_conf.powersave);
-        let balance = Self::table_merge(std_conf.balance, local_conf.balance);
-        let performance = Self::table_merge(std_conf.performance, local_conf.performance);
-        let fast = Self::table_merge(std_conf.fast, local_conf.fast);
+            return Ok(Merged {
+                config: toml::to_string(&new_conf)?,
+                report: merger.report,
+                local,
+            });
+        }
 
         let new_conf = ConfigData {
-            config,
+            version: CONFIG_VERSION,
+            config: merger.table("config", std_conf.config, local_conf.config),
             game_list: local_conf.game_list,
-            powersave,
-            balance,
-            performance,
-            fast,
+            profiles: merger.profiles(std_conf.profiles, local_conf.profiles),
+            powersave: merger.table("powersave", std_conf.powersave, local_conf.powersave),
+            balance: merger.table("balance", std_conf.balance, local_conf.balance),
+            performance: merger.table("performance", std_conf.performance, local_conf.performance),
+            fast: merger.table("fast", std_conf.fast, local_conf.fast),
+            thermal: merger.table("thermal", std_conf.thermal, local_conf.thermal),
+            battery: merger.table("battery", std_conf.battery, local_conf.battery),
+            monitor: merger.table("monitor", std_conf.monitor, local_conf.monitor),
+            daemon: merger.table("daemon", std_conf.daemon, local_conf.daemon),
+            packages: merger.table("packages", std_conf.packages, local_conf.packages),
+            psi: merger.table("psi", std_conf.psi, local_conf.psi),
+            capture: merger.table("capture", std_conf.capture, local_conf.capture),
+            irq: merger.table("irq", std_conf.irq, local_conf.irq),
+            log: merger.table("log", std_conf.log, local_conf.log),
+            tunables: merger.tunables(std_conf.tunables, local_conf.tunables),
+            mitigation: local_conf.mitigation,
+            memory: merger.table("memory", std_conf.memory, local_conf.memory),
+            paths: merger.table("paths", std_conf.paths, local_conf.paths),
+            oem: local_conf.oem,
         };
 
-        Ok(toml::to_string(&new_conf)?)
+        Ok(Merged {
+            config: toml::to_string(&new_conf)?,
+            report: merger.report,
+            local,
+        })
     }
+}
 
-    fn table_merge(mut s: Table, l: Table) -> Table {
-        let old: Table = l.into_iter().filter(|(k, _)| s.contains_key(k)).collect();
-        s.extend(old);
-        s
-    }
+// Integers are accepted where the standard config has a float
+fn same_type(std: &Value, value: &Value) -> bool {
+    matches!(
+        (std, value),
+        (Value::Float(_), Value::Integer(_) | Value::Float(_))
+    ) || std.type_str() == value.type_str()
 }
@@ -0,0 +1,105 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use toml::{Table, Value};
+
+use super::Config;
+
+// Configs written before the `version` key existed are treated as version 1
+pub const CONFIG_VERSION: i64 = 2;
+const MODE_SECTIONS: [&str; 4] = ["powersave", "balance", "performance", "fast"];
+
+// MIGRATIONS[n] upgrades a config from version n + 1 to n + 2
+const MIGRATIONS: [fn(&mut Table); CONFIG_VERSION as usize - 1] = [v1_to_v2];
+
+impl Config {
+    pub fn migrate(table: &mut Table) {
+        let version = Self::config_version(table);
+
+        for migration in MIGRATIONS.iter().skip((version.max(1) - 1) as usize) {
+            migration(table);
+        }
+
+        if version < CONFIG_VERSION {
+            table.insert("version".into(), Value::Integer(CONFIG_VERSION));
+        }
+    }
+
+    pub fn config_version(table: &Table) -> i64 {
+        table
+            .get("version")
+            .and_then(Value::as_integer)
+            .unwrap_or(1)
+    }
+}
+
+fn v1_to_v2(table: &mut Table) {
+    for mode in MODE_SECTIONS {
+        if let Some(Value::Table(mode)) = table.get_mut(mode) {
+            rename_key(mode, "margin", "margin_fps");
+        }
+    }
+
+    if let Some(Value::Table(game_list)) = table.get_mut("game_list") {
+        for value in game_list.values_mut() {
+            match value {
+                Value::Table(game) => {
+                    rename_key(game, "fps", "target_fps");
+                    if let Some(target_fps) = game.get_mut("target_fps") {
+                        convert_fps_list(target_fps);
+                    }
+                }
+                value => convert_fps_list(value),
+            }
+        }
+    }
+}
+
+fn rename_key(table: &mut Table, old: &str, new: &str) {
+    if let Some(value) = table.remove(old) {
+        table.entry(new).or_insert(value);
+    }
+}
+
+// Old configs accepted fps lists as strings ("30,60" or "30 60") and arrays of strings or floats
+fn convert_fps_list(value: &mut Value) {
+    let fps: Vec<i64> = match value {
+        Value::String(s) if s == "auto" => return,
+        Value::String(s) => s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(|fps| fps.trim().parse::<f64>().ok())
+            .map(|fps| fps.round() as i64)
+            .collect(),
+        Value::Float(fps) => vec![fps.round() as i64],
+        Value::Array(arr) => arr
+            .iter()
+            .filter_map(|fps| match fps {
+                Value::Integer(fps) => Some(*fps),
+                Value::Float(fps) => Some(fps.round() as i64),
+                Value::String(s) => s.trim().parse::<f64>().ok().map(|fps| fps.round() as i64),
+                _ => None,
+            })
+            .collect(),
+        _ => return,
+    };
+
+    *value = match fps.as_slice() {
+        [] => Value::String("auto".into()),
+        [fps] => Value::Integer(*fps),
+        _ => Value::Array(fps.into_iter().map(Value::Integer).collect()),
+    };
+}
@@ -16,19 +16,56 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 mod data;
+mod diff;
 mod inner;
+mod matcher;
 mod merge;
+mod migrate;
+mod overlay;
 mod read;
+mod scan;
+#[cfg(feature = "update")]
+mod update;
+mod validate;
+#[cfg(feature = "tuner")]
+mod write;
 
-use std::{fs, path::Path, sync::mpsc, thread};
+use std::{
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 
+use hashbrown::HashMap;
 use inner::Inner;
-use log::{error, info};
+use log::{debug, error, info};
+use matcher::GameMatcher;
 use toml::Value;
 
-use crate::framework::{error::Result, node::Mode};
-pub use data::{Config as ConfigConfig, ConfigData, MarginFps, ModeConfig, TemperatureThreshold};
-use read::wait_and_read;
+use crate::{
+    cpu_common::{AffinityRule, ClusterPolicy, LadderConfig, RenderPriority, StrategyKind},
+    framework::{error::Result, node::Mode},
+};
+pub use data::{
+    BatteryConfig, CaptureConfig, Config as ConfigConfig, ConfigData, DaemonConfig, FocusSource,
+    IrqConfig, LogConfig, LogFormat, LogLevel, MarginFps, MemoryConfig, MitigationConfig,
+    ModeConfig, MonitorConfig, OemConfig, PackagesConfig, PathsConfig, PidGains, PsiConfig,
+    TemperatureThreshold, ThermalConfig,
+};
+use read::{read_user_config, wait_and_read};
+
+// Decode and network threads of common streaming clients, MediaCodec callbacks included
+const STREAM_THREADS: [&str; 7] = [
+    "MediaCodec_*",
+    "CodecLooper*",
+    "*Decode*",
+    "*decode*",
+    "*Video*",
+    "*Network*",
+    "*Rtp*",
+];
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TargetFps {
@@ -36,9 +73,29 @@ pub enum TargetFps {
     Array(Vec<u32>),
 }
 
+// A streaming client presents what the server sends, so the stream fps is the target and the
+// decode / network threads stand in for the render thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Streaming {
+    pub fps: Option<u32>,
+    pub threads: Vec<String>,
+}
+
+impl Default for Streaming {
+    fn default() -> Self {
+        Self {
+            fps: None,
+            threads: STREAM_THREADS.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Config {
     inner: Inner,
+    path: PathBuf,
+    profile: Option<String>,
+    matcher: GameMatcher,
 }
 
 impl Config {
@@ -65,55 +122,375 @@ impl Config {
 
         info!("Config watcher started");
 
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            path: path.to_owned(),
+            profile: None,
+            matcher: GameMatcher::default(),
+        })
+    }
+
+    // Reads the config once and never watches it, for one-shot commands like `simulate`
+    pub fn snapshot<P: AsRef<Path>>(p: P) -> Result<Self> {
+        let path = p.as_ref();
+        let config = read_user_config(path)?;
+        let (_, rx) = mpsc::channel();
+
+        Ok(Self {
+            inner: Inner::new(config, rx),
+            path: path.to_owned(),
+            profile: None,
+            matcher: GameMatcher::default(),
+        })
+    }
+
+    pub fn reload(&mut self) -> Result<()> {
+        let config = read_user_config(&self.path)?;
+        self.inner.set(config);
+        info!("Config reloaded");
+        Ok(())
+    }
+
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        match &profile {
+            Some(profile) => info!("Switch profile: {profile}"),
+            None => info!("Switch profile: default"),
+        }
+
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    // The game_list key a process name falls under, see `GameMatcher::resolve`
+    fn game_key(&mut self, name: &str) -> Option<String> {
+        self.inner.config();
+        let generation = self.inner.generation();
+        let game_list = &self.inner.config().game_list;
+
+        self.matcher.resolve(game_list, generation, name)
+    }
+
+    // The game's entry over the base profile it extends, with the keys of the active profile
+    // laid over both
+    fn game_entry(&mut self, pkg: &str) -> Option<Value> {
+        let key = self.game_key(pkg)?;
+        let mut value = self.inner.config().game_list.get(&key)?.clone();
+
+        if let Value::Table(table) = &mut value {
+            if let Some(Value::String(base)) = table.remove("extends") {
+                match self.inner.config().profiles.get(&base) {
+                    Some(Value::Table(base)) => {
+                        let mut merged = base.clone();
+                        merged.extend(mem::take(table));
+                        *table = merged;
+                    }
+                    // `fas-rs check` reports it, the entry is used on its own
+                    _ => debug!("{key} extends unknown profile '{base}'"),
+                }
+            }
+
+            let profiles = table.remove("profiles");
+            let profile = self
+                .profile
+                .as_ref()
+                .and_then(|profile| profiles.as_ref()?.get(profile)?.as_table());
+
+            if let Some(profile) = profile {
+                table.extend(profile.clone());
+            }
+        }
+
+        Some(value)
+    }
+
+    // The blocklist always wins, a non-empty allowlist limits fas-rs to the packages it names
+    pub fn boost_allowed<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        let Some(pkg) = pkg.as_ref().split(':').next() else {
+            return false;
+        };
+        let packages = &self.inner.config().packages;
+
+        !packages.blocklist.iter().any(|blocked| blocked == pkg)
+            && (packages.allowlist.is_empty()
+                || packages.allowlist.iter().any(|allowed| allowed == pkg))
     }
 
     pub fn need_fas<S: AsRef<str>>(&mut self, pkg: S) -> bool {
         let pkg = pkg.as_ref();
 
-        self.inner.config().game_list.contains_key(pkg)
-            || self.inner.config().scene_game_list.contains(pkg)
+        self.game_key(pkg).is_some()
+            || self
+                .inner
+                .config()
+                .scene_game_list
+                .contains(pkg.split(':').next().unwrap_or(pkg))
     }
 
     pub fn target_fps<S: AsRef<str>>(&mut self, pkg: S) -> Option<TargetFps> {
-        let pkg = pkg.as_ref();
-        let pkg = pkg.split(':').next()?;
+        let name = pkg.as_ref();
+        let pkg = name.split(':').next()?;
+
+        if let Some(fps) = self.streaming(name).and_then(|streaming| streaming.fps) {
+            return Some(TargetFps::Value(fps));
+        }
+
+        match self.game_entry(name) {
+            Some(value) => Self::parse_target_fps(pkg, value),
+            None if self.inner.config().scene_game_list.contains(pkg) => {
+                Some(TargetFps::Array(vec![30, 45, 60, 90, 120, 144]))
+            }
+            None => None,
+        }
+    }
 
-        self.inner.config().game_list.get(pkg).cloned().map_or_else(
-            || {
-                if self.inner.config().scene_game_list.contains(pkg) {
+    fn parse_target_fps(pkg: &str, value: Value) -> Option<TargetFps> {
+        match value {
+            Value::Array(arr) => {
+                let mut arr: Vec<_> = arr
+                    .iter()
+                    .filter_map(toml::Value::as_integer)
+                    .map(|i| i as u32)
+                    .collect();
+                arr.sort_unstable();
+                Some(TargetFps::Array(arr))
+            }
+            Value::Integer(i) => Some(TargetFps::Value(i as u32)),
+            Value::String(s) => {
+                if s == "auto" {
                     Some(TargetFps::Array(vec![30, 45, 60, 90, 120, 144]))
                 } else {
+                    error!("Find target game {pkg} in config, but meet illegal data type");
+                    error!("Sugg: try \'{pkg} = \"auto\"\'");
                     None
                 }
-            },
-            |value| match value {
-                Value::Array(arr) => {
-                    let mut arr: Vec<_> = arr
+            }
+            Value::Table(mut table) => Self::parse_target_fps(
+                pkg,
+                table
+                    .remove("target_fps")
+                    .unwrap_or_else(|| Value::String("auto".into())),
+            ),
+            _ => {
+                error!("Find target game {pkg} in config, but meet illegal data type");
+                error!("Sugg: try \'{pkg} = \"auto\"\'");
+                None
+            }
+        }
+    }
+
+    pub fn game_mode<S: AsRef<str>>(&mut self, pkg: S) -> Option<Mode> {
+        let pkg = pkg.as_ref();
+
+        let mode = self.game_entry(pkg)?.get("mode")?.as_str()?.parse().ok();
+
+        if mode.is_none() {
+            error!("Find target game {pkg} in config, but meet illegal mode");
+        }
+
+        mode
+    }
+
+    pub fn cluster_policies<S: AsRef<str>>(&mut self, pkg: S) -> HashMap<String, ClusterPolicy> {
+        let pkg = pkg.as_ref();
+
+        let Some(Value::Table(clusters)) = self
+            .game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("clusters"))
+        else {
+            return HashMap::new();
+        };
+
+        clusters
+            .iter()
+            .map(|(name, cluster)| {
+                let default = ClusterPolicy::default();
+                let margin = cluster
+                    .get("margin")
+                    .and_then(Value::as_integer)
+                    .map_or(default.margin, |margin| margin as isize);
+                let ceiling = cluster
+                    .get("ceiling")
+                    .and_then(|ceiling| {
+                        ceiling
+                            .as_float()
+                            .or_else(|| ceiling.as_integer().map(|i| i as f64))
+                    })
+                    .unwrap_or(default.ceiling);
+                let mhz = |key: &str| {
+                    cluster
+                        .get(key)
+                        .and_then(Value::as_integer)
+                        .map(|mhz| mhz as isize * 1000)
+                };
+
+                (
+                    name.clone(),
+                    ClusterPolicy {
+                        margin,
+                        ceiling,
+                        min_freq: mhz("min_freq_mhz"),
+                        max_freq: mhz("max_freq_mhz"),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    pub fn record<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_flag(pkg, "record")
+    }
+
+    pub fn touch_boost<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_flag(pkg, "touch_boost")
+    }
+
+    pub fn pin_big_cores<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_flag(pkg, "pin_big_cores")
+    }
+
+    pub fn shallow_idle<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_flag(pkg, "shallow_idle")
+    }
+
+    pub fn protect_memory<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_flag(pkg, "protect_memory")
+    }
+
+    pub fn strategy<S: AsRef<str>>(&mut self, pkg: S) -> StrategyKind {
+        let pkg = pkg.as_ref();
+
+        let Some(value) = self
+            .game_entry(pkg)
+            .and_then(|entry| entry.get("strategy").cloned())
+        else {
+            return StrategyKind::default();
+        };
+
+        value.try_into().unwrap_or_else(|_| {
+            error!("Find target game {pkg} in config, but meet illegal strategy");
+            StrategyKind::default()
+        })
+    }
+
+    pub fn ladder<S: AsRef<str>>(&mut self, pkg: S) -> LadderConfig {
+        let pkg = pkg.as_ref();
+
+        self.game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("ladder"))
+            .and_then(|ladder| {
+                ladder
+                    .try_into()
+                    .map_err(|_| {
+                        error!("Find target game {pkg} in config, but meet illegal ladder")
+                    })
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn render_priority<S: AsRef<str>>(&mut self, pkg: S) -> Option<RenderPriority> {
+        let pkg = pkg.as_ref();
+
+        match self.game_entry(pkg)?.get("render_priority")? {
+            Value::String(s) if s == "fifo" => Some(RenderPriority::Fifo),
+            Value::Integer(nice) if (-20..=19).contains(nice) => {
+                Some(RenderPriority::Nice(*nice as i32))
+            }
+            _ => {
+                error!("Find target game {pkg} in config, but meet illegal render_priority");
+                None
+            }
+        }
+    }
+
+    pub fn thread_affinity<S: AsRef<str>>(&mut self, pkg: S) -> Vec<AffinityRule> {
+        let pkg = pkg.as_ref();
+        let Some(Value::Table(rules)) = self
+            .game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("thread_affinity"))
+        else {
+            return Vec::new();
+        };
+
+        rules
+            .iter()
+            .filter_map(|(thread, cpus)| {
+                let cpus = match cpus {
+                    Value::String(cpus) => cpus.clone(),
+                    Value::Integer(cpu) => cpu.to_string(),
+                    _ => String::new(),
+                };
+                let rule = AffinityRule::parse(thread, &cpus);
+                if rule.is_none() {
+                    error!("Find target game {pkg} in config, but meet illegal cpus for thread {thread}");
+                }
+                rule
+            })
+            .collect()
+    }
+
+    pub fn streaming<S: AsRef<str>>(&mut self, pkg: S) -> Option<Streaming> {
+        match self.game_entry(pkg.as_ref())?.get("streaming")? {
+            Value::Boolean(true) => Some(Streaming::default()),
+            Value::Table(table) => {
+                let mut streaming = Streaming {
+                    fps: table
+                        .get("fps")
+                        .and_then(Value::as_integer)
+                        .filter(|fps| *fps > 0)
+                        .map(|fps| fps as u32),
+                    ..Streaming::default()
+                };
+                if let Some(threads) = table.get("threads").and_then(Value::as_array) {
+                    streaming.threads = threads
                         .iter()
-                        .filter_map(toml::Value::as_integer)
-                        .map(|i| i as u32)
+                        .filter_map(Value::as_str)
+                        .map(String::from)
                         .collect();
-                    arr.sort_unstable();
-                    Some(TargetFps::Array(arr))
                 }
-                Value::Integer(i) => Some(TargetFps::Value(i as u32)),
-                Value::String(s) => {
-                    if s == "auto" {
-                        Some(TargetFps::Array(vec![30, 45, 60, 90, 120, 144]))
-                    } else {
-                        error!("Find target game {pkg} in config, but meet illegal data type");
-                        error!("Sugg: try \'{pkg} = \"auto\"\'");
-                        None
-                    }
-                }
-                _ => {
-                    error!("Find target game {pkg} in config, but meet illegal data type");
-                    error!("Sugg: try \'{pkg} = \"auto\"\'");
-                    None
-                }
-            },
-        )
+                Some(streaming)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn refresh_rate<S: AsRef<str>>(&mut self, pkg: S) -> Option<u32> {
+        self.game_entry(pkg.as_ref())?
+            .get("refresh_rate")?
+            .as_integer()
+            .filter(|rate| *rate > 0)
+            .map(|rate| rate as u32)
+    }
+
+    pub fn launch_boost<S: AsRef<str>>(&mut self, pkg: S) -> Option<Duration> {
+        self.game_entry(pkg.as_ref())?
+            .get("launch_boost_ms")?
+            .as_integer()
+            .filter(|ms| *ms > 0)
+            .map(|ms| Duration::from_millis(ms as u64))
+    }
+
+    // Per-game value wins, otherwise the global [config] switch
+    pub fn adaptive_margin<S: AsRef<str>>(&mut self, pkg: S) -> bool {
+        self.game_entry(pkg.as_ref())
+            .and_then(|value| value.get("adaptive_margin")?.as_bool())
+            .unwrap_or(self.config().adaptive_margin)
+    }
+
+    fn game_flag<S: AsRef<str>>(&mut self, pkg: S, flag: &str) -> bool {
+        self.game_entry(pkg.as_ref())
+            .and_then(|value| value.get(flag)?.as_bool())
+            .unwrap_or(false)
+    }
+
+    pub fn governor<S: AsRef<str>>(&mut self, pkg: S) -> Option<String> {
+        self.game_entry(pkg.as_ref())?
+            .get("governor")?
+            .as_str()
+            .map(String::from)
     }
 
     #[must_use]
@@ -126,6 +503,190 @@ impl Config {
         }
     }
 
+    pub fn margin_fps<S: AsRef<str>>(&mut self, pkg: S, m: Mode) -> MarginFps {
+        let pkg = pkg.as_ref();
+
+        self.game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("margin_fps"))
+            .and_then(|margin_fps| {
+                margin_fps
+                    .try_into()
+                    .map_err(|_| {
+                        error!("Find target game {pkg} in config, but meet illegal margin_fps")
+                    })
+                    .ok()
+            })
+            .unwrap_or_else(|| self.mode_config(m).margin_fps.clone())
+    }
+
+    pub fn pid_gains<S: AsRef<str>>(&mut self, pkg: S) -> PidGains {
+        let pkg = pkg.as_ref();
+
+        self.game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("pid"))
+            .and_then(|pid| {
+                pid.try_into()
+                    .map_err(|_| error!("Find target game {pkg} in config, but meet illegal pid"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn thermal_config(&mut self) -> ThermalConfig {
+        self.inner.config().thermal.clone()
+    }
+
+    #[must_use]
+    // Read once before the logger exists, a broken or missing file falls back to the defaults
+    pub fn daemon_config<P: AsRef<Path>>(path: P) -> DaemonConfig {
+        #[derive(serde::Deserialize)]
+        struct Daemon {
+            #[serde(default)]
+            daemon: DaemonConfig,
+        }
+
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str::<Daemon>(&content).ok())
+            .map(|config| config.daemon)
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    // Read once before any node is looked up, like `daemon_config`
+    pub fn paths_config<P: AsRef<Path>>(path: P) -> PathsConfig {
+        #[derive(serde::Deserialize)]
+        struct Paths {
+            #[serde(default)]
+            paths: PathsConfig,
+        }
+
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| toml::from_str::<Paths>(&content).ok())
+            .map(|config| config.paths)
+            .unwrap_or_default()
+    }
+
+    // Tunable names and values per cluster name, other values than integers and strings are skipped
+    pub fn governor_tunables(&mut self) -> HashMap<String, Vec<(String, String)>> {
+        self.inner
+            .config()
+            .tunables
+            .iter()
+            .filter_map(|(cluster, tunables)| {
+                let tunables = tunables
+                    .as_table()?
+                    .iter()
+                    .filter(|(name, _)| !name.contains('/') && !name.starts_with('.'))
+                    .filter_map(|(name, value)| {
+                        let value = match value {
+                            Value::Integer(i) => i.to_string(),
+                            Value::String(s) => s.clone(),
+                            _ => return None,
+                        };
+                        Some((name.clone(), value))
+                    })
+                    .collect();
+                Some((cluster.clone(), tunables))
+            })
+            .collect()
+    }
+
+    pub fn capture_config(&mut self) -> CaptureConfig {
+        self.inner.config().capture.clone()
+    }
+
+    pub fn irq_config(&mut self) -> IrqConfig {
+        self.inner.config().irq.clone()
+    }
+
+    pub fn log_config(&mut self) -> LogConfig {
+        self.inner.config().log.clone()
+    }
+
+    pub fn mitigation_config(&mut self) -> MitigationConfig {
+        self.inner.config().mitigation.clone()
+    }
+
+    pub fn oem_config(&mut self) -> OemConfig {
+        self.inner.config().oem.clone()
+    }
+
+    pub fn memory_config(&mut self) -> MemoryConfig {
+        self.inner.config().memory
+    }
+
+    #[must_use]
+    pub fn battery_config(&mut self) -> BatteryConfig {
+        self.inner.config().battery
+    }
+
+    pub fn psi_config(&mut self) -> PsiConfig {
+        self.inner.config().psi
+    }
+
+    pub fn monitor_config<S: AsRef<str>>(&mut self, pkg: S) -> MonitorConfig {
+        let pkg = pkg.as_ref();
+        let mut monitor = self.inner.config().monitor;
+
+        let Some(Value::Table(overrides)) = self
+            .game_entry(pkg)
+            .and_then(|mut value| value.as_table_mut()?.remove("monitor"))
+        else {
+            return monitor;
+        };
+
+        let get = |key: &str| {
+            overrides
+                .get(key)
+                .and_then(Value::as_integer)
+                .filter(|value| *value > 0)
+        };
+
+        if let Some(sample_interval) = get("sample_interval") {
+            monitor.sample_interval = sample_interval as u64;
+        }
+        if let Some(max_sample_interval) = get("max_sample_interval") {
+            monitor.max_sample_interval = max_sample_interval as u64;
+        }
+        if let Some(refresh_interval) = get("refresh_interval") {
+            monitor.refresh_interval = refresh_interval as u64;
+        }
+        if let Some(top_threads) = get("top_threads") {
+            monitor.top_threads = top_threads as usize;
+        }
+        if let Some(util_median_window) = get("util_median_window") {
+            monitor.util_median_window = util_median_window as usize;
+        }
+
+        let get_float = |key: &str| {
+            overrides.get(key).and_then(|value| {
+                value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|i| i as f64))
+            })
+        };
+
+        if let Some(runqueue_weight) = get_float("runqueue_weight").filter(|value| *value >= 0.0) {
+            monitor.runqueue_weight = runqueue_weight;
+        }
+        if let Some(alpha) =
+            get_float("util_ewma_alpha").filter(|value| *value > 0.0 && *value <= 1.0)
+        {
+            monitor.util_ewma_alpha = alpha;
+        }
+        if let Some(smoothing) = overrides
+            .get("util_smoothing")
+            .and_then(|value| value.clone().try_into().ok())
+        {
+            monitor.util_smoothing = smoothing;
+        }
+
+        monitor
+    }
+
     #[must_use]
     pub fn config(&mut self) -> ConfigConfig {
         self.inner.config().config
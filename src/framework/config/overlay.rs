@@ -0,0 +1,130 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use toml::{Table, Value};
+
+use super::Config;
+use crate::{framework::error::Result, misc::getprop};
+
+// Next to the standard config, `overlays/<name>.toml`
+const OVERLAY_DIR: &str = "overlays";
+// From the least to the most specific, a device overlay is laid over the one of its SoC
+const OVERLAY_PROPS: [&str; 3] = ["ro.board.platform", "ro.soc.model", "ro.product.device"];
+
+impl Config {
+    // The standard config with the overlays of this device laid over it, and their file names.
+    // Frequency ceilings and tunables differ per SoC, no single standard config fits them all
+    pub fn read_std<P: AsRef<Path>>(path: P) -> Result<(String, Vec<String>)> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+
+        match path.parent() {
+            Some(dir) => Self::with_overlays(content, dir),
+            None => Ok((content, Vec::new())),
+        }
+    }
+
+    // For a standard config that does not live next to its overlays, like a downloaded one
+    pub fn with_overlays(content: String, dir: &Path) -> Result<(String, Vec<String>)> {
+        let overlays = find_overlays(&dir.join(OVERLAY_DIR));
+        if overlays.is_empty() {
+            return Ok((content, Vec::new()));
+        }
+
+        let mut table: Table = toml::from_str(&content)?;
+        let mut names = Vec::new();
+        for overlay in overlays {
+            let overlay_table: Table = toml::from_str(&fs::read_to_string(&overlay)?)?;
+            expand_games(&mut table, &overlay_table);
+            lay_over(&mut table, overlay_table);
+
+            let name = overlay
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            info!("Standard config overlay: {name}");
+            names.push(name);
+        }
+
+        Ok((toml::to_string(&table)?, names))
+    }
+}
+
+// Overlays named after the props of this device, case-insensitively, in the order of the props
+fn find_overlays(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let files: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+
+    let mut overlays = Vec::new();
+    for prop in OVERLAY_PROPS {
+        let Some(value) = getprop(prop).filter(|value| !value.is_empty()) else {
+            continue;
+        };
+
+        for file in &files {
+            let matches = file
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().eq_ignore_ascii_case(&value));
+            if matches && !overlays.contains(file) {
+                overlays.push(file.clone());
+            }
+        }
+    }
+
+    overlays
+}
+
+// `"pkg" = [30, 60]` is short for `{ target_fps = [30, 60] }`, an overlay table for the game
+// must not wipe its target fps
+fn expand_games(base: &mut Table, overlay: &Table) {
+    let (Some(Value::Table(games)), Some(Value::Table(overlay_games))) =
+        (base.get_mut("game_list"), overlay.get("game_list"))
+    else {
+        return;
+    };
+
+    for (pkg, value) in games.iter_mut() {
+        if !value.is_table() && overlay_games.get(pkg).is_some_and(Value::is_table) {
+            let mut entry = Table::new();
+            entry.insert("target_fps".into(), value.clone());
+            *value = Value::Table(entry);
+        }
+    }
+}
+
+// Tables are merged key by key, anything else (arrays included) is replaced
+fn lay_over(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => lay_over(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
@@ -20,14 +20,17 @@ use std::{fs, path::Path, sync::mpsc::Sender, time::Duration};
 use inotify::{Inotify, WatchMask};
 use log::{debug, error};
 
-use super::data::{ConfigData, SceneAppList};
+use super::{
+    Config,
+    data::{ConfigData, SceneAppList},
+};
 use crate::framework::error::Result;
 
 const SCENE_PROFILE: &str = "/data/data/com.omarea.vtools/shared_prefs/games.xml";
 const MAX_RETRY_COUNT: u8 = 10;
 
 pub(super) fn wait_and_read(path: &Path, std_path: &Path, sx: &Sender<ConfigData>) -> Result<()> {
-    let std_config = read_config(std_path)?;
+    let std_config = read_std_config(std_path)?;
 
     loop {
         match read_config_with_retry(path) {
@@ -41,6 +44,11 @@ pub(super) fn wait_and_read(path: &Path, std_path: &Path, sx: &Sender<ConfigData
             }
             Err(e) => {
                 error!("Too many retries reading config: {e}");
+                if let Ok(content) = fs::read_to_string(path) {
+                    for diagnostic in Config::validate(&content) {
+                        error!("{}:{diagnostic}", path.display());
+                    }
+                }
                 error!("Using standard profile until user config is available.");
                 sx.send(std_config.clone()).unwrap();
             }
@@ -50,12 +58,30 @@ pub(super) fn wait_and_read(path: &Path, std_path: &Path, sx: &Sender<ConfigData
     }
 }
 
+pub(super) fn read_user_config(path: &Path) -> Result<ConfigData> {
+    let mut config = read_config(path)?;
+    if config.config.scene_game_list {
+        if let Err(e) = read_scene_games(&mut config) {
+            error!("Failed to read scene games: {e}");
+        }
+    }
+
+    Ok(config)
+}
+
 fn read_config(path: &Path) -> Result<ConfigData> {
     let content = fs::read_to_string(path)?;
     let config = toml::from_str(&content)?;
     Ok(config)
 }
 
+// The fallback gets the device overlays too, it is what a fresh install would have written
+fn read_std_config(path: &Path) -> Result<ConfigData> {
+    let (content, _) = Config::read_std(path)?;
+    let config = toml::from_str(&content)?;
+    Ok(config)
+}
+
 fn read_config_with_retry(path: &Path) -> Result<ConfigData> {
     let mut retry_count = 0;
 
@@ -0,0 +1,125 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use super::{Config, matcher::GameMatcher};
+use crate::framework::error::Result;
+
+const GAMES_DB: &str = include_str!("games_db.toml");
+
+#[derive(Deserialize)]
+struct GamesDb {
+    games: BTreeMap<String, KnownGame>,
+}
+
+#[derive(Deserialize)]
+struct KnownGame {
+    #[serde(default)]
+    name: Option<String>,
+    target_fps: Value,
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameSuggestion {
+    pub pkg: String,
+    pub name: Option<String>,
+    pub entry: Value,
+}
+
+impl GameSuggestion {
+    pub fn line(&self) -> String {
+        let line = format!("\"{}\" = {}", self.pkg, self.entry);
+        match &self.name {
+            Some(name) => format!("{line} # {name}"),
+            None => line,
+        }
+    }
+}
+
+impl Config {
+    // Known games among `installed` that games.toml does not list yet
+    pub fn suggest_games<S: AsRef<str>>(
+        content: S,
+        installed: &[String],
+    ) -> Result<Vec<GameSuggestion>> {
+        let table: Table = toml::from_str(content.as_ref())?;
+        let db: GamesDb = toml::from_str(GAMES_DB)?;
+        let configured = table.get("game_list").and_then(Value::as_table);
+        let mut matcher = GameMatcher::default();
+
+        Ok(db
+            .games
+            .into_iter()
+            .filter(|(pkg, _)| installed.contains(pkg))
+            .filter(|(pkg, _)| {
+                configured.is_none_or(|game_list| matcher.resolve(game_list, 0, pkg).is_none())
+            })
+            .map(|(pkg, game)| {
+                let entry = match game.mode {
+                    Some(mode) => Value::Table(
+                        [
+                            ("target_fps".to_string(), game.target_fps),
+                            ("mode".to_string(), Value::String(mode)),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    ),
+                    None => game.target_fps,
+                };
+
+                GameSuggestion {
+                    pkg,
+                    name: game.name,
+                    entry,
+                }
+            })
+            .collect())
+    }
+
+    // Edits the text in place so comments and layout of games.toml survive, unlike `merge`
+    pub fn insert_games<S: AsRef<str>>(content: S, games: &[GameSuggestion]) -> String {
+        let mut lines: Vec<String> = content.as_ref().lines().map(str::to_string).collect();
+        let new_lines = games.iter().map(GameSuggestion::line);
+
+        if let Some(header) = lines.iter().position(|line| line.trim() == "[game_list]") {
+            let end = lines[header + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with('['))
+                .map_or(lines.len(), |pos| header + 1 + pos);
+            let mut at = end;
+            while at > header + 1 && lines[at - 1].trim().is_empty() {
+                at -= 1;
+            }
+
+            lines.splice(at..at, new_lines);
+        } else {
+            lines.push(String::new());
+            lines.push("[game_list]".into());
+            lines.extend(new_lines);
+        }
+
+        let mut content = lines.join("\n");
+        content.push('\n');
+        content
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, io::Read, time::Duration};
+
+use anyhow::{Context, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use super::Config;
+use crate::framework::error::Result;
+
+// The standard config and its signature, published with every release by the release workflow
+// from what `cargo xtask sign-config` writes
+const STD_CONFIG_URL: &str =
+    "https://github.com/reigadegr/fas-rs-perf/releases/latest/download/games.toml";
+const STD_SIGNATURE_URL: &str =
+    "https://github.com/reigadegr/fas-rs-perf/releases/latest/download/games.toml.sig";
+// Serial of the last standard config merged, an older one is a replay and refused
+const SERIAL_PATH: &str = "/data/adb/fas-rs/config_serial";
+// Base64 ed25519 public key the standard config is signed with, `cargo xtask build` passes it
+// in. Without one nothing downloaded can be trusted, so updating is refused
+const SIGNING_KEY: Option<&str> = option_env!("FAS_RS_CONFIG_PUBKEY");
+const TIMEOUT: Duration = Duration::from_secs(20);
+// Far above any real games.toml, a broken or hostile server must not fill the memory
+const MAX_SIZE: u64 = 1024 * 1024;
+
+impl Config {
+    // Downloads the curated standard config and returns it with its serial once the signature
+    // checked out, the serial is not older than the last one merged and it validates. None when
+    // that serial was merged already. Nothing is written here
+    pub fn fetch_std() -> Result<Option<(String, u64)>> {
+        let key = verifying_key()?;
+
+        let config = download(STD_CONFIG_URL)?;
+        // `<serial>\n<base64 signature of "<serial>\n" followed by the config>`
+        let signature = download(STD_SIGNATURE_URL)?;
+        let (serial, signature) = signature
+            .trim()
+            .split_once('\n')
+            .context("Signature has no serial")?;
+        let serial: u64 = serial.trim().parse().context("Invalid config serial")?;
+        let signature = STANDARD
+            .decode(signature.trim())
+            .context("Signature is not valid base64")?;
+        let signature =
+            Signature::from_slice(&signature).context("Signature has the wrong length")?;
+
+        let message = format!("{serial}\n{config}");
+        key.verify_strict(message.as_bytes(), &signature)
+            .context("Signature does not match the downloaded config, refusing it")?;
+
+        let last = last_serial();
+        if serial < last {
+            return Err(anyhow!(
+                "Downloaded config has serial {serial}, older than the merged {last}, refusing it"
+            )
+            .into());
+        }
+        if serial == last {
+            return Ok(None);
+        }
+
+        if let Some(diagnostic) = Self::validate(&config).first() {
+            return Err(anyhow!("Downloaded config does not validate: {diagnostic}").into());
+        }
+
+        Ok(Some((config, serial)))
+    }
+
+    // Called once the config of `serial` was merged
+    pub fn record_std_serial(serial: u64) -> Result<()> {
+        fs::write(SERIAL_PATH, serial.to_string())
+            .with_context(|| format!("Failed to write {SERIAL_PATH}"))?;
+        Ok(())
+    }
+}
+
+fn last_serial() -> u64 {
+    fs::read_to_string(SERIAL_PATH)
+        .ok()
+        .and_then(|serial| serial.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+fn verifying_key() -> Result<VerifyingKey> {
+    let Some(key) = SIGNING_KEY else {
+        return Err(
+            anyhow!("This build has no config signing key, update-config is unavailable").into(),
+        );
+    };
+
+    let key: [u8; 32] = STANDARD
+        .decode(key.trim())
+        .context("Invalid config signing key")?
+        .try_into()
+        .map_err(|_| anyhow!("Config signing key has the wrong length"))?;
+
+    Ok(VerifyingKey::from_bytes(&key).context("Invalid config signing key")?)
+}
+
+fn download(url: &str) -> Result<String> {
+    let response = ureq::get(url)
+        .timeout(TIMEOUT)
+        .call()
+        .with_context(|| format!("Failed to download {url}"))?;
+
+    let mut content = String::new();
+    response
+        .into_reader()
+        .take(MAX_SIZE + 1)
+        .read_to_string(&mut content)
+        .with_context(|| format!("Failed to read {url}"))?;
+
+    if content.len() as u64 > MAX_SIZE {
+        return Err(anyhow!("{url} is larger than {MAX_SIZE} bytes").into());
+    }
+
+    Ok(content)
+}
@@ -0,0 +1,1057 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use toml::{Table, Value};
+
+use super::{
+    Config,
+    data::{ConfigData, MarginFps, MonitorConfig, PidGains},
+    matcher,
+    migrate::CONFIG_VERSION,
+};
+use crate::{
+    cpu_common::{AffinityRule, LadderConfig, StrategyKind, UtilSmoothing},
+    framework::{
+        mitigation::{package_allowed, prop_allowed, service_allowed},
+        node::Mode,
+    },
+    paths::Root,
+};
+
+const SECTIONS: [&str; 21] = [
+    "config",
+    "game_list",
+    "profiles",
+    "powersave",
+    "balance",
+    "performance",
+    "fast",
+    "thermal",
+    "battery",
+    "monitor",
+    "daemon",
+    "packages",
+    "psi",
+    "capture",
+    "irq",
+    "log",
+    "tunables",
+    "mitigation",
+    "memory",
+    "paths",
+    "oem",
+];
+const MODE_SECTIONS: [&str; 4] = ["powersave", "balance", "performance", "fast"];
+const GAME_KEYS: [&str; 22] = [
+    "target_fps",
+    "governor",
+    "mode",
+    "record",
+    "touch_boost",
+    "pin_big_cores",
+    "shallow_idle",
+    "protect_memory",
+    "streaming",
+    "adaptive_margin",
+    "launch_boost_ms",
+    "refresh_rate",
+    "thread_affinity",
+    "render_priority",
+    "clusters",
+    "margin_fps",
+    "monitor",
+    "pid",
+    "strategy",
+    "ladder",
+    "profiles",
+    "extends",
+];
+const CLUSTER_NAMES: [&str; 3] = ["little", "big", "prime"];
+const CLUSTER_KEYS: [&str; 4] = ["margin", "ceiling", "min_freq_mhz", "max_freq_mhz"];
+const MAX_FPS: i64 = 240;
+// A burst past ten times the target frametime, or held for over a second, is a typo
+const MAX_JANK_BURST_THRESHOLD: i64 = 1000;
+const MAX_JANK_BURST_DURATION: i64 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    diagnostics: Vec<Diagnostic>,
+    // Where game keys are reported, `game_list` or `profiles` while base profiles are checked
+    game_section: &'static str,
+    // Names of the base profiles, for `extends`
+    profiles: Vec<String>,
+}
+
+impl Config {
+    pub fn validate<S: AsRef<str>>(s: S) -> Vec<Diagnostic> {
+        let source = s.as_ref();
+        let mut validator = Validator {
+            source,
+            diagnostics: Vec::new(),
+            game_section: "game_list",
+            profiles: Vec::new(),
+        };
+
+        let table: Table = match toml::from_str(source) {
+            Ok(table) => table,
+            Err(e) => {
+                validator.report_toml_error(&e);
+                return validator.diagnostics;
+            }
+        };
+
+        let data: ConfigData = match toml::from_str(source) {
+            Ok(data) => data,
+            Err(e) => {
+                validator.report_toml_error(&e);
+                return validator.diagnostics;
+            }
+        };
+
+        validator.check_sections(&table, &data);
+        validator.diagnostics
+    }
+}
+
+impl Validator<'_> {
+    fn report_toml_error(&mut self, e: &toml::de::Error) {
+        let (line, column) = e.span().map_or((1, 1), |span| self.position_of(span.start));
+        self.diagnostics.push(Diagnostic {
+            line,
+            column,
+            message: e.message().to_string(),
+        });
+    }
+
+    fn report<S: Into<String>>(&mut self, section: &str, key: &str, message: S) {
+        let (line, column) = self.locate(section, key);
+        self.diagnostics.push(Diagnostic {
+            line,
+            column,
+            message: message.into(),
+        });
+    }
+
+    fn position_of(&self, offset: usize) -> (usize, usize) {
+        let before = &self.source[..offset.min(self.source.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rfind('\n').map_or(offset, |pos| offset - pos - 1) + 1;
+        (line, column)
+    }
+
+    // Best effort: find the first line declaring `key` after the `[section]` header
+    fn locate(&self, section: &str, key: &str) -> (usize, usize) {
+        let mut in_section = section.is_empty();
+        let quoted = format!("\"{key}\"");
+
+        for (index, line) in self.source.lines().enumerate() {
+            let trimmed = line.trim_start();
+
+            if trimmed.starts_with('[') {
+                let header = trimmed.trim_matches(|c| c == '[' || c == ']' || c == ' ');
+                in_section = header == section || header.starts_with(&format!("{section}."));
+                // A table of its own, like `[profiles.esports]`
+                let own_table =
+                    header == format!("{section}.{key}") || header == format!("{section}.{quoted}");
+                if (key.is_empty() && header == section) || own_table {
+                    return (index + 1, 1);
+                }
+                continue;
+            }
+
+            if in_section && (trimmed.starts_with(key) || trimmed.starts_with(&quoted)) {
+                return (index + 1, line.len() - trimmed.len() + 1);
+            }
+        }
+
+        (1, 1)
+    }
+
+    fn check_sections(&mut self, table: &Table, data: &ConfigData) {
+        for (section, value) in table {
+            if section == "version" {
+                self.check_version(value);
+            } else if !SECTIONS.contains(&section.as_str()) {
+                self.report(section, "", format!("Unknown section [{section}]"));
+            }
+        }
+
+        if let Some(Value::Table(config)) = table.get("config") {
+            let known = Self::known_keys(&data.config);
+            self.check_unknown_keys("config", config, &known);
+            self.check_jank_burst(config);
+        }
+
+        if let Some(Value::Table(thermal)) = table.get("thermal") {
+            let known = Self::known_keys(&data.thermal);
+            self.check_unknown_keys("thermal", thermal, &known);
+        }
+
+        if let Some(Value::Table(battery)) = table.get("battery") {
+            let known = Self::known_keys(&data.battery);
+            self.check_unknown_keys("battery", battery, &known);
+        }
+
+        if let Some(Value::Table(monitor)) = table.get("monitor") {
+            let known = Self::known_keys(&data.monitor);
+            self.check_unknown_keys("monitor", monitor, &known);
+        }
+
+        if let Some(Value::Table(daemon)) = table.get("daemon") {
+            let known = Self::known_keys(&data.daemon);
+            self.check_unknown_keys("daemon", daemon, &known);
+        }
+
+        if let Some(Value::Table(packages)) = table.get("packages") {
+            let known = Self::known_keys(&data.packages);
+            self.check_unknown_keys("packages", packages, &known);
+        }
+
+        if let Some(Value::Table(psi)) = table.get("psi") {
+            let known = Self::known_keys(&data.psi);
+            self.check_unknown_keys("psi", psi, &known);
+        }
+
+        if let Some(tunables) = table.get("tunables") {
+            self.check_tunables(tunables);
+        }
+
+        if let Some(Value::Table(capture)) = table.get("capture") {
+            let known = Self::known_keys(&data.capture);
+            self.check_unknown_keys("capture", capture, &known);
+        }
+
+        if let Some(Value::Table(irq)) = table.get("irq") {
+            let known = Self::known_keys(&data.irq);
+            self.check_unknown_keys("irq", irq, &known);
+        }
+
+        if let Some(Value::Table(memory)) = table.get("memory") {
+            let known = Self::known_keys(&data.memory);
+            self.check_unknown_keys("memory", memory, &known);
+        }
+
+        if let Some(Value::Table(paths)) = table.get("paths") {
+            let known = Self::known_keys(&data.paths);
+            self.check_unknown_keys("paths", paths, &known);
+            self.check_paths(paths);
+        }
+
+        if let Some(Value::Table(log)) = table.get("log") {
+            let known = Self::known_keys(&data.log);
+            self.check_unknown_keys("log", log, &known);
+        }
+
+        if let Some(Value::Table(mitigation)) = table.get("mitigation") {
+            let known = Self::known_keys(&data.mitigation);
+            self.check_unknown_keys("mitigation", mitigation, &known);
+            self.check_mitigation(mitigation);
+        }
+
+        if let Some(Value::Table(oem)) = table.get("oem") {
+            let known = Self::known_keys(&data.oem);
+            self.check_unknown_keys("oem", oem, &known);
+            self.check_oem(oem);
+        }
+
+        for mode in MODE_SECTIONS {
+            if let Some(Value::Table(mode_table)) = table.get(mode) {
+                let known = Self::known_keys(&data.balance);
+                self.check_unknown_keys(mode, mode_table, &known);
+            }
+        }
+
+        if let Some(profiles) = table.get("profiles") {
+            self.check_base_profiles(profiles);
+        }
+
+        if let Some(Value::Table(game_list)) = table.get("game_list") {
+            for (pkg, value) in game_list {
+                self.check_game(pkg, value);
+            }
+        }
+    }
+
+    fn check_base_profiles(&mut self, value: &Value) {
+        let Some(profiles) = value.as_table() else {
+            self.report("profiles", "", "[profiles] must be a table");
+            return;
+        };
+
+        self.profiles = profiles.keys().cloned().collect();
+        self.game_section = "profiles";
+        for (profile, value) in profiles {
+            let name = format!("profiles.{profile}");
+            match value.as_table() {
+                Some(table) => self.check_game_keys(profile, &name, table),
+                None => self.report("profiles", profile, format!("{name} must be a table")),
+            }
+        }
+        self.game_section = "game_list";
+    }
+
+    fn known_keys<T: serde::Serialize>(value: &T) -> Vec<String> {
+        Value::try_from(value)
+            .ok()
+            .and_then(|value| {
+                value
+                    .as_table()
+                    .map(|table| table.keys().cloned().collect())
+            })
+            .unwrap_or_default()
+    }
+
+    fn check_unknown_keys(&mut self, section: &str, table: &Table, known: &[String]) {
+        for key in table.keys() {
+            if !known.contains(key) {
+                self.report(section, key, format!("Unknown key '{key}' in [{section}]"));
+            }
+        }
+    }
+
+    fn check_game(&mut self, pkg: &str, value: &Value) {
+        if matcher::is_pattern(pkg) {
+            if let Err(e) = matcher::check_pattern(pkg) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("Invalid pattern '{pkg}': {e}"),
+                );
+            }
+        } else if !pkg.split(':').next().is_some_and(is_valid_package_name) {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("Malformed package name '{pkg}'"),
+            );
+        }
+
+        match value {
+            Value::Table(table) => self.check_game_keys(pkg, pkg, table),
+            value => self.check_target_fps(pkg, value),
+        }
+    }
+
+    // `name` is the path shown in messages, e.g. `pkg` or `pkg.profiles.battle`
+    fn check_game_keys(&mut self, pkg: &str, name: &str, table: &Table) {
+        for (key, value) in table {
+            if !GAME_KEYS.contains(&key.as_str()) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("Unknown key '{key}' for {name}"),
+                );
+                continue;
+            }
+
+            match key.as_str() {
+                "target_fps" => self.check_target_fps(pkg, value),
+                "governor" if !value.is_str() => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.governor must be a string"),
+                    );
+                }
+                "mode" => {
+                    if value
+                        .as_str()
+                        .and_then(|m| m.parse::<Mode>().ok())
+                        .is_none()
+                    {
+                        self.report(
+                            self.game_section,
+                            pkg,
+                            format!(
+                                "{name}.mode must be one of powersave, balance, performance, fast"
+                            ),
+                        );
+                    }
+                }
+                "record" | "touch_boost" | "pin_big_cores" | "shallow_idle" | "protect_memory"
+                | "adaptive_margin"
+                    if !value.is_bool() =>
+                {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.{key} must be a bool"),
+                    );
+                }
+                "launch_boost_ms" if !value.as_integer().is_some_and(|ms| ms >= 0) => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.launch_boost_ms must be a non-negative integer"),
+                    );
+                }
+                "refresh_rate"
+                    if !value
+                        .as_integer()
+                        .is_some_and(|rate| (1..=MAX_FPS).contains(&rate)) =>
+                {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.refresh_rate must be an integer in 1..={MAX_FPS}"),
+                    );
+                }
+                "thread_affinity" => self.check_thread_affinity(pkg, name, value),
+                "streaming" => self.check_streaming(pkg, name, value),
+                "clusters" => self.check_clusters(pkg, name, value),
+                "monitor" => self.check_monitor(pkg, name, value),
+                "pid" => self.check_pid(pkg, name, value),
+                "ladder" => self.check_ladder(pkg, name, value),
+                "render_priority"
+                    if value.as_str() != Some("fifo")
+                        && !value
+                            .as_integer()
+                            .is_some_and(|nice| (-20..=19).contains(&nice)) =>
+                {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!(
+                            "{name}.render_priority must be \"fifo\" or a nice value in -20..=19"
+                        ),
+                    );
+                }
+                "margin_fps" if value.clone().try_into::<MarginFps>().is_err() => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.margin_fps must be a number or {{ base = <number>, ... }}"),
+                    );
+                }
+                "strategy" if value.clone().try_into::<StrategyKind>().is_err() => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!(
+                            "{name}.strategy must be \"heuristic\", \"pid\" or \"step_ladder\""
+                        ),
+                    );
+                }
+                "profiles" if name == pkg => self.check_profiles(pkg, value),
+                "profiles" => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name} can not declare nested profiles"),
+                    );
+                }
+                "extends" if self.game_section == "profiles" => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name} can not extend another profile"),
+                    );
+                }
+                "extends" if name != pkg => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name} can not extend a profile"),
+                    );
+                }
+                "extends" => match value.as_str() {
+                    Some(base) if self.profiles.iter().any(|profile| profile == base) => (),
+                    Some(base) => self.report(
+                        self.game_section,
+                        pkg,
+                        format!(
+                            "{name} extends unknown profile '{base}', declare [profiles.{base}]"
+                        ),
+                    ),
+                    None => self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.extends must be a string"),
+                    ),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn check_profiles(&mut self, pkg: &str, value: &Value) {
+        let Some(profiles) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{pkg}.profiles must be a table"),
+            );
+            return;
+        };
+
+        for (profile, value) in profiles {
+            let name = format!("{pkg}.profiles.{profile}");
+            match value.as_table() {
+                Some(table) => self.check_game_keys(pkg, &name, table),
+                None => self.report(self.game_section, pkg, format!("{name} must be a table")),
+            }
+        }
+    }
+
+    fn check_monitor(&mut self, pkg: &str, name: &str, value: &Value) {
+        let Some(monitor) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.monitor must be a table"),
+            );
+            return;
+        };
+
+        let known = Self::known_keys(&MonitorConfig::default());
+        for (key, value) in monitor {
+            if !known.contains(key) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("Unknown key '{key}' in {name}.monitor"),
+                );
+            } else if key == "util_smoothing" {
+                if value.clone().try_into::<UtilSmoothing>().is_err() {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.monitor.{key} must be \"none\", \"ewma\" or \"median\""),
+                    );
+                }
+            } else if key == "util_ewma_alpha" {
+                if !value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|i| i as f64))
+                    .is_some_and(|value| value > 0.0 && value <= 1.0)
+                {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.monitor.{key} must be a number in (0, 1]"),
+                    );
+                }
+            } else if key == "runqueue_weight" {
+                if !value
+                    .as_float()
+                    .or_else(|| value.as_integer().map(|i| i as f64))
+                    .is_some_and(|value| value >= 0.0)
+                {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.monitor.{key} must be a non-negative number"),
+                    );
+                }
+            } else if !value.as_integer().is_some_and(|value| value > 0) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("{name}.monitor.{key} must be a positive integer"),
+                );
+            }
+        }
+    }
+
+    fn check_pid(&mut self, pkg: &str, name: &str, value: &Value) {
+        let Some(pid) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.pid must be a table"),
+            );
+            return;
+        };
+
+        let known = Self::known_keys(&PidGains::default());
+        for (key, value) in pid {
+            if !known.contains(key) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("Unknown key '{key}' in {name}.pid"),
+                );
+            } else if !value.as_float().is_some_and(|gain| gain >= 0.0)
+                && !value.as_integer().is_some_and(|gain| gain >= 0)
+            {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("{name}.pid.{key} must be a non-negative number"),
+                );
+            }
+        }
+    }
+
+    fn check_ladder(&mut self, pkg: &str, name: &str, value: &Value) {
+        let Some(table) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.ladder must be a table"),
+            );
+            return;
+        };
+
+        let known = Self::known_keys(&LadderConfig::default());
+        for key in table.keys().filter(|key| !known.contains(*key)) {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("Unknown key '{key}' in {name}.ladder"),
+            );
+        }
+
+        match value.clone().try_into::<LadderConfig>() {
+            Ok(ladder)
+                if (0.0..=1.0).contains(&ladder.down)
+                    && ladder.down <= ladder.up
+                    && ladder.up <= ladder.jump => {}
+            Ok(_) => self.report(
+                self.game_section,
+                pkg,
+                format!(
+                    "{name}.ladder needs 0 <= down <= up <= jump, utilization shares of a core"
+                ),
+            ),
+            Err(_) => self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.ladder tiers must be numbers and hold times non-negative integers"),
+            ),
+        }
+    }
+
+    fn check_version(&mut self, value: &Value) {
+        match value.as_integer() {
+            Some(version) if (1..CONFIG_VERSION).contains(&version) => self.report(
+                "",
+                "version",
+                format!(
+                    "Config version {version} is outdated, run merge to upgrade it to version {CONFIG_VERSION}"
+                ),
+            ),
+            Some(version) if version > CONFIG_VERSION => self.report(
+                "",
+                "version",
+                format!("Config version {version} is newer than supported version {CONFIG_VERSION}"),
+            ),
+            Some(CONFIG_VERSION) => (),
+            _ => self.report("", "version", "version must be a positive integer"),
+        }
+    }
+
+    fn check_target_fps(&mut self, pkg: &str, value: &Value) {
+        let valid = match value {
+            Value::Integer(fps) => (1..=MAX_FPS).contains(fps),
+            Value::Array(arr) => {
+                !arr.is_empty()
+                    && arr.iter().all(|fps| {
+                        fps.as_integer()
+                            .is_some_and(|fps| (1..=MAX_FPS).contains(&fps))
+                    })
+            }
+            Value::String(s) => s == "auto",
+            _ => false,
+        };
+
+        if !valid {
+            self.report(
+                self.game_section,
+                pkg,
+                format!(
+                    "Invalid target fps for {pkg}: expected an integer or array of integers in 1..={MAX_FPS}, or \"auto\""
+                ),
+            );
+        }
+    }
+
+    fn check_tunables(&mut self, value: &Value) {
+        let Some(clusters) = value.as_table() else {
+            self.report("tunables", "", "[tunables] must be a table");
+            return;
+        };
+
+        for (cluster_name, cluster) in clusters {
+            if !CLUSTER_NAMES.contains(&cluster_name.as_str()) {
+                self.report(
+                    "tunables",
+                    cluster_name,
+                    format!("Unknown cluster '{cluster_name}' in [tunables]"),
+                );
+            }
+
+            let Some(cluster) = cluster.as_table() else {
+                self.report(
+                    "tunables",
+                    cluster_name,
+                    format!("tunables.{cluster_name} must be a table"),
+                );
+                continue;
+            };
+
+            for (key, value) in cluster {
+                if key.contains('/') || key.starts_with('.') {
+                    self.report(
+                        "tunables",
+                        cluster_name,
+                        format!("tunables.{cluster_name}.{key} must be a plain tunable name"),
+                    );
+                }
+
+                if !(value.is_integer() || value.is_str()) {
+                    self.report(
+                        "tunables",
+                        cluster_name,
+                        format!("tunables.{cluster_name}.{key} must be an integer or a string"),
+                    );
+                }
+            }
+        }
+    }
+
+    // Entries off the allow-list are skipped at runtime anyway, saying so here saves a search
+    // Checked on the device the config is for, an override that is wrong here would be
+    // ignored at startup
+    fn check_paths(&mut self, paths: &Table) {
+        for root in Root::ALL {
+            let key = root.key();
+            let Some(value) = paths.get(key) else {
+                continue;
+            };
+
+            match value.as_str() {
+                Some("") => (),
+                Some(path) => {
+                    if let Err(e) = root.check(Path::new(path)) {
+                        self.report("paths", key, format!("paths.{key} '{path}' {e}"));
+                    }
+                }
+                None => self.report("paths", key, format!("paths.{key} must be a string")),
+            }
+        }
+    }
+
+    fn check_mitigation(&mut self, mitigation: &Table) {
+        let lists: [(&str, fn(&str) -> bool); 2] =
+            [("services", service_allowed), ("packages", package_allowed)];
+        for (key, allowed) in lists {
+            let Some(value) = mitigation.get(key) else {
+                continue;
+            };
+            let Some(names) = value.as_array() else {
+                self.report(
+                    "mitigation",
+                    key,
+                    format!("mitigation.{key} must be an array"),
+                );
+                continue;
+            };
+
+            for name in names {
+                match name.as_str() {
+                    Some(name) if !allowed(name) => self.report(
+                        "mitigation",
+                        key,
+                        format!("'{name}' in mitigation.{key} is not on the allow-list"),
+                    ),
+                    Some(_) => (),
+                    None => self.report(
+                        "mitigation",
+                        key,
+                        format!("mitigation.{key} must only contain strings"),
+                    ),
+                }
+            }
+        }
+
+        let Some(props) = mitigation.get("props") else {
+            return;
+        };
+        let Some(props) = props.as_table() else {
+            self.report("mitigation", "props", "mitigation.props must be a table");
+            return;
+        };
+
+        for (prop, value) in props {
+            if !prop_allowed(prop) {
+                self.report(
+                    "mitigation",
+                    "props",
+                    format!("Prop '{prop}' in mitigation.props is not on the allow-list"),
+                );
+            }
+
+            if !value.is_str() {
+                self.report(
+                    "mitigation",
+                    "props",
+                    format!("mitigation.props.{prop} must be a string"),
+                );
+            }
+        }
+    }
+
+    fn check_jank_burst(&mut self, config: &Table) {
+        if config.get("jank_burst_threshold").is_some_and(|value| {
+            !value
+                .as_integer()
+                .is_some_and(|percent| (0..=MAX_JANK_BURST_THRESHOLD).contains(&percent))
+        }) {
+            self.report(
+                "config",
+                "jank_burst_threshold",
+                format!(
+                    "config.jank_burst_threshold must be an integer from 0 to {MAX_JANK_BURST_THRESHOLD}"
+                ),
+            );
+        }
+
+        if config.get("jank_burst_duration").is_some_and(|value| {
+            !value
+                .as_integer()
+                .is_some_and(|ms| (1..=MAX_JANK_BURST_DURATION).contains(&ms))
+        }) {
+            self.report(
+                "config",
+                "jank_burst_duration",
+                format!(
+                    "config.jank_burst_duration must be an integer from 1 to {MAX_JANK_BURST_DURATION}"
+                ),
+            );
+        }
+    }
+
+    fn check_oem(&mut self, oem: &Table) {
+        for key in ["props", "settings"] {
+            let Some(value) = oem.get(key) else {
+                continue;
+            };
+            let Some(sources) = value.as_table() else {
+                self.report("oem", key, format!("oem.{key} must be a table"));
+                continue;
+            };
+
+            for (source, modes) in sources {
+                if key == "settings" && !valid_setting(source) {
+                    self.report(
+                        "oem",
+                        key,
+                        format!(
+                            "Setting '{source}' in oem.settings must be system/<key>, secure/<key> or global/<key>"
+                        ),
+                    );
+                }
+
+                let Some(modes) = modes.as_table() else {
+                    self.report(
+                        "oem",
+                        key,
+                        format!("oem.{key}.\"{source}\" must be a table of values to modes"),
+                    );
+                    continue;
+                };
+                for (value, mode) in modes {
+                    if mode.as_str().and_then(|m| m.parse::<Mode>().ok()).is_none() {
+                        self.report(
+                            "oem",
+                            key,
+                            format!(
+                                "oem.{key}.\"{source}\".\"{value}\" must be one of powersave, balance, performance, fast"
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_clusters(&mut self, pkg: &str, name: &str, value: &Value) {
+        let Some(clusters) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.clusters must be a table"),
+            );
+            return;
+        };
+
+        for (cluster_name, cluster) in clusters {
+            if !CLUSTER_NAMES.contains(&cluster_name.as_str()) {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("Unknown cluster '{cluster_name}' for {name}"),
+                );
+            }
+
+            let Some(cluster) = cluster.as_table() else {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!("{name}.clusters.{cluster_name} must be a table"),
+                );
+                continue;
+            };
+
+            for (key, value) in cluster {
+                if !CLUSTER_KEYS.contains(&key.as_str()) {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("Unknown key '{key}' in {name}.clusters.{cluster_name}"),
+                    );
+                } else if key.ends_with("_mhz") && !value.as_integer().is_some_and(|mhz| mhz > 0) {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("{name}.clusters.{cluster_name}.{key} must be a positive integer"),
+                    );
+                }
+            }
+
+            let mhz = |key: &str| cluster.get(key).and_then(Value::as_integer);
+            if let (Some(min), Some(max)) = (mhz("min_freq_mhz"), mhz("max_freq_mhz")) {
+                if min > max {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!(
+                            "{name}.clusters.{cluster_name}.min_freq_mhz is above max_freq_mhz"
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_streaming(&mut self, pkg: &str, name: &str, value: &Value) {
+        let table = match value {
+            Value::Boolean(_) => return,
+            Value::Table(table) => table,
+            _ => {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!(
+                        "{name}.streaming must be a bool or {{ fps = <fps>, threads = [...] }}"
+                    ),
+                );
+                return;
+            }
+        };
+
+        for (key, value) in table {
+            let valid = match key.as_str() {
+                "fps" => value.as_integer().is_some_and(|fps| fps > 0),
+                "threads" => value
+                    .as_array()
+                    .is_some_and(|threads| threads.iter().all(Value::is_str)),
+                _ => {
+                    self.report(
+                        self.game_section,
+                        pkg,
+                        format!("Unknown key '{key}' in {name}.streaming"),
+                    );
+                    continue;
+                }
+            };
+
+            if !valid {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!(
+                        "{name}.streaming.{key} must be {}",
+                        if key == "fps" {
+                            "a positive integer"
+                        } else {
+                            "an array of thread name globs"
+                        }
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_thread_affinity(&mut self, pkg: &str, name: &str, value: &Value) {
+        let Some(rules) = value.as_table() else {
+            self.report(
+                self.game_section,
+                pkg,
+                format!("{name}.thread_affinity must be a table"),
+            );
+            return;
+        };
+
+        for (thread, cpus) in rules {
+            let cpus = match cpus {
+                Value::String(cpus) => cpus.clone(),
+                Value::Integer(cpu) => cpu.to_string(),
+                _ => String::new(),
+            };
+
+            if AffinityRule::parse(thread, &cpus).is_none() {
+                self.report(
+                    self.game_section,
+                    pkg,
+                    format!(
+                        "{name}.thread_affinity.\"{thread}\" must be a cpu list like \"7\" or \"0-3,6\""
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn is_valid_package_name(pkg: &str) -> bool {
+    let segments: Vec<_> = pkg.split('.').collect();
+    segments.len() >= 2
+        && segments.iter().all(|segment| {
+            segment
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+fn valid_setting(source: &str) -> bool {
+    source.split_once('/').is_some_and(|(namespace, key)| {
+        matches!(namespace, "system" | "secure" | "global") && !key.is_empty()
+    })
+}
@@ -0,0 +1,61 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::fs;
+
+use log::info;
+use toml::{Table, Value};
+
+use super::Config;
+use crate::framework::error::{Error, Result};
+
+impl Config {
+    // Comments in games.toml are lost, the same as with `merge`
+    pub fn set_game_value<S: AsRef<str>>(&mut self, pkg: S, key: &str, value: Value) -> Result<()> {
+        let pkg = pkg.as_ref();
+        let pkg = pkg.split(':').next().unwrap_or(pkg);
+
+        let mut table: Table = toml::from_str(&fs::read_to_string(&self.path)?)?;
+        let game_list = table
+            .entry("game_list")
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or(Error::ParseConfig)?;
+        let entry = game_list
+            .entry(pkg)
+            .or_insert_with(|| Value::String("auto".into()));
+
+        if !entry.is_table() {
+            let target_fps = entry.clone();
+            *entry = Value::Table(
+                [("target_fps".to_string(), target_fps)]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        let Value::Table(entry) = entry else {
+            return Err(Error::ParseConfig);
+        };
+
+        info!("Write {pkg}.{key} = {value} to {}", self.path.display());
+        entry.insert(key.into(), value);
+        fs::write(&self.path, toml::to_string(&table)?)?;
+
+        self.reload()
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+use super::{CONTROL_SOCKET, Command, Response};
+use crate::framework::error::Result;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub fn request(command: &Command) -> Result<Response> {
+    let mut stream = UnixStream::connect(CONTROL_SOCKET)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    serde_json::to_writer(&mut stream, command)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+
+    Ok(serde_json::from_str(&line)?)
+}
@@ -0,0 +1,126 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod client;
+mod server;
+
+use std::{
+    fs::{self, set_permissions},
+    os::unix::{fs::PermissionsExt, net::UnixListener},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cpu_common::ClusterStat,
+    error::Degradation,
+    framework::{decision_log::DecisionEvent, error::Result, scheduler::BatteryStat},
+};
+pub use client::request;
+
+pub const CONTROL_SOCKET: &str = "/dev/fas_rs/control.sock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Command {
+    Status,
+    SetMode { mode: String },
+    SetProfile { profile: Option<String> },
+    ReloadConfig,
+    GetCurrentGame,
+    DumpLog,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    Error(String),
+    Status(Status),
+    CurrentGame(Option<GameInfo>),
+    // Oldest first
+    Log(Vec<DecisionEvent>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub mode: String,
+    pub profile: Option<String>,
+    pub working: bool,
+    // Stopped through `fas-rs.ctl.enabled`
+    #[serde(default)]
+    pub paused: bool,
+    pub game: Option<GameInfo>,
+    pub battery: Option<BatteryStat>,
+    pub clusters: Vec<ClusterStat>,
+    // Estimated cpu energy of the running game session
+    pub session_energy_mwh: Option<f64>,
+    // Nodes that are missing, denied or overridden, empty on a fully working device
+    #[serde(default)]
+    pub degraded: Vec<Degradation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub pkg: String,
+    pub pid: i32,
+    pub target_fps: Option<u32>,
+    pub current_fps: f64,
+    // cpu / gpu / network / unknown / saturated, see `bound_detection`
+    #[serde(default)]
+    pub bound: String,
+}
+
+pub struct Request {
+    pub command: Command,
+    reply: Sender<Response>,
+}
+
+impl Request {
+    pub fn reply(self, response: Response) {
+        let _ = self.reply.send(response);
+    }
+}
+
+pub struct Control {
+    rx: Receiver<Request>,
+}
+
+impl Control {
+    pub fn init() -> Result<Self> {
+        let _ = fs::remove_file(CONTROL_SOCKET);
+        let listener = UnixListener::bind(CONTROL_SOCKET)?;
+        set_permissions(CONTROL_SOCKET, PermissionsExt::from_mode(0o666))?;
+
+        let (sx, rx) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("ControlThread".into())
+            .spawn(move || server::thread(&listener, &sx))?;
+
+        info!("Control server listening on {CONTROL_SOCKET}");
+
+        Ok(Self { rx })
+    }
+
+    pub fn try_recv(&self) -> Option<Request> {
+        self.rx.try_recv().ok()
+    }
+}
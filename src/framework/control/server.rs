@@ -0,0 +1,90 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::mpsc::{self, Sender},
+    time::Duration,
+};
+
+use log::warn;
+
+use super::{Command, Request, Response};
+use crate::{framework::error::Result, misc::peer_cred};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+// Clients are served one after another, an idle one must not hold up the next
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+// Root and system, the socket is world-connectable but apps must not switch modes through it
+const ALLOWED_UIDS: [u32; 2] = [0, 1000];
+
+pub fn thread(listener: &UnixListener, sx: &Sender<Request>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                match peer_cred(&stream) {
+                    Some(cred) if ALLOWED_UIDS.contains(&cred.uid) => (),
+                    cred => {
+                        warn!(
+                            "Refusing control client with uid {}",
+                            cred.map_or_else(|| "unknown".into(), |cred| cred.uid.to_string())
+                        );
+                        continue;
+                    }
+                }
+
+                if let Err(e) = handle_client(stream, sx) {
+                    warn!("Control client disconnected with error: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to accept control client: {e}"),
+        }
+    }
+}
+
+fn handle_client(stream: UnixStream, sx: &Sender<Request>) -> Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+
+    while reader.read_line(&mut line)? > 0 {
+        let response = match serde_json::from_str::<Command>(line.trim()) {
+            Ok(command) => dispatch(command, sx),
+            Err(e) => Response::Error(format!("Invalid command: {e}")),
+        };
+
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+fn dispatch(command: Command, sx: &Sender<Request>) -> Response {
+    let (reply, rx) = mpsc::channel();
+
+    if sx.send(Request { command, reply }).is_err() {
+        return Response::Error("Scheduler is not running".into());
+    }
+
+    rx.recv_timeout(REPLY_TIMEOUT)
+        .unwrap_or_else(|_| Response::Error("Scheduler did not respond in time".into()))
+}
@@ -0,0 +1,105 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+// One control decision, the same fields as the `control` debug event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionEvent {
+    pub timestamp_ms: u64,
+    pub pkg: String,
+    pub pid: i32,
+    pub target_fps: Option<u32>,
+    pub control: isize,
+    pub janked: bool,
+    pub util: f64,
+    pub gpu_busy: Option<f64>,
+    pub bound: String,
+}
+
+impl fmt::Display for DecisionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}) target {} control {}khz{} util {:.2} gpu {} bound {}",
+            self.timestamp_ms,
+            self.pkg,
+            self.pid,
+            self.target_fps
+                .map_or_else(|| "unknown".into(), |fps| fps.to_string()),
+            self.control,
+            if self.janked { " janked" } else { "" },
+            self.util,
+            self.gpu_busy
+                .map_or_else(|| "unknown".into(), |busy| format!("{busy:.2}")),
+            self.bound
+        )
+    }
+}
+
+// The last decisions whatever the log level, so a misbehaving game can be looked at after
+// the fact without running the whole daemon at debug level
+#[derive(Debug, Default)]
+pub struct DecisionLog {
+    events: VecDeque<DecisionEvent>,
+    capacity: usize,
+}
+
+impl DecisionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    // Checked before building an event, nothing is allocated per frame while it is off
+    pub const fn enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    // Stamped here, `timestamp_ms` of `event` is overwritten
+    pub fn push(&mut self, mut event: DecisionEvent) {
+        if !self.enabled() {
+            return;
+        }
+
+        event.timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    // Oldest first
+    pub fn events(&self) -> Vec<DecisionEvent> {
+        self.events.iter().cloned().collect()
+    }
+}
@@ -40,6 +40,8 @@ pub enum Error {
     DeToml(#[from] toml::de::Error),
     #[error(transparent)]
     SerXml(#[from] quick_xml::DeError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
     #[error("Missing {0} when building Scheduler")]
     SchedulerMissing(&'static str),
     #[error(transparent)]
@@ -0,0 +1,82 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use crate::framework::node::Mode;
+
+// What happened in the looper that other modules care about. Published once per change, not
+// once per frame
+#[derive(Debug, Clone)]
+pub enum Event {
+    GameStarted {
+        pkg: String,
+        pid: i32,
+        target_fps: Option<u32>,
+        mode: Mode,
+    },
+    GameStopped {
+        pkg: Option<String>,
+        pid: Option<i32>,
+    },
+    ModeChanged {
+        from: Mode,
+        to: Mode,
+    },
+    // Fraction of the max frequency thermal throttling allows, 1.0 once it is lifted
+    ThermalEvent {
+        freq_cap: f64,
+    },
+    // The first janked frame after smooth ones
+    JankDetected {
+        pkg: String,
+        frametime: Duration,
+    },
+}
+
+// Every subscriber gets a channel of its own and reads it on its own thread, publishing never
+// waits for one. A subscriber goes away by dropping its receiver
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<Event>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().push(sender);
+        receiver
+    }
+
+    pub fn publish(&self, event: &Event) {
+        self.subscribers
+            .lock()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
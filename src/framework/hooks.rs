@@ -0,0 +1,199 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::Receiver,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::framework::{
+    events::{Event, EventBus},
+    node::Mode,
+};
+
+const HOOKS_DIR: &str = "/data/adb/fas-rs/hooks";
+// A hook that hangs must not hold back the ones after it forever
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hook {
+    GameStart,
+    GameStop,
+    ThermalThrottle,
+}
+
+impl Hook {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::GameStart => "on_game_start",
+            Self::GameStop => "on_game_stop",
+            Self::ThermalThrottle => "on_thermal_throttle",
+        }
+    }
+}
+
+type Job = (Hook, Vec<(&'static str, String)>);
+
+// Runs `<HOOKS_DIR>/<hook>.sh` with `sh` when something happens, the environment describes the
+// game. Scripts run one at a time in the order of their events, off the looper
+pub fn spawn(events: &EventBus) {
+    let receiver = events.subscribe();
+    if let Err(e) = thread::Builder::new()
+        .name("HookRunner".into())
+        .spawn(move || run_loop(&receiver))
+    {
+        warn!("Failed to spawn hook runner, hooks disabled: {e}");
+    }
+}
+
+// The game as far as its hooks know, for the environment of the hooks after its start
+#[derive(Debug, Default)]
+struct Game {
+    pkg: Option<String>,
+    mode: Option<Mode>,
+    throttled: bool,
+}
+
+impl Game {
+    fn job(&mut self, event: Event) -> Option<Job> {
+        match event {
+            Event::GameStarted {
+                pkg,
+                pid,
+                target_fps,
+                mode,
+            } => {
+                self.pkg = Some(pkg.clone());
+                self.mode = Some(mode);
+                Some((
+                    Hook::GameStart,
+                    vec![
+                        ("FAS_RS_PKG", pkg),
+                        ("FAS_RS_PID", pid.to_string()),
+                        (
+                            "FAS_RS_TARGET_FPS",
+                            target_fps.map(|fps| fps.to_string()).unwrap_or_default(),
+                        ),
+                        ("FAS_RS_MODE", mode.to_string()),
+                    ],
+                ))
+            }
+            Event::GameStopped { pkg, pid } => {
+                let mode = self.mode.take();
+                self.pkg = None;
+                // Every game starts unthrottled as far as its hooks know
+                self.throttled = false;
+                Some((
+                    Hook::GameStop,
+                    vec![
+                        ("FAS_RS_PKG", pkg.unwrap_or_default()),
+                        (
+                            "FAS_RS_PID",
+                            pid.map(|pid| pid.to_string()).unwrap_or_default(),
+                        ),
+                        (
+                            "FAS_RS_MODE",
+                            mode.map(|mode| mode.to_string()).unwrap_or_default(),
+                        ),
+                    ],
+                ))
+            }
+            // Runs when throttling starts and once more when it is lifted, not on every step in
+            // between
+            Event::ThermalEvent { freq_cap } => {
+                let throttled = freq_cap < 1.0;
+                if throttled == self.throttled {
+                    return None;
+                }
+
+                self.throttled = throttled;
+                Some((
+                    Hook::ThermalThrottle,
+                    vec![
+                        ("FAS_RS_PKG", self.pkg.clone().unwrap_or_default()),
+                        (
+                            "FAS_RS_MODE",
+                            self.mode.map(|mode| mode.to_string()).unwrap_or_default(),
+                        ),
+                        ("FAS_RS_THROTTLED", u8::from(throttled).to_string()),
+                        ("FAS_RS_FREQ_CAP", format!("{freq_cap:.2}")),
+                    ],
+                ))
+            }
+            Event::ModeChanged { .. } | Event::JankDetected { .. } => None,
+        }
+    }
+}
+
+fn run_loop(receiver: &Receiver<Event>) {
+    let mut game = Game::default();
+
+    while let Ok(event) = receiver.recv() {
+        let Some((hook, env)) = game.job(event) else {
+            continue;
+        };
+
+        let script = Path::new(HOOKS_DIR).join(format!("{}.sh", hook.name()));
+        if !script.is_file() {
+            continue;
+        }
+
+        let child = Command::new("sh")
+            .arg(&script)
+            .env("FAS_RS_HOOK", hook.name())
+            .envs(env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Failed to run hook {}: {e}", script.display());
+                continue;
+            }
+        };
+
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => break,
+                Ok(Some(status)) => {
+                    warn!("Hook {} exited with {status}", script.display());
+                    break;
+                }
+                Ok(None) if started.elapsed() < HOOK_TIMEOUT => thread::sleep(POLL_INTERVAL),
+                Ok(None) => {
+                    warn!("Hook {} timed out, killing it", script.display());
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to wait for hook {}: {e}", script.display());
+                    break;
+                }
+            }
+        }
+    }
+}
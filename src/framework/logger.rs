@@ -0,0 +1,137 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::{self, Write},
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{
+    LevelFilter, Log, Metadata, Record,
+    kv::{self, Key, VisitSource},
+};
+use serde_json::{Map, Value};
+use tklog::{LEVEL, LOG};
+
+use super::config::{DaemonConfig, LogFormat, LogLevel};
+
+// The level from `[daemon]`, a game with its own level in `[log]` only ever raises it
+static BASE_LEVEL: OnceLock<LevelFilter> = OnceLock::new();
+
+const fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
+    }
+}
+
+pub fn init_logger(config: DaemonConfig) {
+    let level = config.log_level;
+    #[cfg(debug_assertions)]
+    let level = match level {
+        LogLevel::Trace => LogLevel::Trace,
+        _ => LogLevel::Debug,
+    };
+
+    match config.log_format {
+        LogFormat::Text => {
+            let logger = LOG;
+            // Records are filtered by the max level alone, which follows the scheduled game
+            logger.set_level(LEVEL::Trace);
+            logger.set_formatter("[{time}] {level}: {message}\n");
+            logger.uselog();
+        }
+        LogFormat::Json => {
+            let _ = log::set_boxed_logger(Box::new(JsonLogger));
+        }
+    }
+
+    let level = level_filter(level);
+    let _ = BASE_LEVEL.set(level);
+    log::set_max_level(level);
+}
+
+// Called when a game is scheduled and with `None` when it stops
+pub fn set_game_level(level: Option<LogLevel>) {
+    let base = BASE_LEVEL.get().copied().unwrap_or(LevelFilter::Info);
+    log::set_max_level(level.map_or(base, |level| level_filter(level).max(base)));
+}
+
+// One object per line: time, level, module, message plus the record's key-values
+struct JsonLogger;
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        let mut object = Map::new();
+        object.insert("time".into(), time.into());
+        object.insert(
+            "level".into(),
+            record.level().as_str().to_lowercase().into(),
+        );
+        object.insert(
+            "module".into(),
+            record.module_path().unwrap_or(record.target()).into(),
+        );
+        object.insert("message".into(), record.args().to_string().into());
+        let _ = record.key_values().visit(&mut Fields(&mut object));
+
+        let mut stdout = io::stdout().lock();
+        let _ = writeln!(stdout, "{}", Value::Object(object));
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+}
+
+struct Fields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(i) = value.to_i64() {
+            i.into()
+        } else if let Some(u) = value.to_u64() {
+            u.into()
+        } else if let Some(f) = value.to_f64() {
+            f.into()
+        } else if let Some(b) = value.to_bool() {
+            b.into()
+        } else {
+            value.to_string().into()
+        };
+
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs, io,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    framework::config::MemoryConfig,
+};
+
+// On /data so the game is not left in our memcg and kswapd tuned after a crash
+const STATE_FILE: &str = "/data/adb/fas-rs/memory.json";
+const WATERMARK_SCALE_FACTOR: &str = "/proc/sys/vm/watermark_scale_factor";
+// Not `fas-rs`, on a unified hierarchy that name is already the cpuset of pin_big_cores
+const GAME_MEMCG: &str = "fas-rs-memory";
+// ActivityManager writes the score again on every state change of the app
+const REASSERT_INTERVAL: Duration = Duration::from_secs(1);
+
+// What was changed and has to be put back. The oom score is not recorded, ActivityManager
+// rewrites it anyway once the game leaves the foreground
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Changes {
+    watermark_scale_factor: Option<String>,
+    // The memcg the game came from, relative to the controller root
+    memcg: Option<String>,
+}
+
+impl Changes {
+    const fn is_empty(&self) -> bool {
+        self.watermark_scale_factor.is_none() && self.memcg.is_none()
+    }
+}
+
+// Keeps a game with `protect_memory` out of reach of the low memory killer and of reclaim
+// stalls while it is controlled: lowers its oom_score_adj, lets kswapd start reclaiming
+// earlier through watermark_scale_factor and moves it into a memcg with a protected
+// memory.min. Everything is undone when the game exits, and on startup after a crash
+pub struct Memory {
+    changes: Changes,
+    pid: Option<i32>,
+    oom_score_adj: i32,
+    // The game's score before, with its pid
+    stock_oom_score_adj: Option<(i32, String)>,
+    reassert_timer: Instant,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        let mut memory = Self {
+            changes: Changes::default(),
+            pid: None,
+            oom_score_adj: 0,
+            stock_oom_score_adj: None,
+            reassert_timer: Instant::now(),
+        };
+
+        if let Some(changes) = fs::read_to_string(STATE_FILE)
+            .ok()
+            .and_then(|state| serde_json::from_str(&state).ok())
+        {
+            warn!("Restoring memory settings left changed by a previous run");
+            memory.changes = changes;
+            memory.restore();
+        }
+
+        memory
+    }
+
+    pub fn engage(&mut self, config: &MemoryConfig, pid: i32) {
+        self.pid = Some(pid);
+        self.oom_score_adj = config.oom_score_adj;
+        self.protect_oom_score_adj(pid);
+
+        if config.watermark_scale_factor > 0 && self.changes.watermark_scale_factor.is_none() {
+            self.scale_watermarks(config.watermark_scale_factor);
+        }
+
+        if config.memcg_min_mb > 0 && self.changes.memcg.is_none() {
+            if let Err(e) = self.join_memcg(pid, config.memcg_min_mb) {
+                warn!("Failed to move the game into a protected memcg: {e}");
+            }
+        }
+
+        self.save();
+    }
+
+    // The game restarted its process, the new one gets the same protection
+    pub fn rebind(&mut self, pid: i32) {
+        if self.pid.is_none() {
+            return;
+        }
+
+        self.pid = Some(pid);
+        self.stock_oom_score_adj = None;
+        self.protect_oom_score_adj(pid);
+
+        if self.changes.memcg.is_some() {
+            if let Some(memcg) = Cgroup::of_process(Subsystem::Memory, pid) {
+                self.changes.memcg = Some(memcg.name());
+            }
+            if let Some(game) = Cgroup::open(Subsystem::Memory, GAME_MEMCG) {
+                let _ = game.add_process(pid as u32);
+            }
+            self.save();
+        }
+    }
+
+    pub fn reassert(&mut self) {
+        if self.reassert_timer.elapsed() < REASSERT_INTERVAL {
+            return;
+        }
+        self.reassert_timer = Instant::now();
+
+        if let Some(pid) = self.pid {
+            self.protect_oom_score_adj(pid);
+        }
+    }
+
+    pub fn restore(&mut self) {
+        if let Some((pid, stock)) = self.stock_oom_score_adj.take() {
+            // Unless ActivityManager got there first
+            if read_oom_score_adj(pid) == Some(self.oom_score_adj.to_string()) {
+                let _ = fs::write(oom_score_adj_path(pid), stock);
+            }
+        }
+        self.pid = None;
+
+        if self.changes.is_empty() {
+            return;
+        }
+
+        if let Some(stock) = self.changes.watermark_scale_factor.take() {
+            info!("watermark_scale_factor -> {stock}");
+            let _ = fs::write(WATERMARK_SCALE_FACTOR, stock);
+        }
+
+        if let Some(name) = self.changes.memcg.take() {
+            leave_memcg(&name);
+        }
+
+        let _ = fs::remove_file(STATE_FILE);
+    }
+
+    fn protect_oom_score_adj(&mut self, pid: i32) {
+        if self.oom_score_adj == 0 {
+            return;
+        }
+
+        let Some(current) = read_oom_score_adj(pid) else {
+            return;
+        };
+        let target = self.oom_score_adj.to_string();
+        // Every write wakes the top app watcher, which follows the scores of the top apps
+        if current == target {
+            return;
+        }
+
+        if self.stock_oom_score_adj.is_none() {
+            info!("oom_score_adj of {pid}: {current} -> {target}");
+            self.stock_oom_score_adj = Some((pid, current));
+        }
+        if let Err(e) = fs::write(oom_score_adj_path(pid), &target) {
+            warn!("Failed to protect the game from the low memory killer: {e}");
+        }
+    }
+
+    fn scale_watermarks(&mut self, factor: u32) {
+        let Ok(stock) = fs::read_to_string(WATERMARK_SCALE_FACTOR) else {
+            return;
+        };
+        let stock = stock.trim().to_string();
+        if stock == factor.to_string() {
+            return;
+        }
+
+        match fs::write(WATERMARK_SCALE_FACTOR, factor.to_string()) {
+            Ok(()) => {
+                info!("watermark_scale_factor: {stock} -> {factor}");
+                self.changes.watermark_scale_factor = Some(stock);
+            }
+            Err(e) => warn!("Failed to write {WATERMARK_SCALE_FACTOR}: {e}"),
+        }
+    }
+
+    // memory.min only exists on the unified hierarchy, a v1 memcg could not protect anything
+    fn join_memcg(&mut self, pid: i32, min_mb: u64) -> io::Result<()> {
+        let stock = Cgroup::of_process(Subsystem::Memory, pid)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "memcg is not mounted"))?;
+        if !stock.is_unified() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "memory.min needs the unified cgroup hierarchy",
+            ));
+        }
+
+        let game = Cgroup::create(Subsystem::Memory, GAME_MEMCG)?;
+        fs::write(game.memory_min(), (min_mb * 1024 * 1024).to_string())?;
+        // Recorded before the move, a crash in between still finds the way back
+        self.changes.memcg = Some(stock.name());
+        self.save();
+        game.add_process(pid as u32)?;
+
+        info!("Moved {pid} into memcg {GAME_MEMCG} with memory.min {min_mb}MB");
+        Ok(())
+    }
+
+    fn save(&self) {
+        if self.changes.is_empty() {
+            return;
+        }
+
+        match serde_json::to_string(&self.changes) {
+            Ok(state) => {
+                if let Err(e) = fs::write(STATE_FILE, state) {
+                    warn!("Failed to record memory state: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to record memory state: {e}"),
+        }
+    }
+}
+
+// Whatever is still in our memcg goes back where the game came from, or to the root if that
+// group went away with the old process
+fn leave_memcg(name: &str) {
+    let Some(game) = Cgroup::open(Subsystem::Memory, GAME_MEMCG) else {
+        return;
+    };
+    let Some(stock) =
+        Cgroup::open(Subsystem::Memory, name).or_else(|| Cgroup::open(Subsystem::Memory, ""))
+    else {
+        return;
+    };
+
+    for pid in game.processes() {
+        let _ = stock.add_process(pid);
+    }
+    let _ = fs::write(game.memory_min(), "0");
+
+    match game.remove() {
+        Ok(()) => info!("Moved the game back to memcg /{name}"),
+        Err(e) => warn!("Failed to remove memcg {GAME_MEMCG}: {e}"),
+    }
+}
+
+fn oom_score_adj_path(pid: i32) -> String {
+    format!("/proc/{pid}/oom_score_adj")
+}
+
+fn read_oom_score_adj(pid: i32) -> Option<String> {
+    fs::read_to_string(oom_score_adj_path(pid))
+        .ok()
+        .map(|adj| adj.trim().to_string())
+}
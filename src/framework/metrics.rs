@@ -0,0 +1,326 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use crate::{framework::error::Result, paths};
+
+const UPDATE_TIME: Duration = Duration::from_secs(1);
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Clone)]
+struct FrameMetrics {
+    pkg: Option<String>,
+    fps: f64,
+    frametime_p95: Duration,
+    util_max: f64,
+}
+
+struct Exporter {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
+
+pub struct Metrics {
+    exporter: Option<Exporter>,
+    // A port that failed to bind is only tried again once the configured port changes
+    failed_port: Option<u16>,
+    frame: Arc<Mutex<FrameMetrics>>,
+    update_timer: Instant,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            exporter: None,
+            failed_port: None,
+            frame: Arc::new(Mutex::new(FrameMetrics::default())),
+            update_timer: Instant::now(),
+        }
+    }
+
+    // `port == 0` disables the exporter
+    pub fn set_port(&mut self, port: u16) {
+        if self.exporter.as_ref().map_or(0, |exporter| exporter.port) == port
+            || self.failed_port == Some(port)
+        {
+            return;
+        }
+
+        self.exporter = None;
+        self.failed_port = None;
+        if port == 0 {
+            return;
+        }
+
+        match Self::spawn(port, self.frame.clone()) {
+            Ok(exporter) => {
+                info!("Metrics exporter listening on 127.0.0.1:{port}");
+                self.exporter = Some(exporter);
+            }
+            Err(e) => {
+                warn!("Failed to start metrics exporter on port {port}: {e}");
+                self.failed_port = Some(port);
+            }
+        }
+    }
+
+    pub fn update(&mut self, pkg: &str, fps: f64, frametimes: &VecDeque<Duration>, util_max: f64) {
+        if self.exporter.is_none() || self.update_timer.elapsed() < UPDATE_TIME {
+            return;
+        }
+
+        self.update_timer = Instant::now();
+
+        let mut sorted: Vec<_> = frametimes.iter().copied().collect();
+        sorted.sort_unstable();
+        let frametime_p95 = sorted
+            .get((sorted.len() * 95 / 100).min(sorted.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default();
+
+        *self.frame.lock() = FrameMetrics {
+            pkg: Some(pkg.to_string()),
+            fps,
+            frametime_p95,
+            util_max,
+        };
+    }
+
+    pub fn clear(&mut self) {
+        *self.frame.lock() = FrameMetrics::default();
+    }
+
+    fn spawn(port: u16, frame: Arc<Mutex<FrameMetrics>>) -> Result<Exporter> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+
+        {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("MetricsThread".into())
+                .spawn(move || serve(&listener, &frame, &stop))?;
+        }
+
+        Ok(Exporter { port, stop })
+    }
+}
+
+fn serve(listener: &TcpListener, frame: &Mutex<FrameMetrics>, stop: &AtomicBool) {
+    while !stop.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = handle_client(stream, frame) {
+                    warn!("Metrics client disconnected with error: {e}");
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+            Err(e) => warn!("Failed to accept metrics client: {e}"),
+        }
+    }
+}
+
+fn handle_client(stream: TcpStream, frame: &Mutex<FrameMetrics>) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Drain headers, the body of a GET is empty
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        line.clear();
+    }
+
+    let mut writer = stream;
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    if path != "/metrics" {
+        writer.write_all(
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        )?;
+        return Ok(());
+    }
+
+    let body = render(&frame.lock().clone());
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    Ok(())
+}
+
+fn render(frame: &FrameMetrics) -> String {
+    let mut body = String::new();
+
+    gauge(
+        &mut body,
+        "fas_rs_working",
+        "Whether fas-rs is scheduling a game.",
+        [(String::new(), f64::from(u8::from(frame.pkg.is_some())))],
+    );
+
+    if let Some(pkg) = &frame.pkg {
+        let label = format!("pkg=\"{pkg}\"");
+        gauge(
+            &mut body,
+            "fas_rs_fps",
+            "Current fps of the scheduled game.",
+            [(label.clone(), frame.fps)],
+        );
+        gauge(
+            &mut body,
+            "fas_rs_frametime_p95_seconds",
+            "95th percentile frametime of the scheduled game.",
+            [(label.clone(), frame.frametime_p95.as_secs_f64())],
+        );
+        gauge(
+            &mut body,
+            "fas_rs_util_max",
+            "Max utilization of the tracked threads.",
+            [(label, frame.util_max)],
+        );
+    }
+
+    let policies = read_policies();
+    gauge(
+        &mut body,
+        "fas_rs_policy_cur_freq_khz",
+        "Current frequency of the cpufreq policy.",
+        policies
+            .iter()
+            .map(|(policy, cur_freq, _)| (format!("policy=\"{policy}\""), *cur_freq as f64)),
+    );
+    gauge(
+        &mut body,
+        "fas_rs_policy_max_freq_khz",
+        "Max frequency limit of the cpufreq policy.",
+        policies
+            .iter()
+            .map(|(policy, _, max_freq)| (format!("policy=\"{policy}\""), *max_freq as f64)),
+    );
+
+    gauge(
+        &mut body,
+        "fas_rs_thermal_zone_celsius",
+        "Temperature of the thermal zone.",
+        read_thermal_zones()
+            .into_iter()
+            .map(|(zone, zone_type, temp)| {
+                (
+                    format!("zone=\"{zone}\",type=\"{zone_type}\""),
+                    temp as f64 / 1000.0,
+                )
+            }),
+    );
+
+    body
+}
+
+fn gauge<I: IntoIterator<Item = (String, f64)>>(
+    body: &mut String,
+    name: &str,
+    help: &str,
+    samples: I,
+) {
+    let _ = writeln!(body, "# HELP {name} {help}");
+    let _ = writeln!(body, "# TYPE {name} gauge");
+
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            let _ = writeln!(body, "{name} {value}");
+        } else {
+            let _ = writeln!(body, "{name}{{{labels}}} {value}");
+        }
+    }
+}
+
+fn read_policies() -> Vec<(String, u64, u64)> {
+    let Ok(entries) = fs::read_dir(paths::cpufreq()) else {
+        return Vec::new();
+    };
+
+    let mut policies: Vec<_> = entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let policy = name.strip_prefix("policy")?.to_string();
+            let path = entry.path();
+            Some((
+                policy,
+                read_u64(&path.join("scaling_cur_freq"))?,
+                read_u64(&path.join("scaling_max_freq"))?,
+            ))
+        })
+        .collect();
+    policies.sort_unstable_by_key(|(policy, _, _)| policy.parse::<u32>().unwrap_or_default());
+    policies
+}
+
+fn read_thermal_zones() -> Vec<(String, String, i64)> {
+    let Ok(entries) = fs::read_dir(paths::thermal()) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let zone = name.strip_prefix("thermal_zone")?.to_string();
+            let path = entry.path();
+            let zone_type = fs::read_to_string(path.join("type")).ok()?;
+            let temp = fs::read_to_string(path.join("temp"))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            Some((zone, zone_type.trim().to_string(), temp))
+        })
+        .collect()
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,218 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, process::Command};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    framework::config::MitigationConfig,
+    misc::{getprop, glob_match, setprop},
+};
+
+// Set to `true` while fas-rs controls a game, for vendor tools that can step back on their own
+pub const GAME_MODE_PROP: &str = "fas-rs-game-mode";
+// On /data so a package disabled before a crash and a reboot still gets enabled again
+const STATE_FILE: &str = "/data/adb/fas-rs/mitigation.json";
+
+// Only these are ever touched, whatever the config says. Perf HALs and boost daemons fight the
+// limits fas-rs writes, thermal daemons are never on the list even though they interfere too
+const SERVICE_ALLOW_LIST: [&str; 6] = [
+    "perfd",
+    "vendor.perfd",
+    "perf-hal-*",
+    "vendor.perf-hal-*",
+    "vendor.perfservice",
+    "vendor.oplus.ormsHalService*",
+];
+const PROP_ALLOW_LIST: [&str; 2] = ["debug.hwui.use_hint_manager", "vendor.perf.*"];
+const PACKAGE_ALLOW_LIST: [&str; 2] = ["com.xiaomi.joyose", "com.oplus.cosa"];
+const DENY_LIST: [&str; 1] = ["*thermal*"];
+
+pub fn service_allowed(name: &str) -> bool {
+    allowed(&SERVICE_ALLOW_LIST, name)
+}
+
+pub fn prop_allowed(name: &str) -> bool {
+    allowed(&PROP_ALLOW_LIST, name)
+}
+
+pub fn package_allowed(name: &str) -> bool {
+    allowed(&PACKAGE_ALLOW_LIST, name)
+}
+
+fn allowed(allow_list: &[&str], name: &str) -> bool {
+    allow_list
+        .iter()
+        .any(|glob| glob_match(glob.as_bytes(), name.as_bytes()))
+        && !DENY_LIST
+            .iter()
+            .any(|glob| glob_match(glob.as_bytes(), name.as_bytes()))
+}
+
+// What was changed and has to be put back
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Changes {
+    services: Vec<String>,
+    // Prop names with their values before
+    props: Vec<(String, String)>,
+    packages: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.services.is_empty() && self.props.is_empty() && self.packages.is_empty()
+    }
+}
+
+// Stops vendor services, sets props and disables OEM game boosters listed in [mitigation] while
+// a game is controlled, and undoes exactly what it changed afterwards. Whatever is still
+// recorded from a previous run that did not get to restore is undone on startup
+pub struct Mitigation {
+    changes: Changes,
+}
+
+impl Mitigation {
+    pub fn new() -> Self {
+        let mut mitigation = Self {
+            changes: Changes::default(),
+        };
+
+        if let Some(changes) = fs::read_to_string(STATE_FILE)
+            .ok()
+            .and_then(|state| serde_json::from_str(&state).ok())
+        {
+            warn!("Restoring vendor services left mitigated by a previous run");
+            mitigation.changes = changes;
+            mitigation.restore();
+        }
+        setprop(GAME_MODE_PROP, "false");
+
+        mitigation
+    }
+
+    pub fn engage(&mut self, config: &MitigationConfig) {
+        setprop(GAME_MODE_PROP, "true");
+
+        for service in &config.services {
+            if !service_allowed(service) {
+                warn!("Service {service} is not on the mitigation allow-list, leaving it alone");
+                continue;
+            }
+
+            if getprop(format!("init.svc.{service}")).as_deref() == Some("running")
+                && !self.changes.services.contains(service)
+            {
+                info!("Stopping vendor service {service}");
+                setprop("ctl.stop", service.as_str());
+                self.changes.services.push(service.clone());
+            }
+        }
+
+        for (prop, value) in &config.props {
+            if !prop_allowed(prop) {
+                warn!("Prop {prop} is not on the mitigation allow-list, leaving it alone");
+                continue;
+            }
+
+            if self.changes.props.iter().any(|(saved, _)| saved == prop) {
+                continue;
+            }
+
+            let stock = getprop(prop).unwrap_or_default();
+            if stock != *value {
+                info!("Prop {prop}: {stock} -> {value}");
+                setprop(prop, value);
+                self.changes.props.push((prop.clone(), stock));
+            }
+        }
+
+        for package in &config.packages {
+            if !package_allowed(package) {
+                warn!("Package {package} is not on the mitigation allow-list, leaving it alone");
+                continue;
+            }
+
+            if package_enabled(package) && !self.changes.packages.contains(package) {
+                info!("Disabling game booster {package}");
+                if pm(&["disable-user", "--user", "0", package]) {
+                    self.changes.packages.push(package.clone());
+                }
+            }
+        }
+
+        self.save();
+    }
+
+    pub fn restore(&mut self) {
+        setprop(GAME_MODE_PROP, "false");
+
+        if self.changes.is_empty() {
+            return;
+        }
+
+        for service in self.changes.services.drain(..) {
+            info!("Starting vendor service {service} again");
+            setprop("ctl.start", service.as_str());
+        }
+
+        for (prop, stock) in self.changes.props.drain(..) {
+            setprop(&prop, &stock);
+        }
+
+        for package in self.changes.packages.drain(..) {
+            info!("Enabling game booster {package} again");
+            pm(&["enable", &package]);
+        }
+
+        let _ = fs::remove_file(STATE_FILE);
+    }
+
+    fn save(&self) {
+        if self.changes.is_empty() {
+            return;
+        }
+
+        match serde_json::to_string(&self.changes) {
+            Ok(state) => {
+                if let Err(e) = fs::write(STATE_FILE, state) {
+                    warn!("Failed to record mitigation state: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to record mitigation state: {e}"),
+        }
+    }
+}
+
+fn package_enabled(package: &str) -> bool {
+    Command::new("pm")
+        .args(["list", "packages", "-e", package])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.trim().strip_prefix("package:") == Some(package))
+        })
+}
+
+fn pm(args: &[&str]) -> bool {
+    Command::new("pm")
+        .args(args)
+        .status()
+        .is_ok_and(|status| status.success())
+}
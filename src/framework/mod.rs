@@ -16,20 +16,48 @@
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
 mod config;
+mod control;
+mod decision_log;
 mod error;
+mod events;
 mod extension;
+mod hooks;
+mod logger;
+mod memory;
+mod metrics;
+mod mitigation;
+mod mode;
 mod node;
+mod notify;
+mod overlay;
 mod pid_utils;
 pub mod prelude;
+mod recorder;
 mod scheduler;
+mod session;
+mod signal;
+mod state;
+#[cfg(feature = "tuner")]
+mod tuner;
+mod watchdog;
 
 #[allow(unused_imports)]
 pub use config::Config;
 #[allow(unused_imports)]
+pub use control::{Command, Response, request};
+#[allow(unused_imports)]
 pub use error::Result;
 #[allow(unused_imports)]
 pub use extension::{Api, Extension, api};
 #[allow(unused_imports)]
+pub use logger::init_logger;
+#[allow(unused_imports)]
+pub use mode::ModePreset;
+#[allow(unused_imports)]
 pub use node::Mode;
 #[allow(unused_imports)]
-pub use scheduler::Scheduler;
+pub use scheduler::{Decision, Engine, Scheduler, SimulateOptions, simulate};
+#[allow(unused_imports)]
+pub use signal::{install as install_signal_handlers, terminated};
+#[allow(unused_imports)]
+pub use watchdog::Heartbeat;
@@ -0,0 +1,60 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use super::node::Mode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModePreset {
+    pub boost: f64,
+    // Fraction of the max frequency the mode may use, no mode caps it out of the box, a game
+    // that needs the top of the table would miss its target otherwise
+    pub freq_ceiling: f64,
+    pub headroom: isize,
+}
+
+impl ModePreset {
+    pub const fn new(mode: Mode) -> Self {
+        match mode {
+            Mode::Powersave => Self {
+                boost: 0.8,
+                freq_ceiling: 1.0,
+                headroom: 0,
+            },
+            Mode::Balance => Self {
+                boost: 1.0,
+                freq_ceiling: 1.0,
+                headroom: 0,
+            },
+            Mode::Performance => Self {
+                boost: 1.2,
+                freq_ceiling: 1.0,
+                headroom: 100_000,
+            },
+            Mode::Fast => Self {
+                boost: 1.5,
+                freq_ceiling: 1.0,
+                headroom: 200_000,
+            },
+        }
+    }
+}
+
+impl Default for ModePreset {
+    fn default() -> Self {
+        Self::new(Mode::Balance)
+    }
+}
@@ -6,7 +6,8 @@ use std::{
 use super::Node;
 use crate::framework::error::{Error, Result};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Ordered from the lightest to the heaviest mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Mode {
     Powersave,
     Balance,
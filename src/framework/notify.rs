@@ -0,0 +1,240 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, set_permissions},
+    io::{self, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    process::{Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::framework::{
+    error::Result,
+    events::{self, EventBus},
+};
+
+pub const EVENT_SOCKET: &str = "/dev/fas_rs/events.sock";
+const BROADCAST_ACTION: &str = "io.github.shadow3aaa.fas_rs.EVENT";
+// Bumped only when a field changes meaning or goes away, new fields and events keep it
+const PAYLOAD_VERSION: u32 = 1;
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Engaged {
+        pkg: String,
+        pid: i32,
+        target_fps: Option<u32>,
+        mode: String,
+    },
+    Disengaged {
+        pkg: Option<String>,
+    },
+    ModeChanged {
+        from: String,
+        to: String,
+    },
+    // Fraction of the max frequency thermal throttling allows, 1.0 once it is lifted
+    ThermalCap {
+        freq_cap: f64,
+    },
+}
+
+impl Event {
+    // Jank is not sent, an `am broadcast` per jank would cost more than the jank
+    fn from_bus(event: events::Event) -> Option<Self> {
+        match event {
+            events::Event::GameStarted {
+                pkg,
+                pid,
+                target_fps,
+                mode,
+            } => Some(Self::Engaged {
+                pkg,
+                pid,
+                target_fps,
+                mode: mode.to_string(),
+            }),
+            events::Event::GameStopped { pkg, .. } => Some(Self::Disengaged { pkg }),
+            events::Event::ModeChanged { from, to } => Some(Self::ModeChanged {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            events::Event::ThermalEvent { freq_cap } => Some(Self::ThermalCap { freq_cap }),
+            events::Event::JankDetected { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    version: u32,
+    timestamp_ms: u128,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+// Tells a companion app what fas-rs is doing, as one JSON line per event to every client of
+// `EVENT_SOCKET` and as an `am broadcast` carrying the same JSON in its `payload` extra
+pub struct Notifier {
+    events: EventBus,
+    // Cleared to stop the thread serving the socket
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl Notifier {
+    pub fn new(events: &EventBus) -> Self {
+        Self {
+            events: events.clone(),
+            running: None,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.running.is_some() == enabled {
+            return;
+        }
+
+        // The socket is unlinked here rather than in the thread, a thread that ends late must
+        // not remove the socket of its successor
+        if let Some(running) = self.running.take() {
+            running.store(false, Ordering::Release);
+            let _ = fs::remove_file(EVENT_SOCKET);
+        }
+        if !enabled {
+            return;
+        }
+
+        match self.spawn() {
+            Ok(running) => {
+                info!("Event socket listening on {EVENT_SOCKET}");
+                self.running = Some(running);
+            }
+            Err(e) => warn!("Failed to start event notifications: {e}"),
+        }
+    }
+
+    fn spawn(&self) -> Result<Arc<AtomicBool>> {
+        let _ = fs::remove_file(EVENT_SOCKET);
+        let listener = UnixListener::bind(EVENT_SOCKET)?;
+        set_permissions(EVENT_SOCKET, PermissionsExt::from_mode(0o666))?;
+        listener.set_nonblocking(true)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let receiver = self.events.subscribe();
+
+        {
+            let running = running.clone();
+            thread::Builder::new()
+                .name("NotifyThread".into())
+                .spawn(move || serve(&listener, &receiver, &running))?;
+        }
+
+        Ok(running)
+    }
+}
+
+// `am` takes a few hundred milliseconds per call, so events are delivered off the looper
+fn serve(listener: &UnixListener, rx: &Receiver<events::Event>, running: &AtomicBool) {
+    let mut clients = Vec::new();
+
+    while running.load(Ordering::Acquire) {
+        accept_clients(listener, &mut clients);
+
+        match rx.recv_timeout(ACCEPT_INTERVAL) {
+            Ok(event) => {
+                let Some(event) = Event::from_bus(event) else {
+                    continue;
+                };
+                let payload = Payload {
+                    version: PAYLOAD_VERSION,
+                    timestamp_ms: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis(),
+                    event: &event,
+                };
+                let Ok(json) = serde_json::to_string(&payload) else {
+                    continue;
+                };
+
+                clients.retain_mut(|client: &mut UnixStream| {
+                    client
+                        .write_all(json.as_bytes())
+                        .and_then(|()| client.write_all(b"\n"))
+                        .is_ok()
+                });
+                broadcast(&json);
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn accept_clients(listener: &UnixListener, clients: &mut Vec<UnixStream>) {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // A client that stops reading is dropped instead of stalling every event
+                if stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok() {
+                    clients.push(stream);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                warn!("Failed to accept event client: {e}");
+                return;
+            }
+        }
+    }
+}
+
+fn broadcast(json: &str) {
+    let status = Command::new("am")
+        .args([
+            "broadcast",
+            "-a",
+            BROADCAST_ACTION,
+            "--receiver-foreground",
+            "--es",
+            "payload",
+            json,
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    if let Err(e) = status {
+        warn!("Failed to broadcast event: {e}");
+    }
+}
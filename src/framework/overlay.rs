@@ -0,0 +1,244 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, set_permissions},
+    io::{self, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::framework::error::Result;
+
+pub const OVERLAY_SOCKET: &str = "/dev/fas_rs/overlay.sock";
+// Bumped only when a field changes meaning or goes away, new fields keep it
+const PAYLOAD_VERSION: u32 = 1;
+// 20 lines a second, smooth enough for a frametime graph without waking overlays every frame
+const SEND_INTERVAL: Duration = Duration::from_millis(50);
+const IDLE_INTERVAL: Duration = Duration::from_millis(200);
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(100);
+// Frames kept for a client that lags behind, about a second at 144 fps
+const MAX_PENDING_FRAMES: usize = 144;
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct OverlayFrame {
+    pkg: String,
+    pid: i32,
+    target_fps: Option<u32>,
+    // The short and long window averages the control loop reacts to
+    fps: f64,
+    fps_avg: f64,
+    // Every frametime that arrived since the previous line, oldest first
+    frametimes_ms: Vec<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    version: u32,
+    working: bool,
+    #[serde(flatten)]
+    frame: Option<&'a OverlayFrame>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    frame: Option<OverlayFrame>,
+    // Whether anything changed since the last line went out
+    dirty: bool,
+}
+
+struct Feed {
+    stop: Arc<AtomicBool>,
+}
+
+// The socket is unlinked here rather than by the thread, a thread that ends late must not remove
+// the socket of its successor
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        let _ = fs::remove_file(OVERLAY_SOCKET);
+    }
+}
+
+// Streams the frame statistics fas-rs itself acts on as JSON lines on `OVERLAY_SOCKET`, so an
+// on-screen overlay shows the same numbers instead of measuring frames on its own
+pub struct OverlayFeed {
+    feed: Option<Feed>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl OverlayFeed {
+    pub fn new() -> Self {
+        Self {
+            feed: None,
+            shared: Arc::new(Mutex::new(Shared::default())),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.feed.is_some() == enabled {
+            return;
+        }
+
+        self.feed = None;
+        if !enabled {
+            return;
+        }
+
+        match Self::spawn(self.shared.clone()) {
+            Ok(feed) => {
+                info!("Overlay feed listening on {OVERLAY_SOCKET}");
+                self.feed = Some(feed);
+            }
+            Err(e) => warn!("Failed to start overlay feed: {e}"),
+        }
+    }
+
+    // Called once per frame, only the cheap bookkeeping happens on the looper
+    pub fn push_frame(
+        &self,
+        pkg: &str,
+        pid: i32,
+        target_fps: Option<u32>,
+        fps: f64,
+        fps_avg: f64,
+        frametime: Duration,
+    ) {
+        if self.feed.is_none() {
+            return;
+        }
+
+        let mut shared = self.shared.lock();
+        let frame = shared.frame.get_or_insert_with(OverlayFrame::default);
+        if frame.pkg != pkg {
+            frame.pkg = pkg.to_string();
+            frame.frametimes_ms.clear();
+        }
+
+        frame.pid = pid;
+        frame.target_fps = target_fps;
+        frame.fps = fps;
+        frame.fps_avg = fps_avg;
+        if frame.frametimes_ms.len() >= MAX_PENDING_FRAMES {
+            frame.frametimes_ms.remove(0);
+        }
+        frame.frametimes_ms.push(frametime.as_secs_f64() * 1000.0);
+        shared.dirty = true;
+    }
+
+    pub fn clear(&self) {
+        let mut shared = self.shared.lock();
+        if shared.frame.take().is_some() {
+            shared.dirty = true;
+        }
+    }
+
+    fn spawn(shared: Arc<Mutex<Shared>>) -> Result<Feed> {
+        let _ = fs::remove_file(OVERLAY_SOCKET);
+        let listener = UnixListener::bind(OVERLAY_SOCKET)?;
+        set_permissions(OVERLAY_SOCKET, PermissionsExt::from_mode(0o666))?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+
+        {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("OverlayThread".into())
+                .spawn(move || serve(&listener, &shared, &stop))?;
+        }
+
+        Ok(Feed { stop })
+    }
+}
+
+fn serve(listener: &UnixListener, shared: &Mutex<Shared>, stop: &AtomicBool) {
+    let mut clients = Vec::new();
+
+    while !stop.load(Ordering::Acquire) {
+        let joined = accept_clients(listener, &mut clients);
+
+        if clients.is_empty() {
+            thread::sleep(IDLE_INTERVAL);
+            continue;
+        }
+
+        let line = {
+            let mut shared = shared.lock();
+            if shared.dirty || joined {
+                shared.dirty = false;
+                let payload = Payload {
+                    version: PAYLOAD_VERSION,
+                    working: shared.frame.is_some(),
+                    frame: shared.frame.as_ref(),
+                };
+                let line = serde_json::to_string(&payload).ok();
+                if let Some(frame) = shared.frame.as_mut() {
+                    frame.frametimes_ms.clear();
+                }
+                line
+            } else {
+                None
+            }
+        };
+
+        if let Some(line) = line {
+            clients.retain_mut(|client: &mut UnixStream| {
+                client
+                    .write_all(line.as_bytes())
+                    .and_then(|()| client.write_all(b"\n"))
+                    .is_ok()
+            });
+        }
+
+        thread::sleep(SEND_INTERVAL);
+    }
+}
+
+// Whether a client joined, it gets the current state right away
+fn accept_clients(listener: &UnixListener, clients: &mut Vec<UnixStream>) -> bool {
+    let mut joined = false;
+
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                // A client that stops reading is dropped instead of stalling the others
+                if stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok() {
+                    clients.push(stream);
+                    joined = true;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return joined,
+            Err(e) => {
+                warn!("Failed to accept overlay client: {e}");
+                return joined;
+            }
+        }
+    }
+}
@@ -27,13 +27,7 @@ pub fn get_process_name(pid: i32) -> Result<String> {
     let mut buffer = [0u8; 128];
     let _ = cmdline.read(&mut buffer)?;
 
-    let pos = sz::find(buffer, b":");
-    if let Some(sub) = pos {
-        let buffer = &buffer[..sub];
-        let buffer = String::from_utf8_lossy(buffer).into();
-        return Ok(buffer);
-    }
-
+    // Keeps the `:sub` suffix of sub-processes, config lookups fall back to the bare package
     let pos = sz::find(buffer, b"\0");
     let buffer = pos.map_or(&buffer[..], |pos| &buffer[..pos]);
 
@@ -0,0 +1,141 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+use crate::framework::error::Result;
+
+const RECORDS_PATH: &str = "/sdcard/Android/fas-rs/records";
+const MAX_RECORD_SIZE: usize = 8 * 1024 * 1024;
+const MAX_RECORD_FILES: usize = 16;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub frametime: Duration,
+    pub util_max: f64,
+    pub freq: isize,
+    pub temperature: u64,
+}
+
+struct Record {
+    pkg: String,
+    writer: BufWriter<File>,
+    written: usize,
+}
+
+pub struct Recorder {
+    record: Option<Record>,
+}
+
+impl Recorder {
+    pub const fn new() -> Self {
+        Self { record: None }
+    }
+
+    pub fn start<S: AsRef<str>>(&mut self, pkg: S) {
+        let pkg = pkg.as_ref();
+        self.stop();
+
+        match Self::create(pkg) {
+            Ok(record) => self.record = Some(record),
+            Err(e) => warn!("Failed to start recording for {pkg}: {e}"),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            let _ = record.writer.flush();
+        }
+    }
+
+    pub fn record(&mut self, sample: &Sample) {
+        let Some(record) = self.record.as_mut() else {
+            return;
+        };
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let line = format!(
+            "{timestamp},{},{:.4},{},{}\n",
+            sample.frametime.as_micros(),
+            sample.util_max,
+            sample.freq,
+            sample.temperature
+        );
+
+        if record.writer.write_all(line.as_bytes()).is_err() {
+            warn!("Failed to write record, recording stopped");
+            self.record = None;
+            return;
+        }
+
+        record.written += line.len();
+        if record.written >= MAX_RECORD_SIZE {
+            let pkg = record.pkg.clone();
+            self.start(pkg);
+        }
+    }
+
+    fn create(pkg: &str) -> Result<Record> {
+        fs::create_dir_all(RECORDS_PATH)?;
+        Self::rotate()?;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = Path::new(RECORDS_PATH).join(format!("{pkg}_{timestamp}.csv"));
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writer.write_all(b"timestamp_ms,frametime_us,util_max,freq_khz,temperature\n")?;
+
+        info!("Recording to {}", path.display());
+
+        Ok(Record {
+            pkg: pkg.to_string(),
+            writer,
+            written: 0,
+        })
+    }
+
+    fn rotate() -> Result<()> {
+        let mut records: Vec<(SystemTime, PathBuf)> = fs::read_dir(RECORDS_PATH)?
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "csv"))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect();
+
+        if records.len() < MAX_RECORD_FILES {
+            return Ok(());
+        }
+
+        records.sort_unstable_by_key(|(modified, _)| *modified);
+        for (_, path) in records.iter().take(records.len() + 1 - MAX_RECORD_FILES) {
+            let _ = fs::remove_file(path);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use crate::{
+    framework::{config::CaptureConfig, pid_utils::get_process_name},
+    misc::getprop,
+};
+
+// A full /proc walk, recordings last long enough for this
+const SAMPLE_TIME: Duration = Duration::from_secs(2);
+
+fn recorder_running(processes: &[String]) -> bool {
+    fs::read_dir("/proc")
+        .into_iter()
+        .flatten()
+        .filter_map(std::result::Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter_map(|pid| get_process_name(pid).ok())
+        .any(|name| {
+            // `screenrecord` started from a shell shows up with its full path
+            let name = name.rsplit('/').next().unwrap_or(&name);
+            processes.iter().any(|process| process == name)
+        })
+}
+
+fn prop_set(props: &[String]) -> bool {
+    props.iter().any(|prop| {
+        getprop(prop).is_some_and(|value| !matches!(value.as_str(), "" | "0" | "false"))
+    })
+}
+
+// Screen recording encodes on the cpu next to the game, its threads are not the game's so the
+// load looks lower than the frames it costs
+pub struct Capture {
+    active: bool,
+    sample_timer: Instant,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self {
+            active: false,
+            sample_timer: Instant::now(),
+        }
+    }
+
+    pub const fn active(&self) -> bool {
+        self.active
+    }
+
+    // Only looks for recorders while the config does something about them
+    pub fn update(&mut self, config: &CaptureConfig) -> bool {
+        let wanted = config.margin_fps > 0.0 || !config.profile.is_empty();
+        if wanted && self.sample_timer.elapsed() < SAMPLE_TIME {
+            return self.active;
+        }
+        self.sample_timer = Instant::now();
+
+        let active = wanted && (prop_set(&config.props) || recorder_running(&config.processes));
+        if active != self.active {
+            info!(
+                "Screen capture {}",
+                if active { "detected" } else { "stopped" }
+            );
+            self.active = active;
+        }
+
+        self.active
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use dumpsys_rs::Dumpsys;
+use log::{info, warn};
+
+const SAMPLE_TIME: Duration = Duration::from_secs(5);
+// LTPO panels switch modes within a second, tracking them needs a faster sample
+const PANEL_SAMPLE_TIME: Duration = Duration::from_secs(1);
+const DRM_PATH: &str = "/sys/class/drm";
+const EXTERNAL_CONNECTORS: [&str; 3] = ["-DP-", "-HDMI-", "-DVI-"];
+// DRM connectors do not expose the active mode, external monitors are assumed to run at 60 Hz
+const EXTERNAL_REFRESH_RATE: f32 = 60.0;
+const REFRESH_RATE_SETTINGS: [&str; 2] = ["peak_refresh_rate", "min_refresh_rate"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayMode {
+    pub external: bool,
+    pub refresh_rate: f32,
+}
+
+pub trait RefreshRateProvider {
+    fn name(&self) -> &'static str;
+    // Active (powered on) displays, None if the provider cannot answer at all
+    fn active_displays(&self) -> Option<Vec<DisplayMode>>;
+}
+
+pub struct DumpsysDisplay {
+    dumper: Dumpsys,
+}
+
+impl DumpsysDisplay {
+    pub fn new() -> Option<Self> {
+        Dumpsys::new("display").map(|dumper| Self { dumper })
+    }
+
+    fn parse_device(line: &str) -> Option<DisplayMode> {
+        let kind = Self::field(line, ", type ")?;
+        if kind == "VIRTUAL" || Self::field(line, ", state ")? != "ON" {
+            return None;
+        }
+
+        let refresh_rate = Self::field(line, "renderFrameRate ")
+            .and_then(|rate| rate.parse().ok())
+            .or_else(|| Self::active_mode_fps(line))?;
+
+        Some(DisplayMode {
+            external: kind != "INTERNAL",
+            refresh_rate,
+        })
+    }
+
+    // Older releases only print the active mode id next to the supported mode list
+    fn active_mode_fps(line: &str) -> Option<f32> {
+        let mode_id = Self::field(line, ", modeId ")?;
+        let mode = format!("{{id={mode_id}, ");
+        let start = line.find(&mode)?;
+        let mode = &line[start..];
+        let mode = &mode[..mode.find('}')?];
+
+        Self::field(mode, "fps=")?.parse().ok()
+    }
+
+    fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+        let start = line.find(key)? + key.len();
+        line[start..]
+            .split([',', ' ', '}'])
+            .next()
+            .filter(|value| !value.is_empty())
+    }
+}
+
+impl RefreshRateProvider for DumpsysDisplay {
+    fn name(&self) -> &'static str {
+        "dumpsys display"
+    }
+
+    fn active_displays(&self) -> Option<Vec<DisplayMode>> {
+        let dump = self.dumper.dump(&[]).ok()?;
+        let displays: Vec<_> = dump
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("DisplayDeviceInfo{"))
+            .filter_map(Self::parse_device)
+            .collect();
+
+        (!displays.is_empty()).then_some(displays)
+    }
+}
+
+pub struct SysfsDisplay;
+
+impl RefreshRateProvider for SysfsDisplay {
+    fn name(&self) -> &'static str {
+        "drm connectors"
+    }
+
+    fn active_displays(&self) -> Option<Vec<DisplayMode>> {
+        let displays = fs::read_dir(DRM_PATH)
+            .ok()?
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                EXTERNAL_CONNECTORS
+                    .iter()
+                    .any(|connector| name.contains(connector))
+            })
+            .filter(|entry| {
+                fs::read_to_string(entry.path().join("status"))
+                    .is_ok_and(|status| status.trim() == "connected")
+            })
+            .map(|_| DisplayMode {
+                external: true,
+                refresh_rate: EXTERNAL_REFRESH_RATE,
+            })
+            .collect();
+
+        Some(displays)
+    }
+}
+
+pub struct Display {
+    providers: Vec<Box<dyn RefreshRateProvider>>,
+    refresh_rate: Option<u32>,
+    sample_timer: Option<Instant>,
+    requested: Option<u32>,
+    // Settings as they were before the first request, None for keys that were unset
+    saved_settings: Option<Vec<(&'static str, Option<String>)>>,
+    // Follow the internal panel too, not only external displays
+    track_panel: bool,
+}
+
+impl Display {
+    pub fn new() -> Self {
+        let mut providers: Vec<Box<dyn RefreshRateProvider>> = Vec::new();
+        if let Some(dumpsys) = DumpsysDisplay::new() {
+            providers.push(Box::new(dumpsys));
+        }
+        providers.push(Box::new(SysfsDisplay));
+
+        Self {
+            providers,
+            refresh_rate: None,
+            sample_timer: None,
+            requested: None,
+            saved_settings: None,
+            track_panel: false,
+        }
+    }
+
+    pub fn set_track_panel(&mut self, track_panel: bool) {
+        if self.track_panel != track_panel {
+            self.track_panel = track_panel;
+            self.sample_timer = None;
+        }
+    }
+
+    // Pin the panel to `rate` through the system settings, None restores what was there before
+    pub fn request_refresh_rate(&mut self, rate: Option<u32>) {
+        if rate == self.requested {
+            return;
+        }
+
+        match rate {
+            Some(rate) => {
+                if self.saved_settings.is_none() {
+                    self.saved_settings = Some(
+                        REFRESH_RATE_SETTINGS
+                            .iter()
+                            .map(|key| (*key, get_setting(key)))
+                            .collect(),
+                    );
+                }
+
+                for key in REFRESH_RATE_SETTINGS {
+                    put_setting(key, Some(&format!("{rate}.0")));
+                }
+                info!("Requested {rate} Hz refresh rate");
+            }
+            None => {
+                for (key, value) in self.saved_settings.take().unwrap_or_default() {
+                    put_setting(key, value.as_deref());
+                }
+                info!("Restored refresh rate settings");
+            }
+        }
+
+        self.requested = rate;
+    }
+
+    // The requested rate, or the minimum of the active displays while an external one is on or
+    // the panel is tracked
+    pub fn refresh_rate(&mut self) -> Option<u32> {
+        let sampled = self.sampled_refresh_rate();
+
+        match (self.requested, sampled) {
+            (Some(requested), Some(sampled)) => Some(requested.min(sampled)),
+            (requested, sampled) => requested.or(sampled),
+        }
+    }
+
+    // A mirrored game is held to the slowest display and a moved one runs at the external
+    // display's rate, both are the minimum. Without an external display the panel only counts
+    // when tracked, its current mode on LTPO panels changes at runtime
+    fn sampled_refresh_rate(&mut self) -> Option<u32> {
+        let sample_time = if self.track_panel {
+            PANEL_SAMPLE_TIME
+        } else {
+            SAMPLE_TIME
+        };
+        if self
+            .sample_timer
+            .is_some_and(|timer| timer.elapsed() < sample_time)
+        {
+            return self.refresh_rate;
+        }
+
+        self.sample_timer = Some(Instant::now());
+
+        let track_panel = self.track_panel;
+        let refresh_rate = self.providers.iter().find_map(|provider| {
+            let displays = provider.active_displays()?;
+            let rate = (!displays.is_empty()
+                && (track_panel || displays.iter().any(|display| display.external)))
+            .then(|| {
+                displays
+                    .iter()
+                    .map(|display| display.refresh_rate)
+                    .fold(f32::MAX, f32::min)
+                    .round() as u32
+            });
+            Some((provider.name(), rate))
+        });
+
+        let (name, refresh_rate) = refresh_rate.unwrap_or(("none", None));
+        if refresh_rate != self.refresh_rate {
+            match refresh_rate {
+                Some(rate) => info!("Display refresh rate {rate} Hz (from {name})"),
+                None => info!("No display refresh rate limit"),
+            }
+            self.refresh_rate = refresh_rate;
+        }
+
+        self.refresh_rate
+    }
+}
+
+fn get_setting(key: &str) -> Option<String> {
+    let output = Command::new("settings")
+        .args(["get", "system", key])
+        .output()
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    (output.status.success() && !value.is_empty() && value != "null").then_some(value)
+}
+
+fn put_setting(key: &str, value: Option<&str>) {
+    let status = match value {
+        Some(value) => Command::new("settings")
+            .args(["put", "system", key, value])
+            .status(),
+        None => Command::new("settings")
+            .args(["delete", "system", key])
+            .status(),
+    };
+
+    if !status.is_ok_and(|status| status.success()) {
+        warn!("Failed to update system setting {key}");
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use dumpsys_rs::Dumpsys;
+#[cfg(debug_assertions)]
+use log::debug;
+
+use crate::framework::config::TargetFps;
+
+const STANDARD_FPS: [u32; 6] = [30, 45, 60, 90, 120, 144];
+const REFRESH_TIME: Duration = Duration::from_secs(5);
+const DEFAULT_REFRESH_RATE: u32 = 60;
+
+pub struct FpsDetector {
+    dumper: Option<Dumpsys>,
+    refresh_rate: u32,
+    last_refresh: Option<Instant>,
+}
+
+impl FpsDetector {
+    pub fn new() -> Self {
+        Self {
+            dumper: Dumpsys::new("SurfaceFlinger"),
+            refresh_rate: DEFAULT_REFRESH_RATE,
+            last_refresh: None,
+        }
+    }
+
+    pub fn refresh_rate(&mut self) -> u32 {
+        if self
+            .last_refresh
+            .is_none_or(|last_refresh| last_refresh.elapsed() > REFRESH_TIME)
+        {
+            self.last_refresh = Some(Instant::now());
+            if let Some(refresh_rate) = self.read_refresh_rate() {
+                self.refresh_rate = refresh_rate;
+            }
+        }
+
+        self.refresh_rate
+    }
+
+    pub fn target_fps(&mut self) -> TargetFps {
+        let refresh_rate = self.refresh_rate();
+        let mut candidates: Vec<_> = STANDARD_FPS
+            .iter()
+            .copied()
+            .filter(|fps| *fps < refresh_rate)
+            .collect();
+        candidates.push(refresh_rate);
+
+        #[cfg(debug_assertions)]
+        debug!("auto detected target fps candidates: {candidates:?}");
+
+        TargetFps::Array(candidates)
+    }
+
+    fn read_refresh_rate(&self) -> Option<u32> {
+        let dump = self.dumper.as_ref()?.dump(&["--latency"]).ok()?;
+        let period: f64 = dump.lines().next()?.trim().parse().ok()?;
+
+        if period <= 0.0 {
+            return None;
+        }
+
+        Some((1_000_000_000.0 / period).round() as u32)
+    }
+}
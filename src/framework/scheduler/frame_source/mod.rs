@@ -0,0 +1,163 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod plugin;
+mod surfaceflinger;
+
+use std::time::Duration;
+
+use frame_analyzer::Analyzer;
+use hashbrown::HashSet;
+use log::{info, warn};
+
+use crate::framework::error::Result;
+use plugin::Plugin;
+use surfaceflinger::SurfaceFlinger;
+
+enum Backend {
+    Ebpf(Analyzer),
+    SurfaceFlinger(SurfaceFlinger),
+}
+
+impl Backend {
+    fn attach_app(&mut self, pid: i32) -> Result<()> {
+        match self {
+            Self::Ebpf(analyzer) => analyzer.attach_app(pid)?,
+            Self::SurfaceFlinger(surfaceflinger) => surfaceflinger.attach_app(pid),
+        }
+
+        Ok(())
+    }
+
+    fn detach_app(&mut self, pid: i32) -> Result<()> {
+        match self {
+            Self::Ebpf(analyzer) => analyzer.detach_app(pid)?,
+            Self::SurfaceFlinger(surfaceflinger) => surfaceflinger.detach_app(pid),
+        }
+
+        Ok(())
+    }
+
+    fn detach_apps(&mut self) {
+        match self {
+            Self::Ebpf(analyzer) => analyzer.detach_apps(),
+            Self::SurfaceFlinger(surfaceflinger) => surfaceflinger.detach_apps(),
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> Option<(i32, Duration)> {
+        match self {
+            Self::Ebpf(analyzer) => analyzer.recv_timeout(timeout),
+            Self::SurfaceFlinger(surfaceflinger) => surfaceflinger.recv_timeout(timeout),
+        }
+    }
+}
+
+// Frames from a hook module are preferred while it pushes them, the built-in backend is only
+// attached while it does not
+pub struct FrameSource {
+    backend: Backend,
+    plugin: Option<Plugin>,
+    pids: HashSet<i32>,
+    plugin_in_use: bool,
+}
+
+impl FrameSource {
+    pub fn new() -> Self {
+        let backend = match Analyzer::new() {
+            Ok(analyzer) => Backend::Ebpf(analyzer),
+            Err(e) => {
+                warn!("Failed to load ebpf frame analyzer: {e}");
+                warn!("Falling back to SurfaceFlinger latency statistics");
+                Backend::SurfaceFlinger(SurfaceFlinger::new())
+            }
+        };
+
+        let plugin = Plugin::new()
+            .map_err(|e| warn!("Failed to start frame plugin socket: {e}"))
+            .ok();
+
+        Self {
+            backend,
+            plugin,
+            pids: HashSet::new(),
+            plugin_in_use: false,
+        }
+    }
+
+    pub fn attach_app(&mut self, pid: i32) -> Result<()> {
+        self.pids.insert(pid);
+        if let Some(plugin) = &self.plugin {
+            plugin.attach_app(pid);
+        }
+
+        if !self.plugin_in_use {
+            self.backend.attach_app(pid)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn detach_app(&mut self, pid: i32) -> Result<()> {
+        self.pids.remove(&pid);
+        if let Some(plugin) = &self.plugin {
+            plugin.detach_app(pid);
+        }
+
+        if !self.plugin_in_use {
+            self.backend.detach_app(pid)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn detach_apps(&mut self) {
+        self.pids.clear();
+        if let Some(plugin) = &self.plugin {
+            plugin.detach_apps();
+        }
+        self.plugin_in_use = false;
+        self.backend.detach_apps();
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<(i32, Duration)> {
+        let Some(plugin) = &self.plugin else {
+            return self.backend.recv_timeout(timeout);
+        };
+
+        let active = plugin.active();
+        if active != self.plugin_in_use {
+            self.plugin_in_use = active;
+            if active {
+                info!("Using frames pushed by a hook module");
+                self.backend.detach_apps();
+            } else {
+                info!("Hook module stopped pushing frames, back to the built-in frame source");
+                for pid in &self.pids {
+                    let _ = self.backend.attach_app(*pid);
+                }
+            }
+        }
+
+        if active {
+            plugin.recv_timeout(timeout)
+        } else {
+            plugin.drain();
+            self.backend.recv_timeout(timeout)
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, set_permissions},
+    io::Read,
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use hashbrown::{HashMap, HashSet};
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use crate::{framework::error::Result, misc::peer_cred};
+
+pub const FRAMES_SOCKET: &str = "/dev/fas_rs/frames.sock";
+// pid (i32) and timestamp in ns (u64), little endian. Longer messages carry fields added later
+const MESSAGE_SIZE: usize = 12;
+const MAX_MESSAGE_SIZE: usize = 256;
+// A hook that went quiet for this long is no longer trusted over the built-in source
+const STALE_TIME: Duration = Duration::from_secs(1);
+// One hook per game process, a few games can be visible at once
+const MAX_CLIENTS: usize = 8;
+
+#[derive(Debug, Default)]
+struct Shared {
+    pids: HashSet<i32>,
+    last_frame: Option<Instant>,
+    clients: usize,
+}
+
+// Frames pushed by an external hook module (a zygisk or libgui hook in the game's process) over
+// `FRAMES_SOCKET`. Each message is a u32 little endian length followed by that many bytes, the
+// first `MESSAGE_SIZE` of which are the pid and the CLOCK_MONOTONIC timestamp of one frame.
+// Every app can connect, so a hook only reports frames of its own process, root excepted
+pub struct Plugin {
+    receiver: Receiver<(i32, Duration)>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Plugin {
+    pub fn new() -> Result<Self> {
+        let _ = fs::remove_file(FRAMES_SOCKET);
+        let listener = UnixListener::bind(FRAMES_SOCKET)?;
+        set_permissions(FRAMES_SOCKET, PermissionsExt::from_mode(0o666))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        {
+            let shared = shared.clone();
+            thread::Builder::new()
+                .name("FramePluginThread".into())
+                .spawn(move || accept_clients(&listener, &sender, &shared))?;
+        }
+
+        info!("Frame plugin listening on {FRAMES_SOCKET}");
+
+        Ok(Self { receiver, shared })
+    }
+
+    pub fn attach_app(&self, pid: i32) {
+        self.shared.lock().pids.insert(pid);
+    }
+
+    pub fn detach_app(&self, pid: i32) {
+        self.shared.lock().pids.remove(&pid);
+    }
+
+    pub fn detach_apps(&self) {
+        let mut shared = self.shared.lock();
+        shared.pids.clear();
+        shared.last_frame = None;
+    }
+
+    // A hook is pushing frames of an attached app
+    pub fn active(&self) -> bool {
+        self.shared
+            .lock()
+            .last_frame
+            .is_some_and(|last_frame| last_frame.elapsed() < STALE_TIME)
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<(i32, Duration)> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    // Frames that arrived while the built-in source was in use
+    pub fn drain(&self) {
+        while self.receiver.try_recv().is_ok() {}
+    }
+}
+
+fn accept_clients(
+    listener: &UnixListener,
+    sender: &Sender<(i32, Duration)>,
+    shared: &Arc<Mutex<Shared>>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Frame plugin failed to accept a client: {e}");
+                continue;
+            }
+        };
+
+        let Some(cred) = peer_cred(&stream) else {
+            continue;
+        };
+
+        {
+            let mut shared = shared.lock();
+            if shared.clients >= MAX_CLIENTS {
+                warn!(
+                    "Frame plugin has {MAX_CLIENTS} clients, refusing pid {}",
+                    cred.pid
+                );
+                continue;
+            }
+            shared.clients += 1;
+        }
+
+        let sender = sender.clone();
+        let spawned = thread::Builder::new()
+            .name("FramePluginClient".into())
+            .spawn({
+                let shared = shared.clone();
+                move || {
+                    read_frames(stream, &cred, &sender, &shared);
+                    shared.lock().clients -= 1;
+                }
+            });
+        if spawned.is_err() {
+            shared.lock().clients -= 1;
+        }
+    }
+}
+
+fn read_frames(
+    mut stream: UnixStream,
+    cred: &libc::ucred,
+    sender: &Sender<(i32, Duration)>,
+    shared: &Mutex<Shared>,
+) {
+    let mut last_timestamps: HashMap<i32, u64> = HashMap::new();
+    let mut message = [0; MAX_MESSAGE_SIZE];
+
+    loop {
+        let mut len = [0; 4];
+        if stream.read_exact(&mut len).is_err() {
+            return;
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if !(MESSAGE_SIZE..=MAX_MESSAGE_SIZE).contains(&len) {
+            warn!("Frame plugin client sent a {len} byte message, disconnecting it");
+            return;
+        }
+
+        if stream.read_exact(&mut message[..len]).is_err() {
+            return;
+        }
+
+        let pid = i32::from_le_bytes(message[0..4].try_into().unwrap_or_default());
+        let timestamp = u64::from_le_bytes(message[4..12].try_into().unwrap_or_default());
+        if cred.uid != 0 && pid != cred.pid {
+            warn!(
+                "Frame plugin client {} sent frames of pid {pid}, disconnecting it",
+                cred.pid
+            );
+            return;
+        }
+
+        let mut shared = shared.lock();
+        if !shared.pids.contains(&pid) {
+            last_timestamps.remove(&pid);
+            continue;
+        }
+
+        let last = last_timestamps.insert(pid, timestamp);
+        let Some(frametime) = last.and_then(|last| timestamp.checked_sub(last)) else {
+            continue;
+        };
+
+        shared.last_frame = Some(Instant::now());
+        drop(shared);
+
+        if sender.send((pid, Duration::from_nanos(frametime))).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    thread,
+    time::{Duration, Instant},
+};
+
+use dumpsys_rs::Dumpsys;
+use hashbrown::HashMap;
+#[cfg(debug_assertions)]
+use log::debug;
+use log::{info, warn};
+use stringzilla::sz;
+
+use crate::framework::pid_utils::get_process_name;
+
+const PENDING_FENCE: u64 = i64::MAX as u64;
+// SurfaceFlinger may still be starting with fas-rs early in boot, startup waits this long for it
+// and later polls try again every `DUMPSYS_RECONNECT`
+const DUMPSYS_RETRIES: u32 = 10;
+const DUMPSYS_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const DUMPSYS_RECONNECT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct LayerState {
+    layer: Option<String>,
+    last_present: u64,
+}
+
+pub struct SurfaceFlinger {
+    dumper: Option<Dumpsys>,
+    apps: HashMap<i32, LayerState>,
+    frames: VecDeque<(i32, Duration)>,
+    last_poll: Instant,
+    last_connect: Instant,
+}
+
+impl SurfaceFlinger {
+    pub fn new() -> Self {
+        let mut dumper = None;
+        for _ in 0..DUMPSYS_RETRIES {
+            dumper = Dumpsys::new("SurfaceFlinger");
+            if dumper.is_some() {
+                break;
+            }
+            thread::sleep(DUMPSYS_RETRY_INTERVAL);
+        }
+
+        if dumper.is_none() {
+            warn!(
+                "SurfaceFlinger dumpsys unavailable after {DUMPSYS_RETRIES} tries, no frames until it comes up"
+            );
+        }
+
+        Self {
+            dumper,
+            apps: HashMap::new(),
+            frames: VecDeque::new(),
+            last_poll: Instant::now(),
+            last_connect: Instant::now(),
+        }
+    }
+
+    pub fn attach_app(&mut self, pid: i32) {
+        self.apps.entry(pid).or_default();
+    }
+
+    pub fn detach_app(&mut self, pid: i32) {
+        self.apps.remove(&pid);
+        self.frames.retain(|(frame_pid, _)| *frame_pid != pid);
+    }
+
+    pub fn detach_apps(&mut self) {
+        self.apps.clear();
+        self.frames.clear();
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<(i32, Duration)> {
+        if let Some(frame) = self.frames.pop_front() {
+            return Some(frame);
+        }
+
+        if let Some(wait) = timeout.checked_sub(self.last_poll.elapsed()) {
+            thread::sleep(wait);
+        }
+
+        self.last_poll = Instant::now();
+        self.poll();
+        self.frames.pop_front()
+    }
+
+    fn poll(&mut self) {
+        if self.dumper.is_none() && self.last_connect.elapsed() >= DUMPSYS_RECONNECT {
+            self.last_connect = Instant::now();
+            self.dumper = Dumpsys::new("SurfaceFlinger");
+            if self.dumper.is_some() {
+                info!("SurfaceFlinger dumpsys is up");
+            }
+        }
+        let Some(dumper) = &self.dumper else {
+            return;
+        };
+
+        let pids: Vec<_> = self.apps.keys().copied().collect();
+
+        for pid in pids {
+            if self
+                .apps
+                .get(&pid)
+                .is_some_and(|state| state.layer.is_none())
+            {
+                let layer = Self::find_layer(dumper, pid);
+                if let Some(state) = self.apps.get_mut(&pid) {
+                    state.layer = layer;
+                }
+            }
+
+            let Some(layer) = self.apps.get(&pid).and_then(|state| state.layer.clone()) else {
+                continue;
+            };
+
+            let Ok(latency) = dumper.dump(&["--latency", layer.as_str()]) else {
+                continue;
+            };
+
+            let presents = parse_latency(&latency);
+            if presents.is_empty() {
+                // The layer is gone, look it up again next time
+                if let Some(state) = self.apps.get_mut(&pid) {
+                    state.layer = None;
+                }
+                continue;
+            }
+
+            let Some(state) = self.apps.get_mut(&pid) else {
+                continue;
+            };
+
+            let mut last_present = state.last_present;
+            for present in presents
+                .into_iter()
+                .filter(|present| *present > last_present)
+            {
+                if last_present != 0 {
+                    self.frames
+                        .push_back((pid, Duration::from_nanos(present - last_present)));
+                }
+                last_present = present;
+            }
+            state.last_present = last_present;
+        }
+    }
+
+    fn find_layer(dumper: &Dumpsys, pid: i32) -> Option<String> {
+        let pkg = get_process_name(pid).ok()?;
+        let list = dumper.dump(&["--list"]).ok()?;
+        let layers: Vec<_> = list
+            .lines()
+            .filter(|layer| sz::find(layer, &pkg).is_some())
+            .collect();
+
+        let layer = layers
+            .iter()
+            .find(|layer| sz::find(layer, "SurfaceView").is_some())
+            .or_else(|| layers.iter().find(|layer| sz::find(layer, "/").is_some()))
+            .map(|layer| (*layer).to_string());
+
+        #[cfg(debug_assertions)]
+        debug!("SurfaceFlinger layer of {pkg}: {layer:?}");
+
+        layer
+    }
+}
+
+fn parse_latency(latency: &str) -> Vec<u64> {
+    let mut presents: Vec<u64> = latency
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1)?.parse().ok())
+        .filter(|present| *present != 0 && *present != PENDING_FENCE)
+        .collect();
+    presents.sort_unstable();
+    presents
+}
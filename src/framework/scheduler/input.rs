@@ -0,0 +1,158 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File},
+    io::Read,
+    os::fd::AsRawFd,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::framework::error::{Error, Result};
+
+const INPUT_DEVICES: &str = "/proc/bus/input/devices";
+const ABS_MT_POSITION_X: usize = 0x35;
+const EV_KEY: u16 = 0x01;
+const BTN_TOUCH: u16 = 0x14a;
+const INPUT_EVENT_SIZE: usize = 24;
+const DEBOUNCE_TIME: Duration = Duration::from_millis(100);
+
+pub struct TouchWatcher {
+    touched: Arc<AtomicBool>,
+}
+
+impl TouchWatcher {
+    pub fn new() -> Result<Self> {
+        let devices = find_touch_devices()?;
+        if devices.is_empty() {
+            return Err(Error::Other("No touchscreen found"));
+        }
+
+        info!("Touch boost devices: {devices:?}");
+
+        let files = devices
+            .iter()
+            .map(File::open)
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let touched = Arc::new(AtomicBool::new(false));
+
+        {
+            let touched = touched.clone();
+            thread::Builder::new()
+                .name("TouchThread".into())
+                .spawn(move || {
+                    if let Err(e) = watch(files, &touched) {
+                        warn!("Touch watcher stopped: {e}");
+                    }
+                })?;
+        }
+
+        Ok(Self { touched })
+    }
+
+    pub fn take_touched(&self) -> bool {
+        self.touched.swap(false, Ordering::AcqRel)
+    }
+}
+
+fn find_touch_devices() -> Result<Vec<String>> {
+    let devices = fs::read_to_string(INPUT_DEVICES)?;
+    let mut result = Vec::new();
+
+    for device in devices.split("\n\n") {
+        let Some(abs) = device.lines().find_map(|line| line.strip_prefix("B: ABS=")) else {
+            continue;
+        };
+
+        // The bitmap is printed as space separated words, most significant first
+        let words: Vec<_> = abs
+            .split_whitespace()
+            .rev()
+            .filter_map(|word| u64::from_str_radix(word, 16).ok())
+            .collect();
+        let bits = usize::BITS as usize;
+        let is_touchscreen = words
+            .get(ABS_MT_POSITION_X / bits)
+            .is_some_and(|word| word & (1 << (ABS_MT_POSITION_X % bits)) != 0);
+
+        if !is_touchscreen {
+            continue;
+        }
+
+        if let Some(event) = device
+            .lines()
+            .find_map(|line| line.strip_prefix("H: Handlers="))
+            .and_then(|handlers| {
+                handlers
+                    .split_whitespace()
+                    .find(|handler| handler.starts_with("event"))
+            })
+        {
+            result.push(format!("/dev/input/{event}"));
+        }
+    }
+
+    Ok(result)
+}
+
+fn watch(mut files: Vec<File>, touched: &AtomicBool) -> Result<()> {
+    let mut fds: Vec<_> = files
+        .iter()
+        .map(|file| libc::pollfd {
+            fd: file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    let mut buffer = [0u8; INPUT_EVENT_SIZE * 64];
+    let mut last_touch = Instant::now()
+        .checked_sub(DEBOUNCE_TIME)
+        .unwrap_or_else(Instant::now);
+
+    loop {
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        for (pollfd, file) in fds.iter_mut().zip(files.iter_mut()) {
+            if pollfd.revents & libc::POLLIN == 0 {
+                continue;
+            }
+            pollfd.revents = 0;
+
+            let len = file.read(&mut buffer)?;
+            let touch_down = buffer[..len].chunks_exact(INPUT_EVENT_SIZE).any(|event| {
+                let event_type = u16::from_ne_bytes([event[16], event[17]]);
+                let code = u16::from_ne_bytes([event[18], event[19]]);
+                let value = i32::from_ne_bytes([event[20], event[21], event[22], event[23]]);
+                event_type == EV_KEY && code == BTN_TOUCH && value == 1
+            });
+
+            if touch_down && last_touch.elapsed() >= DEBOUNCE_TIME {
+                last_touch = Instant::now();
+                touched.store(true, Ordering::Release);
+            }
+        }
+    }
+}
@@ -15,7 +15,10 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::Duration;
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
 
 use likely_stable::unlikely;
 #[cfg(debug_assertions)]
@@ -24,6 +27,13 @@ use log::debug;
 use super::Buffer;
 use crate::{Extension, api::trigger_target_fps_change, framework::config::TargetFps};
 
+// How long the frametime target takes to follow a refresh rate change
+const RAMP_TIME: Duration = Duration::from_millis(500);
+// How long the game has to keep pacing to another configured target before the target follows
+// it, on top of the median needing half the frame window. A loading screen does not drop a
+// 120 fps game to 60
+pub const SWITCH_TIME: Duration = Duration::from_secs(1);
+
 impl Buffer {
     pub fn calculate_current_fps(&mut self) {
         let avg_time_long = self.calculate_average_frametime(None);
@@ -38,8 +48,11 @@ impl Buffer {
 
         self.frametime_state.current_fps_long = current_fps_long;
 
-        let avg_time_short = self
-            .calculate_average_frametime(self.target_fps().map(|target_fps| target_fps as usize));
+        let avg_time_short = self.calculate_average_frametime(
+            self.target_fps_state
+                .target_fps
+                .map(|target_fps| target_fps as usize),
+        );
         #[cfg(debug_assertions)]
         debug!("avg_time_short: {avg_time_short:?}");
 
@@ -73,17 +86,47 @@ impl Buffer {
     }
 
     pub fn calculate_target_fps(&mut self, extension: &Extension) {
+        let refresh_changed = mem::take(&mut self.target_fps_state.refresh_changed);
         let new_target_fps = self.target_fps();
+        let new_target_fps = self.lock_target_fps(new_target_fps, refresh_changed);
         if self.target_fps_state.target_fps != new_target_fps || new_target_fps.is_none() {
+            if let (true, Some(target_fps), Some(_)) = (
+                refresh_changed,
+                new_target_fps,
+                self.target_fps_state.target_fps,
+            ) {
+                // The game keeps running through a display mode switch, ramping the target over
+                // keeps control instead of dropping frames while the history refills
+                self.target_fps_state.ramp =
+                    self.ramped_target_fps().map(|from| (from, Instant::now()));
+                self.trigger_target_fps_change(extension, target_fps);
+                self.target_fps_state.target_fps = new_target_fps;
+                return;
+            }
+
             self.reset_frametime_state();
             if let Some(target_fps) = new_target_fps {
                 self.trigger_target_fps_change(extension, target_fps);
             }
             self.target_fps_state.target_fps = new_target_fps;
+            self.target_fps_state.ramp = None;
             self.unusable();
         }
     }
 
+    // The target the control loop aims at, on its way to `target_fps` after a refresh rate change
+    pub fn ramped_target_fps(&self) -> Option<f64> {
+        let target_fps = f64::from(self.target_fps_state.target_fps?);
+
+        Some(match self.target_fps_state.ramp {
+            Some((from, start)) if start.elapsed() < RAMP_TIME => {
+                let progress = start.elapsed().as_secs_f64() / RAMP_TIME.as_secs_f64();
+                (target_fps - from).mul_add(progress, from)
+            }
+            _ => target_fps,
+        })
+    }
+
     fn reset_frametime_state(&mut self) {
         self.frametime_state.frametimes.clear();
     }
@@ -92,26 +135,114 @@ impl Buffer {
         trigger_target_fps_change(extension, target_fps, self.package_info.pkg.clone());
     }
 
-    fn target_fps(&self) -> Option<u32> {
-        let target_fpses = match &self.target_fps_state.target_fps_config {
-            TargetFps::Value(t) => vec![*t],
-            TargetFps::Array(arr) => arr.clone(),
+    fn target_fps(&mut self) -> Option<u32> {
+        let pacing_fps = pacing_fps(
+            self.frametime_state.frametimes.iter().copied(),
+            &mut self.frametime_state.sorted,
+        );
+
+        let cap = match (
+            self.target_fps_state.refresh_rate,
+            self.target_fps_state.fps_cap,
+        ) {
+            (Some(refresh_rate), Some(fps_cap)) => Some(refresh_rate.min(fps_cap)),
+            (refresh_rate, fps_cap) => refresh_rate.or(fps_cap),
         };
 
-        let current_fps = self.frametime_state.current_fps_long;
+        pick_target_fps(
+            &self.target_fps_state.target_fps_config,
+            cap,
+            self.frametime_state.current_fps_long,
+            pacing_fps,
+        )
+    }
 
-        if unlikely(current_fps < (target_fpses.first()?.saturating_sub(10).max(10)).into()) {
-            return None;
+    // Keeps the current target until the game paced to another one for `SWITCH_TIME`. Losing
+    // the target (the game paused) and refresh rate changes take effect right away
+    fn lock_target_fps(
+        &mut self,
+        new_target_fps: Option<u32>,
+        refresh_changed: bool,
+    ) -> Option<u32> {
+        let state = &mut self.target_fps_state;
+        match (state.target_fps, new_target_fps) {
+            (Some(target_fps), Some(new_target_fps))
+                if target_fps != new_target_fps && !refresh_changed =>
+            {
+                match state.switch {
+                    Some((pending, since))
+                        if pending == new_target_fps && since.elapsed() >= SWITCH_TIME =>
+                    {
+                        state.switch = None;
+                        Some(new_target_fps)
+                    }
+                    Some((pending, _)) if pending == new_target_fps => Some(target_fps),
+                    _ => {
+                        state.switch = Some((new_target_fps, Instant::now()));
+                        Some(target_fps)
+                    }
+                }
+            }
+            _ => {
+                state.switch = None;
+                new_target_fps
+            }
         }
+    }
+}
 
-        for &target_fps in &target_fpses {
-            if current_fps <= f64::from(target_fps) + 3.0 {
-                #[cfg(debug_assertions)]
-                debug!("Matched target_fps: current: {current_fps:.2} target_fps: {target_fps}");
-                return Some(target_fps);
-            }
+// The fps the game paces to, from the median frametime. Unlike the average it is not dragged
+// down by a few janked frames, so a 90 fps game dropping frames still reads as 90. Shared with
+// `simulate`
+pub fn pacing_fps<I: Iterator<Item = Duration>>(frametimes: I, sorted: &mut Vec<Duration>) -> f64 {
+    sorted.clear();
+    sorted.extend(frametimes);
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let middle = sorted.len() / 2;
+    let (_, median, _) = sorted.select_nth_unstable(middle);
+    if median.is_zero() {
+        return 0.0;
+    }
+
+    1.0 / median.as_secs_f64()
+}
+
+// The configured target nearest to the fps the game paces to, None while it runs far below all
+// of them. Shared with `simulate`
+pub fn pick_target_fps(
+    target_fps_config: &TargetFps,
+    refresh_rate: Option<u32>,
+    current_fps: f64,
+    pacing_fps: f64,
+) -> Option<u32> {
+    let mut target_fpses = match target_fps_config {
+        TargetFps::Value(t) => vec![*t],
+        TargetFps::Array(arr) => arr.clone(),
+    };
+
+    // Frames can not be presented faster than the slowest active display refreshes
+    if let Some(refresh_rate) = refresh_rate {
+        for target_fps in &mut target_fpses {
+            *target_fps = (*target_fps).min(refresh_rate);
         }
+        target_fpses.dedup();
+    }
 
-        target_fpses.last().copied()
+    if unlikely(current_fps < (target_fpses.first()?.saturating_sub(10).max(10)).into()) {
+        return None;
     }
+
+    // Ties go to the higher target, undershooting it costs frames while overshooting costs power
+    let target_fps = target_fpses.iter().copied().min_by(|a, b| {
+        let a_distance = (f64::from(*a) - pacing_fps).abs();
+        let b_distance = (f64::from(*b) - pacing_fps).abs();
+        a_distance.total_cmp(&b_distance).then(b.cmp(a))
+    })?;
+
+    #[cfg(debug_assertions)]
+    debug!("Matched target_fps: pacing: {pacing_fps:.2} target_fps: {target_fps}");
+    Some(target_fps)
 }
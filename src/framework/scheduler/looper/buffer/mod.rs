@@ -47,6 +47,8 @@ pub struct FrameTimeState {
     pub avg_time_short: Duration,
     pub frametimes: VecDeque<Duration>,
     pub additional_frametime: Duration,
+    // Scratch space for the median frametime, reused every calculation
+    sorted: Vec<Duration>,
 }
 
 impl FrameTimeState {
@@ -58,6 +60,7 @@ impl FrameTimeState {
             avg_time_short: Duration::ZERO,
             frametimes: VecDeque::with_capacity(1440),
             additional_frametime: Duration::ZERO,
+            sorted: Vec::with_capacity(1440),
         }
     }
 }
@@ -66,6 +69,15 @@ impl FrameTimeState {
 pub struct TargetFpsState {
     pub target_fps: Option<u32>,
     target_fps_config: TargetFps,
+    refresh_rate: Option<u32>,
+    // Highest target while the cpu cannot keep up, see `saturation_drop_fps`
+    fps_cap: Option<u32>,
+    // Set when the refresh rate or the cap moved since the target was last calculated
+    refresh_changed: bool,
+    // Target fps the current ramp started from, and when
+    ramp: Option<(f64, Instant)>,
+    // Another target the game paces to, and since when
+    switch: Option<(u32, Instant)>,
 }
 
 impl TargetFpsState {
@@ -73,6 +85,11 @@ impl TargetFpsState {
         Self {
             target_fps: None,
             target_fps_config,
+            refresh_rate: None,
+            fps_cap: None,
+            refresh_changed: false,
+            ramp: None,
+            switch: None,
         }
     }
 }
@@ -115,6 +132,42 @@ impl Buffer {
         }
     }
 
+    pub fn set_target_fps_config(&mut self, target_fps_config: TargetFps) {
+        self.target_fps_state.target_fps_config = target_fps_config;
+    }
+
+    pub fn set_refresh_rate(&mut self, refresh_rate: Option<u32>) {
+        if self.target_fps_state.refresh_rate != refresh_rate {
+            self.target_fps_state.refresh_rate = refresh_rate;
+            self.target_fps_state.refresh_changed = true;
+        }
+    }
+
+    // Takes effect right away and ramps like a refresh rate change
+    pub fn set_fps_cap(&mut self, fps_cap: Option<u32>) {
+        if self.target_fps_state.fps_cap != fps_cap {
+            self.target_fps_state.fps_cap = fps_cap;
+            self.target_fps_state.refresh_changed = true;
+        }
+    }
+
+    pub const fn fps_cap(&self) -> Option<u32> {
+        self.target_fps_state.fps_cap
+    }
+
+    // The next configured target below the current one
+    pub fn lower_target_fps(&self) -> Option<u32> {
+        let target_fps = self.target_fps_state.target_fps?;
+        match &self.target_fps_state.target_fps_config {
+            TargetFps::Value(_) => None,
+            TargetFps::Array(targets) => targets
+                .iter()
+                .copied()
+                .filter(|fps| *fps < target_fps)
+                .max(),
+        }
+    }
+
     pub fn push_frametime(&mut self, d: Duration, extension: &Extension) {
         self.frametime_state.additional_frametime = Duration::ZERO;
         self.state.last_update = Instant::now();
@@ -151,6 +204,13 @@ impl Buffer {
         self.state.working_state_timer = Instant::now();
     }
 
+    // The game restarted its process, the frame window carries over to the new one
+    pub fn rebind(&mut self, pid: pid_t) {
+        self.package_info.pid = pid;
+        self.frametime_state.additional_frametime = Duration::ZERO;
+        self.state.last_update = Instant::now();
+    }
+
     pub fn additional_frametime(&mut self, extension: &Extension) {
         self.frametime_state.additional_frametime = self.state.last_update.elapsed();
         self.try_calculate(extension);
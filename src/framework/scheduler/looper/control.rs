@@ -0,0 +1,83 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use super::{Looper, State};
+use crate::framework::{
+    control::{Command, GameInfo, Response, Status},
+    node::Mode,
+};
+
+impl Looper {
+    pub fn handle_control(&mut self) {
+        while let Some(request) = self.control.try_recv() {
+            let response = match &request.command {
+                Command::Status => Response::Status(self.status()),
+                Command::SetMode { mode } => self.set_mode(mode),
+                Command::SetProfile { profile } => {
+                    self.set_profile(profile.clone());
+                    Response::Ok
+                }
+                Command::ReloadConfig => match self.config.reload() {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                Command::GetCurrentGame => Response::CurrentGame(self.current_game()),
+                Command::DumpLog => Response::Log(self.decision_log.events()),
+            };
+
+            request.reply(response);
+        }
+    }
+
+    fn status(&mut self) -> Status {
+        Status {
+            mode: self.current_mode().to_string(),
+            profile: self.config.profile().map(String::from),
+            battery: self.battery.stat(),
+            working: self.fas_state.working_state == State::Working,
+            paused: !self.quick_control.enabled(),
+            game: self.current_game(),
+            clusters: self.controller_state.controller.cluster_stats(),
+            session_energy_mwh: self.session.cpu_energy_mwh(),
+            degraded: self.controller_state.controller.degraded(),
+        }
+    }
+
+    fn set_mode(&mut self, mode: &str) -> Response {
+        let mode = match Mode::from_str(mode) {
+            Ok(mode) => mode.to_string(),
+            Err(e) => return Response::Error(e.to_string()),
+        };
+
+        match self.node.create_node("mode", mode.as_str()) {
+            Ok(()) => Response::Ok,
+            Err(e) => Response::Error(e.to_string()),
+        }
+    }
+
+    fn current_game(&self) -> Option<GameInfo> {
+        self.fas_state.buffer.as_ref().map(|buffer| GameInfo {
+            pkg: buffer.package_info.pkg.clone(),
+            pid: buffer.package_info.pid,
+            target_fps: buffer.target_fps_state.target_fps,
+            current_fps: buffer.frametime_state.current_fps_long,
+            bound: self.controller_state.bound.bound().to_string(),
+        })
+    }
+}
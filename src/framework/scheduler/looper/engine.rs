@@ -0,0 +1,173 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::VecDeque, time::Duration};
+
+use super::{
+    super::thermal::step_thermal_offset,
+    buffer::calculate::{SWITCH_TIME, pacing_fps, pick_target_fps},
+    policy::{
+        ControllerParams,
+        controll::{is_janked, margin_fps, pid_control, step_target_fps_offset},
+    },
+};
+use crate::framework::{
+    ModePreset,
+    config::{Config, MarginFps, TargetFps, TemperatureThreshold},
+    node::Mode,
+};
+
+// Same cadences as the looper, on the caller's clock
+const TARGET_FPS_INTERVAL_MS: u64 = 100;
+const UTIL_INTERVAL_MS: u64 = 1000;
+// calculate_control waits for this much history
+const MIN_FRAMES: usize = 60;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Decision {
+    pub target_fps: u32,
+    // Frequency change in kHz, the caller adds it to the current frequency
+    pub control: isize,
+    pub janked: bool,
+}
+
+// The looper's control math fed with frames, utilization and temperatures from outside, no
+// node is read or written. `simulate` replays recordings through it and the C interface hands it
+// to other daemons. Learned adaptive margins and launch / touch boosts are left out
+#[derive(Debug)]
+pub struct Engine {
+    target_fps_config: TargetFps,
+    margin: MarginFps,
+    core_temp_thresh: TemperatureThreshold,
+    boost: f64,
+    params: ControllerParams,
+    frametimes: VecDeque<Duration>,
+    sorted: Vec<Duration>,
+    target_fps: Option<u32>,
+    target_fps_timer: u64,
+    // Another target the game paces to, and since when
+    target_fps_switch: Option<(u32, u64)>,
+    util_offset: f64,
+    util_timer: u64,
+    thermal_offset: f64,
+}
+
+impl Engine {
+    // None when `pkg` has no target fps in `config`
+    pub fn new(config: &mut Config, pkg: &str, mode: Mode) -> Option<Self> {
+        let target_fps_config = config.target_fps(pkg)?;
+
+        Some(Self {
+            target_fps_config,
+            margin: config.margin_fps(pkg, mode),
+            core_temp_thresh: config.mode_config(mode).core_temp_thresh,
+            boost: ModePreset::new(mode).boost,
+            params: ControllerParams::new(config.pid_gains(pkg)),
+            frametimes: VecDeque::new(),
+            sorted: Vec::new(),
+            target_fps: None,
+            target_fps_timer: 0,
+            target_fps_switch: None,
+            util_offset: 0.0,
+            util_timer: 0,
+            thermal_offset: 0.0,
+        })
+    }
+
+    pub const fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    // One presented frame. `timestamp_ms` is any monotonic clock, `util_max` the busiest game
+    // thread's share of a cpu and `temperature` the hottest core in 0.001℃. None while the
+    // target fps is unknown or the frame history is too short to act on
+    pub fn frame(
+        &mut self,
+        timestamp_ms: u64,
+        frametime: Duration,
+        util_max: f64,
+        temperature: u64,
+    ) -> Option<Decision> {
+        let capacity = self.target_fps.unwrap_or(144) as usize * 5;
+        self.frametimes.truncate(capacity.saturating_sub(1));
+        self.frametimes.push_front(frametime);
+        let current_fps =
+            self.frametimes.len() as f64 / self.frametimes.iter().sum::<Duration>().as_secs_f64();
+
+        if timestamp_ms.saturating_sub(self.target_fps_timer) >= TARGET_FPS_INTERVAL_MS {
+            self.target_fps_timer = timestamp_ms;
+            self.update_target_fps(timestamp_ms, current_fps);
+        }
+
+        if timestamp_ms.saturating_sub(self.util_timer) >= UTIL_INTERVAL_MS {
+            self.util_timer = timestamp_ms;
+            self.util_offset = step_target_fps_offset(self.util_offset, util_max);
+        }
+
+        let target_fps = self
+            .target_fps
+            .filter(|_| self.frametimes.len() >= MIN_FRAMES)?;
+        let target_fps_f64 = f64::from(target_fps);
+        self.thermal_offset =
+            step_thermal_offset(self.thermal_offset, temperature, self.core_temp_thresh);
+        let margin_fps = margin_fps(&self.margin, target_fps_f64);
+        let limited_target_fps = (target_fps_f64 + self.thermal_offset).clamp(0.0, target_fps_f64);
+        let adjusted_target_fps = limited_target_fps + self.util_offset - margin_fps;
+
+        let control = pid_control(&mut self.params, frametime.mul_f64(adjusted_target_fps));
+        let control = if control > 0 {
+            (control as f64 * self.boost) as isize
+        } else {
+            control
+        };
+
+        Some(Decision {
+            target_fps,
+            control,
+            janked: is_janked(current_fps, limited_target_fps),
+        })
+    }
+
+    // Same selection and lock-on as `Buffer::calculate_target_fps`
+    fn update_target_fps(&mut self, timestamp_ms: u64, current_fps: f64) {
+        let pacing_fps = pacing_fps(self.frametimes.iter().copied(), &mut self.sorted);
+        let mut new_target_fps =
+            pick_target_fps(&self.target_fps_config, None, current_fps, pacing_fps);
+
+        match (self.target_fps, new_target_fps) {
+            (Some(current), Some(new)) if current != new => match self.target_fps_switch {
+                Some((pending, since))
+                    if pending == new
+                        && timestamp_ms.saturating_sub(since) >= SWITCH_TIME.as_millis() as u64 =>
+                {
+                    self.target_fps_switch = None;
+                }
+                Some((pending, _)) if pending == new => new_target_fps = Some(current),
+                _ => {
+                    self.target_fps_switch = Some((new, timestamp_ms));
+                    new_target_fps = Some(current);
+                }
+            },
+            _ => self.target_fps_switch = None,
+        }
+
+        if new_target_fps != self.target_fps || new_target_fps.is_none() {
+            self.frametimes.clear();
+            self.target_fps = new_target_fps;
+        }
+    }
+}
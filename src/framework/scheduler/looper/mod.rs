@@ -17,34 +17,86 @@
 
 mod buffer;
 mod clean;
+mod control;
+mod engine;
+mod oem;
 mod policy;
+mod profile;
+mod quick;
+mod simulate;
+#[cfg(feature = "tuner")]
+mod tuner;
 
-use std::time::{Duration, Instant};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
 
-use frame_analyzer::Analyzer;
 use likely_stable::{likely, unlikely};
-#[cfg(debug_assertions)]
-use log::debug;
-use log::info;
-use policy::{ControllerParams, controll::calculate_control};
+use log::{debug, info, warn};
+use policy::{
+    ControllerParams,
+    adaptive::AdaptiveMargin,
+    bound::{Bound, BoundDetector, SATURATION_TIME},
+    controll::{calculate_control, jank_burst_due},
+};
 
-use super::{FasData, thermal::Thermal, topapp::TopAppsWatcher};
+use super::{
+    FasData, capture::Capture, display::Display, fps_detect::FpsDetector,
+    frame_source::FrameSource, input::TouchWatcher, power::Battery, psi::Psi, screen::Screen,
+    thermal::Thermal, topapp::TopAppsWatcher,
+};
+#[cfg(feature = "tuner")]
+use crate::framework::tuner::Tuner;
 use crate::{
-    Controller,
+    Controller, GpuController, GpuLoad,
     api::{trigger_load_fas, trigger_start_fas, trigger_stop_fas, trigger_unload_fas},
+    cpu_common::Cadence,
+    file_handler,
     framework::{
-        Extension,
+        Extension, ModePreset,
         config::Config,
+        control::Control,
+        decision_log::{DecisionEvent, DecisionLog},
         error::Result,
+        events::{Event, EventBus},
+        hooks,
+        logger::set_game_level,
+        memory::Memory,
+        metrics::Metrics,
+        mitigation::Mitigation,
         node::{Mode, Node},
+        notify::Notifier,
+        overlay::OverlayFeed,
         pid_utils::get_process_name,
+        recorder::{Recorder, Sample},
+        session::SessionTracker,
+        signal,
+        state::StateStore,
+        watchdog::Heartbeat,
     },
 };
 
 use buffer::{Buffer, BufferWorkingState};
 use clean::Cleaner;
+pub use engine::{Decision, Engine};
+use oem::OemState;
+use profile::ProfileState;
+use quick::QuickControl;
+pub use simulate::{SimulateOptions, simulate};
 
 const DELAY_TIME: Duration = Duration::from_secs(3);
+const SCREEN_OFF_SLEEP: Duration = Duration::from_secs(1);
+const CRITICAL_SLEEP: Duration = Duration::from_secs(1);
+const MAX_RENDER_THREADS: usize = 8;
+// How long the buffer waits for a game whose process died to come back under another pid
+const RESPAWN_WAIT: Duration = Duration::from_secs(5);
+const SATURATION_RECOVERY: Duration = Duration::from_secs(30);
+// Mode, battery, config and top app bookkeeping between frames, stretched towards
+// `max_sample_interval` while the game runs steadily
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_millis(100);
+// Short and long fps further apart than this mean the frametimes are moving
+const STEADY_FPS_DELTA: f64 = 2.0;
 
 #[derive(PartialEq)]
 enum State {
@@ -55,22 +107,43 @@ enum State {
 
 struct FasState {
     mode: Mode,
+    game_mode: Option<Mode>,
+    battery_powersave: bool,
     working_state: State,
     delay_timer: Instant,
     buffer: Option<Buffer>,
+    // Other configured games visible next to the buffer's, with their per-game modes
+    extra_games: Vec<(i32, Option<Mode>)>,
+    // Buffer and top app pids the extra games were last looked up for
+    extra_games_key: (Option<i32>, Vec<i32>),
+    // Since when the buffer's process is gone without a successor
+    respawn_wait: Option<Instant>,
 }
 
 struct AnalyzerState {
-    analyzer: Analyzer,
+    analyzer: FrameSource,
     restart_counter: u8,
     restart_timer: Instant,
 }
 
 struct ControllerState {
     controller: Controller,
+    gpu_controller: Option<GpuController>,
+    gpu_load: Option<GpuLoad>,
+    bound: BoundDetector,
     params: ControllerParams,
+    adaptive: AdaptiveMargin,
     target_fps_offset: f64,
+    // Extra margin while the screen is being recorded
+    capture_margin: f64,
     usage_sample_timer: Instant,
+    // Last time the saturation fps cap moved
+    fps_cap_timer: Instant,
+    // Last thermal cap and jank state published, events are sent on changes only
+    freq_cap: f64,
+    janked: bool,
+    // A frame since the last housekeeping was janked or off the average
+    unsteady: bool,
 }
 
 pub struct Looper {
@@ -78,21 +151,56 @@ pub struct Looper {
     config: Config,
     node: Node,
     extension: Extension,
+    control: Control,
     therminal: Thermal,
+    battery: Battery,
+    screen: Screen,
+    capture: Capture,
+    display: Display,
     windows_watcher: TopAppsWatcher,
+    fps_detector: FpsDetector,
+    touch_watcher: Option<TouchWatcher>,
+    touch_boost: bool,
+    psi: Psi,
+    recorder: Recorder,
+    session: SessionTracker,
+    metrics: Metrics,
+    events: EventBus,
+    notifier: Notifier,
+    overlay: OverlayFeed,
+    decision_log: DecisionLog,
+    state: StateStore,
+    // Game whose learned state is being updated
+    learning: Option<String>,
+    #[cfg(feature = "tuner")]
+    tuner: Tuner,
     cleaner: Cleaner,
+    mitigation: Mitigation,
+    memory: Memory,
+    profile_state: ProfileState,
+    quick_control: QuickControl,
+    oem_state: OemState,
+    housekeeping: Cadence,
+    housekeeping_timer: Instant,
     fas_state: FasState,
     controller_state: ControllerState,
+    heartbeat: Heartbeat,
 }
 
 impl Looper {
     pub fn new(
-        analyzer: Analyzer,
+        analyzer: FrameSource,
         config: Config,
         node: Node,
         extension: Extension,
+        control: Control,
         controller: Controller,
+        gpu_controller: Option<GpuController>,
+        heartbeat: Heartbeat,
     ) -> Self {
+        let events = EventBus::new();
+        hooks::spawn(&events);
+
         Self {
             analyzer_state: AnalyzerState {
                 analyzer,
@@ -102,31 +210,115 @@ impl Looper {
             config,
             node,
             extension,
+            control,
             therminal: Thermal::new().unwrap(),
+            battery: Battery::new(),
+            screen: Screen::new(),
+            capture: Capture::new(),
+            display: Display::new(),
             windows_watcher: TopAppsWatcher::new(),
+            fps_detector: FpsDetector::new(),
+            touch_watcher: TouchWatcher::new()
+                .map_err(|e| warn!("Touch boost unavailable: {e}"))
+                .ok(),
+            touch_boost: false,
+            psi: Psi::new(),
+            recorder: Recorder::new(),
+            session: SessionTracker::new(),
+            metrics: Metrics::new(),
+            notifier: Notifier::new(&events),
+            events,
+            overlay: OverlayFeed::new(),
+            decision_log: DecisionLog::new(),
+            state: StateStore::load(),
+            learning: None,
+            #[cfg(feature = "tuner")]
+            tuner: Tuner::new(),
             cleaner: Cleaner::new(),
+            mitigation: Mitigation::new(),
+            memory: Memory::new(),
+            profile_state: ProfileState::new(),
+            quick_control: QuickControl::new(),
+            oem_state: OemState::new(),
+            housekeeping: Cadence::new(HOUSEKEEPING_INTERVAL, HOUSEKEEPING_INTERVAL),
+            housekeeping_timer: Instant::now(),
             fas_state: FasState {
                 mode: Mode::Balance,
+                game_mode: None,
+                battery_powersave: false,
                 buffer: None,
+                extra_games: Vec::new(),
+                extra_games_key: (None, Vec::new()),
+                respawn_wait: None,
                 working_state: State::NotWorking,
                 delay_timer: Instant::now(),
             },
             controller_state: ControllerState {
                 controller,
+                gpu_controller,
+                gpu_load: GpuLoad::new(),
+                bound: BoundDetector::new(),
                 params: ControllerParams::default(),
+                adaptive: AdaptiveMargin::new(),
                 target_fps_offset: 0.0,
+                capture_margin: 0.0,
                 usage_sample_timer: Instant::now(),
+                fps_cap_timer: Instant::now(),
+                freq_cap: 1.0,
+                janked: false,
+                unsteady: false,
             },
+            heartbeat,
         }
     }
 
     pub fn enter_loop(&mut self) -> Result<()> {
         loop {
-            self.switch_mode();
-            let _ = self.update_analyzer();
+            self.heartbeat.beat();
+
+            if signal::terminated() {
+                self.heartbeat.idle();
+                self.shutdown();
+                return Ok(());
+            }
+
+            self.handle_control();
+
+            if self.therminal.critical(&mut self.config) {
+                self.cool_down();
+                thread::sleep(CRITICAL_SLEEP);
+                continue;
+            }
+
+            if self.screen.is_off() {
+                self.park();
+                thread::sleep(SCREEN_OFF_SLEEP);
+                continue;
+            }
+
+            // `fas-rs.ctl.enabled=false` lets go of the game as if the screen was off
+            self.poll_quick_control();
+            if !self.quick_control.enabled() {
+                self.park();
+                self.controller_state.controller.cancel_launch_boost();
+                thread::sleep(SCREEN_OFF_SLEEP);
+                continue;
+            }
+
+            self.housekeeping();
+            self.poll_profile_prop();
+            self.touch_boost();
+            self.psi_boost();
+            self.memory.reassert();
+            self.launch_boost_update();
+            #[cfg(feature = "tuner")]
+            self.handle_tuner();
             self.retain_topapp();
 
-            if self.windows_watcher.visible_freeform_window() {
+            // A floating window of another game is scheduled along, anything else stops fas
+            if self.windows_watcher.visible_freeform_window()
+                && self.fas_state.extra_games.is_empty()
+            {
                 self.disable_fas();
             }
 
@@ -139,7 +331,12 @@ impl Looper {
                         BufferWorkingState::Unusable => self.disable_fas(),
                     }
                 }
-            } else if let Some(buffer) = self.fas_state.buffer.as_mut() {
+            } else if let Some(buffer) = self
+                .fas_state
+                .buffer
+                .as_mut()
+                .filter(|_| self.fas_state.respawn_wait.is_none())
+            {
                 #[cfg(debug_assertions)]
                 debug!("janked !");
                 buffer.additional_frametime(&self.extension);
@@ -155,22 +352,352 @@ impl Looper {
         }
     }
 
+    // Nothing in here has to react within a frame. While frametimes and util_max hold still it
+    // runs less and less often, down to once per `max_sample_interval`
+    fn housekeeping(&mut self) {
+        if self.housekeeping_timer.elapsed() < self.housekeeping.interval() {
+            return;
+        }
+        self.housekeeping_timer = Instant::now();
+
+        let steady =
+            self.fas_state.working_state == State::Working && !self.controller_state.unsteady;
+        self.controller_state.unsteady = false;
+        self.housekeeping.settle(steady);
+
+        self.poll_oem();
+        self.switch_mode();
+        self.battery_update();
+        self.capture_update();
+        self.metrics.set_port(self.config.config().metrics_port);
+        file_handler::set_reassert_interval(Duration::from_millis(
+            self.config.config().reassert_interval,
+        ));
+        self.notifier.set_enabled(self.config.config().broadcast);
+        self.overlay.set_enabled(self.config.config().overlay_feed);
+        self.windows_watcher
+            .set_focus_source(self.config.config().focus_source);
+        let _ = self.update_analyzer();
+        self.extra_games_update();
+        self.display_update();
+    }
+
     fn switch_mode(&mut self) {
         if let Ok(new_mode) = self.node.get_mode() {
             if likely(self.fas_state.mode != new_mode) {
-                info!("Switch mode: {} -> {}", self.fas_state.mode, new_mode);
+                info!(
+                    event_type = "mode_switch",
+                    from:% = self.fas_state.mode,
+                    to:% = new_mode;
+                    "Switch mode: {} -> {}",
+                    self.fas_state.mode,
+                    new_mode
+                );
+                self.events.publish(&Event::ModeChanged {
+                    from: self.fas_state.mode,
+                    to: new_mode,
+                });
                 self.fas_state.mode = new_mode;
 
                 if self.fas_state.working_state == State::Working {
-                    self.controller_state.controller.init_game(
-                        self.fas_state.buffer.as_ref().unwrap().package_info.pid,
-                        &self.extension,
-                    );
+                    self.init_game();
                 }
             }
         }
     }
 
+    fn battery_update(&mut self) {
+        let powersave = self.battery.powersave(self.config.battery_config());
+
+        if self.fas_state.battery_powersave != powersave {
+            self.fas_state.battery_powersave = powersave;
+
+            if self.fas_state.working_state == State::Working {
+                self.init_game();
+            }
+        }
+    }
+
+    fn display_update(&mut self) {
+        self.display
+            .set_track_panel(self.config.config().vrr_tracking);
+        let refresh_rate = self.display.refresh_rate();
+
+        if let Some(buffer) = self.fas_state.buffer.as_mut() {
+            buffer.set_refresh_rate(refresh_rate);
+        }
+    }
+
+    fn current_mode(&self) -> Mode {
+        if self.fas_state.battery_powersave {
+            return Mode::Powersave;
+        }
+
+        let mode = self.fas_state.game_mode.unwrap_or(self.fas_state.mode);
+
+        // With several games visible the heaviest mode wins
+        self.fas_state
+            .extra_games
+            .iter()
+            .map(|(_, mode)| mode.unwrap_or(self.fas_state.mode))
+            .fold(mode, Mode::max)
+    }
+
+    // Split screen and floating windows can show several configured games at once. The buffer's
+    // game drives the frame pacing, the others add their threads to util_max and their modes
+    fn extra_games_update(&mut self) {
+        let primary = self
+            .fas_state
+            .buffer
+            .as_ref()
+            .map(|buffer| buffer.package_info.pid);
+        let topapp_pids = self.windows_watcher.topapp_pids().clone();
+        if self.fas_state.extra_games_key.0 == primary
+            && self.fas_state.extra_games_key.1 == topapp_pids
+        {
+            return;
+        }
+
+        let extra_games: Vec<_> = match primary {
+            Some(primary) => topapp_pids
+                .iter()
+                .copied()
+                .filter(|pid| *pid != primary)
+                .filter_map(|pid| {
+                    let pkg = get_process_name(pid).ok()?;
+                    (self.config.boost_allowed(&pkg) && self.config.need_fas(&pkg))
+                        .then(|| (pid, self.config.game_mode(&pkg)))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        self.fas_state.extra_games_key = (primary, topapp_pids);
+
+        if extra_games == self.fas_state.extra_games {
+            return;
+        }
+
+        if !extra_games.is_empty() {
+            info!(
+                "Scheduling {} more visible games along: {:?}",
+                extra_games.len(),
+                extra_games.iter().map(|(pid, _)| pid).collect::<Vec<_>>()
+            );
+        }
+
+        let mode = self.current_mode();
+        self.fas_state.extra_games = extra_games;
+
+        if self.fas_state.working_state == State::Working {
+            self.controller_state
+                .controller
+                .set_extra_pids(self.extra_game_pids());
+            if self.current_mode() != mode {
+                self.init_game();
+            }
+        }
+    }
+
+    fn extra_game_pids(&self) -> Vec<i32> {
+        self.fas_state
+            .extra_games
+            .iter()
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
+    fn init_game(&mut self) {
+        self.learn();
+
+        let package_info = &self.fas_state.buffer.as_ref().unwrap().package_info;
+        let governor = self.config.governor(&package_info.pkg);
+
+        self.touch_boost = self.config.touch_boost(&package_info.pkg);
+        let log_config = self.config.log_config();
+        set_game_level(log_config.games.get(&package_info.pkg).copied());
+        self.decision_log.set_capacity(log_config.ring_size);
+        let cluster_policies = self.config.cluster_policies(&package_info.pkg);
+        self.controller_state
+            .controller
+            .set_cluster_policies(&cluster_policies);
+
+        if self.config.record(&package_info.pkg) {
+            self.recorder.start(&package_info.pkg);
+        } else {
+            self.recorder.stop();
+        }
+
+        self.fas_state.game_mode = self.config.game_mode(&package_info.pkg);
+        if let Some(mode) = self.fas_state.game_mode {
+            info!("Using per-game mode: {mode}");
+        }
+        let mode_preset = ModePreset::new(self.current_mode());
+        self.controller_state
+            .controller
+            .set_mode_preset(mode_preset);
+        self.controller_state.controller.set_uclamp_boost(
+            self.config.config().uclamp_boost,
+            self.config.config().uclamp_scope,
+        );
+        self.controller_state
+            .controller
+            .set_bus_boost(self.config.config().bus_boost);
+        self.controller_state
+            .controller
+            .set_freq_backend(self.config.config().freq_backend);
+        self.controller_state
+            .controller
+            .set_limit_guard(self.config.config().limit_guard);
+        self.controller_state
+            .controller
+            .set_pin_big_cores(self.config.pin_big_cores(&package_info.pkg));
+        self.controller_state
+            .controller
+            .set_shallow_idle(self.config.shallow_idle(&package_info.pkg));
+        self.controller_state
+            .controller
+            .set_render_priority(self.config.render_priority(&package_info.pkg));
+        self.controller_state
+            .controller
+            .set_thread_affinity(self.config.thread_affinity(&package_info.pkg));
+        self.controller_state.controller.set_strategy(
+            self.config.strategy(&package_info.pkg),
+            self.config.ladder(&package_info.pkg),
+        );
+        self.controller_state
+            .controller
+            .set_governor_tunables(self.config.governor_tunables());
+        let irq = self.config.irq_config();
+        self.controller_state
+            .controller
+            .set_irq_steering(irq.patterns, irq.cpus);
+        self.controller_state
+            .controller
+            .set_battery_powersave(self.fas_state.battery_powersave);
+        self.controller_state.params =
+            ControllerParams::new(self.config.pid_gains(&package_info.pkg));
+        self.controller_state.bound.reset();
+        let streaming = self.config.streaming(&package_info.pkg);
+        self.controller_state
+            .bound
+            .set_streaming(streaming.is_some());
+        self.controller_state.controller.set_stream_threads(
+            streaming
+                .map(|streaming| streaming.threads)
+                .unwrap_or_default(),
+        );
+        let learned = self.state.game(&package_info.pkg);
+        self.controller_state.adaptive.start(
+            &package_info.pkg,
+            self.config.adaptive_margin(&package_info.pkg),
+            learned.adaptive_margin,
+        );
+        let monitor = self.config.monitor_config(&package_info.pkg);
+        self.housekeeping.set_bounds(
+            HOUSEKEEPING_INTERVAL,
+            Duration::from_millis(monitor.max_sample_interval),
+        );
+        self.controller_state.controller.set_monitor_params(
+            Duration::from_millis(monitor.sample_interval),
+            Duration::from_millis(monitor.max_sample_interval),
+            Duration::from_millis(monitor.refresh_interval),
+            monitor.top_threads,
+            monitor.runqueue_weight,
+        );
+        self.controller_state.controller.set_util_smoothing(
+            monitor.util_smoothing,
+            monitor.util_ewma_alpha,
+            monitor.util_median_window,
+        );
+
+        self.controller_state
+            .controller
+            .set_known_threads(learned.render_threads);
+
+        self.controller_state.controller.init_game(
+            package_info.pid,
+            governor.as_deref(),
+            &self.extension,
+        );
+        self.controller_state.controller.seed_util_max(learned.util);
+        self.controller_state
+            .controller
+            .set_extra_pids(self.extra_game_pids());
+        self.learning = Some(package_info.pkg.clone());
+
+        if let Some(gpu_controller) = self.controller_state.gpu_controller.as_mut() {
+            gpu_controller.init_game();
+        }
+
+        let refresh_rate = self.config.refresh_rate(&package_info.pkg);
+        self.display.request_refresh_rate(refresh_rate);
+        self.display_update();
+    }
+
+    fn touch_boost(&mut self) {
+        let Some(touch_watcher) = &self.touch_watcher else {
+            return;
+        };
+
+        if touch_watcher.take_touched()
+            && self.touch_boost
+            && self.fas_state.working_state == State::Working
+        {
+            let config = self.config.config();
+            self.controller_state.controller.touch_boost(
+                Duration::from_millis(config.touch_boost_duration),
+                config.touch_boost_ratio,
+            );
+        }
+    }
+
+    // Slow frames boost the render thread's cluster right away instead of waiting for the
+    // control loop
+    fn jank_burst(&mut self) {
+        let config = self.config.config();
+        let Some(buffer) = &self.fas_state.buffer else {
+            return;
+        };
+        let Some(target_fps) = buffer.target_fps_state.target_fps else {
+            return;
+        };
+
+        if jank_burst_due(
+            &buffer.frametime_state.frametimes,
+            target_fps,
+            config.jank_burst_threshold,
+        ) {
+            self.controller_state
+                .controller
+                .jank_burst(Duration::from_millis(config.jank_burst_duration));
+        }
+    }
+
+    fn launch_boost_update(&mut self) {
+        if self.controller_state.controller.launch_boost_expired()
+            && self.fas_state.working_state != State::Working
+        {
+            self.controller_state
+                .controller
+                .init_default(&self.extension);
+        }
+    }
+
+    fn psi_boost(&mut self) {
+        if self.fas_state.working_state != State::Working {
+            return;
+        }
+
+        let config = self.config.psi_config();
+        if self.psi.stalled(&config) {
+            debug!("psi boost: {:?}", self.psi.stat());
+            self.controller_state.controller.touch_boost(
+                Duration::from_millis(config.boost_duration),
+                config.boost_ratio,
+            );
+        }
+    }
+
     fn recv_message(&mut self) -> Option<FasData> {
         self.analyzer_state
             .analyzer
@@ -181,7 +708,11 @@ impl Looper {
     fn update_analyzer(&mut self) -> Result<()> {
         for pid in self.windows_watcher.topapp_pids().iter().copied() {
             let pkg = get_process_name(pid)?;
-            if self.config.need_fas(&pkg) {
+            if !self.config.boost_allowed(&pkg) {
+                continue;
+            }
+
+            if self.config.need_fas(&pkg) || self.config.config().auto_target_fps {
                 self.analyzer_state.analyzer.attach_app(pid)?;
             }
         }
@@ -208,14 +739,21 @@ impl Looper {
             return;
         }
 
+        let mode = self.current_mode();
         let (control, is_janked) = if let Some(buffer) = &self.fas_state.buffer {
             let target_fps_offset = self
                 .therminal
-                .target_fps_offset(&mut self.config, self.fas_state.mode);
+                .target_fps_offset(&mut self.config, mode);
+            let freq_cap = self.therminal.freq_cap(&mut self.config);
+            self.controller_state.controller.set_freq_cap(freq_cap);
+            if (self.controller_state.freq_cap - freq_cap).abs() > f64::EPSILON {
+                self.controller_state.freq_cap = freq_cap;
+                self.events.publish(&Event::ThermalEvent { freq_cap });
+            }
             calculate_control(
                 buffer,
                 &mut self.config,
-                self.fas_state.mode,
+                mode,
                 &mut self.controller_state,
                 target_fps_offset,
             )
@@ -224,29 +762,172 @@ impl Looper {
             return;
         };
 
-        #[cfg(debug_assertions)]
-        debug!("control: {control}khz");
+        let gpu_busy = self
+            .controller_state
+            .gpu_load
+            .as_mut()
+            .and_then(GpuLoad::sample);
+        let bound =
+            if self.config.config().bound_detection || self.controller_state.bound.streaming() {
+                self.controller_state.bound.update(
+                    self.controller_state.controller.util_max(),
+                    gpu_busy,
+                    self.controller_state.controller.at_ceiling(),
+                )
+            } else {
+                Bound::Unknown
+            };
+        let (cpu_control, gpu_control) = bound.split_control(control);
+        self.saturation_fallback(bound);
+
+        if let Some(buffer) = &self.fas_state.buffer {
+            debug!(
+                event_type = "control",
+                game = buffer.package_info.pkg.as_str(),
+                pid = buffer.package_info.pid,
+                target_fps = buffer.target_fps_state.target_fps.unwrap_or_default(),
+                control = control,
+                janked = is_janked,
+                util = self.controller_state.controller.util_max(),
+                util_trend = self
+                    .controller_state
+                    .controller
+                    .util_history()
+                    .trend()
+                    .unwrap_or_default(),
+                gpu_busy = gpu_busy.unwrap_or_default(),
+                bound:% = bound;
+                "control: {control}khz"
+            );
+
+            if is_janked && !self.controller_state.janked {
+                self.events.publish(&Event::JankDetected {
+                    pkg: buffer.package_info.pkg.clone(),
+                    frametime: buffer
+                        .frametime_state
+                        .frametimes
+                        .front()
+                        .copied()
+                        .unwrap_or_default(),
+                });
+            }
+            self.controller_state.janked = is_janked;
+            let fps = &buffer.frametime_state;
+            if is_janked || (fps.current_fps_short - fps.current_fps_long).abs() > STEADY_FPS_DELTA
+            {
+                self.controller_state.unsteady = true;
+                self.housekeeping.settle(false);
+            }
+
+            if self.decision_log.enabled() {
+                self.decision_log.push(DecisionEvent {
+                    timestamp_ms: 0,
+                    pkg: buffer.package_info.pkg.clone(),
+                    pid: buffer.package_info.pid,
+                    target_fps: buffer.target_fps_state.target_fps,
+                    control,
+                    janked: is_janked,
+                    util: self.controller_state.controller.util_max(),
+                    gpu_busy,
+                    bound: bound.to_string(),
+                });
+            }
+        }
 
+        if bound.boosts_cpu() {
+            self.jank_burst();
+        }
         self.controller_state
             .controller
-            .fas_update_freq(control, is_janked);
+            .fas_update_freq(cpu_control, is_janked && bound.boosts_cpu());
+
+        if let Some(gpu_controller) = self.controller_state.gpu_controller.as_mut() {
+            if self.config.config().gpu_control {
+                gpu_controller.fas_update_freq(
+                    gpu_control,
+                    is_janked && bound.boosts_gpu(),
+                    self.controller_state.controller.max_freq(),
+                    gpu_busy,
+                );
+            } else {
+                gpu_controller.init_default();
+            }
+        }
+
+        if let Some(buffer) = &self.fas_state.buffer {
+            self.recorder.record(&Sample {
+                frametime: buffer
+                    .frametime_state
+                    .frametimes
+                    .front()
+                    .copied()
+                    .unwrap_or_default(),
+                util_max: self.controller_state.controller.util_max(),
+                freq: self.controller_state.controller.cur_fas_freq_max(),
+                temperature: self.therminal.core_temperature(),
+            });
+            let mode = self.current_mode();
+            self.session
+                .sample(self.controller_state.controller.cur_fas_freq_max(), mode);
+            self.metrics.update(
+                &buffer.package_info.pkg,
+                buffer.frametime_state.current_fps_long,
+                &buffer.frametime_state.frametimes,
+                self.controller_state.controller.util_max(),
+            );
+        }
+
+        if self.state.due() {
+            self.learn();
+            self.state.save();
+        }
+
+        #[cfg(feature = "tuner")]
+        self.tuner_sample();
+    }
+
+    // Hands what the control loop learned about the current game to the state store
+    fn learn(&mut self) {
+        let Some(pkg) = &self.learning else {
+            return;
+        };
+
+        let controller_state = &self.controller_state;
+        let util = controller_state.controller.util_max();
+        let render_threads = controller_state.controller.render_thread_names();
+
+        self.state.update(pkg, |game| {
+            if let Some(offset) = controller_state.adaptive.learned() {
+                game.adaptive_margin = Some(offset);
+            }
+            game.target_fps_offset = controller_state.target_fps_offset;
+            game.observe_util(util);
+
+            for name in render_threads {
+                if !game.render_threads.contains(&name) {
+                    game.render_threads.push(name);
+                }
+            }
+            let excess = game.render_threads.len().saturating_sub(MAX_RENDER_THREADS);
+            game.render_threads.drain(..excess);
+        });
     }
 
     pub fn retain_topapp(&mut self) {
-        if let Some(buffer) = self.fas_state.buffer.as_ref() {
-            if !self
+        let lost = self.fas_state.buffer.as_ref().is_some_and(|buffer| {
+            !self
                 .windows_watcher
                 .topapp_pids()
                 .contains(&buffer.package_info.pid)
-            {
-                let _ = self
-                    .analyzer_state
-                    .analyzer
-                    .detach_app(buffer.package_info.pid);
-                let pkg = buffer.package_info.pkg.clone();
-                trigger_unload_fas(&self.extension, buffer.package_info.pid, pkg);
-                self.fas_state.buffer = None;
+        });
+
+        if lost && !self.follow_respawn() {
+            if let Some(buffer) = self.fas_state.buffer.take() {
+                let pid = buffer.package_info.pid;
+                let _ = self.analyzer_state.analyzer.detach_app(pid);
+                trigger_unload_fas(&self.extension, pid, buffer.package_info.pkg);
             }
+            self.fas_state.respawn_wait = None;
         }
 
         if self.fas_state.buffer.is_none() {
@@ -256,17 +937,170 @@ impl Looper {
         }
     }
 
+    // Some games kill and restart their main process between loading and gameplay. Once the
+    // buffer's process is gone, a top app process of the same name takes over the buffer and the
+    // controller without tearing the game down. Returns whether the buffer is kept
+    fn follow_respawn(&mut self) -> bool {
+        let Some(buffer) = self.fas_state.buffer.as_ref() else {
+            return false;
+        };
+        let pid = buffer.package_info.pid;
+        let pkg = buffer.package_info.pkg.clone();
+
+        // Still running, the game just left the foreground
+        if get_process_name(pid).is_ok_and(|name| name == pkg) {
+            return false;
+        }
+
+        let respawned = self
+            .windows_watcher
+            .topapp_pids()
+            .iter()
+            .copied()
+            .find(|new_pid| get_process_name(*new_pid).is_ok_and(|name| name == pkg));
+
+        match respawned {
+            Some(new_pid) => {
+                self.rebind_game(new_pid);
+                true
+            }
+            None => {
+                let since = *self.fas_state.respawn_wait.get_or_insert_with(|| {
+                    info!("Process {pid} of [{pkg}] exited, waiting for it to come back");
+                    Instant::now()
+                });
+                since.elapsed() < RESPAWN_WAIT
+            }
+        }
+    }
+
+    fn rebind_game(&mut self, pid: i32) {
+        let Some(buffer) = self.fas_state.buffer.as_mut() else {
+            return;
+        };
+        let old_pid = buffer.package_info.pid;
+        let pkg = buffer.package_info.pkg.clone();
+
+        info!(
+            event_type = "game_respawn",
+            game = pkg.as_str(),
+            pid = pid;
+            "[{pkg}] restarted its process: {old_pid} -> {pid}"
+        );
+
+        let _ = self.analyzer_state.analyzer.detach_app(old_pid);
+        trigger_unload_fas(&self.extension, old_pid, pkg.clone());
+        buffer.rebind(pid);
+        trigger_load_fas(&self.extension, pid, pkg);
+        self.fas_state.respawn_wait = None;
+
+        if self.fas_state.working_state == State::Working {
+            self.controller_state.controller.rebind_game(pid);
+            self.memory.rebind(pid);
+            self.controller_state
+                .controller
+                .set_extra_pids(self.extra_game_pids());
+        }
+    }
+
+    // With `saturation_drop_fps` a game the cpu cannot keep up with even at its ceiling gets the
+    // next lower target instead of missing the current one. The cap is lifted SATURATION_RECOVERY
+    // after it last moved unless the game is still saturated, and set again if it saturates anew
+    fn saturation_fallback(&mut self, bound: Bound) {
+        let Some(buffer) = self.fas_state.buffer.as_mut() else {
+            return;
+        };
+        let timer = &mut self.controller_state.fps_cap_timer;
+
+        if !self.config.config().saturation_drop_fps {
+            buffer.set_fps_cap(None);
+            return;
+        }
+
+        if bound == Bound::Saturated {
+            if timer.elapsed() < SATURATION_TIME {
+                return;
+            }
+            if let Some(lower) = buffer.lower_target_fps() {
+                info!(
+                    "[{}] cpu saturated at its ceiling, target fps capped at {lower}",
+                    buffer.package_info.pkg
+                );
+                buffer.set_fps_cap(Some(lower));
+            }
+            *timer = Instant::now();
+        } else if buffer.fps_cap().is_some() && timer.elapsed() >= SATURATION_RECOVERY {
+            info!(
+                "[{}] no longer saturated, target fps uncapped",
+                buffer.package_info.pkg
+            );
+            buffer.set_fps_cap(None);
+            *timer = Instant::now();
+        }
+    }
+
+    // Forget the game while the screen is off, it is picked up again from the top apps afterwards
+    fn park(&mut self) {
+        if let Some(buffer) = self.fas_state.buffer.take() {
+            let pid = buffer.package_info.pid;
+            let _ = self.analyzer_state.analyzer.detach_app(pid);
+            trigger_unload_fas(&self.extension, pid, buffer.package_info.pkg);
+        }
+
+        self.disable_fas();
+    }
+
+    // Above the critical temperature nothing of fas-rs may keep the device hot, a launch boost
+    // pinning the max frequency included
+    fn cool_down(&mut self) {
+        self.disable_fas();
+        self.controller_state.controller.cancel_launch_boost();
+    }
+
+    // Hand every node we touched back to the system before the process exits
+    fn shutdown(&mut self) {
+        info!("Received termination signal, restoring defaults");
+        self.disable_fas();
+    }
+
     pub fn disable_fas(&mut self) {
         match self.fas_state.working_state {
             State::Working => {
                 self.fas_state.working_state = State::NotWorking;
+                self.recorder.stop();
+                self.session.finish();
+                self.metrics.clear();
+                self.overlay.clear();
+                self.events.publish(&Event::GameStopped {
+                    pkg: self.learning.clone(),
+                    pid: self
+                        .fas_state
+                        .buffer
+                        .as_ref()
+                        .map(|buffer| buffer.package_info.pid),
+                });
+                self.controller_state.janked = false;
+                self.learn();
+                self.learning = None;
+                self.state.save();
+                set_game_level(None);
+                self.controller_state.adaptive.stop();
+                self.display.request_refresh_rate(None);
                 self.cleaner.undo_cleanup();
+                self.mitigation.restore();
+                self.memory.restore();
                 self.controller_state
                     .controller
                     .init_default(&self.extension);
+                if let Some(gpu_controller) = self.controller_state.gpu_controller.as_mut() {
+                    gpu_controller.init_default();
+                }
                 trigger_stop_fas(&self.extension);
             }
-            State::Waiting => self.fas_state.working_state = State::NotWorking,
+            State::Waiting => {
+                self.fas_state.working_state = State::NotWorking;
+                self.controller_state.controller.cancel_launch_boost();
+            }
             State::NotWorking => (),
         }
     }
@@ -282,11 +1116,26 @@ impl Looper {
                 if self.fas_state.delay_timer.elapsed() > DELAY_TIME {
                     self.fas_state.working_state = State::Working;
                     self.cleaner.cleanup();
+                    self.mitigation.engage(&self.config.mitigation_config());
                     self.controller_state.target_fps_offset = 0.0;
-                    self.controller_state.controller.init_game(
-                        self.fas_state.buffer.as_ref().unwrap().package_info.pid,
-                        &self.extension,
-                    );
+                    if let Some(buffer) = &self.fas_state.buffer {
+                        self.session.start(&buffer.package_info.pkg);
+                        self.controller_state.target_fps_offset =
+                            self.state.game(&buffer.package_info.pkg).target_fps_offset;
+                        if self.config.protect_memory(&buffer.package_info.pkg) {
+                            self.memory
+                                .engage(&self.config.memory_config(), buffer.package_info.pid);
+                        }
+                    }
+                    self.init_game();
+                    if let Some(buffer) = &self.fas_state.buffer {
+                        self.events.publish(&Event::GameStarted {
+                            pkg: buffer.package_info.pkg.clone(),
+                            pid: buffer.package_info.pid,
+                            target_fps: buffer.target_fps_state.target_fps,
+                            mode: self.current_mode(),
+                        });
+                    }
                 }
             }
             State::Working => (),
@@ -304,18 +1153,55 @@ impl Looper {
         let frametime = data.frametime;
 
         if let Some(buffer) = self.fas_state.buffer.as_mut() {
+            // Frames of extra games must not mix into the buffer's frame pacing
+            if buffer.package_info.pid != pid {
+                return None;
+            }
+
             buffer.push_frametime(frametime, &self.extension);
+            if self.fas_state.working_state == State::Working {
+                self.session.frame(frametime);
+                self.overlay.push_frame(
+                    &buffer.package_info.pkg,
+                    pid,
+                    buffer.target_fps_state.target_fps,
+                    buffer.frametime_state.current_fps_short,
+                    buffer.frametime_state.current_fps_long,
+                    frametime,
+                );
+            }
             Some(buffer.state.working_state)
         } else {
             let Ok(pkg) = get_process_name(data.pid) else {
                 return None;
             };
-            let target_fps = self.config.target_fps(&pkg)?;
+            if !self.config.boost_allowed(&pkg) {
+                return None;
+            }
+            let target_fps = match self.config.target_fps(&pkg) {
+                Some(target_fps) => target_fps,
+                None if self.config.config().auto_target_fps => self.fps_detector.target_fps(),
+                None => return None,
+            };
 
-            info!("New fas buffer on: [{pkg}]");
+            info!(
+                event_type = "game_load",
+                game = pkg.as_str(),
+                pid = pid;
+                "New fas buffer on: [{pkg}]"
+            );
 
             trigger_load_fas(&self.extension, pid, pkg.clone());
 
+            if let Some(duration) = self
+                .config
+                .launch_boost(&pkg)
+                .filter(|_| !self.therminal.cooling_down())
+            {
+                info!("Launch boost for {}ms", duration.as_millis());
+                self.controller_state.controller.launch_boost(duration);
+            }
+
             let mut buffer = Buffer::new(target_fps, pid, pkg);
             buffer.push_frametime(frametime, &self.extension);
 
@@ -0,0 +1,106 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    process::Command,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use super::Looper;
+use crate::{framework::node::Mode, misc::getprop};
+
+// Every setting is a `settings` process, the OEM UI is not touched that often
+const OEM_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Last seen value of every prop and setting in `[oem]`
+pub struct OemState {
+    values: HashMap<String, String>,
+    poll_timer: Instant,
+}
+
+impl OemState {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            poll_timer: Instant::now(),
+        }
+    }
+}
+
+impl Looper {
+    // The choice made in an OEM game space becomes the fas-rs mode, so the two do not pull in
+    // different directions. Like `fas-rs.ctl.mode` a value only acts when it changes, a mode
+    // switched elsewhere afterwards sticks until the user picks something in the OEM UI again
+    pub fn poll_oem(&mut self) {
+        if self.oem_state.poll_timer.elapsed() < OEM_POLL_INTERVAL {
+            return;
+        }
+        self.oem_state.poll_timer = Instant::now();
+
+        let config = self.config.oem_config();
+        let props = config
+            .props
+            .iter()
+            .map(|(prop, modes)| (prop, getprop(prop).filter(|value| !value.is_empty()), modes));
+        let settings = config
+            .settings
+            .iter()
+            .map(|(setting, modes)| (setting, get_setting(setting), modes));
+
+        for (source, value, modes) in props.chain(settings) {
+            let Some(value) = value else {
+                self.oem_state.values.remove(source);
+                continue;
+            };
+            if self.oem_state.values.get(source) == Some(&value) {
+                continue;
+            }
+
+            let mode = modes.get(&value);
+            self.oem_state.values.insert(source.clone(), value.clone());
+            let Some(mode) = mode else {
+                continue;
+            };
+
+            match Mode::from_str(mode) {
+                Ok(mode) => {
+                    info!("OEM game space {source}={value}: switching to {mode}");
+                    if let Err(e) = self.node.create_node("mode", mode.to_string().as_str()) {
+                        warn!("Failed to switch mode from {source}: {e}");
+                    }
+                }
+                Err(e) => warn!("Ignoring mode \"{mode}\" of {source}={value}: {e}"),
+            }
+        }
+    }
+}
+
+// `<namespace>/<key>`, None for a malformed source or an unset setting
+fn get_setting(source: &str) -> Option<String> {
+    let (namespace, key) = source.split_once('/')?;
+    let output = Command::new("settings")
+        .args(["get", namespace, key])
+        .output()
+        .ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    (output.status.success() && !value.is_empty() && value != "null").then_some(value)
+}
@@ -0,0 +1,178 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+#[cfg(debug_assertions)]
+use log::debug;
+use log::info;
+
+const WINDOW_TIME: Duration = Duration::from_secs(3);
+// A frame counts as missed past 110% of its budget and as early under 90% of it
+const MISS_RATIO: f64 = 1.1;
+const EARLY_RATIO: f64 = 0.9;
+const MISS_RATE_SHRINK: f64 = 0.05;
+const MISS_RATE_WIDEN: f64 = 0.01;
+const EARLY_RATE_WIDEN: f64 = 0.9;
+const SHRINK_STEP: f64 = 0.5;
+const WIDEN_STEP: f64 = 0.1;
+const MIN_OFFSET: f64 = -5.0;
+const MAX_OFFSET: f64 = 3.0;
+
+// Learned margin_fps offset per game. margin_fps is how far below the target the frames may
+// fall, so misses shrink it fast and frames finishing early widen it slowly. The offset is
+// persisted with the rest of the learned game state
+#[derive(Debug)]
+pub struct AdaptiveMargin {
+    enabled: bool,
+    offset: f64,
+    frames: usize,
+    missed: usize,
+    early: usize,
+    window_timer: Instant,
+}
+
+impl AdaptiveMargin {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            offset: 0.0,
+            frames: 0,
+            missed: 0,
+            early: 0,
+            window_timer: Instant::now(),
+        }
+    }
+
+    pub fn start(&mut self, pkg: &str, enabled: bool, learned: Option<f64>) {
+        self.enabled = enabled;
+        self.offset = learned.unwrap_or(0.0).clamp(MIN_OFFSET, MAX_OFFSET);
+        self.reset_window();
+
+        if enabled {
+            info!(
+                "Adaptive margin for {pkg} starts at {:+.1} fps",
+                self.offset
+            );
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.offset = 0.0;
+    }
+
+    // None while disabled, a disabled game keeps what it learned before
+    pub fn learned(&self) -> Option<f64> {
+        self.enabled.then_some(self.offset)
+    }
+
+    pub const fn offset(&self) -> f64 {
+        if self.enabled { self.offset } else { 0.0 }
+    }
+
+    pub fn observe(&mut self, frametime: Duration, target_fps: f64) {
+        if !self.enabled || target_fps <= 0.0 {
+            return;
+        }
+
+        let ratio = frametime.as_secs_f64() * target_fps;
+        self.frames += 1;
+        if ratio > MISS_RATIO {
+            self.missed += 1;
+        } else if ratio < EARLY_RATIO {
+            self.early += 1;
+        }
+
+        if self.window_timer.elapsed() >= WINDOW_TIME {
+            self.adapt();
+            self.reset_window();
+        }
+    }
+
+    fn adapt(&mut self) {
+        if self.frames == 0 {
+            return;
+        }
+
+        let miss_rate = self.missed as f64 / self.frames as f64;
+        let early_rate = self.early as f64 / self.frames as f64;
+        let offset = if miss_rate >= MISS_RATE_SHRINK {
+            self.offset - SHRINK_STEP
+        } else if miss_rate <= MISS_RATE_WIDEN && early_rate >= EARLY_RATE_WIDEN {
+            self.offset + WIDEN_STEP
+        } else {
+            return;
+        }
+        .clamp(MIN_OFFSET, MAX_OFFSET);
+
+        #[cfg(debug_assertions)]
+        debug!(
+            "adaptive margin: miss rate {miss_rate:.3}, early rate {early_rate:.3}, offset {offset:+.1}"
+        );
+
+        self.offset = offset;
+    }
+
+    fn reset_window(&mut self) {
+        self.frames = 0;
+        self.missed = 0;
+        self.early = 0;
+        self.window_timer = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(margin: &mut AdaptiveMargin, frames: usize, missed: usize, early: usize) {
+        margin.frames = frames;
+        margin.missed = missed;
+        margin.early = early;
+        margin.adapt();
+    }
+
+    #[test]
+    fn misses_shrink_the_margin() {
+        let mut margin = AdaptiveMargin::new();
+        margin.start("test", true, None);
+
+        window(&mut margin, 100, 10, 0);
+        assert!(margin.offset() < 0.0);
+    }
+
+    #[test]
+    fn early_frames_widen_the_margin() {
+        let mut margin = AdaptiveMargin::new();
+        margin.start("test", true, None);
+
+        window(&mut margin, 100, 0, 95);
+        assert!(margin.offset() > 0.0);
+    }
+
+    #[test]
+    fn offset_stays_in_bounds() {
+        let mut margin = AdaptiveMargin::new();
+        margin.start("test", true, None);
+
+        for _ in 0..100 {
+            window(&mut margin, 100, 50, 0);
+        }
+        assert!((margin.offset() - MIN_OFFSET).abs() < f64::EPSILON);
+    }
+}
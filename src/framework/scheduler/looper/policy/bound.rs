@@ -0,0 +1,170 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+// A saturated gpu with cpu threads to spare is gpu bound, and the other way around
+const GPU_BUSY_BOUND: f64 = 0.9;
+const GPU_BUSY_IDLE: f64 = 0.7;
+const CPU_UTIL_BOUND: f64 = 0.8;
+// Stream threads this idle while frames are missing are waiting on the network, not the cpu
+const STREAM_UTIL_IDLE: f64 = 0.3;
+// Consecutive agreeing classifications before the bound side changes
+const SWITCH_STREAK: u32 = 5;
+// The busiest thread on a full core with the cpu at its ceiling, for this long
+const CPU_UTIL_SATURATED: f64 = 0.95;
+pub const SATURATION_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Cpu,
+    Gpu,
+    // A streaming client starved by the network, neither side is boosted
+    Network,
+    // Boost both sides, as without a gpu load signal
+    Unknown,
+    // Cpu bound with the cpu already at its ceiling, more frequency buys nothing. Nothing is
+    // boosted and the jank floors are not raised, so the missed frames stop costing power
+    Saturated,
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu => write!(f, "cpu"),
+            Self::Gpu => write!(f, "gpu"),
+            Self::Network => write!(f, "network"),
+            Self::Unknown => write!(f, "unknown"),
+            Self::Saturated => write!(f, "saturated"),
+        }
+    }
+}
+
+impl Bound {
+    fn classify(cpu_util: f64, gpu_busy: Option<f64>, streaming: bool) -> Self {
+        if streaming
+            && cpu_util < STREAM_UTIL_IDLE
+            && gpu_busy.is_none_or(|gpu_busy| gpu_busy < GPU_BUSY_IDLE)
+        {
+            return Self::Network;
+        }
+
+        match gpu_busy {
+            Some(gpu_busy) if gpu_busy >= GPU_BUSY_BOUND && cpu_util < CPU_UTIL_BOUND => Self::Gpu,
+            Some(gpu_busy) if gpu_busy < GPU_BUSY_IDLE && cpu_util >= CPU_UTIL_BOUND => Self::Cpu,
+            _ => Self::Unknown,
+        }
+    }
+
+    // Only the bound side is boosted, the other one may still lower its frequency
+    pub fn split_control(self, control: isize) -> (isize, isize) {
+        match self {
+            Self::Cpu => (control, control.min(0)),
+            Self::Gpu => (control.min(0), control),
+            Self::Network | Self::Saturated => (control.min(0), control.min(0)),
+            Self::Unknown => (control, control),
+        }
+    }
+
+    // Whether a jank on the cpu / gpu side is worth boosting for
+    pub const fn boosts_cpu(self) -> bool {
+        matches!(self, Self::Cpu | Self::Unknown)
+    }
+
+    pub const fn boosts_gpu(self) -> bool {
+        matches!(self, Self::Gpu | Self::Unknown)
+    }
+}
+
+// Combines the cpu and gpu load signals into the side a game is bound on
+#[derive(Debug)]
+pub struct BoundDetector {
+    bound: Bound,
+    candidate: Bound,
+    streak: u32,
+    streaming: bool,
+    saturated_since: Option<Instant>,
+}
+
+impl BoundDetector {
+    pub const fn new() -> Self {
+        Self {
+            bound: Bound::Unknown,
+            candidate: Bound::Unknown,
+            streak: 0,
+            streaming: false,
+            saturated_since: None,
+        }
+    }
+
+    pub const fn reset(&mut self) {
+        *self = Self {
+            streaming: self.streaming,
+            ..Self::new()
+        };
+    }
+
+    // Streaming clients may be bound on the network as well
+    pub const fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    pub const fn streaming(&self) -> bool {
+        self.streaming
+    }
+
+    pub const fn bound(&self) -> Bound {
+        self.bound
+    }
+
+    // `cpu_util` is the busiest game thread's share of a core, `gpu_busy` the gpu busy ratio,
+    // `at_ceiling` whether the cpu already runs at the most it may
+    pub fn update(&mut self, cpu_util: f64, gpu_busy: Option<f64>, at_ceiling: bool) -> Bound {
+        // Time based rather than a streak, it must outlast a loading screen
+        if cpu_util >= CPU_UTIL_SATURATED && at_ceiling {
+            let since = *self.saturated_since.get_or_insert_with(Instant::now);
+            if since.elapsed() >= SATURATION_TIME {
+                self.bound = Bound::Saturated;
+                self.candidate = Bound::Saturated;
+                self.streak = 0;
+                return self.bound;
+            }
+        } else {
+            self.saturated_since = None;
+        }
+
+        let bound = Bound::classify(cpu_util, gpu_busy, self.streaming);
+
+        if bound == self.bound {
+            self.streak = 0;
+        } else if bound == self.candidate {
+            self.streak += 1;
+            if self.streak >= SWITCH_STREAK {
+                self.bound = bound;
+                self.streak = 0;
+            }
+        } else {
+            self.candidate = bound;
+            self.streak = 1;
+        }
+
+        self.bound
+    }
+}
@@ -15,15 +15,24 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::{Duration, Instant};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
 use likely_stable::unlikely;
 #[cfg(debug_assertions)]
 use log::debug;
 
 use super::super::buffer::Buffer;
+use super::ControllerParams;
 use crate::framework::{config::MarginFps, prelude::*, scheduler::looper::ControllerState};
 
+// Keeps the integral term from winding up over long stalls, in ns of normalized frametime
+const INTEGRAL_LIMIT: f64 = 5_000_000_000.0;
+// Slow frames in a row that set off a jank burst
+const JANK_BURST_FRAMES: usize = 3;
+
 pub fn calculate_control(
     buffer: &Buffer,
     config: &mut Config,
@@ -37,34 +46,67 @@ pub fn calculate_control(
     }
 
     let target_fps = f64::from(buffer.target_fps_state.target_fps?);
-    let margin_fps: f64 = match &config.mode_config(mode).margin_fps {
-        MarginFps::BaseOnly(base) => target_fps / 60.0 * f64::from(*base),
-        MarginFps::Advanced { base, overrides } => overrides
-            .get(&target_fps.to_string())
-            .copied()
-            .map_or_else(|| target_fps / 60.0 * f64::from(*base), f64::from),
-    };
-
-    assert!(margin_fps.is_sign_positive(), "margin_fps must be positive");
-
+    let margin_fps = margin_fps(
+        &config.margin_fps(&buffer.package_info.pkg, mode),
+        target_fps,
+    );
+    let margin_fps =
+        (margin_fps + controller_state.adaptive.offset() + controller_state.capture_margin)
+            .max(0.0);
+
+    let target_fps = buffer.ramped_target_fps()?;
     let target_fps = (target_fps + target_fps_offset_thermal).clamp(0.0, target_fps);
+    if buffer.frametime_state.additional_frametime == Duration::ZERO {
+        if let Some(frametime) = buffer.frametime_state.frametimes.front() {
+            controller_state.adaptive.observe(*frametime, target_fps);
+        }
+    }
     let adjusted_target_fps = adjust_target_fps(target_fps, controller_state) - margin_fps;
     let adjusted_last_frame = get_normalized_last_frame(buffer, adjusted_target_fps);
-    let target_frametime = Duration::from_secs(1);
 
     #[cfg(debug_assertions)]
     {
         debug!("adjusted_target_fps: {adjusted_target_fps}");
         debug!("adjusted_last_frame: {adjusted_last_frame:?}");
-        debug!("target_frametime: {target_frametime:?}");
     }
 
     Some((
-        calculate_control_inner(controller_state, adjusted_last_frame, target_frametime),
-        buffer.frametime_state.current_fps_long < target_fps - 2.0,
+        pid_control(&mut controller_state.params, adjusted_last_frame),
+        is_janked(buffer.frametime_state.current_fps_long, target_fps),
     ))
 }
 
+// Shared with `simulate`, which replays recordings through the same math
+pub fn margin_fps(margin: &MarginFps, target_fps: f64) -> f64 {
+    let margin_fps = match margin {
+        MarginFps::BaseOnly(base) => target_fps / 60.0 * f64::from(*base),
+        MarginFps::Advanced { base, overrides } => overrides
+            .get(&target_fps.to_string())
+            .copied()
+            .map_or_else(|| target_fps / 60.0 * f64::from(*base), f64::from),
+    };
+
+    assert!(margin_fps.is_sign_positive(), "margin_fps must be positive");
+    margin_fps
+}
+
+pub fn is_janked(current_fps: f64, target_fps: f64) -> bool {
+    current_fps < target_fps - 2.0
+}
+
+// The newest frames all ran `threshold` percent over the target frametime, 0 never bursts
+pub fn jank_burst_due(frametimes: &VecDeque<Duration>, target_fps: u32, threshold: u32) -> bool {
+    if threshold == 0 || target_fps == 0 || frametimes.len() < JANK_BURST_FRAMES {
+        return false;
+    }
+
+    let limit = (Duration::from_secs(1) / target_fps).mul_f64(1.0 + f64::from(threshold) / 100.0);
+    frametimes
+        .iter()
+        .take(JANK_BURST_FRAMES)
+        .all(|frametime| *frametime > limit)
+}
+
 fn get_normalized_last_frame(buffer: &Buffer, target_fps: f64) -> Duration {
     let last_frame = buffer
         .frametime_state
@@ -84,31 +126,79 @@ fn get_normalized_last_frame(buffer: &Buffer, target_fps: f64) -> Duration {
 fn adjust_target_fps(target_fps: f64, controller_state: &mut ControllerState) -> f64 {
     if controller_state.usage_sample_timer.elapsed() >= Duration::from_secs(1) {
         controller_state.usage_sample_timer = Instant::now();
-        let util = controller_state.controller.util_max();
-
-        if util <= 0.1 {
-            controller_state.target_fps_offset = 0.0;
-        } else if util <= 0.55 {
-            controller_state.target_fps_offset -= 0.1;
-        } else if util >= 0.65 {
-            controller_state.target_fps_offset += 0.1;
-        }
+        controller_state.target_fps_offset = step_target_fps_offset(
+            controller_state.target_fps_offset,
+            controller_state.controller.util_max(),
+        );
     }
 
     controller_state.target_fps_offset = controller_state.target_fps_offset.clamp(-3.0, 0.0);
     target_fps + controller_state.target_fps_offset
 }
 
-fn calculate_control_inner(
-    controller_state: &ControllerState,
-    current_frametime: Duration,
-    target_frametime: Duration,
-) -> isize {
-    let error_p = (current_frametime.as_nanos() as f64 - target_frametime.as_nanos() as f64)
-        * controller_state.params.kp;
+// Once a second, a lightly loaded game gets its target lowered a little
+pub fn step_target_fps_offset(offset: f64, util: f64) -> f64 {
+    let offset = if util <= 0.1 {
+        0.0
+    } else if util <= 0.55 {
+        offset - 0.1
+    } else if util >= 0.65 {
+        offset + 0.1
+    } else {
+        offset
+    };
+
+    offset.clamp(-3.0, 0.0)
+}
+
+// `current_frametime` is normalized to the target, a frame right on time is one second
+pub fn pid_control(params: &mut ControllerParams, current_frametime: Duration) -> isize {
+    let target_frametime = Duration::from_secs(1);
+    let error = current_frametime.as_nanos() as f64 - target_frametime.as_nanos() as f64;
+
+    params.integral = (params.integral + error).clamp(-INTEGRAL_LIMIT, INTEGRAL_LIMIT);
+    let derivative = params
+        .last_error
+        .map_or(0.0, |last_error| error - last_error);
+    params.last_error = Some(error);
+
+    let output =
+        error * params.gains.kp + params.integral * params.gains.ki + derivative * params.gains.kd;
 
     #[cfg(debug_assertions)]
-    debug!("error_p {error_p}");
+    debug!(
+        "pid error: {error}, integral: {}, derivative: {derivative}, output: {output}",
+        params.integral
+    );
+
+    output as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frametimes(ms: &[u64]) -> VecDeque<Duration> {
+        ms.iter().copied().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn burst_needs_consecutive_slow_frames() {
+        // 60 fps with a 50% threshold bursts past 25ms
+        assert!(jank_burst_due(&frametimes(&[26, 30, 26, 16]), 60, 50));
+        assert!(!jank_burst_due(&frametimes(&[26, 16, 26, 30]), 60, 50));
+        assert!(!jank_burst_due(&frametimes(&[30, 30]), 60, 50));
+    }
 
-    error_p as isize
+    #[test]
+    fn burst_stays_off_without_threshold_or_target() {
+        let slow = frametimes(&[100, 100, 100]);
+        assert!(!jank_burst_due(&slow, 60, 0));
+        assert!(!jank_burst_due(&slow, 0, 50));
+    }
+
+    #[test]
+    fn huge_threshold_does_not_overflow() {
+        assert!(!jank_burst_due(&frametimes(&[100, 100, 100]), 60, u32::MAX));
+    }
 }
@@ -15,15 +15,24 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod adaptive;
+pub mod bound;
 pub mod controll;
 
-#[derive(Debug, Copy, Clone)]
+use crate::framework::config::PidGains;
+
+#[derive(Debug, Default, Copy, Clone)]
 pub struct ControllerParams {
-    pub kp: f64,
+    pub gains: PidGains,
+    pub integral: f64,
+    pub last_error: Option<f64>,
 }
 
-impl Default for ControllerParams {
-    fn default() -> Self {
-        Self { kp: 0.000_3 }
+impl ControllerParams {
+    pub fn new(gains: PidGains) -> Self {
+        Self {
+            gains,
+            ..Self::default()
+        }
     }
 }
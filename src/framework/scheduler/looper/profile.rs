@@ -0,0 +1,94 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use super::{Looper, State};
+use crate::misc::getprop;
+
+const PROFILE_PROP: &str = "fas-rs-profile";
+const PROP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ProfileState {
+    prop: Option<String>,
+    poll_timer: Instant,
+    // The profile a screen recording switched away from
+    before_capture: Option<Option<String>>,
+}
+
+impl ProfileState {
+    pub fn new() -> Self {
+        Self {
+            prop: None,
+            poll_timer: Instant::now(),
+            before_capture: None,
+        }
+    }
+}
+
+impl Looper {
+    // Only react when the prop changes, so a profile set through the control socket sticks
+    pub fn poll_profile_prop(&mut self) {
+        if self.profile_state.poll_timer.elapsed() < PROP_POLL_INTERVAL {
+            return;
+        }
+        self.profile_state.poll_timer = Instant::now();
+
+        let prop = getprop(PROFILE_PROP).filter(|prop| !prop.is_empty());
+        if prop != self.profile_state.prop {
+            self.profile_state.prop.clone_from(&prop);
+            self.set_profile(prop);
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        if self.config.profile() == profile.as_deref() {
+            return;
+        }
+
+        self.config.set_profile(profile);
+
+        if self.fas_state.working_state == State::Working {
+            if let Some(buffer) = self.fas_state.buffer.as_mut() {
+                if let Some(target_fps) = self.config.target_fps(&buffer.package_info.pkg) {
+                    buffer.set_target_fps_config(target_fps);
+                }
+            }
+
+            self.init_game();
+        }
+    }
+
+    pub fn capture_update(&mut self) {
+        let config = self.config.capture_config();
+        let was_active = self.capture.active();
+        let active = self.capture.update(&config);
+
+        self.controller_state.capture_margin = if active { config.margin_fps } else { 0.0 };
+
+        if active == was_active {
+            return;
+        }
+
+        if active && !config.profile.is_empty() {
+            self.profile_state.before_capture = Some(self.config.profile().map(String::from));
+            self.set_profile(Some(config.profile));
+        } else if let Some(profile) = self.profile_state.before_capture.take() {
+            self.set_profile(profile);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use super::Looper;
+use crate::{framework::node::Mode, misc::getprop};
+
+// For automation apps like Tasker, which can set props but cannot reach the control socket
+const MODE_PROP: &str = "fas-rs.ctl.mode";
+const ENABLED_PROP: &str = "fas-rs.ctl.enabled";
+const PROP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct QuickControl {
+    mode: Option<String>,
+    enabled: bool,
+    poll_timer: Instant,
+}
+
+impl QuickControl {
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            enabled: true,
+            poll_timer: Instant::now(),
+        }
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+impl Looper {
+    // Like `fas-rs-profile` the mode prop only acts when it changes, a mode switched through
+    // scene or the control socket sticks
+    pub fn poll_quick_control(&mut self) {
+        if self.quick_control.poll_timer.elapsed() < PROP_POLL_INTERVAL {
+            return;
+        }
+        self.quick_control.poll_timer = Instant::now();
+
+        let mode = getprop(MODE_PROP).filter(|mode| !mode.is_empty());
+        if mode != self.quick_control.mode {
+            self.quick_control.mode.clone_from(&mode);
+            if let Some(mode) = mode {
+                match Mode::from_str(&mode) {
+                    Ok(mode) => {
+                        info!("{MODE_PROP}: switching to {mode}");
+                        if let Err(e) = self.node.create_node("mode", mode.to_string().as_str()) {
+                            warn!("Failed to switch mode from {MODE_PROP}: {e}");
+                        }
+                    }
+                    Err(e) => warn!("Ignoring {MODE_PROP}={mode}: {e}"),
+                }
+            }
+        }
+
+        // Only an explicit off stops fas-rs, an empty or mistyped prop must not
+        let enabled = !matches!(getprop(ENABLED_PROP).as_deref(), Some("false" | "0"));
+        if enabled != self.quick_control.enabled {
+            self.quick_control.enabled = enabled;
+            if enabled {
+                info!("{ENABLED_PROP}: control resumed");
+            } else {
+                info!("{ENABLED_PROP}: control stopped until it is set to true");
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+
+use super::engine::Engine;
+use crate::framework::{config::Config, error::Result, node::Mode};
+
+#[derive(Debug)]
+pub struct SimulateOptions {
+    pub recording: PathBuf,
+    pub config: PathBuf,
+    // Taken from the recording's file name when not given
+    pub pkg: Option<String>,
+    // The game's configured mode, or balance, when not given
+    pub mode: Option<Mode>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    timestamp_ms: u64,
+    frametime: Duration,
+    util_max: f64,
+    freq: isize,
+    temperature: u64,
+}
+
+#[derive(Debug, Default)]
+struct Summary {
+    frames: usize,
+    decisions: usize,
+    janked: usize,
+    control: f64,
+    freq_delta: f64,
+}
+
+// Replays a recording written with `record = true` through `Engine` and prints the decision for
+// every frame as CSV, nothing on the device is touched. Frequencies are not simulated: each
+// decision starts from the recorded frequency, so the output shows how the current control code
+// reacts to the recorded frames, util and temperatures
+pub fn simulate(options: &SimulateOptions) -> Result<()> {
+    let content = fs::read_to_string(&options.recording)
+        .with_context(|| format!("Failed to read {}", options.recording.display()))?;
+    let samples = parse_recording(&content)?;
+
+    let pkg = match &options.pkg {
+        Some(pkg) => pkg.clone(),
+        None => pkg_of(&options.recording).context(
+            "Can not tell the game from the recording's file name, pass --pkg <package>",
+        )?,
+    };
+
+    let mut config = Config::snapshot(&options.config)?;
+    let mode = options
+        .mode
+        .or_else(|| config.game_mode(&pkg))
+        .unwrap_or(Mode::Balance);
+    let Some(mut engine) = Engine::new(&mut config, &pkg, mode) else {
+        return Err(anyhow!("{pkg} has no target fps in {}", options.config.display()).into());
+    };
+
+    eprintln!("Replaying {} frames of {pkg} in {mode} mode", samples.len());
+    println!(
+        "timestamp_ms,frametime_us,target_fps,control_khz,janked,recorded_freq_khz,decided_freq_khz"
+    );
+
+    let mut summary = Summary::default();
+
+    for sample in &samples {
+        summary.frames += 1;
+
+        let decision = engine.frame(
+            sample.timestamp_ms,
+            sample.frametime,
+            sample.util_max,
+            sample.temperature,
+        );
+
+        match decision {
+            Some(decision) => {
+                let control = decision.control;
+                let janked = decision.janked;
+                let decided = sample.freq.saturating_add(control).max(0);
+                summary.decisions += 1;
+                summary.janked += usize::from(janked);
+                summary.control += control as f64;
+                summary.freq_delta += (decided - sample.freq) as f64;
+
+                println!(
+                    "{},{},{},{control},{janked},{},{decided}",
+                    sample.timestamp_ms,
+                    sample.frametime.as_micros(),
+                    decision.target_fps,
+                    sample.freq
+                );
+            }
+            None => println!(
+                "{},{},{},,,{},",
+                sample.timestamp_ms,
+                sample.frametime.as_micros(),
+                engine.target_fps().unwrap_or_default(),
+                sample.freq
+            ),
+        }
+    }
+
+    let decisions = summary.decisions.max(1) as f64;
+    eprintln!(
+        "frames: {}, decisions: {}, janked: {:.1}%, mean control: {:.0} kHz, mean decided - recorded freq: {:.0} kHz",
+        summary.frames,
+        summary.decisions,
+        summary.janked as f64 / decisions * 100.0,
+        summary.control / decisions,
+        summary.freq_delta / decisions
+    );
+
+    Ok(())
+}
+
+// `timestamp_ms,frametime_us,util_max,freq_khz,temperature`, as `Recorder` writes it
+fn parse_recording(content: &str) -> Result<Vec<Sample>> {
+    content
+        .lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            parse_line(line)
+                .ok_or_else(|| anyhow!("Invalid recording line {}: {line}", index + 1).into())
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<Sample> {
+    let mut fields = line.trim().split(',');
+
+    Some(Sample {
+        timestamp_ms: fields.next()?.parse().ok()?,
+        frametime: Duration::from_micros(fields.next()?.parse().ok()?),
+        util_max: fields.next()?.parse().ok()?,
+        freq: fields.next()?.parse().ok()?,
+        temperature: fields.next()?.parse().ok()?,
+    })
+}
+
+// Recordings are named `<pkg>_<unix seconds>.csv`
+fn pkg_of(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let (pkg, timestamp) = stem.rsplit_once('_')?;
+
+    timestamp
+        .chars()
+        .all(|c| c.is_ascii_digit())
+        .then(|| pkg.to_string())
+}
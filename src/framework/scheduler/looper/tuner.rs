@@ -0,0 +1,109 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use log::warn;
+use toml::Value;
+
+use super::{Looper, State};
+use crate::framework::{
+    config::MarginFps,
+    node::Mode,
+    tuner::{Sample, Tune},
+};
+
+impl Looper {
+    pub fn handle_tuner(&mut self) {
+        self.tuner.set_port(self.config.config().tuner_port);
+
+        if !self.tuner.enabled() {
+            return;
+        }
+
+        while let Some(tune) = self.tuner.try_recv() {
+            self.apply_tune(tune);
+        }
+
+        let mode = self.current_mode();
+        let pkg = self
+            .fas_state
+            .buffer
+            .as_ref()
+            .filter(|_| self.fas_state.working_state == State::Working)
+            .map(|buffer| buffer.package_info.pkg.clone());
+        let margin_fps = pkg
+            .as_ref()
+            .map_or(0.0, |pkg| match self.config.margin_fps(pkg, mode) {
+                MarginFps::BaseOnly(base) | MarginFps::Advanced { base, .. } => f64::from(base),
+            });
+
+        self.tuner
+            .set_game(pkg.as_deref(), &mode.to_string(), margin_fps);
+    }
+
+    pub fn tuner_sample(&mut self) {
+        let Some(buffer) = &self.fas_state.buffer else {
+            return;
+        };
+
+        let controller = &self.controller_state.controller;
+        self.tuner.push(Sample {
+            frametime_ms: buffer
+                .frametime_state
+                .frametimes
+                .front()
+                .copied()
+                .unwrap_or_default()
+                .as_secs_f64()
+                * 1000.0,
+            util: controller.util_max(),
+            freq_mhz: controller.cur_fas_freq_max() as f64 / 1000.0,
+        });
+    }
+
+    fn apply_tune(&mut self, tune: Tune) {
+        let Some(pkg) = self
+            .fas_state
+            .buffer
+            .as_ref()
+            .map(|buffer| buffer.package_info.pkg.clone())
+        else {
+            return;
+        };
+
+        if let Some(margin_fps) = tune.margin_fps {
+            if let Err(e) = self
+                .config
+                .set_game_value(&pkg, "margin_fps", Value::Float(margin_fps))
+            {
+                warn!("Failed to save margin_fps of {pkg}: {e}");
+            }
+        }
+
+        if let Some(mode) = tune.mode {
+            let result = mode.parse::<Mode>().and_then(|mode| {
+                self.config
+                    .set_game_value(&pkg, "mode", Value::String(mode.to_string()))
+            });
+
+            match result {
+                Ok(()) if self.fas_state.working_state == State::Working => self.init_game(),
+                Ok(()) => (),
+                Err(e) => warn!("Failed to save mode of {pkg}: {e}"),
+            }
+        }
+    }
+}
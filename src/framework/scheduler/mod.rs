@@ -15,22 +15,37 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
+mod capture;
+mod display;
+mod fps_detect;
+mod frame_source;
+mod input;
 mod looper;
+mod power;
+mod psi;
+mod screen;
 mod thermal;
 mod topapp;
 
 use std::time::Duration;
 
+use log::warn;
+
 use super::{
     Extension,
     config::Config,
+    control::Control,
     error::{Error, Result},
     node::Node,
+    signal,
+    watchdog::{self, Heartbeat},
 };
-use crate::Controller;
+use crate::{Controller, GpuController};
 
-use frame_analyzer::Analyzer;
+use frame_source::FrameSource;
 use looper::Looper;
+pub use looper::{Decision, Engine, SimulateOptions, simulate};
+pub use power::BatteryStat;
 
 #[derive(Debug, Clone, Copy)]
 pub struct FasData {
@@ -40,6 +55,7 @@ pub struct FasData {
 
 pub struct Scheduler {
     controller: Option<Controller>,
+    gpu_controller: Option<GpuController>,
     config: Option<Config>,
 }
 
@@ -48,6 +64,7 @@ impl Scheduler {
     pub const fn new() -> Self {
         Self {
             controller: None,
+            gpu_controller: None,
             config: None,
         }
     }
@@ -66,17 +83,43 @@ impl Scheduler {
         self
     }
 
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn gpu_controller(mut self, g: GpuController) -> Self {
+        self.gpu_controller = Some(g);
+        self
+    }
+
     pub fn start_run(self) -> Result<()> {
         let extension = Extension::init()?;
         let config = self.config.ok_or(Error::SchedulerMissing("Config"))?;
 
-        let controller = self
+        let mut controller = self
             .controller
             .ok_or(Error::SchedulerMissing("Controller"))?;
 
+        let heartbeat = Heartbeat::new("scheduler loop");
+        let monitor_heartbeat = Heartbeat::new("process monitor");
+        controller.set_heartbeat(monitor_heartbeat.clone());
+        if let Err(e) = watchdog::start(vec![heartbeat.clone(), monitor_heartbeat]) {
+            warn!("Failed to start watchdog: {e}");
+        }
+
+        signal::install()?;
         let node = Node::init()?;
-        let analyzer = Analyzer::new()?;
+        let control = Control::init()?;
+        let analyzer = FrameSource::new();
 
-        Looper::new(analyzer, config, node, extension, controller).enter_loop()
+        Looper::new(
+            analyzer,
+            config,
+            node,
+            extension,
+            control,
+            controller,
+            self.gpu_controller,
+            heartbeat,
+        )
+        .enter_loop()
     }
 }
@@ -0,0 +1,116 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::framework::config::BatteryConfig;
+
+const BATTERY_PATH: &str = "/sys/class/power_supply/battery";
+const SAMPLE_TIME: Duration = Duration::from_secs(5);
+const HOT_HEALTHS: [&str; 3] = ["Warm", "Hot", "Overheat"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatteryStat {
+    pub capacity: u8,
+    pub status: String,
+    pub health: String,
+    pub current_now: i64,
+}
+
+impl BatteryStat {
+    fn read() -> Option<Self> {
+        let path = Path::new(BATTERY_PATH);
+        let read = |node: &str| {
+            fs::read_to_string(path.join(node))
+                .ok()
+                .map(|value| value.trim().to_string())
+        };
+
+        Some(Self {
+            capacity: read("capacity")?.parse().ok()?,
+            status: read("status")?,
+            health: read("health").unwrap_or_default(),
+            current_now: read("current_now")
+                .and_then(|current| current.parse().ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    // The charger is connected but the battery is too warm to take the full current
+    fn hot_charging(&self) -> bool {
+        matches!(self.status.as_str(), "Charging" | "Not charging")
+            && HOT_HEALTHS.contains(&self.health.as_str())
+    }
+}
+
+pub struct Battery {
+    stat: Option<BatteryStat>,
+    powersave: bool,
+    sample_timer: Instant,
+}
+
+impl Battery {
+    pub fn new() -> Self {
+        Self {
+            stat: BatteryStat::read(),
+            powersave: false,
+            sample_timer: Instant::now(),
+        }
+    }
+
+    pub fn stat(&self) -> Option<BatteryStat> {
+        self.stat.clone()
+    }
+
+    // Whether the scheduler should fall back to the powersave preset
+    pub fn powersave(&mut self, config: BatteryConfig) -> bool {
+        if self.sample_timer.elapsed() < SAMPLE_TIME {
+            return self.powersave;
+        }
+
+        self.sample_timer = Instant::now();
+        self.stat = BatteryStat::read();
+
+        let Some(stat) = &self.stat else {
+            self.powersave = false;
+            return false;
+        };
+
+        let low_capacity = stat.capacity < config.powersave_capacity.min(100)
+            && stat.status != "Charging"
+            && stat.status != "Full";
+        let hot_charging = config.powersave_on_hot_charging && stat.hot_charging();
+        let powersave = low_capacity || hot_charging;
+
+        if powersave != self.powersave {
+            info!(
+                "Battery powersave: {powersave} (capacity: {}%, status: {}, health: {}, current: {}uA)",
+                stat.capacity, stat.status, stat.health, stat.current_now
+            );
+            self.powersave = powersave;
+        }
+
+        self.powersave
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(debug_assertions)]
+use log::debug;
+use log::{info, warn};
+
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    framework::{
+        config::PsiConfig,
+        error::{Error, Result},
+    },
+};
+
+const CPU_PRESSURE: &str = "/proc/pressure/cpu";
+const MEMORY_PRESSURE: &str = "/proc/pressure/memory";
+const SAMPLE_TIME: Duration = Duration::from_secs(1);
+// The kernel wakes the trigger when tasks stalled this long within one window
+const TRIGGER: &str = "some 100000 1000000";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PsiStat {
+    pub cpu: f64,
+    pub memory: f64,
+    pub top_app_cpu: Option<f64>,
+}
+
+pub struct Psi {
+    top_app_cpu: Option<PathBuf>,
+    stat: PsiStat,
+    sample_timer: Instant,
+    stalled: Option<Arc<AtomicBool>>,
+    trigger_failed: bool,
+}
+
+impl Psi {
+    pub fn new() -> Self {
+        let top_app_cpu = Cgroup::open(Subsystem::Cpu, "top-app")
+            .map(|cgroup| cgroup.pressure())
+            .filter(|path| path.exists());
+
+        Self {
+            top_app_cpu,
+            stat: PsiStat::default(),
+            sample_timer: Instant::now(),
+            stalled: None,
+            trigger_failed: false,
+        }
+    }
+
+    pub const fn stat(&self) -> PsiStat {
+        self.stat
+    }
+
+    // Whether the system or top-app stalls enough to boost, either on a trigger event or on avg10
+    pub fn stalled(&mut self, config: &PsiConfig) -> bool {
+        if !config.enabled {
+            return false;
+        }
+
+        if config.poll_trigger && self.stalled.is_none() && !self.trigger_failed {
+            match start_trigger() {
+                Ok(stalled) => {
+                    info!("Watching {CPU_PRESSURE} and {MEMORY_PRESSURE} with psi triggers");
+                    self.stalled = Some(stalled);
+                }
+                Err(e) => {
+                    warn!("Psi triggers unavailable, using averages only: {e}");
+                    self.trigger_failed = true;
+                }
+            }
+        }
+
+        let triggered = self
+            .stalled
+            .as_ref()
+            .is_some_and(|stalled| stalled.swap(false, Ordering::AcqRel))
+            && config.poll_trigger;
+
+        if self.sample_timer.elapsed() >= SAMPLE_TIME {
+            self.sample_timer = Instant::now();
+            self.sample();
+        }
+
+        triggered
+            || self.stat.cpu >= config.cpu_threshold
+            || self.stat.memory >= config.memory_threshold
+            || self
+                .stat
+                .top_app_cpu
+                .is_some_and(|pressure| pressure >= config.cpu_threshold)
+    }
+
+    fn sample(&mut self) {
+        self.stat = PsiStat {
+            cpu: read_avg10(CPU_PRESSURE).unwrap_or_default(),
+            memory: read_avg10(MEMORY_PRESSURE).unwrap_or_default(),
+            top_app_cpu: self.top_app_cpu.as_deref().and_then(read_avg10),
+        };
+
+        #[cfg(debug_assertions)]
+        debug!("psi: {:?}", self.stat);
+    }
+}
+
+// Share of the last 10s in which at least one task stalled, in percent
+fn read_avg10<P: AsRef<Path>>(path: P) -> Option<f64> {
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with("some"))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+fn start_trigger() -> Result<Arc<AtomicBool>> {
+    let files = [CPU_PRESSURE, MEMORY_PRESSURE]
+        .into_iter()
+        .filter(|path| Path::new(path).exists())
+        .map(|path| {
+            let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+            file.write_all(format!("{TRIGGER}\0").as_bytes())?;
+            Ok::<_, Error>(file)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if files.is_empty() {
+        return Err(Error::Other("Kernel has no psi"));
+    }
+
+    let stalled = Arc::new(AtomicBool::new(false));
+
+    {
+        let stalled = stalled.clone();
+        thread::Builder::new()
+            .name("PsiThread".into())
+            .spawn(move || {
+                if let Err(e) = watch(&files, &stalled) {
+                    warn!("Psi trigger watcher stopped: {e}");
+                }
+            })?;
+    }
+
+    Ok(stalled)
+}
+
+fn watch(files: &[File], stalled: &AtomicBool) -> Result<()> {
+    let mut fds: Vec<_> = files
+        .iter()
+        .map(|file| libc::pollfd {
+            fd: file.as_raw_fd(),
+            events: libc::POLLPRI,
+            revents: 0,
+        })
+        .collect();
+
+    loop {
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+
+        for pollfd in &mut fds {
+            if pollfd.revents & libc::POLLERR != 0 {
+                return Err(Error::Other("Psi trigger was removed"));
+            }
+
+            if pollfd.revents & libc::POLLPRI != 0 {
+                stalled.store(true, Ordering::Release);
+            }
+            pollfd.revents = 0;
+        }
+    }
+}
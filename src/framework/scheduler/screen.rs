@@ -0,0 +1,82 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+const BACKLIGHT_PATH: &str = "/sys/class/backlight";
+const LCD_BACKLIGHT: &str = "/sys/class/leds/lcd-backlight/brightness";
+const SAMPLE_TIME: Duration = Duration::from_secs(1);
+
+pub struct Screen {
+    nodes: Vec<PathBuf>,
+    off: bool,
+    sample_timer: Instant,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        let mut nodes: Vec<_> = fs::read_dir(BACKLIGHT_PATH)
+            .into_iter()
+            .flatten()
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path().join("brightness"))
+            .filter(|path| path.exists())
+            .collect();
+
+        if nodes.is_empty() && fs::exists(LCD_BACKLIGHT).unwrap_or(false) {
+            nodes.push(LCD_BACKLIGHT.into());
+        }
+
+        info!("Screen state nodes: {nodes:?}");
+
+        Self {
+            nodes,
+            off: false,
+            sample_timer: Instant::now(),
+        }
+    }
+
+    // Without any readable backlight node the screen is assumed to stay on
+    pub fn is_off(&mut self) -> bool {
+        if self.nodes.is_empty() || self.sample_timer.elapsed() < SAMPLE_TIME {
+            return self.off;
+        }
+
+        self.sample_timer = Instant::now();
+
+        let brightness: Vec<_> = self
+            .nodes
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .collect();
+        // Nodes that cannot be read say nothing, they must not park the loop for good
+        let off = !brightness.is_empty() && brightness.iter().all(|value| value.trim() == "0");
+
+        if off != self.off {
+            info!("Screen turned {}", if off { "off" } else { "on" });
+            self.off = off;
+        }
+
+        self.off
+    }
+}
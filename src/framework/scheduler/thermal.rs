@@ -15,26 +15,101 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 #[cfg(debug_assertions)]
 use log::debug;
+use log::{info, warn};
 
-use crate::{Config, Mode, framework::config::TemperatureThreshold};
+use crate::{
+    Config, Mode,
+    framework::config::TemperatureThreshold,
+    paths::{self, Root},
+};
 
 use stringzilla::sz;
 
+const VIRTUAL_THERMAL_PATH: &str = "/sys/devices/virtual/thermal";
+const THROTTLE_SAMPLE_TIME: Duration = Duration::from_secs(1);
+const THROTTLE_HYSTERESIS: u64 = 2000;
+const THROTTLE_STEP: f64 = 0.05;
+const THROTTLE_MIN_CAP: f64 = 0.5;
+const CRITICAL_SAMPLE_TIME: Duration = Duration::from_secs(1);
+
 pub struct Thermal {
     target_fps_offset: f64,
     core_temperature: u64,
     nodes: Vec<PathBuf>,
+    zones: ThermalZones,
+    critical: Critical,
+}
+
+// Safety interlock above every game and mode config, control stays off until the cooldown
+// after the last sample above the critical temperature ran out
+struct Critical {
+    sample_timer: Instant,
+    until: Option<Instant>,
+}
+
+struct ThermalZones {
+    types: Vec<String>,
+    nodes: Vec<PathBuf>,
+    temperature: u64,
+    freq_cap: f64,
+    sample_timer: Instant,
+}
+
+impl ThermalZones {
+    fn new() -> Self {
+        Self {
+            types: Vec::new(),
+            nodes: Vec::new(),
+            temperature: 0,
+            freq_cap: 1.0,
+            sample_timer: Instant::now(),
+        }
+    }
+
+    fn load(&mut self, types: Vec<String>) {
+        self.nodes.clear();
+
+        if let Ok(zones) = fs::read_dir(paths::thermal()) {
+            for zone in zones.filter_map(std::result::Result::ok) {
+                let Ok(zone_type) = fs::read_to_string(zone.path().join("type")) else {
+                    continue;
+                };
+
+                if types.iter().any(|t| t == zone_type.trim()) {
+                    self.nodes.push(zone.path().join("temp"));
+                }
+            }
+        }
+
+        info!("Thermal zones for throttling: {:?}", self.nodes);
+        self.types = types;
+    }
+
+    fn temperature_update(&mut self) {
+        self.temperature = self
+            .nodes
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .map(|temp| temp.trim().parse::<u64>().unwrap_or_default())
+            .max()
+            .unwrap_or_default();
+    }
 }
 
 impl Thermal {
     pub fn new() -> Result<Self> {
         let mut nodes = Vec::new();
-        for device in fs::read_dir("/sys/devices/virtual/thermal")? {
+        let devices = paths::get(Root::Thermal).unwrap_or_else(|| Path::new(VIRTUAL_THERMAL_PATH));
+        for device in fs::read_dir(devices)? {
             let device = device?;
             let device_type = device.path().join("type");
             let Ok(device_type) = fs::read_to_string(device_type) else {
@@ -52,29 +127,115 @@ impl Thermal {
             target_fps_offset: 0.0,
             core_temperature: 0,
             nodes,
+            zones: ThermalZones::new(),
+            critical: Critical {
+                sample_timer: Instant::now(),
+                until: None,
+            },
         })
     }
 
-    pub fn target_fps_offset(&mut self, config: &mut Config, mode: Mode) -> f64 {
-        let target_core_temperature = match config.mode_config(mode).core_temp_thresh {
+    // Whether a core or zone got critically hot within the cooldown, fas-rs must not touch any
+    // node meanwhile
+    pub fn critical(&mut self, config: &mut Config) -> bool {
+        let thermal_config = config.thermal_config();
+        let TemperatureThreshold::Temp(critical_temp) = thermal_config.critical_temp else {
+            self.critical.until = None;
+            return false;
+        };
+
+        if self.critical.sample_timer.elapsed() >= CRITICAL_SAMPLE_TIME {
+            self.critical.sample_timer = Instant::now();
+
+            if self.zones.types != thermal_config.zones {
+                self.zones.load(thermal_config.zones);
+            }
+            self.temperature_update();
+            self.zones.temperature_update();
+
+            let temperature = self.core_temperature.max(self.zones.temperature);
+            if temperature > critical_temp {
+                let cooldown = Duration::from_secs(thermal_config.critical_cooldown);
+                if self.critical.until.is_none() {
+                    warn!(
+                        "Critical temperature {temperature} above {critical_temp}, restoring defaults for {}s",
+                        cooldown.as_secs()
+                    );
+                }
+                self.critical.until = Some(Instant::now() + cooldown);
+            }
+        }
+
+        match self.critical.until {
+            Some(until) if Instant::now() >= until => {
+                info!("Cooled down below the critical temperature, resuming control");
+                self.critical.until = None;
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    pub const fn core_temperature(&self) -> u64 {
+        self.core_temperature
+    }
+
+    pub const fn cooling_down(&self) -> bool {
+        self.critical.until.is_some()
+    }
+
+    pub fn freq_cap(&mut self, config: &mut Config) -> f64 {
+        let thermal_config = config.thermal_config();
+
+        if self.zones.types != thermal_config.zones {
+            self.zones.load(thermal_config.zones);
+        }
+
+        if self.zones.sample_timer.elapsed() < THROTTLE_SAMPLE_TIME {
+            return self.zones.freq_cap;
+        }
+
+        self.zones.sample_timer = Instant::now();
+        self.zones.temperature_update();
+
+        let throttle_temp = match thermal_config.throttle_temp {
             TemperatureThreshold::Disabled => u64::MAX,
             TemperatureThreshold::Temp(t) => t,
         };
 
+        #[cfg(debug_assertions)]
+        debug!(
+            "throttle_temp: {throttle_temp}, zones temperature: {}",
+            self.zones.temperature
+        );
+
+        if self.zones.temperature > throttle_temp {
+            self.zones.freq_cap -= THROTTLE_STEP;
+        } else if self.zones.temperature.saturating_add(THROTTLE_HYSTERESIS) < throttle_temp {
+            self.zones.freq_cap += THROTTLE_STEP;
+        }
+
+        self.zones.freq_cap = self.zones.freq_cap.clamp(THROTTLE_MIN_CAP, 1.0);
+        self.zones.freq_cap
+    }
+
+    pub fn target_fps_offset(&mut self, config: &mut Config, mode: Mode) -> f64 {
+        let core_temp_thresh = config.mode_config(mode).core_temp_thresh;
+
         self.temperature_update();
 
         #[cfg(debug_assertions)]
         {
-            debug!("target_core_temperature: {target_core_temperature}");
+            debug!("core_temp_thresh: {core_temp_thresh:?}");
             debug!("core_temperature: {}", self.core_temperature);
         }
 
-        if self.core_temperature > target_core_temperature {
-            self.target_fps_offset -= 0.1;
-        } else {
-            self.target_fps_offset += 0.1;
-        }
-
+        self.target_fps_offset = step_thermal_offset(
+            self.target_fps_offset,
+            self.core_temperature,
+            core_temp_thresh,
+        );
         self.target_fps_offset
     }
 
@@ -88,3 +249,22 @@ impl Thermal {
             .unwrap_or_default();
     }
 }
+
+// Called every frame, the target drops while the cores are above the mode's threshold. Shared
+// with `simulate`
+pub fn step_thermal_offset(
+    offset: f64,
+    core_temperature: u64,
+    core_temp_thresh: TemperatureThreshold,
+) -> f64 {
+    let target_core_temperature = match core_temp_thresh {
+        TemperatureThreshold::Disabled => u64::MAX,
+        TemperatureThreshold::Temp(t) => t,
+    };
+
+    if core_temperature > target_core_temperature {
+        offset - 0.1
+    } else {
+        offset + 0.1
+    }
+}
@@ -15,13 +15,46 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::{Duration, Instant};
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use dumpsys_rs::Dumpsys;
+use inotify::{Inotify, WatchDescriptor, WatchMask, Watches};
+use log::{info, warn};
+use parking_lot::Mutex;
 
 use stringzilla::sz;
 
+use crate::{
+    cgroup::{Cgroup, Subsystem},
+    framework::{
+        config::FocusSource,
+        error::{Error, Result},
+    },
+};
+
 const REFRESH_TIME: Duration = Duration::from_secs(1);
+const EVENT_REFRESH_TIME: Duration = Duration::from_secs(5);
+const FALLBACK_DELAY: Duration = Duration::from_secs(10);
+const FALLBACK_REFRESH_TIME: Duration = Duration::from_secs(3);
+// Event log tags of a process start or a focus change, the names differ between Android versions
+const FOCUS_EVENT_TAGS: [&str; 6] = [
+    "am_proc_start",
+    "am_focused_activity",
+    "am_focused_stack",
+    "am_set_resumed_activity",
+    "wm_set_resumed_activity",
+    "wm_focused_root_task",
+];
+const LOGCAT_RESTART_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Default)]
 struct WindowsInfo {
@@ -51,10 +84,255 @@ impl WindowsInfo {
     }
 }
 
+// Some ROMs leave the window dump without sessions, ask ActivityManager for the TOP process instead
+struct ActivityFallback {
+    dumper: Option<Dumpsys>,
+    empty_since: Option<Instant>,
+    last_refresh: Option<Instant>,
+    pids: Vec<i32>,
+}
+
+impl ActivityFallback {
+    fn new() -> Self {
+        Self {
+            dumper: Dumpsys::new("activity"),
+            empty_since: None,
+            last_refresh: None,
+            pids: Vec::new(),
+        }
+    }
+
+    fn pids(&mut self, primary_empty: bool) -> Option<Vec<i32>> {
+        if !primary_empty {
+            self.empty_since = None;
+            if self.last_refresh.take().is_some() {
+                info!("Window dump reports top apps again, leave activity fallback");
+            }
+            return None;
+        }
+
+        let empty_since = *self.empty_since.get_or_insert_with(Instant::now);
+        if empty_since.elapsed() < FALLBACK_DELAY {
+            return None;
+        }
+
+        match self.last_refresh {
+            Some(last_refresh) if last_refresh.elapsed() < FALLBACK_REFRESH_TIME => (),
+            last_refresh => {
+                if last_refresh.is_none() {
+                    warn!("Window dump reports no top app, fallback to activity lru");
+                }
+
+                let dump = self.dumper.as_ref()?.dump(&["lru"]).ok()?;
+                self.pids = Self::parse_lru_top(&dump);
+                self.last_refresh = Some(Instant::now());
+            }
+        }
+
+        Some(self.pids.clone())
+    }
+
+    // e.g. `  #45: fg     TOP  LCMN 12345:com.example.game/u0a123 act:activities|recents`
+    fn parse_lru_top(dump: &str) -> Vec<i32> {
+        dump.lines()
+            .filter(|l| l.split_whitespace().nth(2) == Some("TOP"))
+            .filter_map(|l| {
+                l.split_whitespace()
+                    .find(|s| s.contains(':') && s.contains('/'))
+            })
+            .filter_map(|s| s.split(':').next()?.parse().ok())
+            .collect()
+    }
+}
+
+struct FocusEvents {
+    changed: Arc<AtomicBool>,
+    // Cleared when the watch thread gives up, the watcher polls again from then on
+    alive: Arc<AtomicBool>,
+    watches: Watches,
+    oom_watches: Vec<WatchDescriptor>,
+}
+
+impl FocusEvents {
+    fn new() -> Result<Self> {
+        let mut inotify = Inotify::init()?;
+        let mut watches = inotify.watches();
+        let procs = Cgroup::open(Subsystem::Cpuset, "top-app")
+            .ok_or(Error::Other("No top-app cpuset"))?
+            .procs();
+        watches.add(&procs, WatchMask::MODIFY | WatchMask::CLOSE_WRITE)?;
+        info!("Watching {} for focus changes", procs.display());
+
+        let changed = Arc::new(AtomicBool::new(true));
+        let alive = Arc::new(AtomicBool::new(true));
+
+        {
+            let changed = changed.clone();
+            let alive = alive.clone();
+            thread::Builder::new()
+                .name("TopAppThread".into())
+                .spawn(move || {
+                    let mut buffer = [0; 1024];
+                    let error = loop {
+                        match inotify.read_events_blocking(&mut buffer) {
+                            Ok(_) => changed.store(true, Ordering::Release),
+                            Err(e) => break e,
+                        }
+                    };
+                    warn!("Focus change watch failed, fallback to polling: {error}");
+                    alive.store(false, Ordering::Release);
+                    changed.store(true, Ordering::Release);
+                })?;
+        }
+
+        Ok(Self {
+            changed,
+            alive,
+            watches,
+            oom_watches: Vec::new(),
+        })
+    }
+
+    fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+
+    fn alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+
+    fn watch_oom_adj(&mut self, pids: &[i32]) {
+        for wd in self.oom_watches.drain(..) {
+            let _ = self.watches.remove(wd);
+        }
+
+        self.oom_watches = pids
+            .iter()
+            .filter_map(|pid| {
+                self.watches
+                    .add(format!("/proc/{pid}/oom_score_adj"), WatchMask::MODIFY)
+                    .ok()
+            })
+            .collect();
+    }
+}
+
+// Follows `logcat -b events`, on ROMs where the top-app cpuset is written seconds after the
+// switch the focus events arrive right away. Lines are only looked at for their tag and a burst
+// of them sets one flag, a busy event log can not queue up lookups
+struct LogEvents {
+    changed: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl LogEvents {
+    fn new() -> Result<Self> {
+        let changed = Arc::new(AtomicBool::new(true));
+        let stop = Arc::new(AtomicBool::new(false));
+        let child = Arc::new(Mutex::new(None));
+
+        {
+            let changed = changed.clone();
+            let stop = stop.clone();
+            let child = child.clone();
+            thread::Builder::new()
+                .name("FocusLogThread".into())
+                .spawn(move || Self::follow(&changed, &stop, &child))?;
+        }
+
+        info!("Following logcat focus events");
+
+        Ok(Self {
+            changed,
+            stop,
+            child,
+        })
+    }
+
+    fn follow(changed: &AtomicBool, stop: &AtomicBool, child: &Mutex<Option<Child>>) {
+        let mut line = String::new();
+
+        while !stop.load(Ordering::Acquire) {
+            let spawned = Command::new("logcat")
+                .args(["-b", "events", "-v", "brief", "-T", "1", "-s"])
+                .args(FOCUS_EVENT_TAGS)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut spawned = match spawned {
+                Ok(spawned) => spawned,
+                Err(e) => {
+                    warn!("Failed to start logcat: {e}");
+                    thread::sleep(LOGCAT_RESTART_DELAY);
+                    continue;
+                }
+            };
+
+            let Some(stdout) = spawned.stdout.take() else {
+                let _ = spawned.kill();
+                continue;
+            };
+            {
+                let mut child = child.lock();
+                *child = Some(spawned);
+                // Dropped between the check of the loop and the spawn, nobody else kills it
+                if stop.load(Ordering::Acquire) {
+                    if let Some(child) = child.as_mut() {
+                        let _ = child.kill();
+                    }
+                }
+            }
+
+            let mut reader = BufReader::new(stdout);
+            loop {
+                line.clear();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if FOCUS_EVENT_TAGS.iter().any(|tag| line.contains(tag)) {
+                            changed.store(true, Ordering::Release);
+                        }
+                    }
+                }
+            }
+
+            if let Some(mut exited) = child.lock().take() {
+                let _ = exited.kill();
+                let _ = exited.wait();
+            }
+
+            // Events missed while logcat was down, look the top apps up again
+            changed.store(true, Ordering::Release);
+            if !stop.load(Ordering::Acquire) {
+                warn!("logcat exited, restarting it");
+                thread::sleep(LOGCAT_RESTART_DELAY);
+            }
+        }
+    }
+
+    fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+}
+
+impl Drop for LogEvents {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        // The thread sees the end of the output and reaps it
+        if let Some(child) = self.child.lock().as_mut() {
+            let _ = child.kill();
+        }
+    }
+}
+
 pub struct TopAppsWatcher {
     windows_dumper: Dumpsys,
     cache: WindowsInfo,
     last_refresh: Instant,
+    focus_events: Option<FocusEvents>,
+    log_events: Option<LogEvents>,
+    fallback: ActivityFallback,
 }
 
 impl TopAppsWatcher {
@@ -66,10 +344,33 @@ impl TopAppsWatcher {
             }
         };
 
+        let focus_events = match FocusEvents::new() {
+            Ok(focus_events) => Some(focus_events),
+            Err(e) => {
+                warn!("Failed to watch focus changes, fallback to polling: {e}");
+                None
+            }
+        };
+
         Self {
             windows_dumper,
             cache: WindowsInfo::default(),
             last_refresh: Instant::now(),
+            focus_events,
+            log_events: None,
+            fallback: ActivityFallback::new(),
+        }
+    }
+
+    pub fn set_focus_source(&mut self, focus_source: FocusSource) {
+        match (focus_source, self.log_events.is_some()) {
+            (FocusSource::Logcat, false) => {
+                self.log_events = LogEvents::new()
+                    .map_err(|e| warn!("Failed to follow logcat focus events: {e}"))
+                    .ok();
+            }
+            (FocusSource::Cpuset, true) => self.log_events = None,
+            _ => (),
         }
     }
 
@@ -81,8 +382,28 @@ impl TopAppsWatcher {
         self.cache().visible_freeform_window
     }
 
+    fn need_refresh(&self) -> bool {
+        let logged = self
+            .log_events
+            .as_ref()
+            .is_some_and(LogEvents::take_changed);
+
+        logged
+            || self
+                .focus_events
+                .as_ref()
+                .filter(|focus_events| focus_events.alive())
+                .map_or_else(
+                    || self.last_refresh.elapsed() > REFRESH_TIME,
+                    |focus_events| {
+                        focus_events.take_changed()
+                            || self.last_refresh.elapsed() > EVENT_REFRESH_TIME
+                    },
+                )
+    }
+
     fn cache(&mut self) -> &WindowsInfo {
-        if self.last_refresh.elapsed() > REFRESH_TIME {
+        if self.need_refresh() {
             let dump = loop {
                 match self.windows_dumper.dump(&["visible-apps"]) {
                     Ok(dump) => break dump,
@@ -92,7 +413,16 @@ impl TopAppsWatcher {
                     }
                 }
             };
-            self.cache = WindowsInfo::new(&dump);
+            let mut cache = WindowsInfo::new(&dump);
+            if let Some(pids) = self.fallback.pids(cache.pids.is_empty()) {
+                cache.pids = pids;
+            }
+            if let Some(focus_events) = self.focus_events.as_mut() {
+                if cache.pids != self.cache.pids {
+                    focus_events.watch_oom_adj(&cache.pids);
+                }
+            }
+            self.cache = cache;
 
             self.last_refresh = Instant::now();
         }
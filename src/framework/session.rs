@@ -0,0 +1,271 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    cpu_common::EnergyModel,
+    framework::{error::Result, node::Mode},
+};
+
+const HISTORY_PATH: &str = "/sdcard/Android/fas-rs/history";
+const BATTERY_PATH: &str = "/sys/class/power_supply/battery";
+const MAX_HISTORY_LINES: usize = 100;
+// Shorter sessions are mostly focus flickers and not worth a history entry
+const MIN_SESSION_TIME: Duration = Duration::from_secs(10);
+const POWER_SAMPLE_TIME: Duration = Duration::from_secs(1);
+// Frametimes are binned by 0.1ms, the last bin takes everything from 1s on
+const BIN_US: u128 = 100;
+const BINS: usize = 10_001;
+const FREQ_BUCKET_MHZ: isize = 100;
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub pkg: String,
+    pub start: u64,
+    pub duration_secs: f64,
+    pub frames: u64,
+    pub avg_fps: f64,
+    pub low_1_frametime_ms: f64,
+    pub low_01_frametime_ms: f64,
+    // Frame count per 1ms frametime bucket
+    pub frametime_histogram: BTreeMap<u32, u64>,
+    // Seconds spent per 100MHz bucket of the highest fas frequency
+    pub freq_residency: BTreeMap<isize, f64>,
+    // Integrated battery discharge power, absent when the battery was not discharging
+    pub energy_mwh: Option<f64>,
+    // Cpu energy from the energy model, in total and per mode that was active meanwhile
+    pub cpu_energy_mwh: f64,
+    pub cpu_energy_by_mode_mwh: BTreeMap<String, f64>,
+}
+
+struct Session {
+    pkg: String,
+    start: u64,
+    start_timer: Instant,
+    histogram: Vec<u64>,
+    frames: u64,
+    freq_residency: BTreeMap<isize, f64>,
+    freq_timer: Instant,
+    energy_mwh: Option<f64>,
+    cpu_energy_by_mode_mwh: BTreeMap<String, f64>,
+    power_timer: Instant,
+}
+
+pub struct SessionTracker {
+    session: Option<Session>,
+    energy_model: EnergyModel,
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        Self {
+            session: None,
+            energy_model: EnergyModel::new(),
+        }
+    }
+
+    pub fn start<S: AsRef<str>>(&mut self, pkg: S) {
+        self.finish();
+        self.energy_model.sample();
+
+        self.session = Some(Session {
+            pkg: pkg.as_ref().to_string(),
+            start: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            start_timer: Instant::now(),
+            histogram: vec![0; BINS],
+            frames: 0,
+            freq_residency: BTreeMap::new(),
+            freq_timer: Instant::now(),
+            energy_mwh: None,
+            cpu_energy_by_mode_mwh: BTreeMap::new(),
+            power_timer: Instant::now(),
+        });
+    }
+
+    pub fn frame(&mut self, frametime: Duration) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+
+        let bin = usize::try_from(frametime.as_micros() / BIN_US)
+            .unwrap_or(BINS - 1)
+            .min(BINS - 1);
+        session.histogram[bin] += 1;
+        session.frames += 1;
+    }
+
+    // Called per policy run, time is charged to the frequency that was in effect since the last call
+    pub fn sample(&mut self, freq: isize, mode: Mode) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+
+        let bucket = freq / 1000 / FREQ_BUCKET_MHZ * FREQ_BUCKET_MHZ;
+        *session.freq_residency.entry(bucket).or_default() +=
+            session.freq_timer.elapsed().as_secs_f64();
+        session.freq_timer = Instant::now();
+
+        let elapsed = session.power_timer.elapsed();
+        if elapsed >= POWER_SAMPLE_TIME {
+            session.power_timer = Instant::now();
+            if let Some(power) = discharge_power_mw() {
+                *session.energy_mwh.get_or_insert(0.0) += power * elapsed.as_secs_f64() / 3600.0;
+            }
+
+            *session
+                .cpu_energy_by_mode_mwh
+                .entry(mode.to_string())
+                .or_default() += self.energy_model.sample();
+        }
+    }
+
+    // Cpu energy of the running session so far
+    pub fn cpu_energy_mwh(&self) -> Option<f64> {
+        self.session
+            .as_ref()
+            .map(|session| session.cpu_energy_by_mode_mwh.values().sum())
+    }
+
+    pub fn finish(&mut self) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+
+        let duration = session.start_timer.elapsed();
+        if duration < MIN_SESSION_TIME || session.frames == 0 {
+            return;
+        }
+
+        let summary = session.summarize(duration);
+        info!(
+            event_type = "session_summary",
+            game = summary.pkg.as_str(),
+            duration_secs = summary.duration_secs,
+            avg_fps = summary.avg_fps,
+            low_1_frametime_ms = summary.low_1_frametime_ms,
+            low_01_frametime_ms = summary.low_01_frametime_ms,
+            cpu_energy_mwh = summary.cpu_energy_mwh;
+            "Session of [{}]: {:.0}s, avg {:.1} fps, 1% low {:.1}ms, 0.1% low {:.1}ms, cpu {:.0}mWh{}",
+            summary.pkg,
+            summary.duration_secs,
+            summary.avg_fps,
+            summary.low_1_frametime_ms,
+            summary.low_01_frametime_ms,
+            summary.cpu_energy_mwh,
+            summary
+                .energy_mwh
+                .map(|energy| format!(", battery {energy:.0}mWh"))
+                .unwrap_or_default()
+        );
+
+        if let Err(e) = Self::save(&summary) {
+            warn!("Failed to save session history of {}: {e}", summary.pkg);
+        }
+    }
+
+    fn save(summary: &Summary) -> Result<()> {
+        fs::create_dir_all(HISTORY_PATH)?;
+        let path = Path::new(HISTORY_PATH).join(format!("{}.jsonl", summary.pkg));
+        let line = serde_json::to_string(summary)?;
+
+        let history = fs::read_to_string(&path).unwrap_or_default();
+        let lines: Vec<&str> = history.lines().collect();
+        if lines.len() >= MAX_HISTORY_LINES {
+            let mut content = lines[lines.len() + 1 - MAX_HISTORY_LINES..].join("\n");
+            content.push('\n');
+            content.push_str(&line);
+            content.push('\n');
+            fs::write(&path, content)?;
+        } else {
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            writeln!(file, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Session {
+    fn summarize(self, duration: Duration) -> Summary {
+        let mut frametime_histogram = BTreeMap::new();
+        for (bin, count) in self.histogram.iter().enumerate().filter(|(_, c)| **c > 0) {
+            let ms = u32::try_from(bin / 10).unwrap_or(u32::MAX);
+            *frametime_histogram.entry(ms).or_default() += count;
+        }
+
+        Summary {
+            avg_fps: self.frames as f64 / duration.as_secs_f64(),
+            low_1_frametime_ms: self.percentile(0.99),
+            low_01_frametime_ms: self.percentile(0.999),
+            pkg: self.pkg,
+            start: self.start,
+            duration_secs: duration.as_secs_f64(),
+            frames: self.frames,
+            frametime_histogram,
+            freq_residency: self.freq_residency,
+            energy_mwh: self.energy_mwh,
+            cpu_energy_mwh: self.cpu_energy_by_mode_mwh.values().sum(),
+            cpu_energy_by_mode_mwh: self.cpu_energy_by_mode_mwh,
+        }
+    }
+
+    // Upper edge of the bin holding the given quantile, in ms
+    fn percentile(&self, quantile: f64) -> f64 {
+        let target = (self.frames as f64 * quantile).ceil() as u64;
+        let mut seen = 0;
+
+        for (bin, count) in self.histogram.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                return (bin + 1) as f64 * BIN_US as f64 / 1000.0;
+            }
+        }
+
+        BINS as f64 * BIN_US as f64 / 1000.0
+    }
+}
+
+// current_now is in uA and voltage_now in uV on most kernels, the sign of current varies by vendor
+fn discharge_power_mw() -> Option<f64> {
+    let path = Path::new(BATTERY_PATH);
+    let read = |node: &str| {
+        fs::read_to_string(path.join(node))
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+
+    if read("status")? != "Discharging" {
+        return None;
+    }
+
+    let current: f64 = read("current_now")?.parse().ok()?;
+    let voltage: f64 = read("voltage_now")?.parse().ok()?;
+    Some(current.abs() * voltage / 1e9)
+}
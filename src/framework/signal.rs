@@ -0,0 +1,48 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::info;
+
+use crate::framework::error::Result;
+
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(_: libc::c_int) {
+    // Only async-signal-safe work here, the looper notices the flag on its next iteration
+    TERMINATED.store(true, Ordering::Release);
+}
+
+pub fn install() -> Result<()> {
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        if unsafe { libc::signal(signal, handle_signal as libc::sighandler_t) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+
+    info!("Termination signal handlers installed");
+
+    Ok(())
+}
+
+pub fn terminated() -> bool {
+    TERMINATED.load(Ordering::Acquire)
+}
@@ -0,0 +1,156 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::framework::error::Result;
+
+const STATE_PATH: &str = "/data/adb/fas-rs/state.json";
+// Where adaptive margins lived before they moved into the state file
+const LEGACY_ADAPTIVE_PATH: &str = "/sdcard/Android/fas-rs/adaptive_margin.toml";
+const SAVE_TIME: Duration = Duration::from_secs(60);
+// Weight of the newest sample in the utilization average
+const UTIL_SMOOTHING: f64 = 0.2;
+
+// What the control loop learned about one game
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameState {
+    pub adaptive_margin: Option<f64>,
+    pub target_fps_offset: f64,
+    // Long running average of util_max while the game was scheduled
+    pub util: Option<f64>,
+    // Names of the threads that carried the frame, including ones without a known render prefix
+    pub render_threads: Vec<String>,
+}
+
+impl GameState {
+    pub fn observe_util(&mut self, util: f64) {
+        if util <= 0.0 {
+            return;
+        }
+
+        self.util = Some(
+            self.util
+                .map_or(util, |avg| (util - avg).mul_add(UTIL_SMOOTHING, avg)),
+        );
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct State {
+    games: BTreeMap<String, GameState>,
+}
+
+// Learned per-game data that survives daemon restarts, reboots and module updates
+#[derive(Debug)]
+pub struct StateStore {
+    state: State,
+    save_timer: Instant,
+    dirty: bool,
+}
+
+impl StateStore {
+    pub fn load() -> Self {
+        let state = match fs::read_to_string(STATE_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| warn!("Ignoring broken {STATE_PATH}: {e}"))
+                .unwrap_or_default(),
+            Err(_) => Self::migrate(),
+        };
+
+        info!("Loaded learned state of {} games", state.games.len());
+
+        Self {
+            state,
+            save_timer: Instant::now(),
+            dirty: false,
+        }
+    }
+
+    fn migrate() -> State {
+        let offsets: BTreeMap<String, f64> = fs::read_to_string(LEGACY_ADAPTIVE_PATH)
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+
+        State {
+            games: offsets
+                .into_iter()
+                .map(|(pkg, offset)| {
+                    (
+                        pkg,
+                        GameState {
+                            adaptive_margin: Some(offset),
+                            ..GameState::default()
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn game(&self, pkg: &str) -> GameState {
+        self.state.games.get(pkg).cloned().unwrap_or_default()
+    }
+
+    pub fn update<F: FnOnce(&mut GameState)>(&mut self, pkg: &str, f: F) {
+        f(self.state.games.entry(pkg.to_string()).or_default());
+        self.dirty = true;
+    }
+
+    // Whether a periodic save is due, callers gather what they learned only then
+    pub fn due(&self) -> bool {
+        self.save_timer.elapsed() >= SAVE_TIME
+    }
+
+    pub fn save(&mut self) {
+        self.save_timer = Instant::now();
+
+        if !self.dirty {
+            return;
+        }
+
+        self.dirty = false;
+        if let Err(e) = self.write() {
+            warn!("Failed to save {STATE_PATH}: {e}");
+        }
+    }
+
+    // Written to a temporary file first, a crash mid-write must not lose everything learned
+    fn write(&self) -> Result<()> {
+        let path = Path::new(STATE_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(&self.state)?)?;
+        fs::rename(temp, path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    os::unix::fs::OpenOptionsExt,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::framework::{error::Result, node::Mode};
+
+const INDEX_HTML: &str = include_str!("index.html");
+const HISTORY_LEN: usize = 240;
+const SAMPLE_TIME: Duration = Duration::from_millis(250);
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_BODY_LEN: usize = 4096;
+// Any app and any page open in a browser can reach localhost, the api answers only to whoever
+// can read this root-only file. A new token is written every time the tuner starts
+const TOKEN_FILE: &str = "/data/adb/fas-rs/tuner.token";
+const TOKEN_HEADER: &str = "x-fas-rs-token";
+const TOKEN_LEN: usize = 16;
+const MAX_MARGIN_FPS: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Sample {
+    pub frametime_ms: f64,
+    pub util: f64,
+    pub freq_mhz: f64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+struct State {
+    pkg: Option<String>,
+    mode: String,
+    margin_fps: f64,
+    samples: VecDeque<Sample>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tune {
+    pub margin_fps: Option<f64>,
+    pub mode: Option<String>,
+}
+
+struct Server {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        let _ = fs::remove_file(TOKEN_FILE);
+    }
+}
+
+// What a request carries besides its body
+#[derive(Debug, Default)]
+struct Headers {
+    content_length: usize,
+    origin: Option<String>,
+    token: Option<String>,
+}
+
+pub struct Tuner {
+    server: Option<Server>,
+    // A port that failed to bind is only tried again once the configured port changes
+    failed_port: Option<u16>,
+    state: Arc<Mutex<State>>,
+    sx: Sender<Tune>,
+    rx: Receiver<Tune>,
+    sample_timer: Instant,
+}
+
+impl Tuner {
+    pub fn new() -> Self {
+        let (sx, rx) = mpsc::channel();
+
+        Self {
+            server: None,
+            failed_port: None,
+            state: Arc::new(Mutex::new(State::default())),
+            sx,
+            rx,
+            sample_timer: Instant::now(),
+        }
+    }
+
+    // `port == 0` disables the tuner
+    pub fn set_port(&mut self, port: u16) {
+        if self.server.as_ref().map_or(0, |server| server.port) == port
+            || self.failed_port == Some(port)
+        {
+            return;
+        }
+
+        self.server = None;
+        self.failed_port = None;
+        if port == 0 {
+            return;
+        }
+
+        match Self::spawn(port, self.state.clone(), self.sx.clone()) {
+            Ok(server) => {
+                info!("Tuner listening on http://127.0.0.1:{port}/?token=<{TOKEN_FILE}>");
+                self.server = Some(server);
+            }
+            Err(e) => {
+                warn!("Failed to start tuner on port {port}: {e}");
+                self.failed_port = Some(port);
+            }
+        }
+    }
+
+    pub const fn enabled(&self) -> bool {
+        self.server.is_some()
+    }
+
+    pub fn set_game(&mut self, pkg: Option<&str>, mode: &str, margin_fps: f64) {
+        let mut state = self.state.lock();
+
+        if state.pkg.as_deref() != pkg {
+            state.samples.clear();
+        }
+
+        state.pkg = pkg.map(String::from);
+        state.mode = mode.to_string();
+        state.margin_fps = margin_fps;
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        if self.server.is_none() || self.sample_timer.elapsed() < SAMPLE_TIME {
+            return;
+        }
+
+        self.sample_timer = Instant::now();
+
+        let mut state = self.state.lock();
+        if state.samples.len() >= HISTORY_LEN {
+            state.samples.pop_front();
+        }
+        state.samples.push_back(sample);
+    }
+
+    pub fn try_recv(&self) -> Option<Tune> {
+        self.rx.try_recv().ok()
+    }
+
+    fn spawn(port: u16, state: Arc<Mutex<State>>, sx: Sender<Tune>) -> Result<Server> {
+        let token = write_token()?;
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+
+        {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("TunerThread".into())
+                .spawn(move || serve(&listener, port, &token, &state, &sx, &stop))?;
+        }
+
+        Ok(Server { port, stop })
+    }
+}
+
+fn write_token() -> Result<String> {
+    let mut bytes = [0; TOKEN_LEN];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token = bytes.iter().fold(String::new(), |mut token, byte| {
+        let _ = write!(token, "{byte:02x}");
+        token
+    });
+
+    let _ = fs::remove_file(TOKEN_FILE);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(TOKEN_FILE)?
+        .write_all(token.as_bytes())?;
+
+    Ok(token)
+}
+
+fn serve(
+    listener: &TcpListener,
+    port: u16,
+    token: &str,
+    state: &Mutex<State>,
+    sx: &Sender<Tune>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Acquire) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = handle_client(stream, port, token, state, sx) {
+                    warn!("Tuner client disconnected with error: {e}");
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+            Err(e) => warn!("Failed to accept tuner client: {e}"),
+        }
+    }
+}
+
+fn handle_client(
+    stream: TcpStream,
+    port: u16,
+    token: &str,
+    state: &Mutex<State>,
+    sx: &Sender<Tune>,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut headers = Headers::default();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                headers.content_length = value.parse().unwrap_or_default();
+            } else if name.eq_ignore_ascii_case("origin") {
+                headers.origin = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case(TOKEN_HEADER) {
+                headers.token = Some(value.to_string());
+            }
+        }
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    // The page is opened as `/?token=...`, the script reads the token from there
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    let mut writer = stream;
+
+    if path.starts_with("/api/") && !authorized(&headers, port, token) {
+        return respond(&mut writer, "403 Forbidden", "text/plain", "");
+    }
+
+    match (method, path) {
+        ("GET", "/") => respond(
+            &mut writer,
+            "200 OK",
+            "text/html; charset=utf-8",
+            INDEX_HTML,
+        ),
+        ("GET", "/api/state") => {
+            let body = serde_json::to_string(&*state.lock())?;
+            respond(&mut writer, "200 OK", "application/json", &body)
+        }
+        ("POST", "/api/tune") if headers.content_length <= MAX_BODY_LEN => {
+            let mut body = vec![0; headers.content_length];
+            reader.read_exact(&mut body)?;
+
+            match serde_json::from_slice::<Tune>(&body)
+                .map_err(|e| e.to_string())
+                .and_then(validate)
+            {
+                Ok(tune) => {
+                    let _ = sx.send(tune);
+                    respond(&mut writer, "204 No Content", "text/plain", "")
+                }
+                Err(e) => respond(&mut writer, "400 Bad Request", "text/plain", &e),
+            }
+        }
+        _ => respond(&mut writer, "404 Not Found", "text/plain", ""),
+    }
+}
+
+// A page of another origin could still send a simple POST, it never gets to see the token
+// though. A browser always sends the origin of a cross-origin request
+fn authorized(headers: &Headers, port: u16, token: &str) -> bool {
+    let local_origin = headers.origin.as_deref().is_none_or(|origin| {
+        origin == format!("http://127.0.0.1:{port}") || origin == format!("http://localhost:{port}")
+    });
+
+    local_origin && headers.token.as_deref() == Some(token)
+}
+
+// Checked before anything reaches games.toml
+fn validate(tune: Tune) -> std::result::Result<Tune, String> {
+    if let Some(margin_fps) = tune.margin_fps {
+        if !(0.0..=MAX_MARGIN_FPS).contains(&margin_fps) {
+            return Err(format!("margin_fps must be within 0 and {MAX_MARGIN_FPS}"));
+        }
+    }
+
+    if let Some(mode) = &tune.mode {
+        if mode.parse::<Mode>().is_err() {
+            return Err("mode must be one of powersave, balance, performance, fast".into());
+        }
+    }
+
+    Ok(tune)
+}
+
+fn respond(writer: &mut TcpStream, status: &str, content_type: &str, body: &str) -> Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    env, fs,
+    os::unix::process::CommandExt,
+    path::Path,
+    process::{self, Command},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicI32, AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{error, info};
+
+use crate::{framework::error::Result, paths};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const STALL_TIME: Duration = Duration::from_secs(10);
+const MSM_PERFORMANCE_MAX: &str = "/sys/module/msm_performance/parameters/cpu_max_freq";
+const MSM_PERFORMANCE_MIN: &str = "/sys/module/msm_performance/parameters/cpu_min_freq";
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// Milliseconds since the watchdog epoch, offset by one so that 0 can mean idle
+fn now_ms() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1
+}
+
+#[derive(Debug, Default)]
+struct Beat {
+    busy_since: AtomicU64,
+    tid: AtomicI32,
+}
+
+// A component reports through its heartbeat that it is making progress. It stalls when it
+// stays busy without a new beat for longer than `STALL_TIME`
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    name: &'static str,
+    beat: Arc<Beat>,
+}
+
+impl Heartbeat {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            beat: Arc::new(Beat::default()),
+        }
+    }
+
+    pub fn beat(&self) {
+        self.beat
+            .tid
+            .store(unsafe { libc::gettid() }, Ordering::Relaxed);
+        self.beat.busy_since.store(now_ms(), Ordering::Release);
+    }
+
+    // For components that only run now and then, idle components never stall
+    pub fn idle(&self) {
+        self.beat.busy_since.store(0, Ordering::Release);
+    }
+
+    // Beats now and goes idle when the guard drops
+    pub fn busy(&self) -> BusyGuard {
+        self.beat();
+        BusyGuard {
+            heartbeat: self.clone(),
+        }
+    }
+
+    fn stalled_for(&self) -> Option<Duration> {
+        let busy_since = self.beat.busy_since.load(Ordering::Acquire);
+        if busy_since == 0 {
+            return None;
+        }
+
+        let stalled_for = Duration::from_millis(now_ms().saturating_sub(busy_since));
+        (stalled_for >= STALL_TIME).then_some(stalled_for)
+    }
+}
+
+pub struct BusyGuard {
+    heartbeat: Heartbeat,
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        self.heartbeat.idle();
+    }
+}
+
+// Once a component stalls, stale caps would stay applied for as long as it hangs. The watchdog
+// hands the frequencies back and restarts the daemon in place, which restarts every component
+pub fn start(heartbeats: Vec<Heartbeat>) -> Result<()> {
+    thread::Builder::new()
+        .name("WatchdogThread".into())
+        .spawn(move || {
+            loop {
+                thread::sleep(CHECK_INTERVAL);
+
+                if let Some((heartbeat, stalled_for)) = heartbeats.iter().find_map(|heartbeat| {
+                    heartbeat
+                        .stalled_for()
+                        .map(|stalled_for| (heartbeat, stalled_for))
+                }) {
+                    report(heartbeat, stalled_for);
+                    restore_frequencies();
+                    restart();
+                }
+            }
+        })?;
+
+    info!("Watchdog started");
+    Ok(())
+}
+
+fn report(heartbeat: &Heartbeat, stalled_for: Duration) {
+    let tid = heartbeat.beat.tid.load(Ordering::Relaxed);
+    let task = Path::new("/proc/self/task").join(tid.to_string());
+    let read = |node: &str| {
+        fs::read_to_string(task.join(node))
+            .map(|content| content.trim().to_string())
+            .unwrap_or_else(|_| "unknown".into())
+    };
+
+    // The state follows the parenthesized comm, which may itself contain spaces
+    let stat = read("stat");
+    let state = stat
+        .rsplit_once(") ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .unwrap_or("unknown")
+        .to_string();
+
+    error!(
+        "Watchdog: {} stalled for {:.1}s on thread {tid} ({}, state {state}, wchan {})",
+        heartbeat.name,
+        stalled_for.as_secs_f64(),
+        read("comm"),
+        read("wchan")
+    );
+
+    if let Ok(stack) = fs::read_to_string(task.join("stack")) {
+        for frame in stack.lines() {
+            error!("Watchdog:     {frame}");
+        }
+    }
+}
+
+// Hardware limits of every policy, written directly since the writer thread may be the one stuck
+fn restore_frequencies() {
+    let Ok(entries) = fs::read_dir(paths::cpufreq()) else {
+        return;
+    };
+
+    let mut msm_max = Vec::new();
+    let mut msm_min = Vec::new();
+
+    for policy in entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+    {
+        let read = |node: &str| {
+            fs::read_to_string(policy.join(node))
+                .map(|content| content.trim().to_string())
+                .ok()
+        };
+        let (Some(max), Some(min)) = (read("cpuinfo_max_freq"), read("cpuinfo_min_freq")) else {
+            continue;
+        };
+
+        let _ = fs::write(policy.join("scaling_max_freq"), &max);
+        let _ = fs::write(policy.join("scaling_min_freq"), &min);
+
+        for cpu in read("affected_cpus").unwrap_or_default().split_whitespace() {
+            msm_max.push(format!("{cpu}:{max}"));
+            msm_min.push(format!("{cpu}:{min}"));
+        }
+    }
+
+    if Path::new(MSM_PERFORMANCE_MAX).exists() {
+        let _ = fs::write(MSM_PERFORMANCE_MAX, msm_max.join(" "));
+        let _ = fs::write(MSM_PERFORMANCE_MIN, msm_min.join(" "));
+    }
+
+    error!("Watchdog: restored cpu frequency limits");
+}
+
+fn restart() -> ! {
+    error!("Watchdog: restarting fas-rs");
+    log::logger().flush();
+
+    let e = Command::new("/proc/self/exe")
+        .args(env::args_os().skip(1))
+        .exec();
+
+    error!("Watchdog: failed to restart: {e}");
+    log::logger().flush();
+    process::exit(1)
+}
@@ -0,0 +1,144 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use stringzilla::sz;
+
+use crate::{
+    file_handler::FileHandler,
+    paths::{self, Root},
+};
+
+const ADRENO_PATH: &str = "/sys/class/kgsl/kgsl-3d0";
+const DEVFREQ_PATH: &str = "/sys/class/devfreq";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Adreno,
+    Mali,
+}
+
+#[derive(Debug)]
+pub struct Info {
+    pub backend: Backend,
+    devfreq_path: PathBuf,
+    freqs: Vec<isize>,
+}
+
+impl Info {
+    pub fn new() -> Result<Self> {
+        let (backend, devfreq_path) = Self::detect()?;
+
+        let freqs_content = fs::read_to_string(devfreq_path.join("available_frequencies"))
+            .context("Failed to read gpu frequencies")?;
+        let mut freqs: Vec<isize> = freqs_content
+            .split_whitespace()
+            .map(|f| f.parse::<isize>().context("Failed to parse gpu frequency"))
+            .collect::<Result<_>>()?;
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        if freqs.is_empty() {
+            bail!("No gpu frequencies available");
+        }
+
+        Ok(Self {
+            backend,
+            devfreq_path,
+            freqs,
+        })
+    }
+
+    fn detect() -> Result<(Backend, PathBuf)> {
+        // Only the kgsl driver puts its devfreq below a kgsl directory
+        if let Some(path) = paths::get(Root::Gpu) {
+            let backend = if path.to_string_lossy().contains("kgsl") {
+                Backend::Adreno
+            } else {
+                Backend::Mali
+            };
+            return Ok((backend, path.to_path_buf()));
+        }
+
+        let adreno_devfreq = Path::new(ADRENO_PATH).join("devfreq");
+        if adreno_devfreq.exists() {
+            return Ok((Backend::Adreno, adreno_devfreq));
+        }
+
+        for entry in fs::read_dir(DEVFREQ_PATH)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            if sz::find(name, "mali").is_some() {
+                return Ok((Backend::Mali, path));
+            }
+        }
+
+        bail!("No supported gpu found")
+    }
+
+    pub fn min_freq(&self) -> isize {
+        self.freqs.first().copied().unwrap_or_default()
+    }
+
+    pub fn max_freq(&self) -> isize {
+        self.freqs.last().copied().unwrap_or_default()
+    }
+
+    pub fn read_freq(&self) -> isize {
+        fs::read_to_string(self.devfreq_path.join("cur_freq"))
+            .ok()
+            .and_then(|freq| freq.trim().parse().ok())
+            .unwrap_or_else(|| self.max_freq())
+    }
+
+    pub fn write_freq(&self, freq: isize, is_janked: bool, file_handler: &mut FileHandler) {
+        let freq = self
+            .freqs
+            .iter()
+            .find(|f| **f >= freq)
+            .copied()
+            .unwrap_or_else(|| self.max_freq());
+        let min_freq = if is_janked { freq } else { self.min_freq() };
+
+        file_handler.queue(self.min_freq_path(), self.min_freq().to_string());
+        file_handler.queue(self.max_freq_path(), freq.to_string());
+        file_handler.queue(self.min_freq_path(), min_freq.to_string());
+        file_handler.flush();
+    }
+
+    pub fn reset(&self, file_handler: &mut FileHandler) {
+        file_handler.queue(self.max_freq_path(), self.max_freq().to_string());
+        file_handler.queue(self.min_freq_path(), self.min_freq().to_string());
+        file_handler.flush();
+    }
+
+    fn max_freq_path(&self) -> PathBuf {
+        self.devfreq_path.join("max_freq")
+    }
+
+    fn min_freq_path(&self) -> PathBuf {
+        self.devfreq_path.join("min_freq")
+    }
+}
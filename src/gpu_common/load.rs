@@ -0,0 +1,169 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+const KGSL_BUSY_PERCENTAGE: &str = "/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage";
+const KGSL_GPUBUSY: &str = "/sys/class/kgsl/kgsl-3d0/gpubusy";
+const MALI_UTIL_PATHS: [&str; 3] = [
+    "/sys/kernel/gpu/gpu_busy",
+    "/sys/class/misc/mali0/device/utilization",
+    "/proc/mali/utilization",
+];
+const TRACING_PATHS: [&str; 2] = ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+const TRACE_INSTANCE: &str = "instances/fas-rs";
+const WORK_PERIOD_EVENT: &str = "events/power/gpu_work_period/enable";
+// Sampled far less often than frames arrive, kgsl counters and work periods need a window
+const SAMPLE_TIME: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+enum Source {
+    // A percentage, optionally followed by more fields
+    Percentage(PathBuf),
+    // kgsl `busy total` counters of the last sampling window
+    Counters(PathBuf),
+    // The gpu_work_period tracepoint, read from a trace instance of our own
+    WorkPeriod { instance: PathBuf, timer: Instant },
+}
+
+// Gpu busy ratio of the last sample window, 0.0 to 1.0
+#[derive(Debug)]
+pub struct GpuLoad {
+    source: Source,
+    busy: Option<f64>,
+    sample_timer: Instant,
+}
+
+impl GpuLoad {
+    pub fn new() -> Option<Self> {
+        let source = Self::detect()?;
+        info!("Gpu load source: {source:?}");
+        Some(Self {
+            source,
+            busy: None,
+            sample_timer: Instant::now(),
+        })
+    }
+
+    fn detect() -> Option<Source> {
+        if readable(KGSL_BUSY_PERCENTAGE) {
+            return Some(Source::Percentage(KGSL_BUSY_PERCENTAGE.into()));
+        }
+
+        if readable(KGSL_GPUBUSY) {
+            return Some(Source::Counters(KGSL_GPUBUSY.into()));
+        }
+
+        if let Some(path) = MALI_UTIL_PATHS.into_iter().find(|path| readable(path)) {
+            return Some(Source::Percentage(path.into()));
+        }
+
+        TRACING_PATHS
+            .into_iter()
+            .map(Path::new)
+            .find(|tracing| tracing.join(WORK_PERIOD_EVENT).exists())
+            .and_then(|tracing| {
+                let instance = tracing.join(TRACE_INSTANCE);
+                let _ = fs::create_dir(&instance);
+                fs::write(instance.join(WORK_PERIOD_EVENT), "1").ok()?;
+                fs::write(instance.join("trace"), "").ok()?;
+                Some(Source::WorkPeriod {
+                    instance,
+                    timer: Instant::now(),
+                })
+            })
+    }
+
+    // The last busy ratio, resampled once `SAMPLE_TIME` passed
+    pub fn sample(&mut self) -> Option<f64> {
+        if self.sample_timer.elapsed() >= SAMPLE_TIME {
+            self.sample_timer = Instant::now();
+            self.busy = self.read();
+        }
+
+        self.busy
+    }
+
+    fn read(&mut self) -> Option<f64> {
+        let busy = match &mut self.source {
+            Source::Percentage(path) => {
+                let content = fs::read_to_string(path).ok()?;
+                let percentage: f64 = content
+                    .split_whitespace()
+                    .next()?
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()?;
+                percentage / 100.0
+            }
+            Source::Counters(path) => {
+                let content = fs::read_to_string(path).ok()?;
+                let mut fields = content.split_whitespace();
+                let busy: f64 = fields.next()?.parse().ok()?;
+                let total: f64 = fields.next()?.parse().ok()?;
+                if total <= 0.0 {
+                    return None;
+                }
+                busy / total
+            }
+            Source::WorkPeriod { instance, timer } => {
+                let elapsed = timer.elapsed().as_nanos() as f64;
+                *timer = Instant::now();
+                let trace = fs::read_to_string(instance.join("trace")).ok()?;
+                let _ = fs::write(instance.join("trace"), "");
+                work_period_active_ns(&trace)? / elapsed.max(1.0)
+            }
+        };
+
+        Some(busy.clamp(0.0, 1.0))
+    }
+}
+
+impl Drop for GpuLoad {
+    fn drop(&mut self) {
+        if let Source::WorkPeriod { instance, .. } = &self.source {
+            let _ = fs::write(instance.join(WORK_PERIOD_EVENT), "0");
+            let _ = fs::remove_dir(instance);
+        }
+    }
+}
+
+// Active time summed over every uid of the gpu, None when the driver reported nothing
+fn work_period_active_ns(trace: &str) -> Option<f64> {
+    let active: Vec<f64> = trace
+        .lines()
+        .filter(|line| line.contains("gpu_work_period:"))
+        .filter_map(|line| {
+            line.split_whitespace()
+                .find_map(|field| field.strip_prefix("total_active_duration_ns="))?
+                .parse()
+                .ok()
+        })
+        .collect();
+
+    (!active.is_empty()).then(|| active.iter().sum())
+}
+
+fn readable(path: &str) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| !content.trim().is_empty())
+}
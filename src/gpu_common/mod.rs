@@ -0,0 +1,102 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+mod gpu_info;
+mod load;
+
+use anyhow::Result;
+#[cfg(debug_assertions)]
+use log::debug;
+use log::info;
+
+use crate::file_handler::FileHandler;
+use gpu_info::Info;
+pub use load::GpuLoad;
+
+#[derive(Debug)]
+pub struct GpuController {
+    info: Info,
+    file_handler: FileHandler,
+    cur_fas_freq: isize,
+    // Whether the limits are fas-rs's, only then there is anything to give back
+    touched: bool,
+}
+
+impl GpuController {
+    pub fn new() -> Result<Self> {
+        let info = Info::new()?;
+        info!(
+            "Gpu frequency control available, backend: {:?}",
+            info.backend
+        );
+
+        #[cfg(debug_assertions)]
+        debug!("gpu info: {info:?}");
+
+        Ok(Self {
+            cur_fas_freq: info.max_freq(),
+            info,
+            file_handler: FileHandler::new(),
+            touched: false,
+        })
+    }
+
+    pub fn init_game(&mut self) {
+        self.cur_fas_freq = self.info.max_freq();
+        self.init_default();
+    }
+
+    // Also what `gpu_control = false` leaves the gpu at
+    pub fn init_default(&mut self) {
+        if self.touched {
+            self.info.reset(&mut self.file_handler);
+            self.touched = false;
+        }
+    }
+
+    // `util` is the busy ratio sampled by `GpuLoad`, None without a load source
+    pub fn fas_update_freq(
+        &mut self,
+        control: isize,
+        is_janked: bool,
+        cpu_max_freq: isize,
+        util: Option<f64>,
+    ) {
+        let scale = self.info.max_freq() as f64 / cpu_max_freq.max(1) as f64;
+        let control = (control as f64 * scale) as isize;
+        let mut freq = self.cur_fas_freq.saturating_add(control);
+
+        if !is_janked {
+            if let Some(util) = util {
+                let util_tracking_sugg_freq = (self.info.read_freq() as f64 * util / 0.5) as isize; // min_util: 50%
+                #[cfg(debug_assertions)]
+                debug!("gpu util: {util}, util_tracking_sugg_freq: {util_tracking_sugg_freq}");
+                freq = freq.min(util_tracking_sugg_freq);
+            }
+        }
+
+        let freq = freq.clamp(self.info.min_freq(), self.info.max_freq());
+        self.cur_fas_freq = freq;
+        self.touched = true;
+
+        #[cfg(debug_assertions)]
+        debug!("change gpu freq: {freq}");
+
+        self.info
+            .write_freq(freq, is_janked, &mut self.file_handler);
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, Write},
+    os::fd::AsRawFd,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+
+const PID_FILE: &str = "/data/adb/fas-rs/fas-rs.pid";
+// The looper restores every frequency limit on SIGTERM before it exits, which takes a moment
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Held for the whole life of `run`. The flock is what marks an instance as running, the pid
+// in the file is only a hint for `stop`, so a stale file left by a crash never blocks a start.
+// The file is opened close-on-exec, the watchdog's in place restart takes the lock again
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    pub fn acquire() -> Result<Self> {
+        let path = Path::new(PID_FILE);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open {PID_FILE}"))?;
+
+        if !try_lock(&file, libc::LOCK_EX)? {
+            match read_pid(&mut file) {
+                Some(pid) => bail!("fas-rs is already running (pid {pid})"),
+                None => bail!("fas-rs is already running"),
+            }
+        }
+
+        let lock = Self { file };
+        lock.write_pid()?;
+
+        Ok(lock)
+    }
+
+    // Called again after `daemonize`, the lock carries over the fork but the pid changes
+    pub fn write_pid(&self) -> Result<()> {
+        let mut file = &self.file;
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+
+        Ok(())
+    }
+}
+
+// Detaches from the launching shell: the parent exits right away, the child leaves its session
+// and keeps stdout and stderr, which the module redirects to the log
+pub fn daemonize() -> Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error().into()),
+        0 => (),
+        _ => unsafe { libc::_exit(0) },
+    }
+
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    std::env::set_current_dir("/")?;
+
+    let null = File::open("/dev/null")?;
+    if unsafe { libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO) } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
+
+// Sends SIGTERM to the running instance and waits until it released the lock, that is until
+// it restored the defaults and exited
+pub fn stop() -> Result<()> {
+    let mut file = match File::open(PID_FILE) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => bail!("fas-rs is not running"),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {PID_FILE}")),
+    };
+
+    if try_lock(&file, libc::LOCK_SH)? {
+        bail!("fas-rs is not running");
+    }
+
+    let pid = read_pid(&mut file).context("fas-rs is running but wrote no pid")?;
+    if unsafe { libc::kill(pid, libc::SIGTERM) } < 0 {
+        return Err(io::Error::last_os_error()).context(format!("Failed to signal pid {pid}"));
+    }
+
+    let start = Instant::now();
+    while start.elapsed() < STOP_TIMEOUT {
+        thread::sleep(STOP_POLL_INTERVAL);
+
+        if try_lock(&file, libc::LOCK_SH)? {
+            println!("Stopped fas-rs (pid {pid})");
+            return Ok(());
+        }
+    }
+
+    bail!(
+        "fas-rs (pid {pid}) did not exit within {}s",
+        STOP_TIMEOUT.as_secs()
+    )
+}
+
+// Whether the lock was taken, false if another process holds it
+fn try_lock(file: &File, operation: libc::c_int) -> Result<bool> {
+    if unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+
+    let e = io::Error::last_os_error();
+    if e.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(e.into())
+    }
+}
+
+fn read_pid(file: &mut File) -> Option<i32> {
+    let mut content = String::new();
+    file.rewind().ok()?;
+    file.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
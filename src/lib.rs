@@ -0,0 +1,62 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+#![deny(clippy::all, clippy::pedantic)]
+#![warn(clippy::nursery)]
+#![allow(
+    clippy::module_name_repetitions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_wrap,
+    clippy::non_std_lazy_statics,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate
+)]
+
+// The daemon's modules, which the `fas-rs` binary links from here. Only the C interface of
+// `libfas_rs.so` is meant for embedders, the linker drops what it does not reach
+
+#[doc(hidden)]
+pub mod cgroup;
+#[doc(hidden)]
+pub mod cpu_common;
+#[doc(hidden)]
+pub mod error;
+mod ffi;
+#[doc(hidden)]
+pub mod file_handler;
+#[doc(hidden)]
+pub mod framework;
+#[doc(hidden)]
+pub mod gpu_common;
+#[doc(hidden)]
+pub mod misc;
+#[doc(hidden)]
+pub mod paths;
+#[doc(hidden)]
+pub mod sysfs;
+
+pub use ffi::{
+    FasDecision, FasEngine, fas_engine_frame, fas_engine_free, fas_engine_new,
+    fas_engine_target_fps,
+};
+
+use cpu_common::Controller;
+use framework::prelude::*;
+use gpu_common::{GpuController, GpuLoad};
@@ -26,44 +26,125 @@
     clippy::non_std_lazy_statics
 )]
 
-mod cpu_common;
-mod file_handler;
-mod framework;
-mod misc;
+mod crash;
+mod doctor;
+mod instance;
+mod overhead;
 
-use std::{env, fs, process};
+use std::{env, fs, path::PathBuf, process, thread, time::Duration};
 
-use framework::prelude::*;
+use fas_rs::{cgroup, cpu_common, framework, gpu_common, misc, paths};
+use framework::{
+    Command, Response, SimulateOptions, init_logger, install_signal_handlers, prelude::*, request,
+    simulate, terminated,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
 use log::{error, warn};
-use tklog::{LEVEL, LOG};
 
 #[cfg(debug_assertions)]
 use log::debug;
 
-use cpu_common::Controller;
-use misc::setprop;
+use cgroup::{Cgroup, Subsystem};
+use cpu_common::{Controller, snapshot};
+use crash::{CrashGuard, SAFE_MODE_PROP};
+use gpu_common::{GpuController, GpuLoad};
+use instance::InstanceLock;
+use misc::{setprop, third_party_packages};
+use overhead::Overhead;
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 const USER_CONFIG: &str = "/sdcard/Android/fas-rs/games.toml";
+const SAFE_MODE_SLEEP: Duration = Duration::from_secs(1);
 
 fn main() -> Result<()> {
     let args: Vec<_> = env::args().collect();
 
     if args[1] == "merge" {
         let local = fs::read_to_string(USER_CONFIG)?;
-        let std = fs::read_to_string(&args[2])?;
+        let (std, overlays) = Config::read_std(&args[2])?;
+        let diff = args.get(3).is_some_and(|arg| arg == "--diff");
 
-        let new = Config::merge(&local, &std).unwrap_or(std);
-        println!("{new}");
+        match Config::merge(&local, &std) {
+            Ok(merged) if diff => {
+                for overlay in &overlays {
+                    println!("# overlay {overlay}");
+                }
+                for entry in &merged.report {
+                    println!("# {entry}");
+                }
+                print!("{}", merged.diff(USER_CONFIG, &args[2]));
+            }
+            Ok(merged) => {
+                println!("{}", merged.config);
+                // Only what the user loses, the installer keeps this as the merge report
+                for entry in merged.report.iter().filter(|entry| entry.is_loss()) {
+                    eprintln!("{USER_CONFIG}: {entry}");
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to merge {USER_CONFIG}: {e}");
+                for diagnostic in Config::validate(&local) {
+                    eprintln!("{USER_CONFIG}:{diagnostic}");
+                }
+                process::exit(1);
+            }
+        }
 
         return Ok(());
+    } else if args[1] == "std-config" {
+        let (std, _) = Config::read_std(&args[2])?;
+        print!("{std}");
+        return Ok(());
+    } else if args[1] == "check" {
+        let path = args.get(2).map_or(USER_CONFIG, String::as_str);
+        return check(path);
+    } else if args[1] == "scan" {
+        let dry_run = args.get(2).is_some_and(|arg| arg == "--dry-run");
+        return scan(dry_run);
+    } else if args[1] == "doctor" {
+        paths::init(&Config::paths_config(USER_CONFIG));
+        if !doctor::diagnose() {
+            process::exit(1);
+        }
+        return Ok(());
+    } else if args[1] == "status" {
+        let json = args.get(2).is_some_and(|arg| arg == "--json");
+        return status(json);
+    } else if args[1] == "dump-log" {
+        let json = args.get(2).is_some_and(|arg| arg == "--json");
+        return dump_log(json);
+    } else if args[1] == "simulate" {
+        return simulate_recording(&args[2..]);
+    } else if args[1] == "update-config" {
+        #[cfg(feature = "update")]
+        {
+            let dry_run = args.get(2).is_some_and(|arg| arg == "--dry-run");
+            return update_config(dry_run);
+        }
+        #[cfg(not(feature = "update"))]
+        bail!("This build has no update-config, it needs the `update` feature");
+    } else if args[1] == "stop" {
+        return instance::stop();
+    } else if args[1] == "restore" {
+        return restore();
     } else if args[1] == "run" {
+        let daemon = args[2..].iter().any(|arg| arg == "--daemon");
+        let profile = args[2..].iter().any(|arg| arg == "--profile");
+        let Some(std_path) = args[2..].iter().find(|arg| !arg.starts_with("--")) else {
+            bail!("Usage: fas-rs run [--daemon] [--profile] <std config>");
+        };
+
+        let lock = InstanceLock::acquire()?;
+        if daemon {
+            instance::daemonize()?;
+            lock.write_pid()?;
+        }
+
         setprop("fas-rs-server-started", "true");
-        run(&args[2]).unwrap_or_else(|e| {
+        run(std_path, profile).unwrap_or_else(|e| {
             for cause in e.chain() {
                 error!("{cause:#?}");
             }
@@ -74,19 +155,249 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run<S: AsRef<str>>(std_path: S) -> Result<()> {
-    let logger = LOG;
-    #[cfg(debug_assertions)]
-    logger.set_level(LEVEL::Debug);
-    #[cfg(not(debug_assertions))]
-    logger.set_level(LEVEL::Info);
-    logger.set_formatter("[{time}] {level}: {message}\n");
-    logger.uselog();
+fn check(path: &str) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let diagnostics = Config::validate(&content);
+
+    if diagnostics.is_empty() {
+        println!("{path}: ok");
+        return Ok(());
+    }
+
+    for diagnostic in &diagnostics {
+        println!("{path}:{diagnostic}");
+    }
+
+    process::exit(1);
+}
+
+fn scan(dry_run: bool) -> Result<()> {
+    let Some(installed) = third_party_packages() else {
+        bail!("Failed to list installed packages with pm");
+    };
+
+    let content = fs::read_to_string(USER_CONFIG)?;
+    let games = Config::suggest_games(&content, &installed)?;
+
+    if games.is_empty() {
+        println!("No known games missing from {USER_CONFIG}");
+        return Ok(());
+    }
+
+    for game in &games {
+        println!("{}", game.line());
+    }
+
+    if !dry_run {
+        fs::write(USER_CONFIG, Config::insert_games(&content, &games))?;
+        println!("Added {} games to {USER_CONFIG}", games.len());
+    }
+
+    Ok(())
+}
+
+fn simulate_recording(args: &[String]) -> Result<()> {
+    const USAGE: &str = "Usage: fas-rs simulate <recording> [--config <games.toml>] [--pkg <package>] [--mode <mode>]";
+
+    let mut recording = None;
+    let mut options = SimulateOptions {
+        recording: PathBuf::new(),
+        config: PathBuf::from(USER_CONFIG),
+        pkg: None,
+        mode: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => options.config = args.next().context(USAGE)?.into(),
+            "--pkg" => options.pkg = Some(args.next().context(USAGE)?.clone()),
+            "--mode" => {
+                let mode = args.next().context(USAGE)?;
+                options.mode = Some(mode.parse().map_err(|_| anyhow!("Unknown mode {mode}"))?);
+            }
+            _ if recording.is_none() && !arg.starts_with("--") => recording = Some(arg.into()),
+            _ => bail!(USAGE),
+        }
+    }
+
+    options.recording = recording.context(USAGE)?;
+    simulate(&options)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "update")]
+fn update_config(dry_run: bool) -> Result<()> {
+    // The binary lives in the module directory, next to the overlays shipped with it
+    let module_dir = env::current_exe()?
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let Some((std, serial)) = Config::fetch_std()? else {
+        println!("The standard config was merged already, nothing to update");
+        return Ok(());
+    };
+    let (std, overlays) = Config::with_overlays(std, &module_dir)?;
+    for overlay in &overlays {
+        println!("# overlay {overlay}");
+    }
+    let local = fs::read_to_string(USER_CONFIG)?;
+
+    let mut merged = Config::merge(&local, &std)?;
+    merged.add_std_games(&std)?;
+
+    for entry in &merged.report {
+        println!("# {entry}");
+    }
+
+    if dry_run {
+        print!("{}", merged.diff(USER_CONFIG, "update"));
+        return Ok(());
+    }
+
+    // The daemon reloads the config as soon as it changes, it must never see half a file
+    let temp = format!("{USER_CONFIG}.update");
+    fs::write(&temp, &merged.config)?;
+    fs::rename(&temp, USER_CONFIG)?;
+    Config::record_std_serial(serial)?;
+    println!("Updated {USER_CONFIG}");
+
+    Ok(())
+}
+
+fn status(json: bool) -> Result<()> {
+    let status = match request(&Command::Status)? {
+        Response::Status(status) => status,
+        Response::Error(e) => bail!("Daemon returned an error: {e}"),
+        response => bail!("Unexpected response: {response:?}"),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    println!("mode: {}", status.mode);
+    println!(
+        "profile: {}",
+        status.profile.as_deref().unwrap_or("default")
+    );
+    println!("working: {}", status.working);
+    if status.paused {
+        println!("paused: fas-rs.ctl.enabled is false");
+    }
+
+    if let Some(game) = &status.game {
+        println!("game: {} (pid {})", game.pkg, game.pid);
+        match game.target_fps {
+            Some(target_fps) => println!("target fps: {target_fps}"),
+            None => println!("target fps: unknown"),
+        }
+        println!("current fps: {:.2}", game.current_fps);
+        if !game.bound.is_empty() {
+            println!("bound: {}", game.bound);
+        }
+        if let Some(energy) = status.session_energy_mwh {
+            println!("session cpu energy: {energy:.1} mWh");
+        }
+    } else {
+        println!("game: none");
+    }
+
+    if let Some(battery) = &status.battery {
+        println!(
+            "battery: {}% {} ({}), {} uA",
+            battery.capacity, battery.status, battery.health, battery.current_now
+        );
+    }
+
+    if !status.degraded.is_empty() {
+        println!("degraded:");
+        for degradation in &status.degraded {
+            println!("  {degradation}");
+        }
+    }
+
+    for cluster in &status.clusters {
+        println!(
+            "policy{} ({}, cpu{}, capacity {}): {} kHz, limits {}-{} kHz, util {:.1}%",
+            cluster.topology.policy,
+            cluster.topology.name,
+            cluster
+                .topology
+                .cpus
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+            cluster.topology.capacity,
+            cluster.cur_freq,
+            cluster.min_limit,
+            cluster.max_limit,
+            cluster.util * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+// Puts back what a crashed or killed daemon left behind, a running one would overwrite it again
+fn restore() -> Result<()> {
+    let _lock = InstanceLock::acquire().context("Stop fas-rs before restoring")?;
+    let (restored, failed) = snapshot::restore()?;
+
+    println!("Restored {restored} nodes");
+    for path in &failed {
+        eprintln!("Failed to restore {}", path.display());
+    }
+
+    if !failed.is_empty() {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn dump_log(json: bool) -> Result<()> {
+    let events = match request(&Command::DumpLog)? {
+        Response::Log(events) => events,
+        Response::Error(e) => bail!("Daemon returned an error: {e}"),
+        response => bail!("Unexpected response: {response:?}"),
+    };
+
+    for event in &events {
+        if json {
+            println!("{}", serde_json::to_string(event)?);
+        } else {
+            println!("{event}");
+        }
+    }
+
+    Ok(())
+}
+
+fn run<S: AsRef<str>>(std_path: S, profile: bool) -> Result<()> {
+    let daemon_config = Config::daemon_config(USER_CONFIG);
+    init_logger(daemon_config);
+    paths::init(&Config::paths_config(USER_CONFIG));
+
+    let crash_guard =
+        CrashGuard::start(daemon_config.crash_limit, daemon_config.crash_window * 60)?;
+    if crash_guard.safe_mode() {
+        safe_mode()?;
+        crash_guard.clean_exit();
+        return Ok(());
+    }
+    setprop(SAFE_MODE_PROP, "false");
 
     let std_path = std_path.as_ref();
+    let overhead = profile.then(Overhead::start);
 
     let self_pid = process::id();
-    let _ = fs::write("/dev/cpuset/background/cgroup.procs", self_pid.to_string());
+    if let Some(background) = Cgroup::open(Subsystem::Cpuset, "background") {
+        let _ = background.add_process(self_pid);
+    }
 
     let config = Config::new(USER_CONFIG, std_path)?;
     let cpu = Controller::new()?;
@@ -94,10 +405,34 @@ fn run<S: AsRef<str>>(std_path: S) -> Result<()> {
     #[cfg(debug_assertions)]
     debug!("{cpu:#?}");
 
-    Scheduler::new()
-        .config(config)
-        .controller(cpu)
-        .start_run()?;
+    let mut scheduler = Scheduler::new().config(config).controller(cpu);
+
+    match GpuController::new() {
+        Ok(gpu) => scheduler = scheduler.gpu_controller(gpu),
+        Err(e) => warn!("Gpu frequency control unavailable: {e}"),
+    }
+
+    let result = scheduler.start_run();
+    if let Some(overhead) = overhead {
+        overhead.report();
+    }
+    result?;
+
+    snapshot::discard();
+    crash_guard.clean_exit();
+
+    Ok(())
+}
+
+// Touches no frequency at all until it is stopped, the prop lets the module warn the user
+fn safe_mode() -> Result<()> {
+    error!("fas-rs keeps crashing, starting in safe mode without frequency control");
+    setprop(SAFE_MODE_PROP, "true");
+    install_signal_handlers()?;
+
+    while !terminated() {
+        thread::sleep(SAFE_MODE_SLEEP);
+    }
 
     Ok(())
 }
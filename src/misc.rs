@@ -15,10 +15,104 @@
 // You should have received a copy of the GNU General Public License along
 // with fas-rs. If not, see <https://www.gnu.org/licenses/>.
 
-use std::process::Command;
+#[cfg(target_os = "android")]
+use std::ffi::{CStr, CString};
+use std::{
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    process::Command,
+};
 
 pub fn setprop<S: AsRef<str>>(k: S, v: S) {
     let key = k.as_ref();
     let value = v.as_ref();
     let _ = Command::new("setprop").args([key, value]).spawn();
 }
+
+// Read straight from the property area, the props are polled every second and a `getprop`
+// process per read would cost more than everything else in the loop
+#[cfg(target_os = "android")]
+pub fn getprop<S: AsRef<str>>(k: S) -> Option<String> {
+    let key = CString::new(k.as_ref()).ok()?;
+    let mut value = [0; libc::PROP_VALUE_MAX as usize];
+    if unsafe { libc::__system_property_get(key.as_ptr(), value.as_mut_ptr()) } < 0 {
+        return None;
+    }
+
+    let value = unsafe { CStr::from_ptr(value.as_ptr()) };
+    Some(value.to_string_lossy().trim().to_string())
+}
+
+#[cfg(not(target_os = "android"))]
+pub fn getprop<S: AsRef<str>>(k: S) -> Option<String> {
+    let output = Command::new("getprop").arg(k.as_ref()).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+pub fn third_party_packages() -> Option<Vec<String>> {
+    let output = Command::new("pm")
+        .args(["list", "packages", "-3"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("package:"))
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+// `*` matches any run of characters, `?` exactly one
+pub fn glob_match(glob: &[u8], name: &[u8]) -> bool {
+    let (mut g, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                backtrack = Some((g, n));
+                g += 1;
+            }
+            Some(c) if *c == b'?' || *c == name[n] => {
+                g += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    g = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == b'*')
+}
+
+// Pid and uid of the process on the other end of a unix socket
+pub fn peer_cred(stream: &UnixStream) -> Option<libc::ucred> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = size_of::<libc::ucred>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&raw mut cred).cast(),
+            &raw mut len,
+        )
+    };
+
+    (ret == 0).then_some(cred)
+}
@@ -0,0 +1,283 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, process,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use crate::framework::terminated;
+
+const REPORT_FILE: &str = "/data/adb/fas-rs/profile.txt";
+// Threads that exit between two samples lose at most this much of their counters
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const SAMPLER_NAME: &str = "ProfileThread";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Subsystem {
+    Scheduler,
+    Monitor,
+    Writer,
+    Other,
+    Profiler,
+}
+
+impl Subsystem {
+    // `comm` is cut to 15 bytes, hence the prefixes
+    fn of(tid: u32, comm: &str) -> Self {
+        const MONITORS: [&str; 5] = [
+            "TopAppThread",
+            "FocusLogThread",
+            "PsiThread",
+            "TouchThread",
+            "FramePlugin",
+        ];
+
+        if tid == process::id() {
+            Self::Scheduler
+        } else if comm == "FileWriter" {
+            Self::Writer
+        } else if comm == SAMPLER_NAME {
+            Self::Profiler
+        } else if MONITORS.iter().any(|name| comm.starts_with(name)) {
+            Self::Monitor
+        } else {
+            Self::Other
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Scheduler => "scheduler",
+            Self::Monitor => "monitor",
+            Self::Writer => "writer",
+            Self::Other => "other",
+            Self::Profiler => "profiler",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counters {
+    cpu_ticks: u64,
+    wakeups: u64,
+    preempted: u64,
+    syscalls: u64,
+}
+
+impl Counters {
+    fn read(tid: u32) -> Option<(String, Self)> {
+        let dir = format!("/proc/self/task/{tid}");
+        let stat = fs::read_to_string(format!("{dir}/stat")).ok()?;
+
+        // The name may contain spaces and parentheses, the fields start after the last ')'
+        let comm = stat.get(stat.find('(')? + 1..stat.rfind(')')?)?.to_string();
+        let mut fields = stat
+            .get(stat.rfind(')')? + 2..)?
+            .split_whitespace()
+            .skip(11);
+        let utime: u64 = fields.next()?.parse().ok()?;
+        let stime: u64 = fields.next()?.parse().ok()?;
+
+        let mut counters = Self {
+            cpu_ticks: utime + stime,
+            ..Self::default()
+        };
+
+        // A voluntary switch is the thread going to sleep, each one is paid back by a wakeup
+        if let Ok(status) = fs::read_to_string(format!("{dir}/status")) {
+            for line in status.lines() {
+                if let Some(value) = line.strip_prefix("voluntary_ctxt_switches:") {
+                    counters.wakeups = value.trim().parse().unwrap_or_default();
+                } else if let Some(value) = line.strip_prefix("nonvoluntary_ctxt_switches:") {
+                    counters.preempted = value.trim().parse().unwrap_or_default();
+                }
+            }
+        }
+
+        // Without ptrace the kernel only counts the read and write family of syscalls, which
+        // is what polling sysfs and writing nodes costs anyway
+        if let Ok(io) = fs::read_to_string(format!("{dir}/io")) {
+            counters.syscalls = io
+                .lines()
+                .filter_map(|line| {
+                    line.strip_prefix("syscr:")
+                        .or_else(|| line.strip_prefix("syscw:"))
+                })
+                .filter_map(|value| value.trim().parse::<u64>().ok())
+                .sum();
+        }
+
+        Some((comm, counters))
+    }
+
+    const fn since(self, start: Self) -> Self {
+        Self {
+            cpu_ticks: self.cpu_ticks.saturating_sub(start.cpu_ticks),
+            wakeups: self.wakeups.saturating_sub(start.wakeups),
+            preempted: self.preempted.saturating_sub(start.preempted),
+            syscalls: self.syscalls.saturating_sub(start.syscalls),
+        }
+    }
+
+    const fn add(&mut self, other: Self) {
+        self.cpu_ticks += other.cpu_ticks;
+        self.wakeups += other.wakeups;
+        self.preempted += other.preempted;
+        self.syscalls += other.syscalls;
+    }
+}
+
+#[derive(Debug)]
+struct Task {
+    subsystem: Subsystem,
+    start: Counters,
+    last: Counters,
+}
+
+// Keyed by tid, exited threads stay with the counters of their last sample
+type Tasks = Arc<Mutex<HashMap<u32, Task>>>;
+
+fn sample(tasks: &Tasks, baseline: bool) {
+    let Ok(entries) = fs::read_dir("/proc/self/task") else {
+        return;
+    };
+
+    let mut tasks = tasks.lock();
+    for tid in entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+    {
+        let Some((comm, counters)) = Counters::read(tid) else {
+            continue;
+        };
+
+        // Threads started during the session count from zero
+        let task = tasks.entry(tid).or_insert_with(|| Task {
+            subsystem: Subsystem::of(tid, &comm),
+            start: if baseline {
+                counters
+            } else {
+                Counters::default()
+            },
+            last: counters,
+        });
+        // Threads are named right after they start
+        task.subsystem = Subsystem::of(tid, &comm);
+        task.last = counters;
+    }
+}
+
+// `fas-rs run --profile`, accounts the daemon's own cost per subsystem over the session
+pub struct Overhead {
+    started: Instant,
+    tasks: Tasks,
+}
+
+impl Overhead {
+    pub fn start() -> Self {
+        let tasks = Tasks::default();
+        sample(&tasks, true);
+
+        {
+            let tasks = tasks.clone();
+            let spawned = thread::Builder::new()
+                .name(SAMPLER_NAME.into())
+                .spawn(move || {
+                    while !terminated() {
+                        thread::sleep(SAMPLE_INTERVAL);
+                        sample(&tasks, false);
+                    }
+                });
+
+            if let Err(e) = spawned {
+                warn!("Failed to start the profiler, only the final sample is counted: {e}");
+            }
+        }
+
+        info!("Profiling fas-rs, the report is written to {REPORT_FILE} on exit");
+
+        Self {
+            started: Instant::now(),
+            tasks,
+        }
+    }
+
+    pub fn report(self) {
+        sample(&self.tasks, false);
+
+        let elapsed = self.started.elapsed().as_secs_f64().max(f64::EPSILON);
+        let ticks = clock_ticks();
+
+        let mut subsystems: HashMap<Subsystem, Counters> = HashMap::new();
+        for task in self.tasks.lock().values() {
+            subsystems
+                .entry(task.subsystem)
+                .or_default()
+                .add(task.last.since(task.start));
+        }
+
+        let mut rows: Vec<_> = subsystems.into_iter().collect();
+        rows.sort_unstable_by_key(|(subsystem, _)| *subsystem);
+
+        let mut report = format!(
+            "fas-rs overhead over {elapsed:.0}s, cpu% is of one core, syscalls are reads and writes\n{:<10} {:>9} {:>7} {:>10} {:>11} {:>10}\n",
+            "subsystem", "cpu", "cpu%", "wakeups/s", "preempted/s", "syscalls"
+        );
+        let mut total = Counters::default();
+        for (subsystem, counters) in &rows {
+            if *subsystem != Subsystem::Profiler {
+                total.add(*counters);
+            }
+            push_row(&mut report, subsystem.name(), *counters, elapsed, ticks);
+        }
+        push_row(&mut report, "total", total, elapsed, ticks);
+
+        for line in report.lines() {
+            info!("{line}");
+        }
+
+        if let Err(e) = fs::write(REPORT_FILE, &report) {
+            warn!("Failed to write {REPORT_FILE}: {e}");
+        }
+    }
+}
+
+fn push_row(report: &mut String, name: &str, counters: Counters, elapsed: f64, ticks: f64) {
+    let cpu = counters.cpu_ticks as f64 / ticks;
+    let _ = writeln!(
+        report,
+        "{name:<10} {:>8.2}s {:>6.2}% {:>10.2} {:>11.2} {:>10}",
+        cpu,
+        cpu / elapsed * 100.0,
+        counters.wakeups as f64 / elapsed,
+        counters.preempted as f64 / elapsed,
+        counters.syscalls
+    );
+}
+
+fn clock_ticks() -> f64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as f64 } else { 100.0 }
+}
@@ -0,0 +1,150 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use log::{error, info};
+
+use crate::framework::config::PathsConfig;
+
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpufreq";
+const THERMAL_ZONES_PATH: &str = "/sys/class/thermal";
+
+static OVERRIDES: OnceLock<[Option<PathBuf>; 4]> = OnceLock::new();
+
+// Directories the kernel of a handheld or a TV box may keep elsewhere, `[paths]` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Root {
+    Cpufreq,
+    Thermal,
+    Gpu,
+    Cpuset,
+}
+
+impl Root {
+    pub const ALL: [Self; 4] = [Self::Cpufreq, Self::Thermal, Self::Gpu, Self::Cpuset];
+
+    pub const fn key(self) -> &'static str {
+        match self {
+            Self::Cpufreq => "cpufreq",
+            Self::Thermal => "thermal",
+            Self::Gpu => "gpu",
+            Self::Cpuset => "cpuset",
+        }
+    }
+
+    fn value(self, config: &PathsConfig) -> &str {
+        match self {
+            Self::Cpufreq => &config.cpufreq,
+            Self::Thermal => &config.thermal,
+            Self::Gpu => &config.gpu,
+            Self::Cpuset => &config.cpuset,
+        }
+    }
+
+    // Why `path` cannot be this directory, judged by what fas-rs reads below it
+    pub fn check(self, path: &Path) -> Result<(), String> {
+        if !path.is_absolute() {
+            return Err("must be an absolute path".into());
+        }
+        if !path.is_dir() {
+            return Err("is not a directory".into());
+        }
+
+        let (found, expected) = match self {
+            Self::Cpufreq => (
+                has_child(path, "policy", "scaling_max_freq"),
+                "no policy*/scaling_max_freq, it should hold the cpufreq policies",
+            ),
+            Self::Thermal => (
+                has_child(path, "thermal_zone", "temp"),
+                "no thermal_zone*/temp, it should hold the thermal zones",
+            ),
+            Self::Gpu => (
+                ["available_frequencies", "min_freq", "max_freq"]
+                    .iter()
+                    .all(|node| path.join(node).exists()),
+                "no available_frequencies, min_freq and max_freq, it should be the devfreq directory of the gpu",
+            ),
+            Self::Cpuset => (
+                ["cpus", "cpuset.cpus", "cgroup.controllers"]
+                    .iter()
+                    .any(|node| path.join(node).exists()),
+                "no cpus, cpuset.cpus or cgroup.controllers, it should be the root of the cpuset hierarchy",
+            ),
+        };
+
+        if found {
+            Ok(())
+        } else {
+            Err(format!("has {expected}"))
+        }
+    }
+}
+
+// Once at startup, before anything is detected. A wrong override is reported and ignored, the
+// directory is then detected as if there was none
+pub fn init(config: &PathsConfig) {
+    let overrides = Root::ALL.map(|root| {
+        let value = root.value(config);
+        if value.is_empty() {
+            return None;
+        }
+
+        let path = PathBuf::from(value);
+        match root.check(&path) {
+            Ok(()) => {
+                info!("[paths] {}: {value}", root.key());
+                Some(path)
+            }
+            Err(e) => {
+                error!(
+                    "[paths] {} = \"{value}\" {e}, detecting it instead",
+                    root.key()
+                );
+                None
+            }
+        }
+    });
+
+    let _ = OVERRIDES.set(overrides);
+}
+
+pub fn get(root: Root) -> Option<&'static Path> {
+    OVERRIDES.get()?[root as usize].as_deref()
+}
+
+pub fn cpufreq() -> &'static Path {
+    get(Root::Cpufreq).unwrap_or_else(|| Path::new(CPUFREQ_PATH))
+}
+
+pub fn thermal() -> &'static Path {
+    get(Root::Thermal).unwrap_or_else(|| Path::new(THERMAL_ZONES_PATH))
+}
+
+fn has_child(dir: &Path, prefix: &str, node: &str) -> bool {
+    fs::read_dir(dir).is_ok_and(|entries| {
+        entries.filter_map(Result::ok).any(|entry| {
+            entry.file_name().to_string_lossy().starts_with(prefix)
+                && entry.path().join(node).exists()
+        })
+    })
+}
@@ -0,0 +1,106 @@
+// Copyright 2025-2025, shadow3aaa
+//
+// This file is part of fas-rs.
+//
+// fas-rs is free software: you can redistribute it and/or modify it under
+// the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option)
+// any later version.
+//
+// fas-rs is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE. See the GNU General Public License for more
+// details.
+//
+// You should have received a copy of the GNU General Public License along
+// with fas-rs. If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static SYSFS: OnceLock<Box<dyn SysFs>> = OnceLock::new();
+
+// Access to the sysfs and procfs nodes the cpu controller and the process monitor read and write.
+// Paths stay the absolute device paths everywhere, only the implementation decides where they
+// really live, so the same code can run against a fake tree in a temp dir
+pub trait SysFs: Send + Sync {
+    // Where an absolute node path really is, for code that keeps files open itself
+    fn resolve(&self, path: &Path) -> PathBuf;
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(self.resolve(path))
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        fs::write(self.resolve(path), content)
+    }
+
+    // Entries as device paths, in directory order
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(self.resolve(path))?
+            .map(|entry| entry.map(|entry| path.join(entry.file_name())))
+            .collect()
+    }
+}
+
+// The real device
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Host;
+
+impl SysFs for Host {
+    fn resolve(&self, path: &Path) -> PathBuf {
+        path.to_path_buf()
+    }
+}
+
+// A tree laid out like the device's below `root`, `/sys/devices/system/cpu` becomes
+// `<root>/sys/devices/system/cpu`. Only the tests run on one
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct Rooted {
+    root: PathBuf,
+}
+
+#[cfg(test)]
+impl Rooted {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[cfg(test)]
+impl SysFs for Rooted {
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+// Replaces the host for the rest of the process, only before the first access. Returns false if
+// something already read through the host
+#[cfg(test)]
+pub fn install(sysfs: Box<dyn SysFs>) -> bool {
+    SYSFS.set(sysfs).is_ok()
+}
+
+fn get() -> &'static dyn SysFs {
+    SYSFS.get_or_init(|| Box::new(Host)).as_ref()
+}
+
+pub fn resolve(path: impl AsRef<Path>) -> PathBuf {
+    get().resolve(path.as_ref())
+}
+
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    get().read_to_string(path.as_ref())
+}
+
+pub fn write(path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> io::Result<()> {
+    get().write(path.as_ref(), content.as_ref())
+}
+
+pub fn read_dir(path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    get().read_dir(path.as_ref())
+}
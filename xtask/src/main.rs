@@ -18,18 +18,26 @@
 mod zip_ext;
 
 use std::{
+    env,
     fs::{self},
     path::{Path, PathBuf},
     process::{self, Command},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
+use base64::{Engine, engine::general_purpose::STANDARD};
 use clap::{Parser, Subcommand};
+use ed25519_dalek::{Signer, SigningKey};
 use fs_extra::{dir, file};
 use zip::{CompressionMethod, write::FileOptions};
 
 use zip_ext::zip_create_from_directory_with_options;
 
+// Base64 ed25519 keys of the standard config, the public one is baked into `update-config`
+const CONFIG_PUBKEY_ENV: &str = "FAS_RS_CONFIG_PUBKEY";
+const CONFIG_SIGNING_KEY_ENV: &str = "FAS_RS_CONFIG_SIGNING_KEY";
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -59,6 +67,21 @@ enum Commands {
         /// Print detailed output (default: false)
         #[clap(short, long, default_value = "false")]
         verbose: bool,
+
+        /// Comma separated optional features to enable, e.g. `tuner`
+        #[clap(short, long)]
+        features: Option<String>,
+
+        /// Base64 public key `update-config` checks the standard config with (default: $FAS_RS_CONFIG_PUBKEY)
+        #[clap(long)]
+        config_pubkey: Option<String>,
+    },
+
+    /// Sign module/games.toml with the key in $FAS_RS_CONFIG_SIGNING_KEY into the release assets for `update-config`
+    SignConfig {
+        /// Serial signed along, must grow with every release (default: the current unix time)
+        #[clap(long)]
+        serial: Option<u64>,
     },
 
     /// Clean build artifacts
@@ -94,8 +117,16 @@ fn main() -> Result<()> {
         Commands::Check { release, verbose } => {
             check(release, verbose)?;
         }
-        Commands::Build { release, verbose } => {
-            build(release, verbose)?;
+        Commands::Build {
+            release,
+            verbose,
+            features,
+            config_pubkey,
+        } => {
+            build(release, verbose, features.as_deref(), config_pubkey)?;
+        }
+        Commands::SignConfig { serial } => {
+            sign_config(serial)?;
         }
         Commands::Clean => {
             clean()?;
@@ -114,7 +145,22 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn build(release: bool, verbose: bool) -> Result<()> {
+fn build(
+    release: bool,
+    verbose: bool,
+    features: Option<&str>,
+    config_pubkey: Option<String>,
+) -> Result<()> {
+    let config_pubkey = config_pubkey.or_else(|| env::var(CONFIG_PUBKEY_ENV).ok());
+    let update = features.is_some_and(|features| {
+        features
+            .split(',')
+            .any(|feature| feature.trim() == "update")
+    });
+    if update && config_pubkey.is_none() {
+        bail!("The update feature needs --config-pubkey or ${CONFIG_PUBKEY_ENV}");
+    }
+
     let temp_dir = temp_dir(release);
 
     let _ = fs::remove_dir_all(&temp_dir);
@@ -139,6 +185,17 @@ fn build(release: bool, verbose: bool) -> Result<()> {
         cargo.arg("--verbose");
     }
 
+    // Only the binary is packaged, and only the binary may override malloc
+    let features = features.map_or_else(
+        || "malloc-override".to_string(),
+        |features| format!("{features},malloc-override"),
+    );
+    cargo.args(["--bin", "fas-rs", "--features", &features]);
+
+    if let Some(config_pubkey) = config_pubkey {
+        cargo.env(CONFIG_PUBKEY_ENV, config_pubkey);
+    }
+
     cargo.spawn()?.wait()?;
 
     let module_dir = module_dir();
@@ -254,6 +311,46 @@ fn update() -> Result<()> {
     Ok(())
 }
 
+fn sign_config(serial: Option<u64>) -> Result<()> {
+    let key = env::var(CONFIG_SIGNING_KEY_ENV)
+        .with_context(|| format!("${CONFIG_SIGNING_KEY_ENV} is not set"))?;
+    let key: [u8; 32] = STANDARD
+        .decode(key.trim())
+        .context("Invalid signing key")?
+        .try_into()
+        .map_err(|_| anyhow!("Signing key has the wrong length"))?;
+    let key = SigningKey::from_bytes(&key);
+
+    let serial = match serial {
+        Some(serial) => serial,
+        None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+
+    // `update-config` refuses a serial older than the last one it merged, so an old signed
+    // config can not be served again
+    let config_path = module_dir().join("games.toml");
+    let config = fs::read_to_string(&config_path)?;
+    let message = format!("{serial}\n{config}");
+    let signature = STANDARD.encode(key.sign(message.as_bytes()).to_bytes());
+
+    let output = Path::new("output");
+    fs::create_dir_all(output)?;
+    fs::write(output.join("games.toml"), &config)?;
+    fs::write(
+        output.join("games.toml.sig"),
+        format!("{serial}\n{signature}\n"),
+    )?;
+
+    println!(
+        "Signed {} with serial {serial} into {}, public key: {}",
+        config_path.display(),
+        output.display(),
+        STANDARD.encode(key.verifying_key().to_bytes())
+    );
+
+    Ok(())
+}
+
 fn module_dir() -> PathBuf {
     Path::new("module").to_path_buf()
 }